@@ -0,0 +1,134 @@
+use graphql_client::GraphQLQuery;
+use serde_json::Value;
+use tokio::runtime::{Builder, Runtime};
+
+use crate::cache::Cache;
+use crate::client::{ClientResult, DiscoveryClient, RefetchQuery, RefetchResults, RequestOptions};
+use crate::response::GraphQLResponse;
+
+pub struct BlockingDiscoveryClient<C> {
+    client: DiscoveryClient<C>,
+    runtime: Runtime,
+}
+
+impl<C: Cache + Send + 'static> BlockingDiscoveryClient<C> {
+    pub fn new(client: DiscoveryClient<C>) -> std::io::Result<Self> {
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        Ok(Self { client, runtime })
+    }
+
+    pub fn client(&self) -> &DiscoveryClient<C> {
+        &self.client
+    }
+
+    pub fn into_async(self) -> DiscoveryClient<C> {
+        self.client
+    }
+
+    pub fn query<Q: GraphQLQuery>(
+        &self,
+        variables: <Q as GraphQLQuery>::Variables,
+    ) -> ClientResult<GraphQLResponse<<Q as GraphQLQuery>::ResponseData>> {
+        self.runtime.block_on(self.client.query::<Q>(variables))
+    }
+
+    pub fn query_with_options<Q: GraphQLQuery>(
+        &self,
+        variables: <Q as GraphQLQuery>::Variables,
+        options: &RequestOptions,
+    ) -> ClientResult<GraphQLResponse<<Q as GraphQLQuery>::ResponseData>> {
+        self.runtime
+            .block_on(self.client.query_with_options::<Q>(variables, options))
+    }
+
+    pub fn query_raw(&self, query: &str, variables: Value) -> ClientResult<GraphQLResponse<Value>> {
+        self.runtime
+            .block_on(self.client.query_raw(query, variables))
+    }
+
+    pub fn query_raw_with_options(
+        &self,
+        query: &str,
+        variables: Value,
+        options: &RequestOptions,
+    ) -> ClientResult<GraphQLResponse<Value>> {
+        self.runtime.block_on(
+            self.client
+                .query_raw_with_options(query, variables, options),
+        )
+    }
+
+    pub fn mutate<M: GraphQLQuery>(
+        &self,
+        variables: <M as GraphQLQuery>::Variables,
+    ) -> ClientResult<GraphQLResponse<<M as GraphQLQuery>::ResponseData>> {
+        self.runtime.block_on(self.client.mutate::<M>(variables))
+    }
+
+    pub fn mutate_with_options<M: GraphQLQuery>(
+        &self,
+        variables: <M as GraphQLQuery>::Variables,
+        options: &RequestOptions,
+    ) -> ClientResult<GraphQLResponse<<M as GraphQLQuery>::ResponseData>> {
+        self.runtime
+            .block_on(self.client.mutate_with_options::<M>(variables, options))
+    }
+
+    pub fn refetch_queries(&self, queries: &[RefetchQuery]) -> ClientResult<RefetchResults> {
+        self.runtime.block_on(self.client.refetch_queries(queries))
+    }
+}
+
+impl<C: Cache + Send + 'static> DiscoveryClient<C> {
+    pub fn blocking(self) -> std::io::Result<BlockingDiscoveryClient<C>> {
+        BlockingDiscoveryClient::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::InMemoryCache;
+    use crate::client::DiscoveryClientBuilder;
+    use crate::link::{Transport, TransportRequest, TransportResponse};
+    use bytes::Bytes;
+    use futures::future::BoxFuture;
+    use reqwest::header::HeaderMap;
+    use reqwest::StatusCode;
+    use serde_json::json;
+
+    struct Fixed;
+
+    impl Transport for Fixed {
+        fn execute(
+            &self,
+            _request: TransportRequest,
+        ) -> BoxFuture<'_, ClientResult<TransportResponse>> {
+            Box::pin(async move {
+                tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+                Ok(TransportResponse {
+                    status: StatusCode::OK,
+                    headers: HeaderMap::new(),
+                    body: Bytes::from_static(br#"{ "data": { "me": { "name": "Luke" } } }"#),
+                })
+            })
+        }
+    }
+
+    #[test]
+    fn query_without_runtime() {
+        let client = DiscoveryClientBuilder::<InMemoryCache>::new()
+            .uri("http://localhost/graphql".to_string())
+            .transport(Fixed)
+            .build()
+            .unwrap()
+            .blocking()
+            .unwrap();
+
+        let response = client
+            .query_raw("query Me { me { name } }", json!({}))
+            .unwrap();
+
+        assert_eq!(response.data, Some(json!({ "me": { "name": "Luke" } })));
+    }
+}
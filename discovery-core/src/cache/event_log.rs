@@ -0,0 +1,176 @@
+use serde_json::Value as JsonValue;
+use std::collections::VecDeque;
+use std::time::SystemTime;
+
+use super::{Location, NormalizedData};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheOperation {
+    Insert,
+    Update,
+    Remove,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: Option<JsonValue>,
+    pub after: Option<JsonValue>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheEvent {
+    pub seq: u64,
+    pub location: Location,
+    pub operation: CacheOperation,
+    pub diff: Vec<FieldChange>,
+    pub timestamp: SystemTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct EventLog {
+    capacity: usize,
+    next_seq: u64,
+    events: VecDeque<CacheEvent>,
+}
+
+impl EventLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            next_seq: 0,
+            events: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn events(&self) -> impl Iterator<Item = &CacheEvent> {
+        self.events.iter()
+    }
+
+    pub fn events_for<'a>(
+        &'a self,
+        location: &'a Location,
+    ) -> impl Iterator<Item = &'a CacheEvent> + 'a {
+        self.events.iter().filter(move |e| &e.location == location)
+    }
+
+    pub fn last_write(&self, location: &Location) -> Option<&CacheEvent> {
+        self.events.iter().rev().find(|e| &e.location == location)
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    pub(crate) fn record(
+        &mut self,
+        location: Location,
+        before: Option<&NormalizedData>,
+        after: Option<&NormalizedData>,
+    ) {
+        if self.capacity == 0 {
+            return;
+        }
+        let operation = match (before, after) {
+            (None, _) => CacheOperation::Insert,
+            (Some(_), Some(_)) => CacheOperation::Update,
+            (Some(_), None) => CacheOperation::Remove,
+        };
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(CacheEvent {
+            seq: self.next_seq,
+            location,
+            operation,
+            diff: diff(before, after),
+            timestamp: SystemTime::now(),
+        });
+        self.next_seq += 1;
+    }
+}
+
+fn diff(before: Option<&NormalizedData>, after: Option<&NormalizedData>) -> Vec<FieldChange> {
+    match (before, after) {
+        (Some(NormalizedData::Object(before)), Some(NormalizedData::Object(after))) => before
+            .keys()
+            .chain(after.keys().filter(|k| !before.contains_key(*k)))
+            .filter(|k| before.get(*k) != after.get(*k))
+            .map(|k| FieldChange {
+                field: k.clone(),
+                before: before.get(k).cloned(),
+                after: after.get(k).cloned(),
+            })
+            .collect(),
+        (before, after) if before != after => vec![FieldChange {
+            field: String::new(),
+            before: before.cloned().map(JsonValue::from),
+            after: after.cloned().map(JsonValue::from),
+        }],
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::test_support::person;
+    use crate::cache::{Cache, InMemoryCache, Key};
+    use serde_json::json;
+
+    #[test]
+    fn records_insert_and_update_with_diff() {
+        let mut cache = InMemoryCache::new().with_event_log(16);
+        cache
+            .store_result_data(&"a".into(), person("Luke"))
+            .unwrap();
+        cache
+            .store_result_data(&"b".into(), person("Anakin"))
+            .unwrap();
+
+        let log = cache.event_log().unwrap();
+        let location = Location::Entity(Key::new("Person", "1"));
+        let events: Vec<_> = log.events_for(&location).collect();
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].operation, CacheOperation::Insert);
+        assert_eq!(events[1].operation, CacheOperation::Update);
+        assert_eq!(
+            events[1].diff,
+            vec![FieldChange {
+                field: "name".to_string(),
+                before: Some(json!("Luke")),
+                after: Some(json!("Anakin")),
+            }]
+        );
+        assert_eq!(log.last_write(&location).unwrap().seq, events[1].seq);
+    }
+
+    #[test]
+    fn bounded_ring_buffer() {
+        let mut cache = InMemoryCache::new().with_event_log(3);
+        for i in 0..5 {
+            cache
+                .store_result_data(&i.to_string().into(), person("Luke"))
+                .unwrap();
+        }
+
+        let seqs: Vec<_> = cache.event_log().unwrap().events().map(|e| e.seq).collect();
+
+        assert_eq!(seqs, vec![7, 8, 9]);
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        let mut cache = InMemoryCache::new();
+        cache
+            .store_result_data(&"a".into(), person("Luke"))
+            .unwrap();
+
+        assert!(cache.event_log().is_none());
+    }
+}
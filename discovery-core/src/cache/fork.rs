@@ -0,0 +1,341 @@
+use futures::channel::mpsc::UnboundedReceiver;
+use serde_json::json;
+use std::collections::HashSet;
+use std::time::{Duration, SystemTime};
+
+use super::{
+    denormalize_data, Cache, CacheError, CachedResult, Data, InMemoryCache, Key, NormalizedData,
+    OptimisticId, ResultKey, ResultMeta, WatchEvent, WatchId, WatchSelector, REF,
+};
+
+pub struct ForkedCache<'a> {
+    parent: &'a mut InMemoryCache,
+    layer: InMemoryCache,
+    removed: HashSet<ResultKey>,
+}
+
+impl InMemoryCache {
+    pub fn fork(&mut self) -> ForkedCache<'_> {
+        let layer = InMemoryCache {
+            read_policies: self.read_policies.clone(),
+            merge_policies: self.merge_policies.clone(),
+            invalidation_rules: self.invalidation_rules.clone(),
+            ttl: self.ttl,
+            ..InMemoryCache::new().with_max_depth(self.max_depth)
+        };
+        ForkedCache {
+            parent: self,
+            layer,
+            removed: HashSet::new(),
+        }
+    }
+}
+
+impl<'a> ForkedCache<'a> {
+    pub fn parent(&self) -> &InMemoryCache {
+        self.parent
+    }
+
+    pub fn changed_result_keys(&self) -> impl Iterator<Item = &ResultKey> {
+        self.layer.result_cache.keys()
+    }
+
+    pub fn changed_entity_keys(&self) -> impl Iterator<Item = &Key> {
+        self.layer.identity_cache.keys()
+    }
+
+    pub fn merge(self) {
+        let ForkedCache {
+            parent,
+            layer,
+            removed,
+        } = self;
+        parent.checkpoint();
+        for key in removed {
+            if parent.result_cache.contains_key(&key) {
+                parent.remove_result(&key);
+            }
+        }
+        for (key, data) in layer.identity_cache {
+            parent.write_identity(&key, data);
+        }
+        for (key, data) in layer.result_cache {
+            parent.write_result(&key, data);
+        }
+        for (key, meta) in layer.result_meta {
+            if parent.result_cache.contains_key(&key) {
+                parent.result_meta.insert(key, meta);
+            }
+        }
+    }
+
+    pub fn discard(self) {}
+
+    fn parent_result(&self, key: &ResultKey) -> Option<&NormalizedData> {
+        match self.removed.contains(key) {
+            true => None,
+            false => self.parent.result_cache.get(key),
+        }
+    }
+
+    fn result_meta(&self, key: &ResultKey) -> Option<&ResultMeta> {
+        match self.layer.result_cache.contains_key(key) || self.layer.result_meta.contains_key(key)
+        {
+            true => self.layer.result_meta.get(key),
+            false => self
+                .parent_result(key)
+                .and(self.parent.result_meta.get(key)),
+        }
+    }
+
+    fn result_meta_mut(&mut self, key: &ResultKey) -> Option<&mut ResultMeta> {
+        if !self.layer.result_meta.contains_key(key) {
+            let meta = self.result_meta(key)?.clone();
+            self.layer.result_meta.insert(key.clone(), meta);
+        }
+        self.layer.result_meta.get_mut(key)
+    }
+}
+
+impl<'a> Cache for ForkedCache<'a> {
+    fn identify(&self, data: &Data) -> Key {
+        self.parent.identify(data)
+    }
+    fn store_result_data(
+        &mut self,
+        key: &ResultKey,
+        data: Data,
+    ) -> Result<NormalizedData, CacheError> {
+        self.removed.remove(key);
+        self.layer.store_result_data(key, data)
+    }
+    fn get_result_data(&self, key: &ResultKey) -> Result<Data, CacheError> {
+        let normalized_data = self
+            .layer
+            .result_cache
+            .get(key)
+            .or_else(|| self.parent_result(key))
+            .ok_or_else(|| CacheError::ResultKeyNotFound(key.clone()))?;
+
+        Ok(Data(denormalize_data(
+            normalized_data.clone().into(),
+            self,
+            &self.parent.read_policies,
+            self.layer.max_depth,
+            &[],
+        )?))
+    }
+    fn store_mutation_data(&mut self, data: Data) -> Result<Vec<ResultKey>, CacheError> {
+        let mut invalidated: Vec<_> = self
+            .parent
+            .results_invalidated_by(&data)
+            .into_iter()
+            .filter(|key| !self.removed.contains(key) && !self.layer.result_cache.contains_key(key))
+            .collect();
+        invalidated.extend(self.layer.store_mutation_data(data)?);
+        invalidated.sort();
+        invalidated.dedup();
+        self.removed.extend(invalidated.iter().cloned());
+        Ok(invalidated)
+    }
+    fn store_identity_data(&mut self, key: &Key, data: NormalizedData) -> Result<(), CacheError> {
+        self.layer.store_identity_data(key, data)
+    }
+    fn get_identity_data(&self, key: &Key) -> Result<Data, CacheError> {
+        Ok(Data(denormalize_data(
+            json!({ REF: key }),
+            self,
+            &self.parent.read_policies,
+            self.layer.max_depth,
+            &[],
+        )?))
+    }
+    fn get_identity_entry(&self, key: &Key) -> Result<NormalizedData, CacheError> {
+        self.layer
+            .get_identity_entry(key)
+            .or_else(|_| self.parent.get_identity_entry(key))
+    }
+    fn write_optimistic(&mut self, data: Data) -> Result<OptimisticId, CacheError> {
+        self.layer.write_optimistic(data)
+    }
+    fn merge_result_data(
+        &mut self,
+        key: &ResultKey,
+        data: Data,
+    ) -> Result<NormalizedData, CacheError> {
+        let merged = match self.get_result_data(key) {
+            Ok(existing) => Data(
+                self.parent
+                    .merge_policies
+                    .merge("Query", existing.0, data.0),
+            ),
+            Err(_) => data,
+        };
+        self.store_result_data(key, merged)
+    }
+    fn remove_optimistic(&mut self, id: OptimisticId) {
+        self.layer.remove_optimistic(id)
+    }
+    fn watch(&mut self, selector: WatchSelector) -> (WatchId, UnboundedReceiver<WatchEvent>) {
+        self.parent.watch(selector)
+    }
+    fn unwatch(&mut self, id: WatchId) {
+        self.parent.unwatch(id)
+    }
+    fn touch_result(&mut self, key: &ResultKey) {
+        if let Some(meta) = self.result_meta_mut(key) {
+            meta.stored_at = SystemTime::now();
+        }
+    }
+    fn set_result_ttl(&mut self, key: &ResultKey, ttl: Option<Duration>) {
+        if let Some(meta) = self.result_meta_mut(key) {
+            meta.ttl = ttl;
+        }
+    }
+    fn read_result(&self, key: &ResultKey) -> Result<CachedResult, CacheError> {
+        let data = self.get_result_data(key)?;
+        let meta = self.result_meta(key).cloned().unwrap_or_default();
+        Ok(meta.cached_result(data, self.layer.ttl))
+    }
+    fn result_keys(&self) -> Vec<ResultKey> {
+        let mut keys: Vec<_> = self
+            .parent
+            .result_keys()
+            .into_iter()
+            .filter(|key| !self.removed.contains(key))
+            .collect();
+        keys.extend(
+            self.layer
+                .result_cache
+                .keys()
+                .filter(|key| self.parent_result(key).is_none())
+                .cloned(),
+        );
+        keys
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::test_support::person;
+    use crate::cache::InvalidationRule;
+
+    fn cache() -> InMemoryCache {
+        let mut cache = InMemoryCache::new();
+        cache
+            .store_result_data(&"test".into(), person("Luke"))
+            .unwrap();
+        cache
+    }
+
+    #[test]
+    fn fork_writes_are_isolated() {
+        let mut cache = cache();
+        let mut fork = cache.fork();
+        assert_eq!(
+            fork.get_identity_data(&Key::new("Person", "1"))
+                .unwrap()
+                .value()["name"],
+            json!("Luke")
+        );
+
+        fork.store_result_data(&"other".into(), person("Anakin"))
+            .unwrap();
+
+        assert_eq!(
+            fork.get_result_data(&"test".into()).unwrap(),
+            person("Anakin")
+        );
+        assert_eq!(
+            fork.parent().get_result_data(&"test".into()).unwrap(),
+            person("Luke")
+        );
+
+        fork.discard();
+
+        assert_eq!(
+            cache.get_result_data(&"test".into()).unwrap(),
+            person("Luke")
+        );
+        assert!(cache.get_result_data(&"other".into()).is_err());
+    }
+
+    #[test]
+    fn merge_fork() {
+        let mut cache = cache();
+        let mut fork = cache.fork();
+        fork.store_result_data(&"other".into(), person("Anakin"))
+            .unwrap();
+
+        fork.merge();
+
+        assert_eq!(
+            cache.get_result_data(&"test".into()).unwrap(),
+            person("Anakin")
+        );
+        assert_eq!(
+            cache.get_result_data(&"other".into()).unwrap(),
+            person("Anakin")
+        );
+    }
+
+    #[test]
+    fn merged_mutation_matches_direct_write() {
+        let todos = || {
+            Data::new(json!({ "todos": [{ "__typename": "Todo", "id": "1", "title": "a" }] }))
+                .unwrap()
+        };
+        let mutation = Data::new(json!({
+          "addTodo": { "__typename": "Todo", "id": "2", "title": "b" },
+          "rename": { "__typename": "Person", "id": "1", "name": "Anakin" }
+        }))
+        .unwrap();
+        let configured = || {
+            let mut cache = InMemoryCache::new()
+                .with_invalidation_rule(InvalidationRule::new("Todo", &["todos"]))
+                .with_ttl(Duration::ZERO);
+            cache
+                .store_result_data(&"test".into(), person("Luke"))
+                .unwrap();
+            cache.store_result_data(&"todos".into(), todos()).unwrap();
+            cache
+        };
+        let hour = Some(Duration::from_secs(3600));
+
+        let mut direct = configured();
+        direct.store_result_data(&"recent".into(), todos()).unwrap();
+        let invalidated = direct.store_mutation_data(mutation.clone()).unwrap();
+        direct.set_result_ttl(&"test".into(), hour);
+
+        let mut merged = configured();
+        let mut fork = merged.fork();
+        fork.store_result_data(&"recent".into(), todos()).unwrap();
+        assert_eq!(fork.store_mutation_data(mutation).unwrap(), invalidated);
+        assert_eq!(
+            invalidated,
+            vec![ResultKey::from("recent"), ResultKey::from("todos")]
+        );
+        assert!(fork.get_result_data(&"todos".into()).is_err());
+        assert_eq!(fork.result_keys(), vec![ResultKey::from("test")]);
+        assert!(fork.read_result(&"test".into()).unwrap().stale);
+        fork.set_result_ttl(&"test".into(), hour);
+        assert!(!fork.read_result(&"test".into()).unwrap().stale);
+        fork.merge();
+
+        assert_eq!(merged.result_keys(), direct.result_keys());
+        let key = ResultKey::from("test");
+        assert_eq!(
+            merged.read_result(&key).unwrap().data,
+            direct.read_result(&key).unwrap().data
+        );
+        assert_eq!(merged.result_meta(&key).unwrap().ttl, hour);
+        assert!(!merged.read_result(&key).unwrap().stale);
+        for key in [Key::new("Person", "1"), Key::new("Todo", "2")] {
+            assert_eq!(
+                merged.get_identity_data(&key).unwrap(),
+                direct.get_identity_data(&key).unwrap()
+            );
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use super::{references, InMemoryCache, Key, NormalizedData, ResultKey};
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn result_node(key: &ResultKey) -> String {
+    quote(&format!("result:{}", key))
+}
+
+fn entity_node(key: &Key) -> String {
+    quote(&String::from(key.clone()))
+}
+
+impl InMemoryCache {
+    pub fn export_graph(&self) -> String {
+        let mut nodes = BTreeSet::new();
+        let mut edges = BTreeSet::new();
+        let mut referenced = BTreeSet::new();
+
+        for (key, data) in &self.result_cache {
+            let node = result_node(key);
+            nodes.insert(format!(
+                "{} [shape=box, label={}]",
+                node,
+                quote(&key.to_string())
+            ));
+            edges.extend(self.graph_edges(&node, data, &mut referenced));
+        }
+        for (key, data) in &self.identity_cache {
+            let node = entity_node(key);
+            nodes.insert(format!("{} [label={}]", node, node));
+            edges.extend(self.graph_edges(&node, data, &mut referenced));
+        }
+        for key in referenced {
+            if !self.identity_cache.contains_key(&key) {
+                let node = entity_node(&key);
+                nodes.insert(format!("{} [label={}, style=dashed]", node, node));
+            }
+        }
+
+        let mut dot = "digraph cache {\n".to_string();
+        for line in nodes.iter().chain(edges.iter()) {
+            writeln!(dot, "    {};", line).unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    fn graph_edges(
+        &self,
+        from: &str,
+        data: &NormalizedData,
+        referenced: &mut BTreeSet<Key>,
+    ) -> Vec<String> {
+        references(data)
+            .into_iter()
+            .filter_map(|(path, reference)| {
+                let key: Key = serde_json::from_value(reference.clone()).ok()?;
+                let edge = format!(
+                    "{} -> {} [label={}]",
+                    from,
+                    entity_node(&key),
+                    quote(path.trim_start_matches("root > "))
+                );
+                referenced.insert(key);
+                Some(edge)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::{Cache, Data};
+    use serde_json::json;
+
+    #[test]
+    fn export_graph() {
+        let mut cache = InMemoryCache::new();
+        cache
+            .store_result_data(
+                &"test".into(),
+                Data::new(json!({
+                  "person": {
+                    "__typename": "Person",
+                    "id": "1",
+                    "homeworld": {
+                      "__typename": "Planet",
+                      "id": "2",
+                    }
+                  }
+                }))
+                .unwrap(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            cache.export_graph(),
+            r#"digraph cache {
+    "Person:1" [label="Person:1"];
+    "Planet:2" [label="Planet:2"];
+    "result:test" [shape=box, label="test"];
+    "Person:1" -> "Planet:2" [label="homeworld"];
+    "result:test" -> "Person:1" [label="person"];
+}
+"#
+        );
+    }
+
+    #[test]
+    fn export_graph_dangling_reference() {
+        let mut cache = InMemoryCache::new();
+        cache
+            .store_result_data(
+                &"test".into(),
+                Data::new(json!({
+                  "person": { "__typename": "Person", "id": "1" }
+                }))
+                .unwrap(),
+            )
+            .unwrap();
+        cache.identity_cache.clear();
+
+        assert!(cache
+            .export_graph()
+            .contains(r#""Person:1" [label="Person:1", style=dashed];"#));
+    }
+}
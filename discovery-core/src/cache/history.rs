@@ -0,0 +1,233 @@
+use im::HashMap;
+use std::collections::VecDeque;
+use std::hash::Hash;
+use std::mem;
+use std::time::SystemTime;
+
+use super::{
+    record_change, CacheError, InMemoryCache, Key, Location, NormalizedData, ResultKey, ResultMeta,
+};
+
+#[derive(Debug, Clone)]
+pub struct CacheSnapshot {
+    pub seq: u64,
+    pub timestamp: SystemTime,
+    result_cache: HashMap<ResultKey, NormalizedData>,
+    identity_cache: HashMap<Key, NormalizedData>,
+    result_meta: HashMap<ResultKey, ResultMeta>,
+}
+
+impl CacheSnapshot {
+    pub fn result_keys(&self) -> impl Iterator<Item = &ResultKey> {
+        self.result_cache.keys()
+    }
+
+    pub fn entity_keys(&self) -> impl Iterator<Item = &Key> {
+        self.identity_cache.keys()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct History {
+    capacity: usize,
+    next_seq: u64,
+    snapshots: VecDeque<CacheSnapshot>,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            next_seq: 0,
+            snapshots: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    fn push(
+        &mut self,
+        result_cache: &HashMap<ResultKey, NormalizedData>,
+        identity_cache: &HashMap<Key, NormalizedData>,
+        result_meta: &HashMap<ResultKey, ResultMeta>,
+    ) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.snapshots.len() == self.capacity {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(CacheSnapshot {
+            seq: self.next_seq,
+            timestamp: SystemTime::now(),
+            result_cache: result_cache.clone(),
+            identity_cache: identity_cache.clone(),
+            result_meta: result_meta.clone(),
+        });
+        self.next_seq += 1;
+    }
+
+    fn position(&self, seq: u64) -> Result<usize, CacheError> {
+        self.snapshots
+            .iter()
+            .position(|s| s.seq == seq)
+            .ok_or(CacheError::SnapshotNotFound(seq))
+    }
+}
+
+impl InMemoryCache {
+    pub fn with_history(mut self, capacity: usize) -> Self {
+        self.history = Some(History::new(capacity));
+        self
+    }
+
+    pub fn snapshots(&self) -> impl Iterator<Item = &CacheSnapshot> {
+        self.history.iter().flat_map(|h| h.snapshots.iter())
+    }
+
+    pub fn snapshot_at(&self, seq: u64) -> Result<InMemoryCache, CacheError> {
+        let history = self
+            .history
+            .as_ref()
+            .ok_or(CacheError::SnapshotNotFound(seq))?;
+        let snapshot = &history.snapshots[history.position(seq)?];
+        let mut cache = InMemoryCache::new().with_max_depth(self.max_depth);
+        cache.result_cache = snapshot.result_cache.clone();
+        cache.identity_cache = snapshot.identity_cache.clone();
+        cache.result_meta = snapshot.result_meta.clone();
+        Ok(cache)
+    }
+
+    pub fn rollback(&mut self, seq: u64) -> Result<(), CacheError> {
+        let history = self
+            .history
+            .as_mut()
+            .ok_or(CacheError::SnapshotNotFound(seq))?;
+        let position = history.position(seq)?;
+        let snapshot = history.snapshots.drain(position..).next().unwrap();
+        self.result_meta = snapshot.result_meta;
+        let identity_cache = mem::replace(&mut self.identity_cache, snapshot.identity_cache);
+        for key in changed_keys(&identity_cache, &self.identity_cache) {
+            let after = self.identity_cache.get(&key);
+            record_change(
+                &mut self.event_log,
+                &mut self.watchers,
+                Location::Entity(key.clone()),
+                identity_cache.get(&key),
+                after,
+            );
+        }
+        let result_cache = mem::replace(&mut self.result_cache, snapshot.result_cache);
+        for key in changed_keys(&result_cache, &self.result_cache) {
+            let after = self.result_cache.get(&key);
+            record_change(
+                &mut self.event_log,
+                &mut self.watchers,
+                Location::Result(key.clone()),
+                result_cache.get(&key),
+                after,
+            );
+        }
+        Ok(())
+    }
+
+    pub(crate) fn checkpoint(&mut self) {
+        if let Some(history) = self.history.as_mut() {
+            history.push(&self.result_cache, &self.identity_cache, &self.result_meta);
+        }
+    }
+}
+
+fn changed_keys<K: Clone + Eq + Hash>(
+    before: &HashMap<K, NormalizedData>,
+    after: &HashMap<K, NormalizedData>,
+) -> Vec<K> {
+    before
+        .keys()
+        .filter(|key| !after.contains_key(*key))
+        .chain(
+            after
+                .iter()
+                .filter(|(key, data)| before.get(*key) != Some(*data))
+                .map(|(key, _)| key),
+        )
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::test_support::person;
+    use crate::cache::{Cache, WatchSelector};
+    use serde_json::json;
+
+    #[test]
+    fn rollback_to_snapshot() {
+        let mut cache = InMemoryCache::new().with_history(4);
+        let key = "test".into();
+        cache.store_result_data(&key, person("Luke")).unwrap();
+        cache.store_result_data(&key, person("Anakin")).unwrap();
+
+        let seqs: Vec<_> = cache.snapshots().map(|s| s.seq).collect();
+        assert_eq!(seqs, vec![0, 1]);
+
+        cache.rollback(1).unwrap();
+
+        assert_eq!(cache.get_result_data(&key).unwrap(), person("Luke"));
+        assert_eq!(cache.snapshots().count(), 1);
+    }
+
+    #[test]
+    fn rollback_restores_metadata_and_notifies_watchers() {
+        let mut cache = InMemoryCache::new().with_history(4);
+        let key: ResultKey = "test".into();
+        cache.store_result_data(&key, person("Luke")).unwrap();
+        let stored_at = cache.result_meta(&key).unwrap().stored_at;
+        cache.set_result_ttl(&key, Some(std::time::Duration::from_secs(60)));
+        let (_, mut receiver) = cache.watch(WatchSelector::parse("Person:1 > name").unwrap());
+        cache.store_result_data(&key, person("Anakin")).unwrap();
+        assert!(receiver.try_recv().is_ok());
+
+        cache.rollback(1).unwrap();
+
+        let meta = cache.result_meta(&key).unwrap();
+        assert_eq!(meta.stored_at, stored_at);
+        assert_eq!(meta.ttl, Some(std::time::Duration::from_secs(60)));
+        let event = receiver.try_recv().unwrap();
+        assert_eq!(
+            (event.before, event.after),
+            (Some(json!("Anakin")), Some(json!("Luke")))
+        );
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn inspect_snapshot() {
+        let mut cache = InMemoryCache::new().with_history(4);
+        let key = "test".into();
+        cache.store_result_data(&key, person("Luke")).unwrap();
+
+        let empty = cache.snapshot_at(0).unwrap();
+
+        assert!(matches!(
+            empty.get_result_data(&key),
+            Err(CacheError::ResultKeyNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn bounded_snapshots() {
+        let mut cache = InMemoryCache::new().with_history(2);
+        for name in ["a", "b", "c"] {
+            cache
+                .store_result_data(&"test".into(), person(name))
+                .unwrap();
+        }
+
+        assert!(matches!(
+            cache.rollback(0),
+            Err(CacheError::SnapshotNotFound(0))
+        ));
+        cache.rollback(1).unwrap();
+        assert_eq!(cache.get_result_data(&"test".into()).unwrap(), person("a"));
+    }
+}
@@ -0,0 +1,155 @@
+use serde_json::Value as JsonValue;
+use std::collections::HashSet;
+
+use super::{Data, InMemoryCache, NormalizedData, ResultKey, TYPENAME};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidationRule {
+    pub typename: String,
+    pub root_fields: Vec<String>,
+}
+
+impl InvalidationRule {
+    pub fn new(typename: impl Into<String>, root_fields: &[&str]) -> Self {
+        Self {
+            typename: typename.into(),
+            root_fields: root_fields.iter().map(|f| f.to_string()).collect(),
+        }
+    }
+}
+
+fn typenames(value: &JsonValue) -> HashSet<&str> {
+    let mut typenames = HashSet::new();
+    let mut stack = vec![value];
+    while let Some(value) = stack.pop() {
+        match value {
+            JsonValue::Object(obj) => {
+                if let Some(typename) = obj.get(TYPENAME).and_then(|t| t.as_str()) {
+                    typenames.insert(typename);
+                }
+                stack.extend(obj.values());
+            }
+            JsonValue::Array(arr) => stack.extend(arr),
+            _ => {}
+        }
+    }
+    typenames
+}
+
+impl InMemoryCache {
+    pub fn with_invalidation_rule(mut self, rule: InvalidationRule) -> Self {
+        self.invalidation_rules.push(rule);
+        self
+    }
+
+    pub fn add_invalidation_rule(&mut self, rule: InvalidationRule) {
+        self.invalidation_rules.push(rule);
+    }
+
+    pub fn invalidate_root_fields(&mut self, root_fields: &[&str]) -> Vec<ResultKey> {
+        let keys = self.results_with_root_fields(root_fields);
+        self.remove_results(keys)
+    }
+
+    pub fn invalidate_after_mutation(&mut self, data: &Data) -> Vec<ResultKey> {
+        let keys = self.results_invalidated_by(data);
+        self.remove_results(keys)
+    }
+
+    pub(super) fn results_invalidated_by(&self, data: &Data) -> Vec<ResultKey> {
+        let typenames = typenames(data.value());
+        let root_fields: HashSet<_> = self
+            .invalidation_rules
+            .iter()
+            .filter(|rule| typenames.contains(rule.typename.as_str()))
+            .flat_map(|rule| rule.root_fields.iter().cloned())
+            .collect();
+        let root_fields: Vec<_> = root_fields.iter().map(String::as_str).collect();
+        self.results_with_root_fields(&root_fields)
+    }
+
+    fn results_with_root_fields(&self, root_fields: &[&str]) -> Vec<ResultKey> {
+        let mut keys: Vec<_> = self
+            .result_cache
+            .iter()
+            .filter(|(_, data)| match data {
+                NormalizedData::Object(obj) => root_fields.iter().any(|f| obj.contains_key(*f)),
+                NormalizedData::Array(_) => false,
+            })
+            .map(|(key, _)| key.clone())
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    fn remove_results(&mut self, keys: Vec<ResultKey>) -> Vec<ResultKey> {
+        if !keys.is_empty() {
+            self.checkpoint();
+        }
+        for key in &keys {
+            self.remove_result(key);
+        }
+        keys
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::{Cache, CacheError};
+    use serde_json::json;
+
+    fn cache() -> InMemoryCache {
+        let mut cache =
+            InMemoryCache::new().with_invalidation_rule(InvalidationRule::new("Todo", &["todos"]));
+        cache
+            .store_result_data(
+                &"todos".into(),
+                Data::new(json!({
+                  "todos": [{ "__typename": "Todo", "id": "1", "title": "a" }]
+                }))
+                .unwrap(),
+            )
+            .unwrap();
+        cache
+            .store_result_data(
+                &"me".into(),
+                Data::new(json!({
+                  "me": { "__typename": "User", "id": "1" }
+                }))
+                .unwrap(),
+            )
+            .unwrap();
+        cache
+    }
+
+    #[test]
+    fn mutation_invalidates_matching_root_fields() {
+        let mut cache = cache();
+        let mutation = Data::new(json!({
+          "addTodo": { "__typename": "Todo", "id": "2", "title": "b" }
+        }))
+        .unwrap();
+
+        let invalidated = cache.invalidate_after_mutation(&mutation);
+
+        assert_eq!(invalidated, vec![ResultKey::from("todos")]);
+        assert!(matches!(
+            cache.get_result_data(&"todos".into()),
+            Err(CacheError::ResultKeyNotFound(_))
+        ));
+        assert!(cache.get_result_data(&"me".into()).is_ok());
+    }
+
+    #[test]
+    fn unrelated_mutation_keeps_results() {
+        let mut cache = cache();
+        let mutation = Data::new(json!({
+          "updateUser": { "__typename": "User", "id": "1" }
+        }))
+        .unwrap();
+
+        assert!(cache.invalidate_after_mutation(&mutation).is_empty());
+        assert!(cache.get_result_data(&"todos".into()).is_ok());
+    }
+}
@@ -0,0 +1,124 @@
+use serde_json::{Map, Value as JsonValue};
+
+use super::{
+    denormalize_data, CacheError, Data, InMemoryCache, Key, NormalizedData, ResultKey, LOCAL,
+};
+
+impl InMemoryCache {
+    pub fn write_local_field(&mut self, key: &Key, field: &str, value: JsonValue) {
+        self.checkpoint();
+        let mut entity = match self.identity_cache.get(key) {
+            Some(NormalizedData::Object(obj)) => obj.clone(),
+            _ => Map::from_iter([(
+                Key::field_name().to_string(),
+                JsonValue::String(key.id().to_string()),
+            )]),
+        };
+        if let JsonValue::Object(local) = entity
+            .entry(LOCAL)
+            .or_insert_with(|| JsonValue::Object(Map::new()))
+        {
+            local.insert(field.to_string(), value);
+        }
+        self.write_identity(key, NormalizedData::Object(entity));
+    }
+
+    pub fn read_local_field(&self, key: &Key, field: &str) -> Option<&JsonValue> {
+        match self.identity_cache.get(key) {
+            Some(NormalizedData::Object(obj)) => obj.get(LOCAL)?.get(field),
+            _ => None,
+        }
+    }
+
+    // Local fields are only merged into entities when the reader selects them.
+    pub fn get_result_data_with_local(
+        &self,
+        key: &ResultKey,
+        fields: &[&str],
+    ) -> Result<Data, CacheError> {
+        let normalized_data = self
+            .result_cache
+            .get(key)
+            .ok_or_else(|| CacheError::ResultKeyNotFound(key.clone()))?;
+        Ok(Data(denormalize_data(
+            normalized_data.clone().into(),
+            self,
+            &self.read_policies,
+            self.max_depth,
+            fields,
+        )?))
+    }
+
+    pub fn remove_local_field(&mut self, key: &Key, field: &str) -> Option<JsonValue> {
+        let mut entity = match self.identity_cache.get(key) {
+            Some(NormalizedData::Object(obj)) => obj.clone(),
+            _ => return None,
+        };
+        let removed = match entity.get_mut(LOCAL) {
+            Some(JsonValue::Object(local)) => local.remove(field)?,
+            _ => return None,
+        };
+        self.checkpoint();
+        self.write_identity(key, NormalizedData::Object(entity));
+        Some(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::test_support::person;
+    use crate::cache::Cache;
+    use serde_json::json;
+
+    #[test]
+    fn local_fields_survive_renormalization() {
+        let mut cache = InMemoryCache::new();
+        let key = Key::new("Person", "1");
+        cache
+            .store_result_data(&"test".into(), person("Luke"))
+            .unwrap();
+
+        cache.write_local_field(&key, "isSelected", json!(true));
+        cache
+            .store_result_data(&"test".into(), person("Anakin"))
+            .unwrap();
+
+        assert_eq!(
+            cache.read_local_field(&key, "isSelected"),
+            Some(&json!(true))
+        );
+        assert_eq!(
+            cache.get_result_data(&"test".into()).unwrap(),
+            person("Anakin")
+        );
+        assert_eq!(
+            cache
+                .get_result_data_with_local(&"test".into(), &["isSelected"])
+                .unwrap()
+                .value(),
+            &json!({
+              "person": {
+                "__typename": "Person",
+                "id": "1",
+                "name": "Anakin",
+                "isSelected": true,
+              }
+            })
+        );
+    }
+
+    #[test]
+    fn remove_local_field() {
+        let mut cache = InMemoryCache::new();
+        let key = Key::new("Person", "1");
+        cache.write_local_field(&key, "isSelected", json!(true));
+
+        assert_eq!(
+            cache.remove_local_field(&key, "isSelected"),
+            Some(json!(true))
+        );
+        assert_eq!(cache.read_local_field(&key, "isSelected"), None);
+        assert_eq!(cache.remove_local_field(&key, "isSelected"), None);
+    }
+}
@@ -1,21 +1,137 @@
-use rstest::rstest;
+use futures::channel::mpsc::UnboundedReceiver;
+use im::HashMap;
 use serde::{Deserialize, Serialize};
-use serde_json::{json, Map, Value as JsonValue, Value};
-use std::cell::{Cell, RefCell};
-use std::collections::HashMap;
+use serde_json::{json, Map, Value as JsonValue};
 use std::fmt::{Display, Formatter};
-use std::rc::Rc;
+use std::time::{Duration, SystemTime};
 use thiserror::Error;
 
-const TYPENAME: &'static str = "__typename";
-const REF: &'static str = "__ref";
+mod event_log;
+mod fork;
+mod graph;
+mod history;
+mod invalidation;
+mod local;
+mod optimistic;
+mod policy;
+mod shared;
+mod staleness;
+mod verify;
+mod watch;
 
-pub type ResultKey = String;
+#[cfg(test)]
+pub(crate) mod test_support {
+    use super::Data;
+    use serde_json::json;
+
+    pub(crate) fn person(name: &str) -> Data {
+        Data::new(json!({
+          "person": {
+            "__typename": "Person",
+            "id": "1",
+            "name": name,
+          }
+        }))
+        .unwrap()
+    }
+
+    pub(crate) fn person_with_height(name: &str, height: u32) -> Data {
+        let mut value = person(name).value().clone();
+        value["person"]["height"] = json!(height);
+        Data::new(value).unwrap()
+    }
+}
+
+pub use event_log::{CacheEvent, CacheOperation, EventLog, FieldChange};
+pub use fork::ForkedCache;
+pub use history::{CacheSnapshot, History};
+pub use invalidation::InvalidationRule;
+pub use optimistic::OptimisticId;
+pub use policy::{
+    concat_list, relay_connection, MergeFunction, MergePolicies, ReadFunction, ReadPolicies,
+};
+pub use shared::{ReadSnapshot, SharedCache};
+pub use staleness::{CachedResult, ResultMeta};
+pub use verify::{Location, VerifyIssue, VerifyReport};
+pub use watch::{WatchEvent, WatchId, WatchSelector};
+
+use optimistic::OptimisticLayer;
+use watch::{notify_watchers, Watcher};
+
+const TYPENAME: &str = "__typename";
+const REF: &str = "__ref";
+const LOCAL: &str = "__local";
+pub const DEFAULT_MAX_DEPTH: usize = 1024;
+
+pub type Namespace = String;
+
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct ResultKey {
+    namespace: Option<Namespace>,
+    key: String,
+}
+
+impl ResultKey {
+    pub fn new(key: impl Into<String>) -> Self {
+        Self {
+            namespace: None,
+            key: key.into(),
+        }
+    }
+
+    pub fn namespaced(namespace: impl Into<Namespace>, key: impl Into<String>) -> Self {
+        Self {
+            namespace: Some(namespace.into()),
+            key: key.into(),
+        }
+    }
+
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    pub fn key(&self) -> &str {
+        self.key.as_str()
+    }
+}
+
+impl From<String> for ResultKey {
+    fn from(key: String) -> Self {
+        Self::new(key)
+    }
+}
+
+impl From<&str> for ResultKey {
+    fn from(key: &str) -> Self {
+        Self::new(key)
+    }
+}
+
+impl Display for ResultKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.namespace {
+            Some(namespace) => write!(f, "{}/{}", namespace, self.key),
+            None => write!(f, "{}", self.key),
+        }
+    }
+}
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct InMemoryCache {
     result_cache: HashMap<ResultKey, NormalizedData>,
     identity_cache: HashMap<Key, NormalizedData>,
+    max_depth: usize,
+    event_log: Option<EventLog>,
+    history: Option<History>,
+    read_policies: ReadPolicies,
+    merge_policies: MergePolicies,
+    invalidation_rules: Vec<InvalidationRule>,
+    ttl: Option<Duration>,
+    result_meta: HashMap<ResultKey, ResultMeta>,
+    watchers: Vec<Watcher>,
+    next_watch_id: WatchId,
+    optimistic_layers: Vec<OptimisticLayer>,
+    next_optimistic_id: OptimisticId,
 }
 
 impl InMemoryCache {
@@ -23,7 +139,175 @@ impl InMemoryCache {
         InMemoryCache {
             result_cache: HashMap::new(),
             identity_cache: HashMap::new(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            event_log: None,
+            history: None,
+            read_policies: ReadPolicies::default(),
+            merge_policies: MergePolicies::default(),
+            invalidation_rules: vec![],
+            ttl: None,
+            result_meta: HashMap::new(),
+            watchers: vec![],
+            next_watch_id: 0,
+            optimistic_layers: vec![],
+            next_optimistic_id: 0,
+        }
+    }
+
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn with_event_log(mut self, capacity: usize) -> Self {
+        self.event_log = Some(EventLog::new(capacity));
+        self
+    }
+
+    pub fn event_log(&self) -> Option<&EventLog> {
+        self.event_log.as_ref()
+    }
+
+    pub fn clear_namespace(&mut self, namespace: &str) {
+        self.checkpoint();
+        let result_keys: Vec<_> = self
+            .result_cache
+            .keys()
+            .filter(|k| k.namespace() == Some(namespace))
+            .cloned()
+            .collect();
+        for key in result_keys {
+            self.remove_result(&key);
+        }
+        let keys: Vec<_> = self
+            .identity_cache
+            .keys()
+            .filter(|k| k.namespace() == Some(namespace))
+            .cloned()
+            .collect();
+        for key in keys {
+            let prev = self.identity_cache.remove(&key);
+            record_change(
+                &mut self.event_log,
+                &mut self.watchers,
+                Location::Entity(key),
+                prev.as_ref(),
+                None,
+            );
+        }
+    }
+
+    pub fn remove_result(&mut self, key: &ResultKey) -> Option<NormalizedData> {
+        let prev = self.result_cache.remove(key);
+        self.result_meta.remove(key);
+        record_change(
+            &mut self.event_log,
+            &mut self.watchers,
+            Location::Result(key.clone()),
+            prev.as_ref(),
+            None,
+        );
+        prev
+    }
+
+    fn normalize_entities(
+        &mut self,
+        namespace: Option<&Namespace>,
+        data: &Data,
+    ) -> Result<NormalizedData, CacheError> {
+        let (normalized, entities) = self.normalize_document(namespace, data)?;
+        for (identity_key, value) in entities {
+            self.write_identity(&identity_key, value);
+        }
+        Ok(normalized)
+    }
+
+    fn normalize_document(
+        &self,
+        namespace: Option<&Namespace>,
+        data: &Data,
+    ) -> Result<(NormalizedData, Vec<(Key, NormalizedData)>), CacheError> {
+        let mut normalized_data_list = vec![];
+        let normalized = match &data.0 {
+            JsonValue::Object(obj) => NormalizedData::Object(
+                obj.iter()
+                    .map(|(k, v)| {
+                        Ok((
+                            k.clone(),
+                            normalize_data(
+                                v,
+                                namespace,
+                                self.max_depth,
+                                &mut normalized_data_list,
+                            )?,
+                        ))
+                    })
+                    .collect::<Result<_, CacheError>>()?,
+            ),
+            JsonValue::Array(arr) => NormalizedData::Array(
+                arr.iter()
+                    .map(|v| {
+                        normalize_data(v, namespace, self.max_depth, &mut normalized_data_list)
+                    })
+                    .collect::<Result<_, CacheError>>()?,
+            ),
+            _ => unreachable!(),
+        };
+
+        let entities = normalized_data_list
+            .into_iter()
+            .map(|(key, value)| (key, NormalizedData::try_from(value).unwrap()))
+            .collect();
+        Ok((normalized, entities))
+    }
+
+    fn write_result(&mut self, key: &ResultKey, data: NormalizedData) {
+        let prev = self.result_cache.insert(key.clone(), data);
+        self.result_meta.entry(key.clone()).or_default().stored_at = SystemTime::now();
+        record_change(
+            &mut self.event_log,
+            &mut self.watchers,
+            Location::Result(key.clone()),
+            prev.as_ref(),
+            self.result_cache.get(key),
+        );
+    }
+
+    fn write_identity(&mut self, key: &Key, mut data: NormalizedData) {
+        if let (NormalizedData::Object(obj), Some(NormalizedData::Object(prev))) =
+            (&mut data, self.identity_cache.get(key))
+        {
+            if let Some(local) = prev.get(LOCAL).filter(|_| !obj.contains_key(LOCAL)) {
+                obj.insert(LOCAL.to_string(), local.clone());
+            }
         }
+        let prev = self.identity_cache.insert(key.clone(), data);
+        record_change(
+            &mut self.event_log,
+            &mut self.watchers,
+            Location::Entity(key.clone()),
+            prev.as_ref(),
+            self.identity_cache.get(key),
+        );
+    }
+}
+
+fn record_change(
+    event_log: &mut Option<EventLog>,
+    watchers: &mut Vec<Watcher>,
+    location: Location,
+    before: Option<&NormalizedData>,
+    after: Option<&NormalizedData>,
+) {
+    notify_watchers(watchers, &location, before, after);
+    if let Some(log) = event_log.as_mut() {
+        log.record(location, before, after);
+    }
+}
+
+impl Default for InMemoryCache {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -35,8 +319,33 @@ pub trait Cache {
         data: Data,
     ) -> Result<NormalizedData, CacheError>;
     fn get_result_data(&self, key: &ResultKey) -> Result<Data, CacheError>;
+    fn merge_result_data(
+        &mut self,
+        key: &ResultKey,
+        data: Data,
+    ) -> Result<NormalizedData, CacheError> {
+        self.store_result_data(key, data)
+    }
+    fn store_mutation_data(&mut self, data: Data) -> Result<Vec<ResultKey>, CacheError>;
     fn store_identity_data(&mut self, key: &Key, data: NormalizedData) -> Result<(), CacheError>;
     fn get_identity_data(&self, key: &Key) -> Result<Data, CacheError>;
+    fn get_identity_entry(&self, key: &Key) -> Result<NormalizedData, CacheError>;
+    fn write_optimistic(&mut self, data: Data) -> Result<OptimisticId, CacheError>;
+    fn remove_optimistic(&mut self, id: OptimisticId);
+    fn watch(&mut self, selector: WatchSelector) -> (WatchId, UnboundedReceiver<WatchEvent>);
+    fn unwatch(&mut self, id: WatchId);
+    fn touch_result(&mut self, _key: &ResultKey) {}
+    fn set_result_ttl(&mut self, _key: &ResultKey, _ttl: Option<Duration>) {}
+    fn result_keys(&self) -> Vec<ResultKey> {
+        vec![]
+    }
+    fn read_result(&self, key: &ResultKey) -> Result<CachedResult, CacheError> {
+        Ok(CachedResult {
+            data: self.get_result_data(key)?,
+            age: Duration::ZERO,
+            stale: false,
+        })
+    }
 }
 
 #[derive(Debug, Error)]
@@ -47,10 +356,14 @@ pub enum CacheError {
     KeyNotFound(Key),
     #[error("expect has \"{}\"", REF)]
     ExpectHasReference(JsonValue),
+    #[error("document exceeds max depth {0}")]
+    DepthLimitExceeded(usize),
+    #[error("snapshot not found")]
+    SnapshotNotFound(u64),
 }
 
 impl Cache for InMemoryCache {
-    fn identify(&self, data: &Data) -> Key {
+    fn identify(&self, _data: &Data) -> Key {
         todo!()
     }
     fn store_result_data(
@@ -58,126 +371,160 @@ impl Cache for InMemoryCache {
         key: &ResultKey,
         data: Data,
     ) -> Result<NormalizedData, CacheError> {
-        let mut normalized_data_list = vec![];
-        let normalized = match &data.0 {
-            JsonValue::Object(obj) => NormalizedData::Object(
-                obj.iter()
-                    .map(|(k, v)| {
-                        (
-                            k.clone(),
-                            normalize_data::<Self>(v, &mut normalized_data_list),
-                        )
-                    })
-                    .collect(),
-            ),
-            JsonValue::Array(arr) => NormalizedData::Array(
-                arr.iter()
-                    .map(|v| normalize_data::<Self>(v, &mut normalized_data_list))
-                    .collect(),
-            ),
-            _ => unreachable!(),
-        };
-
-        for (key, value) in normalized_data_list {
-            self.store_identity_data(&key, NormalizedData::try_from(value).unwrap());
-        }
-        let _prev = self.result_cache.insert(key.clone(), normalized.clone());
+        self.checkpoint();
+        let normalized = self.normalize_entities(key.namespace.as_ref(), &data)?;
+        self.write_result(key, normalized.clone());
         Ok(normalized)
     }
+    fn merge_result_data(
+        &mut self,
+        key: &ResultKey,
+        data: Data,
+    ) -> Result<NormalizedData, CacheError> {
+        let merged = match self.get_result_data(key) {
+            Ok(existing) => Data(self.merge_policies.merge("Query", existing.0, data.0)),
+            Err(_) => data,
+        };
+        self.store_result_data(key, merged)
+    }
+    fn store_mutation_data(&mut self, data: Data) -> Result<Vec<ResultKey>, CacheError> {
+        self.checkpoint();
+        self.normalize_entities(None, &data)?;
+        Ok(self.invalidate_after_mutation(&data))
+    }
     fn get_result_data(&self, key: &ResultKey) -> Result<Data, CacheError> {
         let normalized_data = self
             .result_cache
             .get(key)
             .ok_or_else(|| CacheError::ResultKeyNotFound(key.clone()))?;
 
-        let data = match normalized_data {
-            NormalizedData::Object(obj) => Data(JsonValue::Object(
-                obj.iter()
-                    .map(|(k, v)| Ok((k.clone(), denormalize_data::<Self>(v, self)?)))
-                    .collect::<Result<_, CacheError>>()?,
-            )),
-            NormalizedData::Array(arr) => Data(JsonValue::Array(
-                arr.iter()
-                    .map(|v| denormalize_data::<Self>(v, &self))
-                    .collect::<Result<_, CacheError>>()?,
-            )),
-            _ => unreachable!(),
-        };
-        Ok(data)
+        Ok(Data(denormalize_data(
+            normalized_data.clone().into(),
+            self,
+            &self.read_policies,
+            self.max_depth,
+            &[],
+        )?))
     }
     fn store_identity_data(&mut self, key: &Key, data: NormalizedData) -> Result<(), CacheError> {
-        let _prev = self.identity_cache.insert(key.clone(), data);
+        self.checkpoint();
+        self.write_identity(key, data);
         Ok(())
     }
     fn get_identity_data(&self, key: &Key) -> Result<Data, CacheError> {
-        let normalized_data = self
-            .identity_cache
-            .get(key)
-            .ok_or_else(|| CacheError::KeyNotFound(key.clone()))?;
-
-        let data = match normalized_data {
-            NormalizedData::Object(obj) => Data(JsonValue::Object(
-                obj.iter()
-                    .map(|(k, v)| {
-                        (
-                            k.clone(),
-                            denormalize_data::<Self>(v, self).unwrap().clone(),
-                        )
-                    })
-                    .chain([(
-                        TYPENAME.to_string(),
-                        JsonValue::String(key.typename().to_string()),
-                    )])
-                    .collect(),
-            )),
-            NormalizedData::Array(arr) => Data(JsonValue::Array(
-                arr.iter()
-                    .map(|v| denormalize_data::<Self>(v, &self).unwrap())
-                    .collect(),
-            )),
-            _ => unreachable!(),
-        };
-        Ok(data)
+        Ok(Data(denormalize_data(
+            json!({ REF: key }),
+            self,
+            &self.read_policies,
+            self.max_depth,
+            &[],
+        )?))
+    }
+    fn read_result(&self, key: &ResultKey) -> Result<CachedResult, CacheError> {
+        let data = self.get_result_data(key)?;
+        let meta = self.result_meta.get(key).cloned().unwrap_or_default();
+        Ok(meta.cached_result(data, self.ttl))
+    }
+    fn touch_result(&mut self, key: &ResultKey) {
+        if let Some(meta) = self.result_meta.get_mut(key) {
+            meta.stored_at = SystemTime::now();
+        }
+    }
+    fn set_result_ttl(&mut self, key: &ResultKey, ttl: Option<Duration>) {
+        if let Some(meta) = self.result_meta.get_mut(key) {
+            meta.ttl = ttl;
+        }
+    }
+    fn result_keys(&self) -> Vec<ResultKey> {
+        self.result_cache.keys().cloned().collect()
+    }
+    fn get_identity_entry(&self, key: &Key) -> Result<NormalizedData, CacheError> {
+        self.effective_identity(key)
+            .cloned()
+            .ok_or_else(|| CacheError::KeyNotFound(key.clone()))
+    }
+    fn write_optimistic(&mut self, data: Data) -> Result<OptimisticId, CacheError> {
+        self.push_optimistic_layer(&data)
+    }
+    fn remove_optimistic(&mut self, id: OptimisticId) {
+        self.pop_optimistic_layer(id);
+    }
+    fn watch(&mut self, selector: WatchSelector) -> (WatchId, UnboundedReceiver<WatchEvent>) {
+        InMemoryCache::watch(self, selector)
+    }
+    fn unwatch(&mut self, id: WatchId) {
+        InMemoryCache::unwatch(self, id)
     }
 }
 
 type GraphQLType = String;
 type Id = String;
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
 #[serde(into = "String", try_from = "String")]
-pub struct Key(GraphQLType, Id);
+pub struct Key(GraphQLType, Id, Option<Namespace>);
 
 impl TryFrom<String> for Key {
     type Error = String;
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
-        let sp: Vec<_> = value.split(":").collect();
+        let sp: Vec<_> = value.split(':').collect();
         if sp.len() != 2 {
             return Err("error".to_string());
         }
-        Ok(Key(sp[0].to_string(), sp[1].to_string()))
+        Ok(match sp[0].rsplit_once('/') {
+            Some((namespace, typename)) => Key(
+                typename.to_string(),
+                sp[1].to_string(),
+                Some(namespace.to_string()),
+            ),
+            None => Key(sp[0].to_string(), sp[1].to_string(), None),
+        })
     }
 }
 
-impl Into<String> for Key {
-    fn into(self) -> String {
-        format!("{}:{}", self.0, self.1)
+impl From<Key> for String {
+    fn from(key: Key) -> String {
+        match key.2 {
+            Some(namespace) => format!("{}/{}:{}", namespace, key.0, key.1),
+            None => format!("{}:{}", key.0, key.1),
+        }
     }
 }
 
 impl Key {
+    pub fn new(typename: impl Into<GraphQLType>, id: impl Into<Id>) -> Self {
+        Key(typename.into(), id.into(), None)
+    }
+
+    pub fn with_namespace(mut self, namespace: impl Into<Namespace>) -> Self {
+        self.2 = Some(namespace.into());
+        self
+    }
+
     pub fn field_name() -> &'static str {
         "id"
     }
 
+    pub(crate) fn id_of(value: &JsonValue) -> Option<Id> {
+        match value {
+            JsonValue::String(id) => Some(id.clone()),
+            JsonValue::Number(id) => Some(id.to_string()),
+            _ => None,
+        }
+    }
+
+    pub fn namespace(&self) -> Option<&str> {
+        self.2.as_deref()
+    }
+
     pub fn typename(&self) -> &str {
         self.0.as_str()
     }
-}
 
-trait HasKeyData {
-    fn key(&self) -> Key;
+    pub fn id(&self) -> &str {
+        self.1.as_str()
+    }
 }
 
 #[derive(Error, Debug)]
@@ -194,102 +541,228 @@ pub enum DataValidationError {
 pub struct Data(JsonValue);
 
 fn validate_data(path: &str, value: &JsonValue) -> Result<(), DataValidationError> {
-    match value {
-        JsonValue::Object(obj) => {
-            if let Some(_) = obj.get(Key::field_name()) {
-                let _ = obj
-                    .get(TYPENAME)
-                    .ok_or_else(|| {
-                        DataValidationError::NotHasTypenameWhenHasId(
-                            path.to_string(),
-                            value.clone(),
-                        )
-                    })?
-                    .as_str()
-                    .ok_or_else(|| {
-                        DataValidationError::TypeNameIsNotString(path.to_string(), value.clone())
-                    })?
-                    .to_string();
-            }
+    let mut stack = vec![(path.to_string(), value)];
+    while let Some((path, value)) = stack.pop() {
+        match value {
+            JsonValue::Object(obj) => {
+                if obj.get(Key::field_name()).is_some() {
+                    obj.get(TYPENAME)
+                        .ok_or_else(|| {
+                            DataValidationError::NotHasTypenameWhenHasId(
+                                path.clone(),
+                                value.clone(),
+                            )
+                        })?
+                        .as_str()
+                        .ok_or_else(|| {
+                            DataValidationError::TypeNameIsNotString(path.clone(), value.clone())
+                        })?;
+                }
 
-            for (k, v) in obj {
-                if !k.starts_with("__") {
-                    validate_data(format!("{} > {}", path, k).as_str(), v)?;
+                for (k, v) in obj.iter().rev() {
+                    if !k.starts_with("__") {
+                        stack.push((format!("{} > {}", path, k), v));
+                    }
                 }
             }
-        }
-        JsonValue::Array(arr) => {
-            for (i, v) in arr.iter().enumerate() {
-                validate_data(format!("{} > {}", path, i).as_str(), v)?;
+            JsonValue::Array(arr) => {
+                for (i, v) in arr.iter().enumerate().rev() {
+                    stack.push((format!("{} > {}", path, i), v));
+                }
             }
+            _ => {}
         }
-        _ => {}
     }
     Ok(())
 }
 
-fn normalize_data<C: Cache>(
+fn references(data: &NormalizedData) -> Vec<(String, &JsonValue)> {
+    let mut stack: Vec<(String, &JsonValue)> = match data {
+        NormalizedData::Object(obj) => obj
+            .iter()
+            .rev()
+            .map(|(k, v)| (format!("root > {}", k), v))
+            .collect(),
+        NormalizedData::Array(arr) => arr
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(i, v)| (format!("root > {}", i), v))
+            .collect(),
+    };
+    let mut references = vec![];
+
+    while let Some((path, value)) = stack.pop() {
+        match value {
+            JsonValue::Object(obj) => {
+                if let Some(reference) = obj.get(REF) {
+                    references.push((path, reference));
+                } else {
+                    for (k, v) in obj.iter().rev() {
+                        stack.push((format!("{} > {}", path, k), v));
+                    }
+                }
+            }
+            JsonValue::Array(arr) => {
+                for (i, v) in arr.iter().enumerate().rev() {
+                    stack.push((format!("{} > {}", path, i), v));
+                }
+            }
+            _ => {}
+        }
+    }
+    references
+}
+
+enum NormalizeFrame<'a> {
+    Enter(&'a JsonValue, usize),
+    Object(&'a Map<String, JsonValue>, Vec<&'a String>),
+    Array(usize),
+}
+
+fn normalize_data(
     value: &JsonValue,
+    namespace: Option<&Namespace>,
+    max_depth: usize,
     normalized_data_list: &mut Vec<(Key, JsonValue)>,
-) -> JsonValue {
-    match value {
-        JsonValue::Object(obj) => {
-            let normalized_obj = obj
-                .iter()
-                .filter_map(|(k, v)| {
-                    (!k.starts_with("__"))
-                        .then(|| (k.to_string(), normalize_data::<C>(v, normalized_data_list)))
-                })
-                .collect::<JsonValue>();
-            if let Some(id) = normalized_obj
-                .get(Key::field_name())
-                .map(|x| x.as_str().unwrap())
-            {
-                let typename = obj.get(TYPENAME).unwrap().as_str().unwrap();
-                let key = Key(typename.to_string(), id.to_string());
-                normalized_data_list.push((key.clone(), normalized_obj));
-                json!({ REF: key })
-            } else {
-                obj.iter()
-                    .filter_map(|(k, v)| {
-                        (!k.starts_with("__"))
-                            .then(|| (k, normalize_data::<C>(v, normalized_data_list)))
-                    })
-                    .collect()
+) -> Result<JsonValue, CacheError> {
+    let mut frames = vec![NormalizeFrame::Enter(value, 1)];
+    let mut results: Vec<JsonValue> = vec![];
+
+    while let Some(frame) = frames.pop() {
+        match frame {
+            NormalizeFrame::Enter(value, depth) => {
+                if depth > max_depth {
+                    return Err(CacheError::DepthLimitExceeded(max_depth));
+                }
+                match value {
+                    JsonValue::Object(obj) => {
+                        let keys: Vec<_> = obj.keys().filter(|k| !k.starts_with("__")).collect();
+                        let children: Vec<_> = keys.iter().map(|k| &obj[k.as_str()]).collect();
+                        frames.push(NormalizeFrame::Object(obj, keys));
+                        for v in children.into_iter().rev() {
+                            frames.push(NormalizeFrame::Enter(v, depth + 1));
+                        }
+                    }
+                    JsonValue::Array(arr) => {
+                        frames.push(NormalizeFrame::Array(arr.len()));
+                        for v in arr.iter().rev() {
+                            frames.push(NormalizeFrame::Enter(v, depth + 1));
+                        }
+                    }
+                    _ => results.push(value.clone()),
+                }
+            }
+            NormalizeFrame::Object(obj, keys) => {
+                let values = results.split_off(results.len() - keys.len());
+                let normalized_obj: Map<String, JsonValue> =
+                    keys.into_iter().cloned().zip(values).collect();
+                if let Some(id) = normalized_obj.get(Key::field_name()).and_then(Key::id_of) {
+                    let typename = obj.get(TYPENAME).unwrap().as_str().unwrap();
+                    let key = Key(typename.to_string(), id, namespace.cloned());
+                    normalized_data_list.push((key.clone(), JsonValue::Object(normalized_obj)));
+                    results.push(json!({ REF: key }));
+                } else {
+                    results.push(JsonValue::Object(normalized_obj));
+                }
+            }
+            NormalizeFrame::Array(len) => {
+                let values = results.split_off(results.len() - len);
+                results.push(JsonValue::Array(values));
             }
         }
-        JsonValue::Array(arr) => arr
-            .iter()
-            .map(|v| normalize_data::<C>(v, normalized_data_list))
-            .collect::<JsonValue>(),
-        _ => value.clone(),
     }
+
+    Ok(results.pop().unwrap())
 }
 
-fn denormalize_data<C: Cache>(value: &JsonValue, cache: &C) -> Result<JsonValue, CacheError> {
-    match value {
-        JsonValue::Object(obj) => {
-            if let Some(reference) = obj.get(REF) {
-                let key: Key = serde_json::from_value(reference.clone())
-                    .map_err(|_| CacheError::ExpectHasReference(value.clone()))?;
-                let data = cache.get_identity_data(&key)?;
-                Ok(data.0)
-            } else {
-                obj.iter()
-                    .map(|(k, v)| Ok((k.clone(), denormalize_data::<C>(v, cache)?)))
-                    .collect::<Result<Map<String, Value>, CacheError>>()
-                    .map(JsonValue::Object)
+enum DenormalizeFrame {
+    Enter(JsonValue, usize),
+    Object(Vec<String>),
+    Array(usize),
+}
+
+fn denormalize_data<C: Cache>(
+    value: JsonValue,
+    cache: &C,
+    read_policies: &ReadPolicies,
+    max_depth: usize,
+    local_fields: &[&str],
+) -> Result<JsonValue, CacheError> {
+    let mut frames = vec![DenormalizeFrame::Enter(value, 1)];
+    let mut results: Vec<JsonValue> = vec![];
+
+    while let Some(frame) = frames.pop() {
+        match frame {
+            DenormalizeFrame::Enter(value, depth) => {
+                if depth > max_depth {
+                    return Err(CacheError::DepthLimitExceeded(max_depth));
+                }
+                match value {
+                    JsonValue::Object(obj) => {
+                        let obj = match obj.get(REF) {
+                            Some(reference) => {
+                                let key: Key =
+                                    serde_json::from_value(reference.clone()).map_err(|_| {
+                                        CacheError::ExpectHasReference(JsonValue::Object(
+                                            obj.clone(),
+                                        ))
+                                    })?;
+                                match cache.get_identity_entry(&key)? {
+                                    NormalizedData::Object(mut entity) => {
+                                        entity.insert(
+                                            TYPENAME.to_string(),
+                                            JsonValue::String(key.typename().to_string()),
+                                        );
+                                        if let Some(JsonValue::Object(local)) = entity.remove(LOCAL)
+                                        {
+                                            for (k, v) in local {
+                                                if local_fields.contains(&k.as_str()) {
+                                                    entity.entry(k).or_insert(v);
+                                                }
+                                            }
+                                        }
+                                        read_policies.apply(key.typename(), &mut entity);
+                                        entity
+                                    }
+                                    NormalizedData::Array(arr) => {
+                                        frames.push(DenormalizeFrame::Enter(
+                                            JsonValue::Array(arr),
+                                            depth,
+                                        ));
+                                        continue;
+                                    }
+                                }
+                            }
+                            None => obj,
+                        };
+                        let (keys, children): (Vec<_>, Vec<_>) = obj.into_iter().unzip();
+                        frames.push(DenormalizeFrame::Object(keys));
+                        for v in children.into_iter().rev() {
+                            frames.push(DenormalizeFrame::Enter(v, depth + 1));
+                        }
+                    }
+                    JsonValue::Array(arr) => {
+                        frames.push(DenormalizeFrame::Array(arr.len()));
+                        for v in arr.into_iter().rev() {
+                            frames.push(DenormalizeFrame::Enter(v, depth + 1));
+                        }
+                    }
+                    value => results.push(value),
+                }
+            }
+            DenormalizeFrame::Object(keys) => {
+                let values = results.split_off(results.len() - keys.len());
+                results.push(JsonValue::Object(keys.into_iter().zip(values).collect()));
+            }
+            DenormalizeFrame::Array(len) => {
+                let values = results.split_off(results.len() - len);
+                results.push(JsonValue::Array(values));
             }
         }
-        JsonValue::Array(arr) => {
-            let ar = arr
-                .iter()
-                .map(|x| denormalize_data(x, cache))
-                .collect::<Result<Vec<JsonValue>, CacheError>>()?;
-            Ok(JsonValue::Array(ar))
-        }
-        _ => Ok(value.clone()),
     }
+
+    Ok(results.pop().unwrap())
 }
 
 impl Data {
@@ -329,6 +802,15 @@ pub enum NormalizedData {
     Array(Vec<JsonValue>),
 }
 
+impl From<NormalizedData> for JsonValue {
+    fn from(data: NormalizedData) -> Self {
+        match data {
+            NormalizedData::Object(obj) => JsonValue::Object(obj),
+            NormalizedData::Array(arr) => JsonValue::Array(arr),
+        }
+    }
+}
+
 impl TryFrom<JsonValue> for NormalizedData {
     type Error = ();
 
@@ -344,6 +826,7 @@ impl TryFrom<JsonValue> for NormalizedData {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rstest::rstest;
 
     fn test_data1() -> (Data, NormalizedData) {
         (
@@ -516,7 +999,7 @@ mod tests {
         let (data, expect_normalized_data) = data;
         let mut cache = InMemoryCache::new();
 
-        let normalized = cache.store_result_data(&"test".to_string(), data).unwrap();
+        let normalized = cache.store_result_data(&"test".into(), data).unwrap();
         assert_eq!(cache.identity_cache.len(), num_identity_entry);
         assert_eq!(normalized, expect_normalized_data)
     }
@@ -527,13 +1010,13 @@ mod tests {
     #[case(test_data3())]
     #[case(test_data4())]
     fn normalize_and_denormalize(#[case] data: (Data, NormalizedData)) {
-        let (data, normalized_data) = data;
+        let (data, _) = data;
         let mut cache = InMemoryCache::new();
 
-        let normalized = cache
-            .store_result_data(&"test".to_string(), data.clone())
+        cache
+            .store_result_data(&"test".into(), data.clone())
             .unwrap();
-        let denormaliaed = cache.get_result_data(&"test".to_string()).unwrap();
+        let denormaliaed = cache.get_result_data(&"test".into()).unwrap();
 
         assert_eq!(data, denormaliaed);
     }
@@ -544,16 +1027,16 @@ mod tests {
     #[case(test_data3())]
     #[case(test_data4())]
     fn normalize_and_denormalize_identity_cache_miss(#[case] data: (Data, NormalizedData)) {
-        let (data, normalized_data) = data;
+        let (data, _) = data;
         let mut cache = InMemoryCache::new();
 
-        let normalized = cache
-            .store_result_data(&"test".to_string(), data.clone())
+        cache
+            .store_result_data(&"test".into(), data.clone())
             .unwrap();
 
         cache.identity_cache.clear();
 
-        let result = cache.get_result_data(&"test".to_string());
+        let result = cache.get_result_data(&"test".into());
 
         assert!(
             matches!(result, Err(CacheError::KeyNotFound(_))),
@@ -568,18 +1051,110 @@ mod tests {
     #[case(test_data3())]
     #[case(test_data4())]
     fn normalize_and_denormalize_result_cache_miss(#[case] data: (Data, NormalizedData)) {
-        let (data, normalized_data) = data;
+        let (data, _) = data;
         let mut cache = InMemoryCache::new();
 
-        let normalized = cache
-            .store_result_data(&"test".to_string(), data.clone())
+        cache
+            .store_result_data(&"test".into(), data.clone())
             .unwrap();
 
         cache.result_cache.clear();
 
-        let result = cache.get_result_data(&"test".to_string());
+        let result = cache.get_result_data(&"test".into());
 
         dbg!(&result);
         assert!(matches!(result, Err(CacheError::ResultKeyNotFound(_))));
     }
+
+    fn deep_data(depth: usize) -> Data {
+        let mut value = json!({ "__typename": "Category", "id": "leaf", "name": "leaf" });
+        for i in 0..depth {
+            value = json!({
+                "__typename": "Category",
+                "id": i.to_string(),
+                "children": [value],
+            });
+        }
+        Data::new(json!({ "root": value })).unwrap()
+    }
+
+    #[test]
+    fn normalize_and_denormalize_deep_document() {
+        let depth = 500;
+        let data = deep_data(depth);
+        let mut cache = InMemoryCache::new().with_max_depth(depth * 3);
+
+        cache
+            .store_result_data(&"test".into(), data.clone())
+            .unwrap();
+        assert_eq!(cache.identity_cache.len(), depth + 1);
+
+        let denormalized = cache.get_result_data(&"test".into()).unwrap();
+        assert!(data == denormalized);
+    }
+
+    #[test]
+    fn normalize_depth_limit_exceeded() {
+        let data = deep_data(10);
+        let mut cache = InMemoryCache::new().with_max_depth(5);
+
+        let result = cache.store_result_data(&"test".into(), data);
+
+        assert!(
+            matches!(result, Err(CacheError::DepthLimitExceeded(5))),
+            "{:?}",
+            &result
+        );
+    }
+
+    #[test]
+    fn denormalize_depth_limit_exceeded() {
+        let data = deep_data(10);
+        let mut cache = InMemoryCache::new();
+        cache.store_result_data(&"test".into(), data).unwrap();
+
+        cache.max_depth = 5;
+        let result = cache.get_result_data(&"test".into());
+
+        assert!(
+            matches!(result, Err(CacheError::DepthLimitExceeded(5))),
+            "{:?}",
+            &result
+        );
+    }
+
+    #[test]
+    fn key_string_roundtrip() {
+        let key = Key::new("Person", "1").with_namespace("tenant");
+        let s: String = key.clone().into();
+
+        assert_eq!(s, "tenant/Person:1");
+        assert_eq!(Key::try_from(s).unwrap(), key);
+    }
+
+    #[test]
+    fn namespaces_are_isolated() {
+        let (data1, _) = test_data1();
+        let mut other = data1.clone();
+        other.0["person"]["name"] = json!("Darth Vader");
+        let key_a = ResultKey::namespaced("a", "test");
+        let key_b = ResultKey::namespaced("b", "test");
+        let mut cache = InMemoryCache::new();
+
+        cache.store_result_data(&key_a, data1.clone()).unwrap();
+        cache.store_result_data(&key_b, other.clone()).unwrap();
+
+        assert_eq!(cache.identity_cache.len(), 4);
+        assert_eq!(cache.get_result_data(&key_a).unwrap(), data1);
+        assert_eq!(cache.get_result_data(&key_b).unwrap(), other);
+
+        cache.clear_namespace("a");
+
+        assert_eq!(cache.identity_cache.len(), 2);
+        assert!(matches!(
+            cache.get_result_data(&key_a),
+            Err(CacheError::ResultKeyNotFound(_))
+        ));
+        assert_eq!(cache.get_result_data(&key_b).unwrap(), other);
+    }
 }
@@ -0,0 +1,119 @@
+use im::HashMap;
+
+use super::{notify_watchers, CacheError, Data, InMemoryCache, Key, Location, NormalizedData};
+
+pub type OptimisticId = u64;
+
+#[derive(Debug, Clone)]
+pub(crate) struct OptimisticLayer {
+    id: OptimisticId,
+    identity_cache: HashMap<Key, NormalizedData>,
+}
+
+impl InMemoryCache {
+    pub fn optimistic_ids(&self) -> impl Iterator<Item = OptimisticId> + '_ {
+        self.optimistic_layers.iter().map(|layer| layer.id)
+    }
+
+    pub(crate) fn effective_identity(&self, key: &Key) -> Option<&NormalizedData> {
+        self.optimistic_layers
+            .iter()
+            .rev()
+            .find_map(|layer| layer.identity_cache.get(key))
+            .or_else(|| self.identity_cache.get(key))
+    }
+
+    pub(crate) fn push_optimistic_layer(
+        &mut self,
+        data: &Data,
+    ) -> Result<OptimisticId, CacheError> {
+        let (_, entities) = self.normalize_document(None, data)?;
+        let id = self.next_optimistic_id;
+        self.next_optimistic_id += 1;
+
+        let mut changes = vec![];
+        for (key, _) in &entities {
+            changes.push((key.clone(), self.effective_identity(key).cloned()));
+        }
+        self.optimistic_layers.push(OptimisticLayer {
+            id,
+            identity_cache: entities.into_iter().collect(),
+        });
+        self.notify_effective_changes(changes);
+        Ok(id)
+    }
+
+    pub(crate) fn pop_optimistic_layer(&mut self, id: OptimisticId) {
+        let position = match self.optimistic_layers.iter().position(|l| l.id == id) {
+            Some(position) => position,
+            None => return,
+        };
+        let changes: Vec<_> = self.optimistic_layers[position]
+            .identity_cache
+            .keys()
+            .map(|key| (key.clone(), self.effective_identity(key).cloned()))
+            .collect();
+        self.optimistic_layers.remove(position);
+        self.notify_effective_changes(changes);
+    }
+
+    fn notify_effective_changes(&mut self, changes: Vec<(Key, Option<NormalizedData>)>) {
+        for (key, before) in changes {
+            let after = self.effective_identity(&key).cloned();
+            notify_watchers(
+                &mut self.watchers,
+                &Location::Entity(key),
+                before.as_ref(),
+                after.as_ref(),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::test_support::person;
+    use crate::cache::{Cache, WatchSelector};
+    use serde_json::json;
+
+    #[test]
+    fn optimistic_layer_shadows_and_rolls_back() {
+        let mut cache = InMemoryCache::new();
+        cache
+            .store_result_data(&"test".into(), person("Luke"))
+            .unwrap();
+
+        let id = cache.write_optimistic(person("Anakin")).unwrap();
+        assert_eq!(
+            cache.get_result_data(&"test".into()).unwrap(),
+            person("Anakin")
+        );
+        assert_eq!(cache.optimistic_ids().collect::<Vec<_>>(), vec![id]);
+
+        cache.remove_optimistic(id);
+        assert_eq!(
+            cache.get_result_data(&"test".into()).unwrap(),
+            person("Luke")
+        );
+        assert_eq!(cache.optimistic_ids().count(), 0);
+    }
+
+    #[test]
+    fn watchers_see_optimistic_and_real_states() {
+        let mut cache = InMemoryCache::new();
+        cache
+            .store_result_data(&"test".into(), person("Luke"))
+            .unwrap();
+        let (_, mut name) = cache.watch(WatchSelector::parse("Person:1 > name").unwrap());
+
+        let id = cache.write_optimistic(person("Anakin")).unwrap();
+        cache.remove_optimistic(id);
+        cache.store_mutation_data(person("Vader")).unwrap();
+
+        let names: Vec<_> = std::iter::from_fn(|| name.try_recv().ok())
+            .map(|event| event.after.unwrap())
+            .collect();
+        assert_eq!(names, vec![json!("Anakin"), json!("Luke"), json!("Vader")]);
+    }
+}
@@ -0,0 +1,269 @@
+use serde_json::{Map, Value as JsonValue};
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+use super::{InMemoryCache, TYPENAME};
+
+pub type ReadFunction =
+    dyn Fn(Option<&JsonValue>, &Map<String, JsonValue>) -> Option<JsonValue> + Send + Sync;
+
+pub type MergeFunction = dyn Fn(Option<&JsonValue>, JsonValue) -> JsonValue + Send + Sync;
+
+#[derive(Clone, Default)]
+pub struct ReadPolicies(HashMap<String, Vec<(String, Arc<ReadFunction>)>>);
+
+impl ReadPolicies {
+    pub fn insert<F>(&mut self, typename: &str, field: &str, read: F)
+    where
+        F: Fn(Option<&JsonValue>, &Map<String, JsonValue>) -> Option<JsonValue>
+            + Send
+            + Sync
+            + 'static,
+    {
+        let fields = self.0.entry(typename.to_string()).or_default();
+        fields.retain(|(f, _)| f != field);
+        fields.push((field.to_string(), Arc::new(read)));
+    }
+
+    pub(crate) fn apply(&self, typename: &str, entity: &mut Map<String, JsonValue>) {
+        for (field, read) in self.0.get(typename).into_iter().flatten() {
+            match read(entity.get(field), entity) {
+                Some(value) => entity.insert(field.clone(), value),
+                None => entity.remove(field),
+            };
+        }
+    }
+}
+
+impl Debug for ReadPolicies {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(self.0.iter().map(|(typename, fields)| {
+                (typename, fields.iter().map(|(f, _)| f).collect::<Vec<_>>())
+            }))
+            .finish()
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct MergePolicies(HashMap<String, Vec<(String, Arc<MergeFunction>)>>);
+
+impl MergePolicies {
+    pub fn insert<F>(&mut self, typename: &str, field: &str, merge: F)
+    where
+        F: Fn(Option<&JsonValue>, JsonValue) -> JsonValue + Send + Sync + 'static,
+    {
+        let fields = self.0.entry(typename.to_string()).or_default();
+        fields.retain(|(f, _)| f != field);
+        fields.push((field.to_string(), Arc::new(merge)));
+    }
+
+    fn get(&self, typename: &str, field: &str) -> Option<&MergeFunction> {
+        self.0
+            .get(typename)?
+            .iter()
+            .find(|(f, _)| f == field)
+            .map(|(_, merge)| merge.as_ref())
+    }
+
+    pub(crate) fn merge(
+        &self,
+        typename: &str,
+        existing: JsonValue,
+        incoming: JsonValue,
+    ) -> JsonValue {
+        let (mut existing, incoming) = match (existing, incoming) {
+            (JsonValue::Object(existing), JsonValue::Object(incoming)) => (existing, incoming),
+            (_, incoming) => return incoming,
+        };
+        let typename = incoming
+            .get(TYPENAME)
+            .and_then(JsonValue::as_str)
+            .unwrap_or(typename)
+            .to_string();
+        for (field, value) in incoming {
+            let merged = match (self.get(&typename, &field), existing.remove(&field)) {
+                (Some(merge), current) => merge(current.as_ref(), value),
+                (None, Some(current)) => self.merge("", current, value),
+                (None, None) => value,
+            };
+            existing.insert(field, merged);
+        }
+        JsonValue::Object(existing)
+    }
+}
+
+impl Debug for MergePolicies {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(self.0.iter().map(|(typename, fields)| {
+                (typename, fields.iter().map(|(f, _)| f).collect::<Vec<_>>())
+            }))
+            .finish()
+    }
+}
+
+pub fn concat_list(existing: Option<&JsonValue>, incoming: JsonValue) -> JsonValue {
+    match (existing, incoming) {
+        (Some(JsonValue::Array(existing)), JsonValue::Array(incoming)) => {
+            JsonValue::Array(existing.iter().cloned().chain(incoming).collect())
+        }
+        (_, incoming) => incoming,
+    }
+}
+
+pub fn relay_connection(existing: Option<&JsonValue>, mut incoming: JsonValue) -> JsonValue {
+    let (existing, connection) = match (existing, incoming.as_object_mut()) {
+        (Some(JsonValue::Object(existing)), Some(connection)) => (existing, connection),
+        _ => return incoming,
+    };
+    if let Some(edges) = connection.remove("edges") {
+        connection.insert(
+            "edges".to_string(),
+            concat_list(existing.get("edges"), edges),
+        );
+    }
+    if let (Some(JsonValue::Object(before)), Some(JsonValue::Object(page_info))) =
+        (existing.get("pageInfo"), connection.get_mut("pageInfo"))
+    {
+        for field in ["startCursor", "hasPreviousPage"] {
+            if let Some(value) = before.get(field) {
+                page_info.insert(field.to_string(), value.clone());
+            }
+        }
+    }
+    incoming
+}
+
+impl InMemoryCache {
+    pub fn with_merge_policy<F>(mut self, typename: &str, field: &str, merge: F) -> Self
+    where
+        F: Fn(Option<&JsonValue>, JsonValue) -> JsonValue + Send + Sync + 'static,
+    {
+        self.merge_policies.insert(typename, field, merge);
+        self
+    }
+
+    pub fn merge_policies_mut(&mut self) -> &mut MergePolicies {
+        &mut self.merge_policies
+    }
+
+    pub fn with_read_policy<F>(mut self, typename: &str, field: &str, read: F) -> Self
+    where
+        F: Fn(Option<&JsonValue>, &Map<String, JsonValue>) -> Option<JsonValue>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.read_policies.insert(typename, field, read);
+        self
+    }
+
+    pub fn read_policies_mut(&mut self) -> &mut ReadPolicies {
+        &mut self.read_policies
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::{Cache, Data, Key};
+    use serde_json::json;
+
+    fn cache() -> InMemoryCache {
+        let mut cache = InMemoryCache::new().with_read_policy("Person", "fullName", |_, person| {
+            Some(json!(format!(
+                "{} {}",
+                person.get("firstName")?.as_str()?,
+                person.get("lastName")?.as_str()?
+            )))
+        });
+        cache
+            .store_result_data(
+                &"test".into(),
+                Data::new(json!({
+                  "person": {
+                    "__typename": "Person",
+                    "id": "1",
+                    "firstName": "Luke",
+                    "lastName": "Skywalker",
+                  }
+                }))
+                .unwrap(),
+            )
+            .unwrap();
+        cache
+    }
+
+    #[test]
+    fn computed_field() {
+        let cache = cache();
+
+        assert_eq!(
+            cache.get_result_data(&"test".into()).unwrap().value()["person"]["fullName"],
+            json!("Luke Skywalker")
+        );
+    }
+
+    #[test]
+    fn computed_field_follows_cached_inputs() {
+        let mut cache = cache();
+        let key = Key::new("Person", "1");
+        cache
+            .store_identity_data(
+                &key,
+                json!({ "id": "1", "firstName": "Anakin", "lastName": "Skywalker" })
+                    .try_into()
+                    .unwrap(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            cache.get_identity_data(&key).unwrap().value()["fullName"],
+            json!("Anakin Skywalker")
+        );
+    }
+
+    #[test]
+    fn merge_relay_connection_pages() {
+        let mut cache =
+            InMemoryCache::new().with_merge_policy("Query", "allFilms", relay_connection);
+        let page = |cursor: &str, titles: &[&str], has_next: bool| {
+            Data::new(json!({
+              "allFilms": {
+                "edges": titles.iter().map(|t| json!({ "node": { "title": t } })).collect::<Vec<_>>(),
+                "pageInfo": { "startCursor": cursor, "endCursor": cursor, "hasNextPage": has_next }
+              }
+            }))
+            .unwrap()
+        };
+        cache
+            .store_result_data(&"test".into(), page("a", &["A New Hope"], true))
+            .unwrap();
+
+        cache
+            .merge_result_data(&"test".into(), page("b", &["Empire"], false))
+            .unwrap();
+
+        assert_eq!(
+            cache.get_result_data(&"test".into()).unwrap().value()["allFilms"],
+            json!({
+              "edges": [{ "node": { "title": "A New Hope" } }, { "node": { "title": "Empire" } }],
+              "pageInfo": { "startCursor": "a", "endCursor": "b", "hasNextPage": false }
+            })
+        );
+    }
+
+    #[test]
+    fn read_function_can_drop_field() {
+        let mut cache = cache();
+        cache
+            .read_policies_mut()
+            .insert("Person", "lastName", |_, _| None);
+
+        let data = cache.get_result_data(&"test".into()).unwrap();
+
+        assert!(data.value()["person"].get("lastName").is_none());
+    }
+}
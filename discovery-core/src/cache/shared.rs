@@ -0,0 +1,227 @@
+use futures::channel::mpsc::UnboundedReceiver;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use super::{
+    Cache, CacheError, CachedResult, Data, InMemoryCache, Key, NormalizedData, OptimisticId,
+    ResultKey, WatchEvent, WatchId, WatchSelector,
+};
+
+#[derive(Debug, Clone)]
+pub struct ReadSnapshot {
+    version: u64,
+    cache: Arc<InMemoryCache>,
+}
+
+impl ReadSnapshot {
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+}
+
+impl Deref for ReadSnapshot {
+    type Target = InMemoryCache;
+
+    fn deref(&self) -> &Self::Target {
+        &self.cache
+    }
+}
+
+impl InMemoryCache {
+    // Entity and result maps are persistent, so this is cheap; watchers, the
+    // event log and history stay with the writer.
+    fn read_view(&self) -> InMemoryCache {
+        InMemoryCache {
+            result_cache: self.result_cache.clone(),
+            identity_cache: self.identity_cache.clone(),
+            max_depth: self.max_depth,
+            event_log: None,
+            history: None,
+            read_policies: self.read_policies.clone(),
+            merge_policies: self.merge_policies.clone(),
+            invalidation_rules: self.invalidation_rules.clone(),
+            ttl: self.ttl,
+            result_meta: self.result_meta.clone(),
+            watchers: vec![],
+            next_watch_id: self.next_watch_id,
+            optimistic_layers: self.optimistic_layers.clone(),
+            next_optimistic_id: self.next_optimistic_id,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SharedCache {
+    writer: Mutex<InMemoryCache>,
+    current: RwLock<ReadSnapshot>,
+}
+
+impl SharedCache {
+    pub fn new(cache: InMemoryCache) -> Self {
+        Self {
+            current: RwLock::new(ReadSnapshot {
+                version: 0,
+                cache: Arc::new(cache.read_view()),
+            }),
+            writer: Mutex::new(cache),
+        }
+    }
+
+    pub fn snapshot(&self) -> ReadSnapshot {
+        self.current.read().unwrap().clone()
+    }
+
+    pub fn version(&self) -> u64 {
+        self.current.read().unwrap().version
+    }
+
+    pub fn write<R>(&self, f: impl FnOnce(&mut InMemoryCache) -> R) -> R {
+        let mut cache = self.writer.lock().unwrap();
+        let result = f(&mut cache);
+        let mut current = self.current.write().unwrap();
+        *current = ReadSnapshot {
+            version: current.version + 1,
+            cache: Arc::new(cache.read_view()),
+        };
+        result
+    }
+}
+
+impl Default for SharedCache {
+    fn default() -> Self {
+        Self::new(InMemoryCache::new())
+    }
+}
+
+impl Cache for SharedCache {
+    fn identify(&self, data: &Data) -> Key {
+        self.snapshot().identify(data)
+    }
+    fn store_result_data(
+        &mut self,
+        key: &ResultKey,
+        data: Data,
+    ) -> Result<NormalizedData, CacheError> {
+        self.write(|cache| cache.store_result_data(key, data))
+    }
+    fn get_result_data(&self, key: &ResultKey) -> Result<Data, CacheError> {
+        self.snapshot().get_result_data(key)
+    }
+    fn store_mutation_data(&mut self, data: Data) -> Result<Vec<ResultKey>, CacheError> {
+        self.write(|cache| cache.store_mutation_data(data))
+    }
+    fn store_identity_data(&mut self, key: &Key, data: NormalizedData) -> Result<(), CacheError> {
+        self.write(|cache| cache.store_identity_data(key, data))
+    }
+    fn get_identity_data(&self, key: &Key) -> Result<Data, CacheError> {
+        self.snapshot().get_identity_data(key)
+    }
+    fn get_identity_entry(&self, key: &Key) -> Result<NormalizedData, CacheError> {
+        self.snapshot().get_identity_entry(key)
+    }
+    fn write_optimistic(&mut self, data: Data) -> Result<OptimisticId, CacheError> {
+        self.write(|cache| cache.write_optimistic(data))
+    }
+    fn merge_result_data(
+        &mut self,
+        key: &ResultKey,
+        data: Data,
+    ) -> Result<NormalizedData, CacheError> {
+        self.write(|cache| cache.merge_result_data(key, data))
+    }
+    fn remove_optimistic(&mut self, id: OptimisticId) {
+        self.write(|cache| cache.remove_optimistic(id))
+    }
+    fn watch(&mut self, selector: WatchSelector) -> (WatchId, UnboundedReceiver<WatchEvent>) {
+        self.write(|cache| cache.watch(selector))
+    }
+    fn unwatch(&mut self, id: WatchId) {
+        self.write(|cache| cache.unwatch(id))
+    }
+    fn read_result(&self, key: &ResultKey) -> Result<CachedResult, CacheError> {
+        self.snapshot().read_result(key)
+    }
+    fn touch_result(&mut self, key: &ResultKey) {
+        self.write(|cache| cache.touch_result(key))
+    }
+    fn set_result_ttl(&mut self, key: &ResultKey, ttl: Option<Duration>) {
+        self.write(|cache| cache.set_result_ttl(key, ttl))
+    }
+    fn result_keys(&self) -> Vec<ResultKey> {
+        self.snapshot().result_keys()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::test_support::person;
+    use std::thread;
+
+    #[test]
+    fn snapshot_isolation() {
+        let mut cache = SharedCache::default();
+        cache
+            .store_result_data(&"test".into(), person("Luke"))
+            .unwrap();
+
+        let snapshot = cache.snapshot();
+        cache
+            .store_result_data(&"test".into(), person("Anakin"))
+            .unwrap();
+
+        assert_eq!(snapshot.version(), 1);
+        assert_eq!(cache.version(), 2);
+        assert_eq!(
+            snapshot.get_result_data(&"test".into()).unwrap(),
+            person("Luke")
+        );
+        assert_eq!(
+            cache.get_result_data(&"test".into()).unwrap(),
+            person("Anakin")
+        );
+    }
+
+    #[test]
+    fn keep_watchers_and_event_log_with_writer() {
+        let mut cache = SharedCache::new(InMemoryCache::new().with_event_log(10));
+        let (_, mut receiver) = cache.watch(WatchSelector::Result("test".into()));
+        cache
+            .store_result_data(&"test".into(), person("Luke"))
+            .unwrap();
+
+        assert!(receiver.try_recv().is_ok());
+        assert!(cache.snapshot().event_log().is_none());
+        assert_eq!(cache.write(|c| c.event_log().unwrap().events().count()), 2);
+    }
+
+    #[test]
+    fn writers_proceed_while_snapshot_is_held() {
+        let cache = Arc::new(SharedCache::default());
+        cache.write(|c| c.store_result_data(&"test".into(), person("Luke")).unwrap());
+        let snapshot = cache.snapshot();
+
+        let writer = {
+            let cache = cache.clone();
+            thread::spawn(move || {
+                for i in 0..10 {
+                    cache.write(|c| {
+                        c.store_result_data(&"test".into(), person(&i.to_string()))
+                            .unwrap()
+                    });
+                }
+            })
+        };
+        writer.join().unwrap();
+
+        assert_eq!(
+            snapshot.get_result_data(&"test".into()).unwrap(),
+            person("Luke")
+        );
+        assert_eq!(
+            cache.snapshot().get_result_data(&"test".into()).unwrap(),
+            person("9")
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use std::time::{Duration, SystemTime};
+
+use super::{Data, InMemoryCache, ResultKey};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedResult {
+    pub data: Data,
+    pub age: Duration,
+    pub stale: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultMeta {
+    pub stored_at: SystemTime,
+    pub ttl: Option<Duration>,
+}
+
+impl ResultMeta {
+    pub fn age(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(self.stored_at)
+            .unwrap_or_default()
+    }
+
+    pub(super) fn cached_result(&self, data: Data, default_ttl: Option<Duration>) -> CachedResult {
+        let age = self.age();
+        let stale = self.ttl.or(default_ttl).is_some_and(|ttl| age >= ttl);
+        CachedResult { data, age, stale }
+    }
+}
+
+impl Default for ResultMeta {
+    fn default() -> Self {
+        Self {
+            stored_at: SystemTime::now(),
+            ttl: None,
+        }
+    }
+}
+
+impl InMemoryCache {
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    pub fn result_meta(&self, key: &ResultKey) -> Option<&ResultMeta> {
+        self.result_meta.get(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::test_support::person;
+    use crate::cache::Cache;
+
+    #[test]
+    fn fresh_without_ttl() {
+        let mut cache = InMemoryCache::new();
+        cache
+            .store_result_data(&"test".into(), person("Luke"))
+            .unwrap();
+
+        let read = cache.read_result(&"test".into()).unwrap();
+
+        assert_eq!(read.data, person("Luke"));
+        assert!(!read.stale);
+    }
+
+    #[test]
+    fn stale_after_ttl() {
+        let mut cache = InMemoryCache::new().with_ttl(Duration::ZERO);
+        cache
+            .store_result_data(&"test".into(), person("Luke"))
+            .unwrap();
+
+        assert!(cache.read_result(&"test".into()).unwrap().stale);
+
+        cache.set_result_ttl(&"test".into(), Some(Duration::from_secs(3600)));
+
+        assert!(!cache.read_result(&"test".into()).unwrap().stale);
+    }
+
+    #[test]
+    fn touch_refreshes_stored_at() {
+        let mut cache = InMemoryCache::new().with_ttl(Duration::from_secs(3600));
+        cache
+            .store_result_data(&"test".into(), person("Luke"))
+            .unwrap();
+        let stored_at = SystemTime::now() - Duration::from_secs(7200);
+        cache.result_meta.get_mut(&"test".into()).unwrap().stored_at = stored_at;
+
+        assert!(cache.read_result(&"test".into()).unwrap().stale);
+
+        cache.touch_result(&"test".into());
+
+        assert!(!cache.read_result(&"test".into()).unwrap().stale);
+        assert!(cache.result_meta(&"test".into()).unwrap().stored_at > stored_at);
+    }
+}
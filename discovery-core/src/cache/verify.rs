@@ -0,0 +1,213 @@
+use serde_json::Value as JsonValue;
+
+use super::{references, InMemoryCache, Key, NormalizedData, ResultKey, TYPENAME};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Location {
+    Result(ResultKey),
+    Entity(Key),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerifyIssue {
+    InvalidReference {
+        location: Location,
+        path: String,
+        value: JsonValue,
+    },
+    DanglingReference {
+        location: Location,
+        path: String,
+        key: Key,
+    },
+    TypenameMismatch {
+        key: Key,
+        typename: JsonValue,
+    },
+    IdMismatch {
+        key: Key,
+        id: JsonValue,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VerifyReport {
+    pub checked_results: usize,
+    pub checked_entities: usize,
+    pub checked_references: usize,
+    pub issues: Vec<VerifyIssue>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl InMemoryCache {
+    pub fn verify(&self) -> VerifyReport {
+        let mut report = VerifyReport::default();
+
+        for (key, data) in &self.result_cache {
+            report.checked_results += 1;
+            self.verify_value(Location::Result(key.clone()), data, &mut report);
+        }
+
+        for (key, data) in &self.identity_cache {
+            report.checked_entities += 1;
+            if let NormalizedData::Object(obj) = data {
+                if let Some(typename) = obj.get(TYPENAME) {
+                    if typename.as_str() != Some(key.typename()) {
+                        report.issues.push(VerifyIssue::TypenameMismatch {
+                            key: key.clone(),
+                            typename: typename.clone(),
+                        });
+                    }
+                }
+                if let Some(id) = obj.get(Key::field_name()) {
+                    if Key::id_of(id).as_deref() != Some(key.id()) {
+                        report.issues.push(VerifyIssue::IdMismatch {
+                            key: key.clone(),
+                            id: id.clone(),
+                        });
+                    }
+                }
+            }
+            self.verify_value(Location::Entity(key.clone()), data, &mut report);
+        }
+
+        report
+    }
+
+    fn verify_value(&self, location: Location, data: &NormalizedData, report: &mut VerifyReport) {
+        for (path, reference) in references(data) {
+            report.checked_references += 1;
+            match serde_json::from_value::<Key>(reference.clone()) {
+                Ok(key) if !self.identity_cache.contains_key(&key) => {
+                    report.issues.push(VerifyIssue::DanglingReference {
+                        location: location.clone(),
+                        path,
+                        key,
+                    });
+                }
+                Ok(_) => {}
+                Err(_) => {
+                    report.issues.push(VerifyIssue::InvalidReference {
+                        location: location.clone(),
+                        path,
+                        value: reference.clone(),
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::{Cache, Data};
+    use serde_json::json;
+
+    fn cache() -> InMemoryCache {
+        let mut cache = InMemoryCache::new();
+        cache
+            .store_result_data(
+                &"test".into(),
+                Data::new(json!({
+                  "person": {
+                    "__typename": "Person",
+                    "id": "1",
+                    "name": "Luke Skywalker",
+                    "homeworld": {
+                      "__typename": "Planet",
+                      "id": "2",
+                      "name": "Tatooine"
+                    }
+                  }
+                }))
+                .unwrap(),
+            )
+            .unwrap();
+        cache
+    }
+
+    #[test]
+    fn verify_consistent_cache() {
+        let report = cache().verify();
+
+        assert!(report.is_ok(), "{:?}", report);
+        assert_eq!(report.checked_results, 1);
+        assert_eq!(report.checked_entities, 2);
+        assert_eq!(report.checked_references, 2);
+    }
+
+    #[test]
+    fn verify_dangling_reference() {
+        let mut cache = cache();
+        let planet = Key::new("Planet", "2");
+        cache.identity_cache.remove(&planet);
+
+        let report = cache.verify();
+
+        assert_eq!(
+            report.issues,
+            vec![VerifyIssue::DanglingReference {
+                location: Location::Entity(Key::new("Person", "1")),
+                path: "root > homeworld".to_string(),
+                key: planet,
+            }]
+        );
+    }
+
+    #[test]
+    fn verify_type_and_id_mismatch() {
+        let mut cache = cache();
+        let key = Key::new("Planet", "2");
+        cache
+            .store_identity_data(
+                &key,
+                NormalizedData::try_from(json!({
+                    "__typename": "Person",
+                    "id": "3",
+                }))
+                .unwrap(),
+            )
+            .unwrap();
+
+        let report = cache.verify();
+
+        assert_eq!(
+            report.issues,
+            vec![
+                VerifyIssue::TypenameMismatch {
+                    key: key.clone(),
+                    typename: json!("Person"),
+                },
+                VerifyIssue::IdMismatch {
+                    key,
+                    id: json!("3"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn verify_numeric_ids() {
+        let mut cache = InMemoryCache::new();
+        cache
+            .store_result_data(
+                &"test".into(),
+                Data::new(
+                    json!({ "planet": { "__typename": "Planet", "id": 2, "name": "Tatooine" } }),
+                )
+                .unwrap(),
+            )
+            .unwrap();
+
+        let report = cache.verify();
+
+        assert!(report.is_ok(), "{:?}", report);
+        assert_eq!(report.checked_entities, 1);
+    }
+}
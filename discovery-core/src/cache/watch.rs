@@ -0,0 +1,190 @@
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use serde_json::Value as JsonValue;
+
+use super::{InMemoryCache, Key, Location, NormalizedData, ResultKey};
+
+pub type WatchId = u64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WatchSelector {
+    Result(ResultKey),
+    Entity(Key),
+    Field(Key, Vec<String>),
+    Any,
+}
+
+impl WatchSelector {
+    pub fn parse(selector: &str) -> Option<Self> {
+        let mut segments = selector.split(" > ").map(str::trim);
+        let key = Key::try_from(segments.next()?.to_string()).ok()?;
+        let path: Vec<_> = segments.map(str::to_string).collect();
+        Some(if path.is_empty() {
+            Self::Entity(key)
+        } else {
+            Self::Field(key, path)
+        })
+    }
+
+    fn matches(&self, location: &Location) -> bool {
+        match (self, location) {
+            (Self::Result(key), Location::Result(location)) => key == location,
+            (Self::Entity(key) | Self::Field(key, _), Location::Entity(location)) => {
+                key == location
+            }
+            (Self::Any, _) => true,
+            _ => false,
+        }
+    }
+
+    fn select(&self, data: Option<&NormalizedData>) -> Option<JsonValue> {
+        let value = JsonValue::from(data?.clone());
+        match self {
+            Self::Field(_, path) => path
+                .iter()
+                .try_fold(&value, |v, segment| match v {
+                    JsonValue::Array(arr) => arr.get(segment.parse::<usize>().ok()?),
+                    _ => v.get(segment),
+                })
+                .cloned(),
+            _ => Some(value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchEvent {
+    pub id: WatchId,
+    pub selector: WatchSelector,
+    pub before: Option<JsonValue>,
+    pub after: Option<JsonValue>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Watcher {
+    id: WatchId,
+    selector: WatchSelector,
+    sender: UnboundedSender<WatchEvent>,
+}
+
+impl InMemoryCache {
+    pub fn watch(&mut self, selector: WatchSelector) -> (WatchId, UnboundedReceiver<WatchEvent>) {
+        let (sender, receiver) = unbounded();
+        let id = self.next_watch_id;
+        self.next_watch_id += 1;
+        self.watchers.push(Watcher {
+            id,
+            selector,
+            sender,
+        });
+        (id, receiver)
+    }
+
+    pub fn unwatch(&mut self, id: WatchId) {
+        self.watchers.retain(|w| w.id != id);
+    }
+}
+
+pub(crate) fn notify_watchers(
+    watchers: &mut Vec<Watcher>,
+    location: &Location,
+    before: Option<&NormalizedData>,
+    after: Option<&NormalizedData>,
+) {
+    watchers.retain(|watcher| {
+        if !watcher.selector.matches(location) {
+            return true;
+        }
+        let before = watcher.selector.select(before);
+        let after = watcher.selector.select(after);
+        if before == after {
+            return true;
+        }
+        watcher
+            .sender
+            .unbounded_send(WatchEvent {
+                id: watcher.id,
+                selector: watcher.selector.clone(),
+                before,
+                after,
+            })
+            .is_ok()
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::test_support::person_with_height;
+    use crate::cache::Cache;
+    use serde_json::json;
+
+    #[test]
+    fn parse_selector() {
+        assert_eq!(
+            WatchSelector::parse("Person:1 > name"),
+            Some(WatchSelector::Field(
+                Key::new("Person", "1"),
+                vec!["name".to_string()]
+            ))
+        );
+        assert_eq!(
+            WatchSelector::parse("Person:1"),
+            Some(WatchSelector::Entity(Key::new("Person", "1")))
+        );
+        assert_eq!(WatchSelector::parse("Person"), None);
+    }
+
+    #[test]
+    fn watch_field_only_notifies_on_change() {
+        let mut cache = InMemoryCache::new();
+        cache
+            .store_result_data(&"test".into(), person_with_height("Luke", 172))
+            .unwrap();
+        let (_, mut name) = cache.watch(WatchSelector::parse("Person:1 > name").unwrap());
+        let (_, mut entity) = cache.watch(WatchSelector::parse("Person:1").unwrap());
+
+        cache
+            .store_result_data(&"test".into(), person_with_height("Luke", 180))
+            .unwrap();
+        assert!(name.try_recv().is_err());
+        assert!(entity.try_recv().is_ok());
+
+        cache
+            .store_result_data(&"test".into(), person_with_height("Anakin", 180))
+            .unwrap();
+        let event = name.try_recv().unwrap();
+        assert_eq!(event.before, Some(json!("Luke")));
+        assert_eq!(event.after, Some(json!("Anakin")));
+    }
+
+    #[test]
+    fn unwatch_and_dropped_receivers() {
+        let mut cache = InMemoryCache::new();
+        let (id, mut receiver) = cache.watch(WatchSelector::Result("test".into()));
+        let (_, dropped) = cache.watch(WatchSelector::Result("test".into()));
+        drop(dropped);
+
+        cache
+            .store_result_data(&"test".into(), person_with_height("Luke", 172))
+            .unwrap();
+        assert!(receiver.try_recv().is_ok());
+        assert_eq!(cache.watchers.len(), 1);
+
+        cache.unwatch(id);
+        assert!(cache.watchers.is_empty());
+    }
+
+    #[test]
+    fn watch_any_write() {
+        let mut cache = InMemoryCache::new();
+        let (_, mut any) = cache.watch(WatchSelector::Any);
+
+        cache
+            .store_result_data(&"test".into(), person_with_height("Luke", 172))
+            .unwrap();
+        let events: Vec<_> = std::iter::from_fn(|| any.try_recv().ok())
+            .map(|event| event.after.is_some())
+            .collect();
+        assert_eq!(events, vec![true, true]);
+    }
+}
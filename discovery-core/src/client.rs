@@ -1,48 +1,184 @@
+use futures::future::{self, BoxFuture, Either};
+use futures::stream::{self, LocalBoxStream, StreamExt};
 use graphql_client::{GraphQLQuery, QueryBody, Response};
-use reqwest::header::{HeaderMap, HeaderValue};
-use reqwest::Client;
-use reqwest::Response as ReqwestResponse;
-use serde::Serialize;
-use serde_json::Value;
-use sha1::Digest;
+use reqwest::cookie::{CookieStore, Jar};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, ETAG, IF_NONE_MATCH};
+use reqwest::{Client, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use std::cell::RefCell;
-use std::collections::HashMap;
-use std::fmt::{Display, Formatter};
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
-use crate::cache::{Cache, Data, DataValidationError};
+use crate::cache::{
+    Cache, CacheError, CachedResult, Data, DataValidationError, ResultKey, WatchSelector,
+};
+use crate::defer::{IncrementalPayload, IncrementalResult, ACCEPT_INCREMENTAL};
+use crate::link::{
+    self, AuthLink, AutomaticPersistedQueryLink, BatchHttpLink, BatchOptions, DedupLink,
+    GraphQLRequest, HttpLink, Interceptors, Link, LinkResponse, LocalField, LocalResolvers,
+    LocalStateLink, Next, PersistedOperationsLink, PersistedQueryManifest, Priority, Progress,
+    ProgressFn, RateLimitLink, RateLimitState, RateLimits, RequestDefaults, RequestSigner,
+    Scheduler, SchedulerLink, StaticToken, TokenProvider, Transport, TransportLink, WireEncoding,
+    LOG_TARGET,
+};
+use crate::live::{self, LivePayload};
+use crate::metrics::MetricsRecorder;
+use crate::network::{NetworkMonitor, NetworkStatus};
+use crate::outbox::{self, Conflict, MutationOutcome, Outbox, ReplaySummary};
+use crate::poll::{PollHandle, PollOptions};
+use crate::response::{GraphQLError, GraphQLResponse};
+use crate::result_key::{canonical_json, CanonicalSha256, Operation, ResultKeyStrategy};
+use crate::runtime::{default_runtime, Runtime};
+use crate::ssr::{HydrationSnapshot, InFlight};
+use crate::subscription::{
+    resumable_sse_events, Backoff, Multiplexer, SseEvent, SubscriptionEvent, SubscriptionTransport,
+};
 
-pub struct CacheWrap<C>(Rc<RefCell<C>>);
+pub struct CacheWrap<C>(Arc<Mutex<C>>);
 
 impl<C> CacheWrap<C> {
-    fn inner(&self) -> Rc<RefCell<C>> {
+    pub fn new(cache: C) -> Self {
+        Self(Arc::new(Mutex::new(cache)))
+    }
+
+    fn inner(&self) -> Arc<Mutex<C>> {
         self.0.clone()
     }
 }
 
+impl<C> Clone for CacheWrap<C> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
 pub struct DiscoveryClientBuilder<C> {
     uri: Option<String>,
-    authorization: Option<String>,
+    token_provider: Option<Arc<dyn TokenProvider>>,
+    client_name: Option<String>,
+    client_version: Option<String>,
+    headers: Vec<(String, String)>,
     cache: Option<CacheWrap<C>>,
+    result_key_strategy: Option<Arc<dyn ResultKeyStrategy>>,
+    subscription_transport: Option<SubscriptionTransport>,
+    subscription_backoff: Backoff,
+    error_policy: ErrorPolicy,
+    links: Vec<Arc<dyn Link>>,
+    metrics: Option<Arc<dyn MetricsRecorder>>,
+    on_error: Option<Arc<ErrorHook>>,
+    persisted_operations: Option<Arc<PersistedQueryManifest>>,
+    batching: Option<BatchOptions>,
+    get_queries: bool,
+    automatic_persisted_queries: bool,
+    defaults: RequestDefaults,
+    interceptors: Interceptors,
+    dedup_window: Option<Duration>,
+    hydration: Option<HydrationSnapshot>,
+    local_resolvers: LocalResolvers,
+    rate_limit_retries: u32,
+    max_response_size: Option<u64>,
+    wire_encoding: (WireEncoding, bool),
+    scheduler: Option<Scheduler>,
+    stale_while_revalidate: Option<Arc<StaleWhileRevalidate>>,
+    upload_progress: Option<Arc<ProgressFn>>,
+    download_progress: Option<Arc<ProgressFn>>,
+    signer: Option<Arc<dyn RequestSigner>>,
+    cookie_jar: Option<Arc<Jar>>,
+    #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+    tls: TlsOptions,
+    pool: PoolOptions,
+    http_client: Option<Client>,
+    transport: Option<Arc<dyn Transport>>,
+    runtime: Option<Arc<dyn Runtime>>,
+    outbox: Option<Arc<Outbox>>,
+    network: Option<NetworkMonitor>,
+}
+
+#[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+#[derive(Default)]
+struct TlsOptions {
+    backend: Option<TlsBackend>,
+    root_certificates: Vec<reqwest::Certificate>,
+    identity: Option<reqwest::Identity>,
+}
+
+#[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TlsBackend {
+    #[cfg(feature = "native-tls")]
+    NativeTls,
+    #[cfg(feature = "rustls-tls")]
+    Rustls,
 }
 
 #[derive(Error, Debug)]
 pub enum BuilderError {
     #[error("uri not found")]
     URINotFound,
+    #[error("async runtime not configured")]
+    RuntimeNotFound,
+    #[error("invalid uri: {0}")]
+    InvalidURI(String),
     #[error("invalid header")]
     InvalidHeader(#[from] reqwest::header::InvalidHeaderValue),
+    #[error("invalid header name")]
+    InvalidHeaderName(#[from] reqwest::header::InvalidHeaderName),
     #[error("reqwest error")]
     ReqwestError(#[from] reqwest::Error),
+    #[error("hydration requires a cache")]
+    CacheNotFound,
+    #[error("invalid hydration snapshot")]
+    InvalidSnapshot(#[source] ClientError),
+    #[error("`{0}` is not supported with a custom transport")]
+    UnsupportedWithTransport(&'static str),
 }
 
-impl<C: Cache> DiscoveryClientBuilder<C> {
+impl<C: Cache + Send + 'static> DiscoveryClientBuilder<C> {
     pub fn new() -> Self {
         Self {
             cache: None,
             uri: None,
-            authorization: None,
+            token_provider: None,
+            client_name: None,
+            client_version: None,
+            headers: vec![],
+            result_key_strategy: None,
+            subscription_transport: None,
+            subscription_backoff: Backoff::default(),
+            error_policy: ErrorPolicy::default(),
+            links: vec![],
+            metrics: None,
+            on_error: None,
+            persisted_operations: None,
+            batching: None,
+            get_queries: false,
+            automatic_persisted_queries: false,
+            defaults: RequestDefaults::default(),
+            interceptors: Interceptors::new(),
+            dedup_window: None,
+            hydration: None,
+            local_resolvers: LocalResolvers::new(),
+            rate_limit_retries: 3,
+            max_response_size: None,
+            wire_encoding: (WireEncoding::Json, false),
+            scheduler: None,
+            stale_while_revalidate: None,
+            upload_progress: None,
+            signer: None,
+            cookie_jar: None,
+            #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+            tls: TlsOptions::default(),
+            pool: PoolOptions::default(),
+            http_client: None,
+            transport: None,
+            runtime: None,
+            outbox: None,
+            network: None,
+            download_progress: None,
         }
     }
 
@@ -51,8 +187,27 @@ impl<C: Cache> DiscoveryClientBuilder<C> {
         self
     }
 
-    pub fn authorization(mut self, authorization: String) -> Self {
-        self.authorization = Some(authorization);
+    pub fn authorization(self, authorization: String) -> Self {
+        self.token_provider(StaticToken::new(authorization))
+    }
+
+    pub fn token_provider(mut self, provider: impl TokenProvider + 'static) -> Self {
+        self.token_provider = Some(Arc::new(provider));
+        self
+    }
+
+    pub fn client_name(mut self, client_name: String) -> Self {
+        self.client_name = Some(client_name);
+        self
+    }
+
+    pub fn client_version(mut self, client_version: String) -> Self {
+        self.client_version = Some(client_version);
+        self
+    }
+
+    pub fn header(mut self, name: String, value: String) -> Self {
+        self.headers.push((name, value));
         self
     }
 
@@ -61,92 +216,2572 @@ impl<C: Cache> DiscoveryClientBuilder<C> {
         self
     }
 
-    pub fn build(self) -> std::result::Result<DiscoveryClient<C>, BuilderError> {
+    pub fn result_key_strategy(mut self, strategy: impl ResultKeyStrategy + 'static) -> Self {
+        self.result_key_strategy = Some(Arc::new(strategy));
+        self
+    }
+
+    pub fn subscription_transport(mut self, transport: SubscriptionTransport) -> Self {
+        self.subscription_transport = Some(transport);
+        self
+    }
+
+    pub fn subscription_backoff(mut self, backoff: Backoff) -> Self {
+        self.subscription_backoff = backoff;
+        self
+    }
+
+    pub fn error_policy(mut self, error_policy: ErrorPolicy) -> Self {
+        self.error_policy = error_policy;
+        self
+    }
+
+    pub fn link(mut self, link: impl Link + 'static) -> Self {
+        self.links.push(Arc::new(link));
+        self
+    }
+
+    pub fn metrics(mut self, metrics: impl MetricsRecorder + 'static) -> Self {
+        self.metrics = Some(Arc::new(metrics));
+        self
+    }
+
+    pub fn on_error(mut self, hook: impl Fn(&str, &ClientError) + Send + Sync + 'static) -> Self {
+        self.on_error = Some(Arc::new(hook));
+        self
+    }
+
+    pub fn local_resolver(
+        mut self,
+        coordinate: impl Into<String>,
+        resolver: impl Fn(&LocalField<'_>) -> Value + Send + Sync + 'static,
+    ) -> Self {
+        self.local_resolvers.insert(coordinate, resolver);
+        self
+    }
+
+    pub fn hydrate(mut self, snapshot: HydrationSnapshot) -> Self {
+        self.hydration = Some(snapshot);
+        self
+    }
+
+    pub fn deduplicate(mut self, window: Duration) -> Self {
+        self.dedup_window = Some(window);
+        self
+    }
+
+    pub fn on_response(
+        mut self,
+        interceptor: impl Fn(&str, &mut Value) + Send + Sync + 'static,
+    ) -> Self {
+        self.interceptors.push(interceptor);
+        self
+    }
+
+    fn default_headers(&self) -> std::result::Result<HeaderMap, BuilderError> {
         let mut headers = HeaderMap::new();
 
-        if let Some(auth) = self.authorization {
-            headers.insert("Authorization", HeaderValue::from_str(&auth)?);
+        if let Some(name) = &self.client_name {
+            headers.insert("apollographql-client-name", HeaderValue::from_str(name)?);
+        }
+        if let Some(version) = &self.client_version {
+            headers.insert(
+                "apollographql-client-version",
+                HeaderValue::from_str(version)?,
+            );
+        }
+        for (name, value) in &self.headers {
+            headers.insert(
+                HeaderName::from_bytes(name.as_bytes())?,
+                HeaderValue::from_str(value)?,
+            );
+        }
+        Ok(headers)
+    }
+
+    pub fn persisted_operations_only(mut self, manifest: PersistedQueryManifest) -> Self {
+        self.persisted_operations = Some(Arc::new(manifest));
+        self
+    }
+
+    pub fn use_get_for_queries(mut self, enable: bool) -> Self {
+        self.get_queries = enable;
+        self
+    }
+
+    pub fn default_variable(mut self, name: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.defaults.variables.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn context<T: std::any::Any + Send + Sync>(mut self, value: T) -> Self {
+        self.defaults.context.insert(value);
+        self
+    }
+
+    pub fn automatic_persisted_queries(mut self, enable: bool) -> Self {
+        self.automatic_persisted_queries = enable;
+        self
+    }
+
+    pub fn rate_limit_retries(mut self, max_retries: u32) -> Self {
+        self.rate_limit_retries = max_retries;
+        self
+    }
+
+    pub fn max_response_size(mut self, bytes: u64) -> Self {
+        self.max_response_size = Some(bytes);
+        self
+    }
+
+    pub fn wire_encoding(mut self, encoding: WireEncoding, encode_requests: bool) -> Self {
+        self.wire_encoding = (encoding, encode_requests);
+        self
+    }
+
+    pub fn scheduler(mut self, scheduler: Scheduler) -> Self {
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    pub fn batching(mut self, options: BatchOptions) -> Self {
+        self.batching = Some(options);
+        self
+    }
+
+    pub fn stale_while_revalidate(
+        mut self,
+        max_age: Duration,
+        spawn: impl Fn(BoxFuture<'static, ()>) + Send + Sync + 'static,
+    ) -> Self {
+        self.stale_while_revalidate = Some(Arc::new(StaleWhileRevalidate {
+            max_age,
+            spawn: Box::new(spawn),
+        }));
+        self
+    }
+
+    pub fn upload_progress(mut self, progress: impl Fn(Progress) + Send + Sync + 'static) -> Self {
+        self.upload_progress = Some(Arc::new(progress));
+        self
+    }
+
+    pub fn download_progress(
+        mut self,
+        progress: impl Fn(Progress) + Send + Sync + 'static,
+    ) -> Self {
+        self.download_progress = Some(Arc::new(progress));
+        self
+    }
+
+    pub fn signer(mut self, signer: impl RequestSigner + 'static) -> Self {
+        self.signer = Some(Arc::new(signer));
+        self
+    }
+
+    pub fn cookie_store(mut self, enable: bool) -> Self {
+        self.cookie_jar = match enable {
+            true => self.cookie_jar.or_else(|| Some(Arc::new(Jar::default()))),
+            false => None,
+        };
+        self
+    }
+
+    pub fn cookie_jar(mut self, jar: Arc<Jar>) -> Self {
+        self.cookie_jar = Some(jar);
+        self
+    }
+
+    pub fn outbox(mut self, outbox: Outbox) -> Self {
+        self.outbox = Some(Arc::new(outbox));
+        self
+    }
+
+    pub fn network_monitor(mut self, monitor: NetworkMonitor) -> Self {
+        self.network = Some(monitor);
+        self
+    }
+
+    #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+    pub fn tls_backend(mut self, backend: TlsBackend) -> Self {
+        self.tls.backend = Some(backend);
+        self
+    }
+
+    #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+    pub fn root_certificate(mut self, certificate: reqwest::Certificate) -> Self {
+        self.tls.root_certificates.push(certificate);
+        self
+    }
+
+    #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+    pub fn identity(mut self, identity: reqwest::Identity) -> Self {
+        self.tls.identity = Some(identity);
+        self
+    }
+
+    #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+    fn apply_tls(&self, mut builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        builder = match self.tls.backend {
+            #[cfg(feature = "native-tls")]
+            Some(TlsBackend::NativeTls) => builder.use_native_tls(),
+            #[cfg(feature = "rustls-tls")]
+            Some(TlsBackend::Rustls) => builder.use_rustls_tls(),
+            None => builder,
+        };
+        for certificate in &self.tls.root_certificates {
+            builder = builder.add_root_certificate(certificate.clone());
+        }
+        if let Some(identity) = &self.tls.identity {
+            builder = builder.identity(identity.clone());
+        }
+        builder
+    }
+
+    pub fn pool(mut self, options: PoolOptions) -> Self {
+        self.pool = options;
+        self
+    }
+
+    pub fn http_client(mut self, client: Client) -> Self {
+        self.http_client = Some(client);
+        self
+    }
+
+    pub fn transport(mut self, transport: impl Transport + 'static) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    pub fn runtime(mut self, runtime: impl Runtime + 'static) -> Self {
+        self.runtime = Some(Arc::new(runtime));
+        self
+    }
+
+    fn build_http_client(&self, headers: HeaderMap) -> std::result::Result<Client, BuilderError> {
+        let mut builder = reqwest::Client::builder().default_headers(headers);
+        if let Some(jar) = &self.cookie_jar {
+            builder = builder.cookie_provider(jar.clone());
+        }
+        #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+        {
+            builder = self.apply_tls(builder);
+        }
+        if let Some(max_idle) = self.pool.max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max_idle);
+        }
+        if let Some(timeout) = self.pool.idle_timeout {
+            builder = builder.pool_idle_timeout(timeout);
+        }
+        if let Some(keepalive) = self.pool.tcp_keepalive {
+            builder = builder.tcp_keepalive(keepalive);
+        }
+        if self.pool.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        Ok(builder.build()?)
+    }
+
+    fn http_only_option(&self) -> Option<&'static str> {
+        [
+            ("signer", self.signer.is_some()),
+            ("upload_progress", self.upload_progress.is_some()),
+            ("download_progress", self.download_progress.is_some()),
+            (
+                "wire_encoding",
+                self.wire_encoding != (WireEncoding::Json, false),
+            ),
+            ("use_get_for_queries", self.get_queries),
+        ]
+        .into_iter()
+        .find_map(|(option, set)| set.then_some(option))
+    }
+
+    pub fn build(mut self) -> std::result::Result<DiscoveryClient<C>, BuilderError> {
+        if let Some(option) = self.http_only_option().filter(|_| self.transport.is_some()) {
+            return Err(BuilderError::UnsupportedWithTransport(option));
         }
+        let headers = self.default_headers()?;
+        let key_headers = headers.clone();
+        let (reqwest_client, default_headers) = match self.http_client.take() {
+            Some(client) => (client, headers),
+            None => (self.build_http_client(headers)?, HeaderMap::new()),
+        };
 
-        let reqwest_client = reqwest::Client::builder()
-            .default_headers(headers)
-            .build()?;
+        let uri = self.uri.ok_or(BuilderError::URINotFound)?;
+        if let Some(snapshot) = &self.hydration {
+            let cache = self.cache.as_ref().ok_or(BuilderError::CacheNotFound)?;
+            let cache = cache.inner();
+            let mut cache = cache.lock().unwrap();
+            snapshot
+                .restore(&mut *cache)
+                .map_err(BuilderError::InvalidSnapshot)?;
+        }
+        let runtime = self
+            .runtime
+            .or_else(default_runtime)
+            .ok_or(BuilderError::RuntimeNotFound)?;
+        let cookies = match self.cookie_jar {
+            Some(jar) => Some(Cookies {
+                jar,
+                url: Url::parse(&uri).map_err(|_| BuilderError::InvalidURI(uri.clone()))?,
+            }),
+            None => None,
+        };
+        let defaults = Arc::new(self.defaults);
+        let mut links = self.links;
+        if !defaults.is_empty() {
+            links.insert(0, defaults.clone());
+        }
+        let interceptors = Arc::new(self.interceptors);
+        if !interceptors.is_empty() {
+            links.insert(0, interceptors.clone());
+        }
+        if !self.local_resolvers.is_empty() {
+            links.push(Arc::new(LocalStateLink::new(
+                self.local_resolvers,
+                self.cache.as_ref().map(CacheWrap::inner),
+            )));
+        }
+        if let Some(window) = self.dedup_window {
+            links.push(Arc::new(DedupLink::new(window).runtime(runtime.clone())));
+        }
+        if let Some(scheduler) = self.scheduler {
+            links.push(Arc::new(SchedulerLink::new(scheduler)));
+        }
+        if let Some(provider) = &self.token_provider {
+            links.push(Arc::new(AuthLink::new(provider.clone())));
+        }
+        if let Some(manifest) = &self.persisted_operations {
+            links.push(Arc::new(PersistedOperationsLink::new(manifest.clone())));
+        } else if self.automatic_persisted_queries {
+            links.push(Arc::new(AutomaticPersistedQueryLink::new()));
+        }
+        let rate_limits = RateLimits::new();
+        links.push(Arc::new(
+            RateLimitLink::new(runtime.clone())
+                .max_retries(self.rate_limit_retries)
+                .limits(rate_limits.clone()),
+        ));
+        match (self.transport, self.batching) {
+            (Some(transport), Some(options)) => links.push(Arc::new(
+                BatchHttpLink::with_transport(transport, options).runtime(runtime.clone()),
+            )),
+            (Some(transport), None) => links.push(Arc::new(
+                TransportLink::new(transport).max_response_size(self.max_response_size),
+            )),
+            (None, batching) => {
+                let http = HttpLink::new(reqwest_client.clone(), uri.as_str())
+                    .with_progress(self.upload_progress, self.download_progress)
+                    .with_signer(self.signer.clone())
+                    .with_headers(default_headers.clone())
+                    .use_get_for_queries(self.get_queries)
+                    .max_response_size(self.max_response_size)
+                    .encoding(self.wire_encoding.0, self.wire_encoding.1);
+                match batching {
+                    Some(options) => links.push(Arc::new(
+                        BatchHttpLink::new(http, options).runtime(runtime.clone()),
+                    )),
+                    None => links.push(Arc::new(http)),
+                }
+            }
+        }
 
         Ok(DiscoveryClient {
-            uri: self.uri.ok_or(BuilderError::URINotFound)?,
+            uri,
+            links,
+            metrics: self.metrics,
+            on_error: self.on_error,
+            persisted_operations: self.persisted_operations,
             reqwest_client,
+            default_headers,
+            key_headers,
+            runtime,
             cache: self.cache,
+            active_queries: Arc::new(Mutex::new(HashMap::new())),
+            result_errors: Arc::new(Mutex::new(HashMap::new())),
+            etags: Arc::new(Mutex::new(HashMap::new())),
+            rate_limits,
+            defaults,
+            interceptors,
+            in_flight: Arc::new(InFlight::default()),
+            subscription_transport: self.subscription_transport,
+            subscription_backoff: self.subscription_backoff,
+            subscriptions: Multiplexer::default(),
+            error_policy: self.error_policy,
+            stale_while_revalidate: self.stale_while_revalidate,
+            token_provider: self.token_provider,
+            cookies,
+            outbox: self.outbox,
+            network: self.network.unwrap_or_default(),
+            revalidating: Arc::new(Mutex::new(HashSet::new())),
+            result_key_strategy: self
+                .result_key_strategy
+                .unwrap_or_else(|| Arc::new(CanonicalSha256)),
         })
     }
 }
 
+impl<C: Cache + Send + 'static> Default for DiscoveryClientBuilder<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub struct DiscoveryClient<C> {
     uri: String,
     cache: Option<CacheWrap<C>>,
     reqwest_client: Client,
+    default_headers: HeaderMap,
+    key_headers: HeaderMap,
+    runtime: Arc<dyn Runtime>,
+    links: Vec<Arc<dyn Link>>,
+    metrics: Option<Arc<dyn MetricsRecorder>>,
+    on_error: Option<Arc<ErrorHook>>,
+    persisted_operations: Option<Arc<PersistedQueryManifest>>,
+    result_key_strategy: Arc<dyn ResultKeyStrategy>,
+    active_queries: Arc<Mutex<HashMap<ResultKey, GraphQLRequest>>>,
+    result_errors: Arc<Mutex<HashMap<ResultKey, Vec<GraphQLError>>>>,
+    etags: Arc<Mutex<HashMap<ResultKey, HeaderValue>>>,
+    rate_limits: RateLimits,
+    defaults: Arc<RequestDefaults>,
+    interceptors: Arc<Interceptors>,
+    in_flight: Arc<InFlight>,
+    subscription_transport: Option<SubscriptionTransport>,
+    subscription_backoff: Backoff,
+    subscriptions: Multiplexer<SubscriptionEvent<SseEvent>>,
+    error_policy: ErrorPolicy,
+    stale_while_revalidate: Option<Arc<StaleWhileRevalidate>>,
+    token_provider: Option<Arc<dyn TokenProvider>>,
+    cookies: Option<Cookies>,
+    outbox: Option<Arc<Outbox>>,
+    network: NetworkMonitor,
+    revalidating: Arc<Mutex<HashSet<ResultKey>>>,
+}
+
+impl<C> Clone for DiscoveryClient<C> {
+    fn clone(&self) -> Self {
+        Self {
+            uri: self.uri.clone(),
+            cache: self.cache.clone(),
+            reqwest_client: self.reqwest_client.clone(),
+            default_headers: self.default_headers.clone(),
+            key_headers: self.key_headers.clone(),
+            runtime: self.runtime.clone(),
+            links: self.links.clone(),
+            metrics: self.metrics.clone(),
+            on_error: self.on_error.clone(),
+            persisted_operations: self.persisted_operations.clone(),
+            result_key_strategy: self.result_key_strategy.clone(),
+            active_queries: self.active_queries.clone(),
+            result_errors: self.result_errors.clone(),
+            etags: self.etags.clone(),
+            rate_limits: self.rate_limits.clone(),
+            defaults: self.defaults.clone(),
+            interceptors: self.interceptors.clone(),
+            in_flight: self.in_flight.clone(),
+            subscription_transport: self.subscription_transport.clone(),
+            subscription_backoff: self.subscription_backoff.clone(),
+            subscriptions: self.subscriptions.clone(),
+            error_policy: self.error_policy,
+            stale_while_revalidate: self.stale_while_revalidate.clone(),
+            token_provider: self.token_provider.clone(),
+            cookies: self.cookies.clone(),
+            outbox: self.outbox.clone(),
+            network: self.network.clone(),
+            revalidating: self.revalidating.clone(),
+        }
+    }
+}
+
+pub type ErrorHook = dyn Fn(&str, &ClientError) + Send + Sync;
+
+#[derive(Clone)]
+struct Cookies {
+    jar: Arc<Jar>,
+    url: Url,
+}
+
+struct StaleWhileRevalidate {
+    max_age: Duration,
+    spawn: Box<dyn Fn(BoxFuture<'static, ()>) + Send + Sync>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FetchPolicy {
+    #[default]
+    CacheFirst,
+    CacheAndNetwork,
+    NetworkOnly,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorPolicy {
+    #[default]
+    None,
+    Ignore,
+    All,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RequestOptions {
+    pub headers: HeaderMap,
+    pub context: Map<String, Value>,
+    pub fetch_policy: Option<FetchPolicy>,
+    pub error_policy: Option<ErrorPolicy>,
+    pub timeout: Option<Duration>,
+    pub ttl: Option<Duration>,
+    pub no_cache: bool,
+}
+
+impl RequestOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    pub fn context(mut self, key: impl Into<String>, value: Value) -> Self {
+        self.context.insert(key.into(), value);
+        self
+    }
+
+    pub fn priority(self, priority: Priority) -> Self {
+        self.context(link::PRIORITY_CONTEXT, priority.to_value())
+    }
+
+    pub fn fetch_policy(mut self, fetch_policy: FetchPolicy) -> Self {
+        self.fetch_policy = Some(fetch_policy);
+        self
+    }
+
+    pub fn error_policy(mut self, error_policy: ErrorPolicy) -> Self {
+        self.error_policy = Some(error_policy);
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    pub fn no_cache(mut self) -> Self {
+        self.no_cache = true;
+        self
+    }
+
+    fn reads_cache(&self) -> bool {
+        !self.no_cache && self.fetch_policy != Some(FetchPolicy::NetworkOnly)
+    }
+
+    fn apply(&self, request: &mut GraphQLRequest) {
+        for (name, value) in &self.headers {
+            request.headers.insert(name, value.clone());
+        }
+        request
+            .context
+            .extend(self.context.iter().map(|(k, v)| (k.clone(), v.clone())));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PoolOptions {
+    pub max_idle_per_host: Option<usize>,
+    pub idle_timeout: Option<Duration>,
+    pub tcp_keepalive: Option<Duration>,
+    pub http2_prior_knowledge: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PaginationOptions {
+    pub min_interval: Option<Duration>,
+    pub max_pages: Option<usize>,
+}
+
+const MAX_ACTIVE_QUERIES: usize = 1024;
+
+pub type RefetchResults = Vec<(ResultKey, ClientResult<()>)>;
+
+pub enum RefetchQuery {
+    OperationName(String),
+    Query(QueryBody<Value>),
+}
+
+impl RefetchQuery {
+    pub fn operation_name(name: impl Into<String>) -> Self {
+        Self::OperationName(name.into())
+    }
+
+    pub fn query<Q: GraphQLQuery>(
+        variables: <Q as GraphQLQuery>::Variables,
+    ) -> Result<Self, serde_json::Error> {
+        erase_variables(&Q::build_query(variables)).map(Self::Query)
+    }
 }
 
 #[derive(Error, Debug)]
-enum ClientError {
-    #[error("reqwest error")]
-    ReqwestError(#[from] reqwest::Error),
+pub enum ClientError {
+    #[error("transport error")]
+    TransportError(#[from] reqwest::Error),
+    #[error("http error: {status}")]
+    HttpError { status: StatusCode, body: String },
+    #[error("request error ({status}): {}", .errors.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+    RequestError {
+        status: StatusCode,
+        errors: Vec<GraphQLError>,
+    },
+    #[error("response exceeds {0} bytes")]
+    ResponseTooLarge(u64),
+    #[error("rate limited")]
+    RateLimited { retry_after: Option<Duration> },
     #[error("deserialize error")]
     DeserializeError(#[from] serde_json::Error),
+    #[error("decode error: {0}")]
+    DecodeError(String),
     #[error("data validation error")]
     DataValidationError(#[from] DataValidationError),
+    #[error("cache error")]
+    CacheError(#[from] CacheError),
+    #[error("graphql error: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+    GraphQLError(Vec<GraphQLError>),
+    #[error("operation {0} is not in the persisted operation manifest")]
+    OperationNotPersisted(String),
+    #[error("batch error: {0}")]
+    BatchError(String),
+    #[error("token is not a valid header value")]
+    InvalidToken,
+    #[error("signing error: {0}")]
+    SigningError(String),
+    #[error("transport does not support file uploads")]
+    UploadsNotSupported,
+    #[error("link chain has no terminating link")]
+    LinkChainNotTerminated,
+    #[error("endpoint not registered: {0}")]
+    EndpointNotFound(String),
+    #[error("subscription transport not configured")]
+    SubscriptionTransportNotFound,
+    #[error("invalid live query patch: {0}")]
+    PatchError(String),
+    #[error("request timed out after {0:?}")]
+    Timeout(Duration),
+    #[error("network unreachable")]
+    Offline,
+    #[error("outbox error")]
+    OutboxError(#[from] std::io::Error),
 }
 
-fn request_body_hash<Q: GraphQLQuery>(qb: &QueryBody<<Q as GraphQLQuery>::Variables>) -> String {
-    let b = bincode::serialize(qb).expect("can not serialize");
-    let d = sha1::Sha1::digest(b);
-    base64::encode(d)
+pub struct QueryHandle<'a, Q: GraphQLQuery, C> {
+    client: &'a DiscoveryClient<C>,
+    request_body: QueryBody<<Q as GraphQLQuery>::Variables>,
+    body_hash: ResultKey,
 }
 
-type ClientResult<T> = std::result::Result<T, ClientError>;
+impl<'a, Q: GraphQLQuery, C: Cache + Send + 'static> QueryHandle<'a, Q, C> {
+    pub fn result_key(&self) -> &ResultKey {
+        &self.body_hash
+    }
 
-impl<C: Cache> DiscoveryClient<C> {
-    pub async fn query<Q: GraphQLQuery>(
+    pub async fn result(&self) -> ClientResult<GraphQLResponse<<Q as GraphQLQuery>::ResponseData>> {
+        let operation_name = self.request_body.operation_name;
+        if let Some(cached) = self.client.cached_data(operation_name, &self.body_hash) {
+            return typed_response(
+                Some(&cached.data),
+                self.client.cached_errors(&self.body_hash),
+            );
+        }
+        self.refetch().await
+    }
+
+    pub async fn refetch(
         &self,
-        variable: <Q as GraphQLQuery>::Variables,
-    ) -> ClientResult<Response<<Q as GraphQLQuery>::ResponseData>> {
-        let request_body = Q::build_query(variable);
+    ) -> ClientResult<GraphQLResponse<<Q as GraphQLQuery>::ResponseData>> {
+        self.client
+            .fetch_query::<Q>(&self.request_body, &self.body_hash)
+            .await
+    }
 
-        let body_hash = request_body_hash::<Q>(&request_body);
+    pub async fn fetch_more(
+        &self,
+        variables: <Q as GraphQLQuery>::Variables,
+    ) -> ClientResult<GraphQLResponse<<Q as GraphQLQuery>::ResponseData>> {
+        let (data, errors) = self.fetch_page(&Q::build_query(variables), true).await?;
+        typed_response(data.as_ref(), errors)
+    }
 
-        let cached = self
-            .cache
-            .as_ref()
-            .and_then(|c| c.inner().borrow_mut().get_result_data(&body_hash).ok());
-
-        Ok(if let Some(data) = cached {
-            let response = serde_json::from_value(data.value().clone())?;
-            response
-        } else {
-            let data = Data::new(self.send::<Q>(request_body).await?)?;
-            self.cache.as_ref().and_then(|c| {
-                c.inner()
-                    .borrow_mut()
-                    .store_result_data(&body_hash, data.clone())
-                    .ok()
-            });
-            let response = serde_json::from_value(data.value().clone())?;
-            response
+    pub fn fetch_all<F>(
+        self,
+        connection: &'a str,
+        next: F,
+        options: PaginationOptions,
+    ) -> LocalBoxStream<'a, ClientResult<GraphQLResponse<<Q as GraphQLQuery>::ResponseData>>>
+    where
+        Q: 'a,
+        F: Fn(&str) -> <Q as GraphQLQuery>::Variables + 'a,
+        <Q as GraphQLQuery>::Variables: 'a,
+        <Q as GraphQLQuery>::ResponseData: 'a,
+    {
+        stream::unfold(Some((self, next, None, 0)), move |state| async move {
+            let (handle, next, cursor, pages): (Self, F, Option<String>, usize) = state?;
+            if options.max_pages.is_some_and(|max| pages >= max) {
+                return None;
+            }
+            if let (true, Some(interval)) = (pages > 0, options.min_interval) {
+                handle.client.runtime.sleep(interval).await;
+            }
+            let page = match &cursor {
+                None => handle.fetch_page(&handle.request_body, false).await,
+                Some(cursor) => handle.fetch_page(&Q::build_query(next(cursor)), true).await,
+            };
+            let (data, errors) = match page {
+                Ok(page) => page,
+                Err(e) => return Some((Err(e), None)),
+            };
+            let cursor = data
+                .as_ref()
+                .and_then(|data| next_cursor(data.value(), connection));
+            let response = typed_response(data.as_ref(), errors);
+            Some((response, cursor.map(|c| (handle, next, Some(c), pages + 1))))
         })
+        .boxed_local()
     }
 
-    async fn send<Q: GraphQLQuery>(
+    async fn fetch_page<V: Serialize>(
         &self,
-        query_body: QueryBody<<Q as GraphQLQuery>::Variables>,
-    ) -> ClientResult<Value> {
-        let res = self
-            .reqwest_client
-            .post(self.uri.as_str())
-            .json(&query_body)
-            .send()
-            .await?;
+        body: &QueryBody<V>,
+        merge: bool,
+    ) -> ClientResult<(Option<Data>, Vec<GraphQLError>)> {
+        let response = self.client.send(body).await?;
+        let (data, errors) = apply_error_policy(self.client.error_policy, response)?;
+        let data = match (data, &self.client.cache) {
+            (Some(data), Some(_)) if !merge => {
+                self.client.store_result(&self.body_hash, data, &errors);
+                self.client.current_result(&self.body_hash)
+            }
+            (Some(data), Some(cache)) => {
+                let cache = cache.inner();
+                let mut cache = cache.lock().unwrap();
+                cache.merge_result_data(&self.body_hash, data)?;
+                Some(cache.get_result_data(&self.body_hash)?)
+            }
+            (data, _) => data,
+        };
+        Ok((data, errors))
+    }
+}
+
+fn raw_operation_name(query: &str) -> &str {
+    let is_name = |c: char| c.is_alphanumeric() || c == '_';
+    let rest = query.trim_start();
+    let keyword = rest.find(|c| !is_name(c)).unwrap_or(rest.len());
+    match &rest[..keyword] {
+        "query" | "mutation" | "subscription" => {
+            let rest = rest[keyword..].trim_start();
+            &rest[..rest.find(|c| !is_name(c)).unwrap_or(rest.len())]
+        }
+        _ => "",
+    }
+}
+
+fn next_cursor(data: &Value, connection: &str) -> Option<String> {
+    let page_info = connection
+        .split(" > ")
+        .try_fold(data, |value, field| value.get(field.trim()))?
+        .get("pageInfo")?;
+    match page_info.get("hasNextPage")?.as_bool()? {
+        true => page_info.get("endCursor")?.as_str().map(str::to_string),
+        false => None,
+    }
+}
+
+fn write_result<C: Cache>(
+    cache: &Mutex<C>,
+    result_errors: &Mutex<HashMap<ResultKey, Vec<GraphQLError>>>,
+    body_hash: &ResultKey,
+    data: Data,
+    errors: &[GraphQLError],
+) {
+    let _ = cache.lock().unwrap().store_result_data(body_hash, data);
+    if errors.is_empty() {
+        result_errors.lock().unwrap().remove(body_hash);
+    } else {
+        result_errors
+            .lock()
+            .unwrap()
+            .insert(body_hash.clone(), errors.to_vec());
+    }
+}
+
+fn apply_error_policy(
+    error_policy: ErrorPolicy,
+    response: Response<Value>,
+) -> ClientResult<(Option<Data>, Vec<GraphQLError>)> {
+    let errors: Vec<_> = response
+        .errors
+        .unwrap_or_default()
+        .into_iter()
+        .map(GraphQLError::from)
+        .collect();
+    match (error_policy, errors) {
+        (ErrorPolicy::None, errors) if !errors.is_empty() => Err(ClientError::GraphQLError(errors)),
+        (ErrorPolicy::Ignore, _) => Ok((response.data.map(Data::new).transpose()?, vec![])),
+        (_, errors) => Ok((response.data.map(Data::new).transpose()?, errors)),
+    }
+}
+
+fn erase_variables<V: Serialize>(body: &QueryBody<V>) -> serde_json::Result<QueryBody<Value>> {
+    Ok(QueryBody {
+        variables: serde_json::to_value(&body.variables)?,
+        query: body.query,
+        operation_name: body.operation_name,
+    })
+}
+
+pub type ClientResult<T> = std::result::Result<T, ClientError>;
+
+fn typed_response<T: for<'de> Deserialize<'de>>(
+    data: Option<&Data>,
+    errors: Vec<GraphQLError>,
+) -> ClientResult<GraphQLResponse<T>> {
+    Ok(GraphQLResponse {
+        data: data.map(|d| T::deserialize(d.value())).transpose()?,
+        errors,
+    })
+}
+
+impl<C: Cache + Send + 'static> DiscoveryClient<C> {
+    pub fn cookie_jar(&self) -> Option<&Arc<Jar>> {
+        self.cookies.as_ref().map(|cookies| &cookies.jar)
+    }
+
+    pub fn cookies(&self) -> Option<String> {
+        let cookies = self.cookies.as_ref()?;
+        let header = cookies.jar.cookies(&cookies.url)?;
+        header.to_str().ok().map(str::to_string)
+    }
+
+    pub fn set_cookie(&self, cookie: &str) {
+        if let Some(cookies) = &self.cookies {
+            cookies.jar.add_cookie_str(cookie, &cookies.url);
+        }
+    }
+
+    pub async fn query<Q: GraphQLQuery>(
+        &self,
+        variable: <Q as GraphQLQuery>::Variables,
+    ) -> ClientResult<GraphQLResponse<<Q as GraphQLQuery>::ResponseData>> {
+        self.query_with_options::<Q>(variable, &RequestOptions::default())
+            .await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "discovery.query",
+            skip_all,
+            fields(
+                operation = tracing::field::Empty,
+                result_key = tracing::field::Empty,
+                cache_hit = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn query_with_options<Q: GraphQLQuery>(
+        &self,
+        variable: <Q as GraphQLQuery>::Variables,
+        options: &RequestOptions,
+    ) -> ClientResult<GraphQLResponse<<Q as GraphQLQuery>::ResponseData>> {
+        let request_body = Q::build_query(variable);
+
+        let body_hash = self.track_query(&request_body)?;
+        record!(
+            "operation" = request_body.operation_name,
+            "result_key" = tracing::field::display(&body_hash),
+        );
+        self.query_request(GraphQLRequest::new(&request_body)?, &body_hash, options)
+            .await
+    }
+
+    pub async fn query_raw(
+        &self,
+        query: &str,
+        variables: Value,
+    ) -> ClientResult<GraphQLResponse<Value>> {
+        self.query_raw_with_options(query, variables, &RequestOptions::default())
+            .await
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "discovery.query",
+            skip_all,
+            fields(
+                operation = tracing::field::Empty,
+                result_key = tracing::field::Empty,
+                cache_hit = tracing::field::Empty,
+            )
+        )
+    )]
+    pub async fn query_raw_with_options(
+        &self,
+        query: &str,
+        variables: Value,
+        options: &RequestOptions,
+    ) -> ClientResult<GraphQLResponse<Value>> {
+        let operation_name = raw_operation_name(query);
+        let body_hash = self.result_key_strategy.result_key(&Operation {
+            uri: self.uri.as_str(),
+            operation_name,
+            query,
+            variables: &variables,
+            headers: &self.key_headers,
+        });
+        record!(
+            "operation" = operation_name,
+            "result_key" = tracing::field::display(&body_hash),
+        );
+        let request = GraphQLRequest::raw(operation_name, query, variables);
+        self.track_request(&body_hash, &request);
+        self.query_request(request, &body_hash, options).await
+    }
+
+    async fn query_request<T: for<'de> Deserialize<'de>>(
+        &self,
+        request: GraphQLRequest,
+        body_hash: &ResultKey,
+        options: &RequestOptions,
+    ) -> ClientResult<GraphQLResponse<T>> {
+        if let Some(cached) = options
+            .reads_cache()
+            .then(|| self.cached_data(&request.operation_name, body_hash))
+            .flatten()
+        {
+            let fetch_policy = options.fetch_policy.unwrap_or_default();
+            let refresh = fetch_policy == FetchPolicy::CacheAndNetwork
+                || cached.stale
+                || self.is_too_old(cached.age);
+            let refreshing = refresh && self.revalidate(&request, body_hash);
+            // Cache-and-network answers from the cache only when the network
+            // request can run in the background; otherwise it waits for it.
+            if refreshing || fetch_policy == FetchPolicy::CacheFirst {
+                record!("cache_hit" = true);
+                return typed_response(Some(&cached.data), self.cached_errors(body_hash));
+            }
+        }
+        record!("cache_hit" = false);
+        self.fetch_request_with(request, body_hash, options).await
+    }
+
+    pub fn query_stream<'a, Q>(
+        &'a self,
+        variables: <Q as GraphQLQuery>::Variables,
+        fetch_policy: FetchPolicy,
+    ) -> LocalBoxStream<'a, ClientResult<GraphQLResponse<<Q as GraphQLQuery>::ResponseData>>>
+    where
+        Q: GraphQLQuery,
+        <Q as GraphQLQuery>::Variables: 'a,
+        <Q as GraphQLQuery>::ResponseData: 'a,
+    {
+        self.query_body_stream::<Q>(Q::build_query(variables), fetch_policy)
+    }
+
+    fn query_body_stream<'a, Q>(
+        &'a self,
+        request_body: QueryBody<<Q as GraphQLQuery>::Variables>,
+        fetch_policy: FetchPolicy,
+    ) -> LocalBoxStream<'a, ClientResult<GraphQLResponse<<Q as GraphQLQuery>::ResponseData>>>
+    where
+        Q: GraphQLQuery,
+        <Q as GraphQLQuery>::Variables: 'a,
+        <Q as GraphQLQuery>::ResponseData: 'a,
+    {
+        let body_hash = match self.track_query(&request_body) {
+            Ok(body_hash) => body_hash,
+            Err(e) => return stream::once(async { Err(e) }).boxed_local(),
+        };
+        let cached = match fetch_policy {
+            FetchPolicy::NetworkOnly => None,
+            _ => self
+                .cached_data(request_body.operation_name, &body_hash)
+                .map(|cached| cached.data),
+        };
+        let cached_errors = self.cached_errors(&body_hash);
+        let offline = self.network.is_offline();
+        let network = match (fetch_policy, &cached) {
+            (FetchPolicy::CacheFirst, Some(_)) => None,
+            (FetchPolicy::CacheAndNetwork, Some(_)) if offline => None,
+            _ => Some((request_body, body_hash)),
+        };
+
+        stream::iter(cached.map(|data| typed_response(Some(&data), cached_errors)))
+            .chain(
+                stream::iter(network).then(move |(request_body, body_hash)| async move {
+                    self.fetch_query::<Q>(&request_body, &body_hash).await
+                }),
+            )
+            .boxed_local()
+    }
+
+    pub fn query_handle<Q: GraphQLQuery>(
+        &self,
+        variables: <Q as GraphQLQuery>::Variables,
+    ) -> ClientResult<QueryHandle<'_, Q, C>> {
+        let request_body = Q::build_query(variables);
+        let body_hash = self.track_query(&request_body)?;
+        Ok(QueryHandle {
+            client: self,
+            request_body,
+            body_hash,
+        })
+    }
+
+    pub fn watch_query<'a, Q>(
+        &'a self,
+        variables: <Q as GraphQLQuery>::Variables,
+        fetch_policy: FetchPolicy,
+    ) -> LocalBoxStream<'a, ClientResult<GraphQLResponse<<Q as GraphQLQuery>::ResponseData>>>
+    where
+        Q: GraphQLQuery,
+        <Q as GraphQLQuery>::Variables: 'a,
+        <Q as GraphQLQuery>::ResponseData: 'a,
+    {
+        let request_body = Q::build_query(variables);
+        let body_hash = match self.result_key(&request_body) {
+            Ok(body_hash) => body_hash,
+            Err(e) => return stream::once(async { Err(e) }).boxed_local(),
+        };
+        let changes = self
+            .cache
+            .as_ref()
+            .map(|c| c.inner().lock().unwrap().watch(WatchSelector::Any).1);
+        let last = Rc::new(RefCell::new(None));
+
+        let initial = {
+            let (last, body_hash) = (last.clone(), body_hash.clone());
+            let results =
+                match self.subscription_transport.is_some() && live::is_live(request_body.query) {
+                    true => self.live_query_stream::<Q>(request_body, body_hash.clone()),
+                    false => self.query_body_stream::<Q>(request_body, fetch_policy),
+                };
+            results.inspect(move |_| *last.borrow_mut() = self.current_result(&body_hash))
+        };
+        let updates = stream::iter(changes)
+            .flatten()
+            .ready_chunks(64)
+            .filter_map(move |_| {
+                let data = self
+                    .current_result(&body_hash)
+                    .filter(|data| last.borrow().as_ref() != Some(data));
+                let response = data.map(|data| {
+                    *last.borrow_mut() = Some(data.clone());
+                    typed_response(Some(&data), self.cached_errors(&body_hash))
+                });
+                async move { response }
+            });
+        initial.chain(updates).boxed_local()
+    }
+
+    fn live_query_stream<'a, Q>(
+        &'a self,
+        request_body: QueryBody<<Q as GraphQLQuery>::Variables>,
+        body_hash: ResultKey,
+    ) -> LocalBoxStream<'a, ClientResult<GraphQLResponse<<Q as GraphQLQuery>::ResponseData>>>
+    where
+        Q: GraphQLQuery,
+        <Q as GraphQLQuery>::Variables: 'a,
+        <Q as GraphQLQuery>::ResponseData: 'a,
+    {
+        let error_policy = self.error_policy;
+        let operation_name = request_body.operation_name;
+        stream::once(async move {
+            let request = self.subscription_request(&request_body)?;
+            self.subscription_events(&request).await
+        })
+        .flat_map(|events| match events {
+            Ok(events) => events.map(Ok).boxed_local(),
+            Err(e) => stream::once(async { Err(e) }).boxed_local(),
+        })
+        .filter_map(|event| async move {
+            match event {
+                Ok(SubscriptionEvent::State(_)) => None,
+                Ok(SubscriptionEvent::Next(event)) => Some(Ok(event)),
+                Err(e) => Some(Err(e)),
+            }
+        })
+        .scan(None, move |current, event| {
+            let response = event.and_then(|event: SseEvent| {
+                let payload: LivePayload = serde_json::from_str(&event.data)?;
+                let mut response = payload.apply(current)?;
+                self.interceptors.apply(operation_name, &mut response);
+                let (data, errors) = apply_error_policy(error_policy, response)?;
+                let typed = typed_response(data.as_ref(), errors.clone())?;
+                if let Some(data) = data {
+                    self.store_result(&body_hash, data, &errors);
+                }
+                Ok(typed)
+            });
+            async move { Some(response) }
+        })
+        .boxed_local()
+    }
+
+    fn current_result(&self, body_hash: &ResultKey) -> Option<Data> {
+        self.cache
+            .as_ref()?
+            .inner()
+            .lock()
+            .unwrap()
+            .get_result_data(body_hash)
+            .ok()
+    }
+
+    pub fn poll_query<'a, Q>(
+        &'a self,
+        variables: <Q as GraphQLQuery>::Variables,
+        options: impl Into<PollOptions>,
+    ) -> (
+        PollHandle,
+        LocalBoxStream<'a, ClientResult<GraphQLResponse<<Q as GraphQLQuery>::ResponseData>>>,
+    )
+    where
+        Q: GraphQLQuery,
+        <Q as GraphQLQuery>::Variables: 'a,
+        <Q as GraphQLQuery>::ResponseData: 'a,
+    {
+        let options = options.into();
+        let handle = PollHandle::default();
+        let request_body = Q::build_query(variables);
+
+        let body_hash = match self.track_query(&request_body) {
+            Ok(body_hash) => body_hash,
+            Err(e) => return (handle, stream::once(async { Err(e) }).boxed_local()),
+        };
+        let polling = handle.clone();
+        let results = stream::unfold(
+            (request_body, body_hash, false),
+            move |(request_body, body_hash, started)| {
+                let polling = polling.clone();
+                async move {
+                    if started {
+                        self.runtime.sleep(options.delay()).await;
+                    }
+                    polling.resumed().await;
+                    let response = self.fetch_query::<Q>(&request_body, &body_hash).await;
+                    Some((response, (request_body, body_hash, true)))
+                }
+            },
+        )
+        .boxed_local();
+        (handle, results)
+    }
+
+    pub fn query_incremental<'a, Q>(
+        &'a self,
+        variables: <Q as GraphQLQuery>::Variables,
+    ) -> LocalBoxStream<'a, ClientResult<GraphQLResponse<<Q as GraphQLQuery>::ResponseData>>>
+    where
+        Q: GraphQLQuery,
+        <Q as GraphQLQuery>::ResponseData: 'a,
+    {
+        let request_body = Q::build_query(variables);
+        let prepared = self.track_query(&request_body).and_then(|body_hash| {
+            let mut request = GraphQLRequest::new(&request_body)?;
+            request
+                .headers
+                .insert(ACCEPT, HeaderValue::from_static(ACCEPT_INCREMENTAL));
+            Ok((body_hash, request))
+        });
+        let (body_hash, request) = match prepared {
+            Ok(prepared) => prepared,
+            Err(e) => return stream::once(async { Err(e) }).boxed_local(),
+        };
+
+        let error_policy = self.error_policy;
+        let operation_name = request.operation_name.clone();
+        stream::once(self.execute(request))
+            .flat_map(|response| match response {
+                Ok(response) => {
+                    let initial = IncrementalPayload {
+                        data: response.body.data,
+                        errors: response.body.errors,
+                        incremental: vec![],
+                        has_next: response.incremental.is_some(),
+                    };
+                    stream::once(async { Ok(initial) })
+                        .chain(stream::iter(response.incremental).flatten())
+                        .boxed_local()
+                }
+                Err(e) => stream::once(async { Err(e) }).boxed_local(),
+            })
+            .scan(IncrementalResult::new(), |result, payload| {
+                let response = payload.map(|payload| {
+                    let changed = result.apply(payload);
+                    changed.then(|| result.response())
+                });
+                async move { Some(response.transpose()) }
+            })
+            .filter_map(|response| async move { response })
+            .map(move |response| {
+                let mut response = response?;
+                self.interceptors.apply(&operation_name, &mut response);
+                let (data, errors) = apply_error_policy(error_policy, response)?;
+                let typed = typed_response(data.as_ref(), errors.clone())?;
+                if let Some(data) = data {
+                    self.store_result(&body_hash, data, &errors);
+                }
+                Ok(typed)
+            })
+            .boxed_local()
+    }
+
+    fn track_query<V: Serialize>(&self, request_body: &QueryBody<V>) -> ClientResult<ResultKey> {
+        let body_hash = self.result_key(request_body)?;
+        if self.cache.is_some() {
+            self.track_request(&body_hash, &GraphQLRequest::new(request_body)?);
+        }
+        Ok(body_hash)
+    }
+
+    fn track_request(&self, body_hash: &ResultKey, request: &GraphQLRequest) {
+        let Some(c) = self.cache.as_ref() else {
+            return;
+        };
+        let mut active_queries = self.active_queries.lock().unwrap();
+        if active_queries.len() >= MAX_ACTIVE_QUERIES {
+            let cached: HashSet<_> = c
+                .inner()
+                .lock()
+                .unwrap()
+                .result_keys()
+                .into_iter()
+                .collect();
+            active_queries.retain(|key, _| cached.contains(key));
+        }
+        active_queries.insert(body_hash.clone(), request.clone());
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "discovery.cache.read",
+            skip_all,
+            fields(result_key = %body_hash, cache_hit = tracing::field::Empty)
+        )
+    )]
+    fn cached_data(&self, operation_name: &str, body_hash: &ResultKey) -> Option<CachedResult> {
+        let data = self
+            .cache
+            .as_ref()
+            .and_then(|c| c.inner().lock().unwrap().read_result(body_hash).ok());
+        record!("cache_hit" = data.is_some());
+        if let Some(metrics) = &self.metrics {
+            metrics.record_cache_lookup(operation_name, data.is_some());
+        }
+        if data.is_some() {
+            log::debug!(
+                target: LOG_TARGET,
+                "{} result_key={} cache_hit=true",
+                operation_name,
+                body_hash
+            );
+        }
+        data
+    }
+
+    fn is_too_old(&self, age: Duration) -> bool {
+        self.stale_while_revalidate
+            .as_ref()
+            .is_some_and(|swr| age >= swr.max_age)
+    }
+
+    fn revalidate(&self, request: &GraphQLRequest, body_hash: &ResultKey) -> bool {
+        let (swr, cache) = match (&self.stale_while_revalidate, &self.cache) {
+            (Some(swr), Some(cache)) => (swr, cache.inner()),
+            _ => return false,
+        };
+        if !self.revalidating.lock().unwrap().insert(body_hash.clone()) {
+            return true;
+        }
+        log::debug!(
+            target: LOG_TARGET,
+            "{} result_key={} revalidating",
+            request.operation_name,
+            body_hash
+        );
+
+        let request = request.clone();
+        let links = self.links.clone();
+        let revalidating = self.revalidating.clone();
+        let result_errors = self.result_errors.clone();
+        let body_hash = body_hash.clone();
+        let error_policy = self.error_policy;
+        (swr.spawn)(Box::pin(async move {
+            if let Ok(response) = Next::new(&links).run(request).await {
+                if let Ok((Some(data), errors)) = apply_error_policy(error_policy, response.body) {
+                    write_result(&cache, &result_errors, &body_hash, data, &errors);
+                }
+            }
+            revalidating.lock().unwrap().remove(&body_hash);
+        }));
+        true
+    }
+
+    fn cached_errors(&self, body_hash: &ResultKey) -> Vec<GraphQLError> {
+        self.result_errors
+            .lock()
+            .unwrap()
+            .get(body_hash)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    async fn fetch_query<Q: GraphQLQuery>(
+        &self,
+        request_body: &QueryBody<<Q as GraphQLQuery>::Variables>,
+        body_hash: &ResultKey,
+    ) -> ClientResult<GraphQLResponse<<Q as GraphQLQuery>::ResponseData>> {
+        let request = GraphQLRequest::new(request_body)?;
+        self.fetch_request_with(request, body_hash, &RequestOptions::default())
+            .await
+    }
+
+    async fn fetch_request_with<T: for<'de> Deserialize<'de>>(
+        &self,
+        mut request: GraphQLRequest,
+        body_hash: &ResultKey,
+        options: &RequestOptions,
+    ) -> ClientResult<GraphQLResponse<T>> {
+        let _in_flight = self.in_flight.start();
+        options.apply(&mut request);
+        let etag = self.etags.lock().unwrap().get(body_hash).cloned();
+        if let Some(etag) =
+            etag.filter(|_| !options.no_cache && self.current_result(body_hash).is_some())
+        {
+            request.headers.entry(IF_NONE_MATCH).or_insert(etag);
+        }
+
+        let response = self.execute_within(request, options.timeout).await?;
+        if response.status == StatusCode::NOT_MODIFIED {
+            return self.not_modified(body_hash);
+        }
+        match response.headers.get(ETAG) {
+            Some(etag) => self
+                .etags
+                .lock()
+                .unwrap()
+                .insert(body_hash.clone(), etag.clone()),
+            None => self.etags.lock().unwrap().remove(body_hash),
+        };
+        let error_policy = options.error_policy.unwrap_or(self.error_policy);
+        let (data, errors) = apply_error_policy(error_policy, response.body)?;
+        let typed = typed_response(data.as_ref(), errors.clone())?;
+        if let Some(data) = data.filter(|_| !options.no_cache) {
+            self.store_result(body_hash, data, &errors);
+            if let (Some(c), Some(ttl)) = (self.cache.as_ref(), options.ttl) {
+                c.inner()
+                    .lock()
+                    .unwrap()
+                    .set_result_ttl(body_hash, Some(ttl));
+            }
+        }
+        Ok(typed)
+    }
+
+    fn not_modified<T: for<'de> Deserialize<'de>>(
+        &self,
+        body_hash: &ResultKey,
+    ) -> ClientResult<GraphQLResponse<T>> {
+        let cached = match self.cache.as_ref() {
+            Some(c) => {
+                let cache = c.inner();
+                let mut cache = cache.lock().unwrap();
+                cache.touch_result(body_hash);
+                cache.get_result_data(body_hash)
+            }
+            None => Err(CacheError::ResultKeyNotFound(body_hash.clone())),
+        };
+        match cached {
+            Ok(data) => typed_response(Some(&data), self.cached_errors(body_hash)),
+            Err(e) => {
+                self.etags.lock().unwrap().remove(body_hash);
+                Err(e.into())
+            }
+        }
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "discovery.cache.write",
+            skip_all,
+            fields(result_key = %body_hash)
+        )
+    )]
+    fn store_result(&self, body_hash: &ResultKey, data: Data, errors: &[GraphQLError]) {
+        if let Some(c) = self.cache.as_ref() {
+            write_result(&c.inner(), &self.result_errors, body_hash, data, errors);
+        }
+    }
+
+    pub async fn mutate<M: GraphQLQuery>(
+        &self,
+        variables: <M as GraphQLQuery>::Variables,
+    ) -> ClientResult<GraphQLResponse<<M as GraphQLQuery>::ResponseData>> {
+        self.mutate_with_update::<M, _>(variables, |_, _| {}).await
+    }
+
+    pub async fn mutate_with_options<M: GraphQLQuery>(
+        &self,
+        variables: <M as GraphQLQuery>::Variables,
+        options: &RequestOptions,
+    ) -> ClientResult<GraphQLResponse<<M as GraphQLQuery>::ResponseData>> {
+        let mut request = GraphQLRequest::new(&M::build_query(variables))?;
+        options.apply(&mut request);
+        let response = self.execute_within(request, options.timeout).await?;
+        self.store_mutation_response_with::<M, _>(response.body, options, |_, _| {})
+    }
+
+    pub async fn mutate_with_update<M, F>(
+        &self,
+        variables: <M as GraphQLQuery>::Variables,
+        update: F,
+    ) -> ClientResult<GraphQLResponse<<M as GraphQLQuery>::ResponseData>>
+    where
+        M: GraphQLQuery,
+        F: FnOnce(&mut C, &<M as GraphQLQuery>::ResponseData),
+    {
+        let request_body = M::build_query(variables);
+
+        let response = self.send(&request_body).await?;
+        self.store_mutation_response::<M, F>(response, update)
+    }
+
+    pub async fn mutate_with_optimistic<M: GraphQLQuery>(
+        &self,
+        variables: <M as GraphQLQuery>::Variables,
+        optimistic_data: impl Serialize,
+    ) -> ClientResult<GraphQLResponse<<M as GraphQLQuery>::ResponseData>> {
+        let request_body = M::build_query(variables);
+
+        let optimistic = Data::new(serde_json::to_value(optimistic_data)?)?;
+        let optimistic_id = match self.cache.as_ref() {
+            Some(c) => Some(c.inner().lock().unwrap().write_optimistic(optimistic)?),
+            None => None,
+        };
+
+        let response = self.send(&request_body).await;
+        if let (Some(c), Some(id)) = (self.cache.as_ref(), optimistic_id) {
+            c.inner().lock().unwrap().remove_optimistic(id);
+        }
+        self.store_mutation_response::<M, _>(response?, |_, _| {})
+    }
+
+    fn store_mutation_response<M, F>(
+        &self,
+        response: Response<Value>,
+        update: F,
+    ) -> ClientResult<GraphQLResponse<<M as GraphQLQuery>::ResponseData>>
+    where
+        M: GraphQLQuery,
+        F: FnOnce(&mut C, &<M as GraphQLQuery>::ResponseData),
+    {
+        self.store_mutation_response_with::<M, F>(response, &RequestOptions::default(), update)
+    }
+
+    fn store_mutation_response_with<M, F>(
+        &self,
+        response: Response<Value>,
+        options: &RequestOptions,
+        update: F,
+    ) -> ClientResult<GraphQLResponse<<M as GraphQLQuery>::ResponseData>>
+    where
+        M: GraphQLQuery,
+        F: FnOnce(&mut C, &<M as GraphQLQuery>::ResponseData),
+    {
+        let error_policy = options.error_policy.unwrap_or(self.error_policy);
+        let (data, errors) = apply_error_policy(error_policy, response)?;
+        let cache = self.cache.as_ref().filter(|_| !options.no_cache);
+        if let (Some(c), Some(data)) = (cache, data.as_ref()) {
+            let _ = c.inner().lock().unwrap().store_mutation_data(data.clone());
+        }
+        let response = typed_response(data.as_ref(), errors)?;
+        if let (Some(c), Some(data)) = (cache, response.data.as_ref()) {
+            update(&mut c.inner().lock().unwrap(), data);
+        }
+        Ok(response)
+    }
+
+    pub fn network_monitor(&self) -> &NetworkMonitor {
+        &self.network
+    }
+
+    pub fn network_status(&self) -> NetworkStatus {
+        self.network.status()
+    }
+
+    pub fn extract_cache(&self) -> HydrationSnapshot {
+        match &self.cache {
+            Some(cache) => HydrationSnapshot::capture(&*cache.inner().lock().unwrap()),
+            None => HydrationSnapshot::new(),
+        }
+    }
+
+    pub async fn extract_cache_when_settled(&self) -> HydrationSnapshot {
+        self.in_flight.settled().await;
+        self.extract_cache()
+    }
+
+    pub fn rate_limit_state(&self) -> RateLimitState {
+        self.rate_limits.state()
+    }
+
+    pub fn outbox(&self) -> Option<&Outbox> {
+        self.outbox.as_deref()
+    }
+
+    pub async fn mutate_or_queue<M: GraphQLQuery>(
+        &self,
+        variables: <M as GraphQLQuery>::Variables,
+    ) -> ClientResult<MutationOutcome<GraphQLResponse<<M as GraphQLQuery>::ResponseData>>> {
+        let request_body = erase_variables(&M::build_query(variables))?;
+        let Some(outbox) = self.outbox.as_ref() else {
+            let response = self.send(&request_body).await?;
+            return Ok(MutationOutcome::Sent(
+                self.store_mutation_response::<M, _>(response, |_, _| {})?,
+            ));
+        };
+        let enqueue = || {
+            outbox.enqueue(
+                request_body.operation_name,
+                request_body.query,
+                request_body.variables.clone(),
+            )
+        };
+
+        if !outbox.is_empty() || self.network.is_offline() {
+            return Ok(MutationOutcome::Queued(enqueue()?));
+        }
+        match self.send(&request_body).await {
+            Ok(response) => Ok(MutationOutcome::Sent(
+                self.store_mutation_response::<M, _>(response, |_, _| {})?,
+            )),
+            Err(e) if outbox::is_offline(&e) => Ok(MutationOutcome::Queued(enqueue()?)),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub async fn replay_outbox(&self) -> ClientResult<ReplaySummary> {
+        let mut summary = ReplaySummary::default();
+        let Some(outbox) = self.outbox.as_ref() else {
+            return Ok(summary);
+        };
+
+        while let Some(mutation) = outbox.front() {
+            let request = GraphQLRequest::raw(
+                &mutation.operation_name,
+                &mutation.query,
+                mutation.variables.clone(),
+            );
+            let result = match self.send_request(request).await {
+                Ok(response) => apply_error_policy(ErrorPolicy::None, response),
+                Err(e) => Err(e),
+            };
+            let error = match result {
+                Ok((data, _)) => {
+                    if let (Some(c), Some(data)) = (self.cache.as_ref(), data) {
+                        let _ = c.inner().lock().unwrap().store_mutation_data(data);
+                    }
+                    outbox.complete(mutation.id)?;
+                    summary.sent += 1;
+                    continue;
+                }
+                Err(e) if outbox::is_offline(&e) => break,
+                Err(e) => e,
+            };
+            match outbox.conflict(&mutation, &error) {
+                Conflict::Discard => {
+                    outbox.complete(mutation.id)?;
+                    summary.discarded += 1;
+                }
+                Conflict::Retry(variables) => outbox.retry(mutation.id, variables)?,
+                Conflict::Stop => break,
+            }
+        }
+
+        summary.remaining = outbox.len();
+        Ok(summary)
+    }
+
+    pub async fn mutate_with_refetch<M: GraphQLQuery>(
+        &self,
+        variables: <M as GraphQLQuery>::Variables,
+        refetch_queries: &[RefetchQuery],
+    ) -> ClientResult<(
+        GraphQLResponse<<M as GraphQLQuery>::ResponseData>,
+        RefetchResults,
+    )> {
+        let response = self.mutate::<M>(variables).await?;
+        let refetched = self.refetch_queries(refetch_queries).await?;
+        Ok((response, refetched))
+    }
+
+    pub async fn refetch_queries(&self, queries: &[RefetchQuery]) -> ClientResult<RefetchResults> {
+        let mut refetched = vec![];
+        for query in queries {
+            let targets: Vec<_> = match query {
+                RefetchQuery::OperationName(name) => self
+                    .active_queries
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .filter(|(_, request)| request.operation_name == *name)
+                    .map(|(key, request)| (key.clone(), request.clone()))
+                    .collect(),
+                RefetchQuery::Query(body) => {
+                    vec![(self.result_key(body)?, GraphQLRequest::new(body)?)]
+                }
+            };
+            for (key, request) in targets {
+                let result = self
+                    .fetch_request_with::<Value>(request, &key, &RequestOptions::default())
+                    .await
+                    .map(|_| ());
+                refetched.push((key, result));
+            }
+        }
+        Ok(refetched)
+    }
+
+    pub async fn subscribe<S: GraphQLQuery>(
+        &self,
+        variables: <S as GraphQLQuery>::Variables,
+    ) -> ClientResult<
+        LocalBoxStream<
+            'static,
+            ClientResult<SubscriptionEvent<GraphQLResponse<<S as GraphQLQuery>::ResponseData>>>,
+        >,
+    > {
+        let request = self.subscription_request(&S::build_query(variables))?;
+        let events = self.subscription_events(&request).await?;
+        let error_policy = self.error_policy;
+        let (interceptors, operation_name) = (self.interceptors.clone(), request.operation_name);
+        Ok(events
+            .map(move |event| match event {
+                SubscriptionEvent::State(state) => Ok(SubscriptionEvent::State(state)),
+                SubscriptionEvent::Next(event) => {
+                    let mut response: Response<Value> = serde_json::from_str(&event.data)?;
+                    interceptors.apply(&operation_name, &mut response);
+                    let (data, errors) = apply_error_policy(error_policy, response)?;
+                    typed_response(data.as_ref(), errors).map(SubscriptionEvent::Next)
+                }
+            })
+            .boxed_local())
+    }
+
+    fn subscription_request<V: Serialize>(
+        &self,
+        query_body: &QueryBody<V>,
+    ) -> ClientResult<GraphQLRequest> {
+        let mut request = GraphQLRequest::new(query_body)?;
+        self.defaults.apply(&mut request);
+        if let Some(manifest) = &self.persisted_operations {
+            manifest.persist(&mut request)?;
+        }
+        Ok(request)
+    }
+
+    async fn subscription_events(
+        &self,
+        request: &GraphQLRequest,
+    ) -> ClientResult<LocalBoxStream<'static, SubscriptionEvent<SseEvent>>> {
+        let uri = match &self.subscription_transport {
+            Some(SubscriptionTransport::Sse { uri }) => uri.as_ref().unwrap_or(&self.uri),
+            None => return Err(ClientError::SubscriptionTransportNotFound),
+        };
+        let body = request.body();
+
+        let key = format!("{}\0{}", uri, canonical_json(&body));
+        let events = match self.subscriptions.join(&key) {
+            Some(events) => events,
+            None => {
+                let events = resumable_sse_events(
+                    self.reqwest_client.clone(),
+                    uri.clone(),
+                    self.default_headers.clone(),
+                    self.token_provider.clone(),
+                    body,
+                    self.subscription_backoff.clone(),
+                    self.runtime.clone(),
+                )
+                .await?;
+                self.subscriptions.insert(&key, events.boxed())
+            }
+        };
+        Ok(events.boxed_local())
+    }
+
+    fn result_key<V: Serialize>(&self, query_body: &QueryBody<V>) -> ClientResult<ResultKey> {
+        let variables = serde_json::to_value(&query_body.variables)?;
+        Ok(self.result_key_strategy.result_key(&Operation {
+            uri: self.uri.as_str(),
+            operation_name: query_body.operation_name,
+            query: query_body.query,
+            variables: &variables,
+            headers: &self.key_headers,
+        }))
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "discovery.send",
+            skip_all,
+            fields(operation = %request.operation_name, bytes = tracing::field::Empty)
+        )
+    )]
+    async fn execute(&self, request: GraphQLRequest) -> ClientResult<LinkResponse> {
+        let operation_name = request.operation_name.clone();
+        let started = Instant::now();
+        let response = Next::new(&self.links).run(request).await;
+        if let Some(metrics) = &self.metrics {
+            let success = matches!(&response, Ok(r) if r.body.errors.is_none());
+            metrics.record_request(&operation_name, started.elapsed(), success);
+        }
+        match &response {
+            Ok(_) => self.network.record_success(),
+            Err(e) => self.network.record_failure(e),
+        }
+        if let Some(hook) = &self.on_error {
+            let errors = match &response {
+                Ok(response) => response.body.errors.as_deref().unwrap_or_default(),
+                Err(e) => {
+                    hook(&operation_name, e);
+                    &[]
+                }
+            };
+            if !errors.is_empty() {
+                let errors = errors.iter().cloned().map(GraphQLError::from).collect();
+                hook(&operation_name, &ClientError::GraphQLError(errors));
+            }
+        }
+        response
+    }
+
+    async fn send_request(&self, request: GraphQLRequest) -> ClientResult<Response<Value>> {
+        Ok(self.execute(request).await?.body)
+    }
+
+    async fn send<V: Serialize>(&self, query_body: &QueryBody<V>) -> ClientResult<Response<Value>> {
+        self.send_request(GraphQLRequest::new(query_body)?).await
+    }
+
+    async fn execute_within(
+        &self,
+        request: GraphQLRequest,
+        timeout: Option<Duration>,
+    ) -> ClientResult<LinkResponse> {
+        let Some(timeout) = timeout else {
+            return self.execute(request).await;
+        };
+        match future::select(Box::pin(self.execute(request)), self.runtime.sleep(timeout)).await {
+            Either::Left((response, _)) => response,
+            Either::Right(_) => Err(ClientError::Timeout(timeout)),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test_support {
+    use bytes::Bytes;
+    use futures::future::{self, BoxFuture};
+    use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+    use reqwest::StatusCode;
+    use serde_json::{json, Value};
+    use std::sync::{Arc, Mutex};
+
+    use crate::cache::InMemoryCache;
+    use crate::client::{CacheWrap, ClientResult, DiscoveryClientBuilder};
+    use crate::link::{Transport, TransportRequest, TransportResponse};
+
+    macro_rules! operation {
+        ($name:ident, $query:literal) => {
+            $crate::client::test_support::operation!($name, $query, serde_json::Value);
+        };
+        ($name:ident, $query:literal, $data:ty) => {
+            struct $name;
+
+            impl graphql_client::GraphQLQuery for $name {
+                type Variables = ();
+                type ResponseData = $data;
+
+                fn build_query(variables: ()) -> graphql_client::QueryBody<()> {
+                    graphql_client::QueryBody {
+                        variables,
+                        query: $query,
+                        operation_name: stringify!($name),
+                    }
+                }
+            }
+        };
+    }
+    pub(crate) use operation;
+
+    type Reply = dyn Fn(&TransportRequest) -> ClientResult<TransportResponse> + Send + Sync;
+
+    /// Answers each request with `reply` and keeps every request it answered.
+    #[derive(Clone)]
+    pub(crate) struct MockTransport {
+        reply: Arc<Reply>,
+        stall_on: Option<&'static str>,
+        requests: Arc<Mutex<Vec<TransportRequest>>>,
+    }
+
+    impl MockTransport {
+        pub(crate) fn new(
+            reply: impl Fn(&TransportRequest) -> ClientResult<TransportResponse> + Send + Sync + 'static,
+        ) -> Self {
+            Self {
+                reply: Arc::new(reply),
+                stall_on: None,
+                requests: Arc::new(Mutex::new(vec![])),
+            }
+        }
+
+        pub(crate) fn data(
+            data: impl Fn(&TransportRequest) -> Value + Send + Sync + 'static,
+        ) -> Self {
+            Self::new(move |request| json_response(json!({ "data": data(request) })))
+        }
+
+        /// Requests carrying `header` never complete.
+        pub(crate) fn stall_on(mut self, header: &'static str) -> Self {
+            self.stall_on = Some(header);
+            self
+        }
+
+        pub(crate) fn requests(&self) -> Vec<TransportRequest> {
+            self.requests.lock().unwrap().clone()
+        }
+
+        pub(crate) fn operations(&self) -> Vec<String> {
+            self.requests()
+                .iter()
+                .map(|request| {
+                    request.body["operationName"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string()
+                })
+                .collect()
+        }
+
+        pub(crate) fn header_values(&self, name: HeaderName) -> Vec<Option<HeaderValue>> {
+            self.requests()
+                .iter()
+                .map(|request| request.headers.get(&name).cloned())
+                .collect()
+        }
+    }
+
+    impl Transport for MockTransport {
+        fn execute(
+            &self,
+            request: TransportRequest,
+        ) -> BoxFuture<'_, ClientResult<TransportResponse>> {
+            if self
+                .stall_on
+                .is_some_and(|header| request.headers.contains_key(header))
+            {
+                return Box::pin(future::pending());
+            }
+            let response = (self.reply)(&request);
+            self.requests.lock().unwrap().push(request);
+            Box::pin(future::ready(response))
+        }
+    }
+
+    pub(crate) fn json_response(body: Value) -> ClientResult<TransportResponse> {
+        Ok(TransportResponse {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: Bytes::from(serde_json::to_vec(&body)?),
+        })
+    }
+
+    pub(crate) fn client_builder(
+        transport: MockTransport,
+    ) -> DiscoveryClientBuilder<InMemoryCache> {
+        DiscoveryClientBuilder::new()
+            .uri("http://localhost/graphql".to_string())
+            .cache(CacheWrap::new(InMemoryCache::new()))
+            .transport(transport)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_support::{client_builder, json_response, operation, MockTransport};
+    use super::*;
+    use crate::cache::{InMemoryCache, Key};
+    use bytes::Bytes;
+    use futures::executor::block_on;
+    use link::TransportResponse;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn partial_response() -> Response<Value> {
+        serde_json::from_value(json!({
+          "data": { "person": null },
+          "errors": [{ "message": "not found", "path": ["person"] }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn request_options_override_headers() {
+        let mut request = GraphQLRequest::raw("Person", "query Person { a }", json!({}));
+        request
+            .headers
+            .insert("x-tenant-id", HeaderValue::from_static("default"));
+
+        RequestOptions::new()
+            .header(
+                HeaderName::from_static("x-tenant-id"),
+                HeaderValue::from_static("acme"),
+            )
+            .context("retry", json!(false))
+            .apply(&mut request);
+
+        assert_eq!(request.headers["x-tenant-id"], "acme");
+        assert_eq!(request.context["retry"], json!(false));
+    }
+
+    #[test]
+    fn raw_operation_names() {
+        assert_eq!(raw_operation_name("query Person($id: ID!) { a }"), "Person");
+        assert_eq!(raw_operation_name("  mutation Save{ a }"), "Save");
+        assert_eq!(raw_operation_name("query { a }"), "");
+        assert_eq!(raw_operation_name("query($id: ID!) { a }"), "");
+        assert_eq!(raw_operation_name("{ person { name } }"), "");
+    }
+
+    #[test]
+    fn follow_end_cursor() {
+        let page = |has_next_page: bool| {
+            json!({
+              "person": {
+                "friends": {
+                  "edges": [],
+                  "pageInfo": { "endCursor": "YXJyYXk6MQ==", "hasNextPage": has_next_page }
+                }
+              }
+            })
+        };
+
+        assert_eq!(
+            next_cursor(&page(true), "person > friends").as_deref(),
+            Some("YXJyYXk6MQ==")
+        );
+        assert_eq!(next_cursor(&page(false), "person > friends"), None);
+        assert_eq!(next_cursor(&page(true), "person > films"), None);
+    }
+
+    #[test]
+    fn error_policy() {
+        assert!(matches!(
+            apply_error_policy(ErrorPolicy::None, partial_response()),
+            Err(ClientError::GraphQLError(errors)) if errors.len() == 1
+        ));
+
+        let (data, errors) = apply_error_policy(ErrorPolicy::Ignore, partial_response()).unwrap();
+        assert!(data.is_some());
+        assert!(errors.is_empty());
+
+        let (data, errors) = apply_error_policy(ErrorPolicy::All, partial_response()).unwrap();
+        assert!(data.is_some());
+        assert_eq!(errors[0].message, "not found");
+    }
+
+    #[test]
+    fn client_error_chains_source() {
+        let error = ClientError::from(CacheError::KeyNotFound(Key::new("Person", "1")));
+        assert_eq!(error.to_string(), "cache error");
+        assert_eq!(
+            std::error::Error::source(&error).unwrap().to_string(),
+            "key not found"
+        );
+
+        let error = apply_error_policy(ErrorPolicy::None, partial_response()).unwrap_err();
+        assert_eq!(error.to_string(), "graphql error: not found (at person)");
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn seed_and_extract_cookies() {
+        let client = DiscoveryClientBuilder::<InMemoryCache>::new()
+            .uri("http://localhost/graphql".to_string())
+            .cookie_store(true)
+            .build()
+            .unwrap();
+
+        client.set_cookie("session=abc; Path=/");
+        assert_eq!(client.cookies().as_deref(), Some("session=abc"));
+
+        let client = DiscoveryClientBuilder::<InMemoryCache>::new()
+            .uri("http://localhost/graphql".to_string())
+            .build()
+            .unwrap();
+        client.set_cookie("session=abc");
+        assert!(client.cookie_jar().is_none() && client.cookies().is_none());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn share_client_across_threads() {
+        fn assert_send_sync<T: Send + Sync + Clone>(_: &T) {}
+        fn assert_send<T: Send>(_: T) {}
+
+        let client = DiscoveryClientBuilder::<InMemoryCache>::new()
+            .uri("http://localhost/graphql".to_string())
+            .cache(CacheWrap::new(InMemoryCache::new()))
+            .build()
+            .unwrap();
+
+        assert_send_sync(&client);
+        assert_send(client.query_raw("query Me { me { name } }", json!({})));
+        assert_send(client.refetch_queries(&[]));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn shared_http_client_keeps_default_headers() {
+        let builder = || {
+            DiscoveryClientBuilder::<InMemoryCache>::new()
+                .uri("http://localhost/graphql".to_string())
+                .client_name("web".to_string())
+        };
+
+        let client = builder().http_client(Client::new()).build().unwrap();
+        assert_eq!(client.default_headers["apollographql-client-name"], "web");
+
+        let client = builder()
+            .pool(PoolOptions {
+                max_idle_per_host: Some(4),
+                ..PoolOptions::default()
+            })
+            .build()
+            .unwrap();
+        assert!(client.default_headers.is_empty());
+    }
+
+    #[cfg(all(feature = "native-tls", feature = "tokio"))]
+    #[test]
+    fn select_tls_backend() {
+        let client = DiscoveryClientBuilder::<InMemoryCache>::new()
+            .uri("https://localhost/graphql".to_string())
+            .tls_backend(TlsBackend::NativeTls)
+            .build();
+
+        assert!(client.is_ok());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn conditional_refetch_with_etag() {
+        operation!(Countries, "query Countries { countries }");
+
+        let transport = MockTransport::new(|request| {
+            let mut headers = HeaderMap::new();
+            headers.insert(ETAG, HeaderValue::from_static("\"v1\""));
+            let (status, body) = match request.headers.contains_key(IF_NONE_MATCH) {
+                true => (StatusCode::NOT_MODIFIED, Bytes::new()),
+                false => (
+                    StatusCode::OK,
+                    Bytes::from_static(br#"{ "data": { "countries": ["JP"] } }"#),
+                ),
+            };
+            Ok(TransportResponse {
+                status,
+                headers,
+                body,
+            })
+        });
+        let client = client_builder(transport.clone()).build().unwrap();
+
+        let handle = client.query_handle::<Countries>(()).unwrap();
+        let first = block_on(handle.refetch()).unwrap();
+        let second = block_on(handle.refetch()).unwrap();
+
+        assert_eq!(first.data, Some(json!({ "countries": ["JP"] })));
+        assert_eq!(second.data, first.data);
+        assert_eq!(
+            transport.header_values(IF_NONE_MATCH),
+            vec![None, Some(HeaderValue::from_static("\"v1\""))]
+        );
+    }
+
+    #[test]
+    fn per_call_options() {
+        operation!(Countries, "query Countries { countries }");
+
+        let transport = MockTransport::data(|_| json!({ "countries": ["JP"] })).stall_on("x-slow");
+        let client = client_builder(transport.clone())
+            .runtime(|_: Duration| -> BoxFuture<'static, ()> { Box::pin(async {}) })
+            .build()
+            .unwrap();
+        let query = |options: RequestOptions| {
+            block_on(client.query_with_options::<Countries>((), &options))
+        };
+
+        query(RequestOptions::new().no_cache()).unwrap();
+        query(RequestOptions::new()).unwrap();
+        query(RequestOptions::new()).unwrap();
+        assert_eq!(transport.requests().len(), 2);
+
+        query(RequestOptions::new().fetch_policy(FetchPolicy::NetworkOnly)).unwrap();
+        assert_eq!(transport.requests().len(), 3);
+
+        let slow = RequestOptions::new()
+            .header(
+                HeaderName::from_static("x-slow"),
+                HeaderValue::from_static("1"),
+            )
+            .fetch_policy(FetchPolicy::NetworkOnly)
+            .timeout(Duration::from_millis(10));
+        assert!(matches!(query(slow), Err(ClientError::Timeout(_))));
+    }
+
+    #[test]
+    fn query_stream_fetch_policies() {
+        operation!(Count, "query Count { count }");
+
+        let served = AtomicUsize::new(0);
+        let client = client_builder(MockTransport::data(
+            move |_| json!({ "count": served.fetch_add(1, Ordering::SeqCst) + 1 }),
+        ))
+        .build()
+        .unwrap();
+        let counts = |fetch_policy| {
+            block_on(
+                client
+                    .query_stream::<Count>((), fetch_policy)
+                    .map(|response| response.unwrap().data.unwrap()["count"].clone())
+                    .collect::<Vec<_>>(),
+            )
+        };
+
+        assert_eq!(counts(FetchPolicy::CacheFirst), vec![json!(1)]);
+        assert_eq!(counts(FetchPolicy::CacheFirst), vec![json!(1)]);
+        assert_eq!(
+            counts(FetchPolicy::CacheAndNetwork),
+            vec![json!(1), json!(2)]
+        );
+        assert_eq!(counts(FetchPolicy::NetworkOnly), vec![json!(3)]);
+        assert_eq!(counts(FetchPolicy::CacheFirst), vec![json!(3)]);
+    }
+
+    #[test]
+    fn revalidate_too_old_results_in_background() {
+        operation!(Count, "query Count { count }");
+
+        let served = AtomicUsize::new(0);
+        let transport = MockTransport::data(
+            move |_| json!({ "count": served.fetch_add(1, Ordering::SeqCst) + 1 }),
+        );
+        let spawned = Arc::new(Mutex::new(Vec::<BoxFuture<'static, ()>>::new()));
+        let client = client_builder(transport.clone())
+            .stale_while_revalidate(Duration::ZERO, {
+                let spawned = spawned.clone();
+                move |task| spawned.lock().unwrap().push(task)
+            })
+            .build()
+            .unwrap();
+        let count = || block_on(client.query::<Count>(())).unwrap().data.unwrap()["count"].clone();
+
+        assert_eq!(count(), json!(1));
+        assert_eq!(count(), json!(1));
+        assert_eq!(count(), json!(1));
+        assert_eq!(transport.requests().len(), 1);
+        assert_eq!(spawned.lock().unwrap().len(), 1);
+
+        let task = spawned.lock().unwrap().pop().unwrap();
+        block_on(task);
+        assert_eq!(transport.requests().len(), 2);
+        assert_eq!(count(), json!(2));
+    }
+
+    #[test]
+    fn cache_and_network_refreshes_cached_results() {
+        operation!(Count, "query Count { count }");
+
+        let served = AtomicUsize::new(0);
+        let transport = MockTransport::data(
+            move |_| json!({ "count": served.fetch_add(1, Ordering::SeqCst) + 1 }),
+        );
+        let spawned = Arc::new(Mutex::new(Vec::<BoxFuture<'static, ()>>::new()));
+        let client = client_builder(transport.clone())
+            .stale_while_revalidate(Duration::from_secs(60), {
+                let spawned = spawned.clone();
+                move |task| spawned.lock().unwrap().push(task)
+            })
+            .build()
+            .unwrap();
+        let options = RequestOptions::new().fetch_policy(FetchPolicy::CacheAndNetwork);
+        let count = || {
+            block_on(client.query_with_options::<Count>((), &options))
+                .unwrap()
+                .data
+                .unwrap()["count"]
+                .clone()
+        };
+
+        assert_eq!(count(), json!(1));
+        assert_eq!(count(), json!(1));
+        assert_eq!(transport.requests().len(), 1);
+        assert_eq!(spawned.lock().unwrap().len(), 1);
+
+        let task = spawned.lock().unwrap().pop().unwrap();
+        block_on(task);
+        assert_eq!(transport.requests().len(), 2);
+        assert_eq!(count(), json!(2));
+
+        let served = AtomicUsize::new(0);
+        let transport = MockTransport::data(
+            move |_| json!({ "count": served.fetch_add(1, Ordering::SeqCst) + 1 }),
+        );
+        let client = client_builder(transport.clone()).build().unwrap();
+        for expected in 1..=2 {
+            let response = block_on(client.query_with_options::<Count>((), &options)).unwrap();
+            assert_eq!(response.data.unwrap()["count"], json!(expected));
+        }
+        assert_eq!(transport.requests().len(), 2);
+    }
+
+    #[test]
+    fn mutations_update_cached_queries() {
+        use crate::cache::InvalidationRule;
+
+        operation!(Person, "query Person { person { __typename id name } }");
+        operation!(Todos, "query Todos { todos { __typename id title } }");
+        operation!(Rename, "mutation Rename { rename { __typename id name } }");
+        operation!(
+            AddTodo,
+            "mutation AddTodo { addTodo { __typename id title } }"
+        );
+
+        let todos = AtomicUsize::new(0);
+        let transport = MockTransport::data(move |request| {
+            match request.body["operationName"].as_str() {
+                Some("Person") => {
+                    json!({ "person": { "__typename": "Person", "id": "1", "name": "Luke" } })
+                }
+                Some("Rename") => {
+                    json!({ "rename": { "__typename": "Person", "id": "1", "name": "Anakin" } })
+                }
+                Some("Todos") => json!({
+                    "todos": (1..=todos.fetch_add(1, Ordering::SeqCst) + 1)
+                        .map(|id| json!({ "__typename": "Todo", "id": id.to_string(), "title": "a" }))
+                        .collect::<Vec<_>>()
+                }),
+                _ => json!({ "addTodo": { "__typename": "Todo", "id": "2", "title": "a" } }),
+            }
+        });
+        let client = client_builder(transport.clone())
+            .cache(CacheWrap::new(InMemoryCache::new().with_invalidation_rule(
+                InvalidationRule::new("Todo", &["todos"]),
+            )))
+            .build()
+            .unwrap();
+        block_on(client.query::<Person>(())).unwrap();
+        block_on(client.query::<Todos>(())).unwrap();
+
+        block_on(client.mutate::<Rename>(())).unwrap();
+        assert_eq!(
+            block_on(client.query::<Person>(())).unwrap().data.unwrap()["person"]["name"],
+            json!("Anakin")
+        );
+
+        block_on(client.mutate::<AddTodo>(())).unwrap();
+        let todos = block_on(client.query::<Todos>(())).unwrap().data.unwrap();
+        assert_eq!(todos["todos"].as_array().unwrap().len(), 2);
+        assert_eq!(
+            transport.operations(),
+            vec!["Person", "Todos", "Rename", "AddTodo", "Todos"]
+        );
+    }
+
+    #[test]
+    fn update_cache_with_typed_mutation_data() {
+        use crate::cache::NormalizedData;
+
+        #[derive(Deserialize)]
+        struct Renamed {
+            id: String,
+            name: String,
+        }
+
+        #[derive(Deserialize)]
+        struct RenameData {
+            rename: Renamed,
+        }
+
+        operation!(Person, "query Person { person { __typename id name } }");
+        operation!(
+            Rename,
+            "mutation Rename { rename { __typename id name } }",
+            RenameData
+        );
+
+        let client = client_builder(MockTransport::data(|request| {
+            match request.body["operationName"].as_str() {
+                Some("Person") => {
+                    json!({ "person": { "__typename": "Person", "id": "1", "name": "Luke" } })
+                }
+                _ => json!({ "rename": { "__typename": "Person", "id": "1", "name": "Anakin" } }),
+            }
+        }))
+        .build()
+        .unwrap();
+        block_on(client.query::<Person>(())).unwrap();
+
+        let mut updated = false;
+        block_on(client.mutate_with_update::<Rename, _>((), |cache, data| {
+            let entity =
+                json!({ "id": data.rename.id, "name": format!("{} Skywalker", data.rename.name) });
+            cache
+                .store_identity_data(
+                    &Key::new("Person", data.rename.id.as_str()),
+                    NormalizedData::try_from(entity).unwrap(),
+                )
+                .unwrap();
+            updated = true;
+        }))
+        .unwrap();
+
+        assert!(updated);
+        assert_eq!(
+            block_on(client.query::<Person>(())).unwrap().data.unwrap()["person"]["name"],
+            json!("Anakin Skywalker")
+        );
+    }
+
+    #[test]
+    fn report_refetch_failures() {
+        operation!(Feed, "query Feed { feed }");
+        operation!(Like, "mutation Like { like }");
+
+        let fetches = AtomicUsize::new(0);
+        let client = client_builder(MockTransport::new(move |request| {
+            match request.body["operationName"].as_str() {
+                Some("Feed") => match fetches.fetch_add(1, Ordering::SeqCst) + 1 {
+                    2 => Err(ClientError::HttpError {
+                        status: StatusCode::SERVICE_UNAVAILABLE,
+                        body: String::new(),
+                    }),
+                    n => json_response(json!({ "data": { "feed": n } })),
+                },
+                _ => json_response(json!({ "data": { "like": true } })),
+            }
+        }))
+        .build()
+        .unwrap();
+        let refetch = [RefetchQuery::operation_name("Feed")];
+        block_on(client.query::<Feed>(())).unwrap();
+
+        let (response, refetched) =
+            block_on(client.mutate_with_refetch::<Like>((), &refetch)).unwrap();
+        assert_eq!(response.data, Some(json!({ "like": true })));
+        assert_eq!(refetched.len(), 1);
+        assert!(matches!(refetched[0].1, Err(ClientError::HttpError { .. })));
+
+        let (_, refetched) = block_on(client.mutate_with_refetch::<Like>((), &refetch)).unwrap();
+        assert!(refetched[0].1.is_ok());
+        assert_eq!(
+            block_on(client.query::<Feed>(())).unwrap().data,
+            Some(json!({ "feed": 3 }))
+        );
+    }
+
+    #[test]
+    fn raw_queries_are_tracked_and_take_options() {
+        let served = AtomicUsize::new(0);
+        let transport = MockTransport::data(
+            move |_| json!({ "feed": served.fetch_add(1, Ordering::SeqCst) + 1 }),
+        );
+        let client = client_builder(transport.clone()).build().unwrap();
+        let feed = |options: &RequestOptions| {
+            block_on(client.query_raw_with_options("query Feed { feed }", json!({}), options))
+                .unwrap()
+                .data
+        };
+
+        assert_eq!(feed(&RequestOptions::new()), Some(json!({ "feed": 1 })));
+        assert_eq!(feed(&RequestOptions::new()), Some(json!({ "feed": 1 })));
+        let network_only = RequestOptions::new()
+            .fetch_policy(FetchPolicy::NetworkOnly)
+            .header(
+                HeaderName::from_static("x-trace"),
+                HeaderValue::from_static("1"),
+            );
+        assert_eq!(feed(&network_only), Some(json!({ "feed": 2 })));
+        assert_eq!(
+            transport.header_values(HeaderName::from_static("x-trace")),
+            vec![None, Some(HeaderValue::from_static("1"))]
+        );
+
+        let refetched =
+            block_on(client.refetch_queries(&[RefetchQuery::operation_name("Feed")])).unwrap();
+        assert_eq!(refetched.len(), 1);
+        assert_eq!(
+            block_on(client.query_raw("query Feed { feed }", json!({})))
+                .unwrap()
+                .data,
+            Some(json!({ "feed": 3 }))
+        );
+    }
+
+    #[test]
+    fn incremental_through_transport() {
+        use reqwest::header::CONTENT_TYPE;
+
+        operation!(
+            Person,
+            "query Person { person { __typename id ... @defer { name } } }"
+        );
+
+        let transport = MockTransport::new(|_| {
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                CONTENT_TYPE,
+                HeaderValue::from_static("multipart/mixed; boundary=\"-\""),
+            );
+            let body = "\r\n---\r\ncontent-type: application/json\r\n\r\n\
+{\"data\":{\"person\":{\"__typename\":\"Person\",\"id\":\"1\"}},\"hasNext\":true}\r\n---\r\n\
+content-type: application/json\r\n\r\n\
+{\"incremental\":[{\"data\":{\"name\":\"Luke\"},\"path\":[\"person\"]}],\"hasNext\":false}\r\n-----\r\n";
+            Ok(TransportResponse {
+                status: StatusCode::OK,
+                headers,
+                body: Bytes::from(body),
+            })
+        });
+        let client = client_builder(transport.clone()).build().unwrap();
+
+        let responses: Vec<_> = block_on(client.query_incremental::<Person>(()).collect());
+
+        let data: Vec<_> = responses
+            .into_iter()
+            .map(|response| response.unwrap().data.unwrap())
+            .collect();
+        assert_eq!(
+            data,
+            vec![
+                json!({ "person": { "__typename": "Person", "id": "1" } }),
+                json!({ "person": { "__typename": "Person", "id": "1", "name": "Luke" } })
+            ]
+        );
+        assert_eq!(
+            transport.header_values(ACCEPT),
+            vec![Some(HeaderValue::from_static(ACCEPT_INCREMENTAL))]
+        );
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn observe_errors() {
+        let observed = Arc::new(Mutex::new(vec![]));
+        let client = {
+            let observed = observed.clone();
+            client_builder(MockTransport::new(|request| {
+                if request.body["operationName"] == "Me" {
+                    return Err(ClientError::HttpError {
+                        status: StatusCode::UNAUTHORIZED,
+                        body: String::new(),
+                    });
+                }
+                json_response(json!({ "errors": [{ "message": "boom" }] }))
+            }))
+            .on_error(move |operation, error| {
+                observed
+                    .lock()
+                    .unwrap()
+                    .push(format!("{}: {}", operation, error));
+            })
+            .build()
+            .unwrap()
+        };
+
+        let _ = block_on(client.query_raw("query Me { me }", json!({})));
+        let _ = block_on(client.query_raw("query Feed { feed }", json!({})));
+
+        assert_eq!(
+            *observed.lock().unwrap(),
+            vec![
+                "Me: http error: 401 Unauthorized",
+                "Feed: graphql error: boom"
+            ]
+        );
+    }
+
+    #[test]
+    fn identification_headers() {
+        let builder = DiscoveryClientBuilder::<InMemoryCache>::new()
+            .client_name("web".to_string())
+            .client_version("1.2.3".to_string())
+            .header("x-team".to_string(), "checkout".to_string());
+
+        let headers = builder.default_headers().unwrap();
+
+        assert_eq!(headers["apollographql-client-name"], "web");
+        assert_eq!(headers["apollographql-client-version"], "1.2.3");
+        assert_eq!(headers["x-team"], "checkout");
+        assert!(matches!(
+            builder
+                .header("bad header".to_string(), "x".to_string())
+                .default_headers(),
+            Err(BuilderError::InvalidHeaderName(_))
+        ));
+    }
 
-        let response_body: Value = res.json().await?;
+    #[test]
+    fn reject_http_options_with_custom_transport() {
+        let build = |builder: DiscoveryClientBuilder<InMemoryCache>| builder.build().err();
+        let transport = || client_builder(MockTransport::data(|_| json!({})));
 
-        Ok(response_body)
+        assert!(build(transport()).is_none());
+        assert!(build(transport().batching(BatchOptions::default())).is_none());
+        assert!(matches!(
+            build(transport().use_get_for_queries(true)),
+            Some(BuilderError::UnsupportedWithTransport(
+                "use_get_for_queries"
+            ))
+        ));
+        assert!(matches!(
+            build(transport().wire_encoding(WireEncoding::MessagePack, false)),
+            Some(BuilderError::UnsupportedWithTransport("wire_encoding"))
+        ));
+        assert!(matches!(
+            build(
+                transport()
+                    .batching(BatchOptions::default())
+                    .upload_progress(|_| {})
+            ),
+            Some(BuilderError::UnsupportedWithTransport("upload_progress"))
+        ));
+        assert!(build(
+            DiscoveryClientBuilder::new()
+                .uri("http://example.com".to_string())
+                .use_get_for_queries(true)
+                .wire_encoding(WireEncoding::MessagePack, false)
+        )
+        .is_none());
     }
 }
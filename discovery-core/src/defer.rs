@@ -0,0 +1,288 @@
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use graphql_client::Response;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::VecDeque;
+
+use crate::client::ClientResult;
+
+pub const ACCEPT_INCREMENTAL: &str = "multipart/mixed;deferSpec=20220824, application/json";
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Incremental {
+    #[serde(default)]
+    pub data: Option<Value>,
+    #[serde(default)]
+    pub items: Option<Vec<Value>>,
+    #[serde(default)]
+    pub path: Vec<Value>,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub errors: Option<Vec<graphql_client::Error>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IncrementalPayload {
+    #[serde(default)]
+    pub data: Option<Value>,
+    #[serde(default)]
+    pub errors: Option<Vec<graphql_client::Error>>,
+    #[serde(default)]
+    pub incremental: Vec<Incremental>,
+    #[serde(default)]
+    pub has_next: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct IncrementalResult {
+    data: Option<Value>,
+    errors: Vec<graphql_client::Error>,
+}
+
+impl IncrementalResult {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn apply(&mut self, payload: IncrementalPayload) -> bool {
+        let mut changed = false;
+        if let Some(data) = payload.data {
+            self.data = Some(data);
+            changed = true;
+        }
+        if let Some(errors) = payload.errors {
+            changed |= !errors.is_empty();
+            self.errors.extend(errors);
+        }
+        for incremental in payload.incremental {
+            if let Some(errors) = incremental.errors {
+                changed |= !errors.is_empty();
+                self.errors.extend(errors);
+            }
+            let target = match self
+                .data
+                .as_mut()
+                .and_then(|d| at_path(d, &incremental.path))
+            {
+                Some(target) => target,
+                None => continue,
+            };
+            if let Some(data) = incremental.data {
+                merge(target, data);
+                changed = true;
+            }
+            if let (Some(items), Value::Array(array)) = (incremental.items, target) {
+                array.extend(items);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    pub fn response(&self) -> Response<Value> {
+        Response {
+            data: self.data.clone(),
+            errors: (!self.errors.is_empty()).then(|| self.errors.clone()),
+        }
+    }
+}
+
+fn at_path<'a>(value: &'a mut Value, path: &[Value]) -> Option<&'a mut Value> {
+    path.iter().try_fold(value, |value, segment| match segment {
+        Value::String(key) => value.get_mut(key.as_str()),
+        Value::Number(index) => value.get_mut(index.as_u64()? as usize),
+        _ => None,
+    })
+}
+
+fn merge(target: &mut Value, patch: Value) {
+    match (target, patch) {
+        (Value::Object(target), Value::Object(patch)) => {
+            for (key, value) in patch {
+                match target.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        target.insert(key, value);
+                    }
+                }
+            }
+        }
+        (target, patch) => *target = patch,
+    }
+}
+
+pub fn boundary(content_type: &str) -> Option<String> {
+    let mut params = content_type.split(';').map(str::trim);
+    if !params.next()?.eq_ignore_ascii_case("multipart/mixed") {
+        return None;
+    }
+    let boundary = params
+        .filter_map(|param| param.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("boundary"))
+        .map(|(_, value)| value.trim().trim_matches('"').to_string());
+    Some(boundary.unwrap_or_else(|| "-".to_string()))
+}
+
+#[derive(Debug)]
+pub struct MultipartParser {
+    delimiter: Vec<u8>,
+    buffer: Vec<u8>,
+    started: bool,
+    closed: bool,
+    pending: VecDeque<String>,
+}
+
+impl MultipartParser {
+    pub fn new(boundary: &str) -> Self {
+        Self {
+            delimiter: format!("\n--{}", boundary).into_bytes(),
+            buffer: b"\n".to_vec(),
+            started: false,
+            closed: false,
+            pending: VecDeque::new(),
+        }
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) {
+        if self.closed {
+            return;
+        }
+        self.buffer.extend(chunk.iter().filter(|b| **b != b'\r'));
+        while let Some(start) = find(&self.buffer, &self.delimiter) {
+            let part: Vec<u8> = self.buffer.drain(..start).collect();
+            self.buffer.drain(..self.delimiter.len());
+            if self.started {
+                self.push_part(&part);
+            }
+            self.started = true;
+            if self.buffer.starts_with(b"--") {
+                self.closed = true;
+                self.buffer.clear();
+                return;
+            }
+        }
+    }
+
+    fn push_part(&mut self, part: &[u8]) {
+        let part = String::from_utf8_lossy(part);
+        let body = match part.split_once("\n\n") {
+            Some((_, body)) => body.trim(),
+            None => return,
+        };
+        if !body.is_empty() {
+            self.pending.push_back(body.to_string());
+        }
+    }
+
+    pub fn next_part(&mut self) -> Option<String> {
+        self.pending.pop_front()
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+pub(crate) fn multipart_parts<S>(
+    chunks: S,
+    boundary: &str,
+) -> impl Stream<Item = ClientResult<String>>
+where
+    S: Stream<Item = ClientResult<Bytes>> + Unpin,
+{
+    futures::stream::unfold(
+        (Some(chunks), MultipartParser::new(boundary)),
+        |(mut chunks, mut parser)| async move {
+            loop {
+                if let Some(part) = parser.next_part() {
+                    return Some((Ok(part), (chunks, parser)));
+                }
+                if parser.is_closed() {
+                    return None;
+                }
+                match chunks.as_mut()?.next().await {
+                    Some(Ok(chunk)) => parser.push(&chunk),
+                    None => return None,
+                    Some(Err(e)) => return Some((Err(e), (None, parser))),
+                }
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parse_multipart_mixed() {
+        assert_eq!(
+            boundary("multipart/mixed; boundary=\"graphql\"; deferSpec=20220824").as_deref(),
+            Some("graphql")
+        );
+        assert_eq!(boundary("multipart/mixed").as_deref(), Some("-"));
+        assert_eq!(boundary("application/json"), None);
+
+        let mut parser = MultipartParser::new("-");
+        parser.push(b"\r\n---\r\nContent-Type: application/json\r\n\r\n{\"data\":");
+        assert_eq!(parser.next_part(), None);
+
+        parser.push(b"{\"a\":\"---\"},\"hasNext\":true}\r\n---\r\n\r\n{}\r\n---");
+        parser.push(b"\r\ncontent-type: application/json\r\n\r\n{\"hasNext\":false}\r\n-----\r\n");
+        assert_eq!(
+            parser.next_part().as_deref(),
+            Some("{\"data\":{\"a\":\"---\"},\"hasNext\":true}")
+        );
+        assert_eq!(parser.next_part().as_deref(), Some("{}"));
+        assert_eq!(parser.next_part().as_deref(), Some("{\"hasNext\":false}"));
+        assert!(parser.is_closed());
+    }
+
+    #[test]
+    fn apply_incremental_payloads() {
+        let payloads = [
+            json!({ "data": { "person": { "id": "1" }, "films": [{ "id": "a" }] }, "hasNext": true }),
+            json!({
+              "incremental": [
+                { "data": { "name": "Luke", "homeworld": { "id": "t" } }, "path": ["person"], "label": "details" },
+                { "items": [{ "id": "b" }], "path": ["films"] }
+              ],
+              "hasNext": true
+            }),
+            json!({
+              "incremental": [{
+                "data": { "title": "A New Hope" },
+                "path": ["films", 0],
+                "errors": [{ "message": "no director", "path": ["films", 0, "director"] }]
+              }],
+              "hasNext": false
+            }),
+            json!({ "hasNext": false }),
+        ];
+
+        let mut result = IncrementalResult::new();
+        let changed: Vec<_> = payloads
+            .iter()
+            .map(|p| result.apply(serde_json::from_value(p.clone()).unwrap()))
+            .collect();
+
+        assert_eq!(changed, vec![true, true, true, false]);
+        let response = result.response();
+        assert_eq!(
+            response.data.unwrap(),
+            json!({
+              "person": { "id": "1", "name": "Luke", "homeworld": { "id": "t" } },
+              "films": [{ "id": "a", "title": "A New Hope" }, { "id": "b" }]
+            })
+        );
+        assert_eq!(response.errors.unwrap()[0].message, "no director");
+    }
+}
@@ -1,5 +1,31 @@
+macro_rules! record {
+    ($($field:literal = $value:expr),* $(,)?) => {
+        #[cfg(feature = "tracing")]
+        {
+            let span = tracing::Span::current();
+            $(span.record($field, $value);)*
+        }
+    };
+}
+
+#[cfg(feature = "tokio")]
+pub mod blocking;
 pub mod cache;
 pub mod client;
+pub mod defer;
+pub mod link;
+pub mod live;
+pub mod metrics;
+pub mod network;
+pub mod outbox;
+pub mod poll;
+pub mod registry;
+pub mod response;
+pub mod result_key;
+pub mod runtime;
+pub mod ssr;
+pub mod subscription;
+pub mod upload;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,149 @@
+use futures::future::BoxFuture;
+use reqwest::header::HeaderValue;
+use serde_json::Value;
+use std::sync::Arc;
+
+use super::{GraphQLRequest, Link, LinkResponse, Next};
+use crate::client::ClientResult;
+
+const INCLUDE_TRACE_HEADER: &str = "apollo-federation-include-trace";
+const FTV1: &str = "ftv1";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceReport {
+    pub operation_name: String,
+    pub query: String,
+    pub trace: Vec<u8>,
+}
+
+type Sink = dyn Fn(TraceReport) + Send + Sync;
+
+pub struct ApolloTraceLink {
+    sink: Arc<Sink>,
+    strip_extension: bool,
+}
+
+impl ApolloTraceLink {
+    pub fn new(sink: impl Fn(TraceReport) + Send + Sync + 'static) -> Self {
+        Self {
+            sink: Arc::new(sink),
+            strip_extension: true,
+        }
+    }
+
+    pub fn keep_extension(mut self) -> Self {
+        self.strip_extension = false;
+        self
+    }
+
+    fn decode(&self, response: &mut LinkResponse) -> Option<Vec<u8>> {
+        let trace = if self.strip_extension {
+            response.extensions.remove(FTV1)?
+        } else {
+            response.extensions.get(FTV1)?.clone()
+        };
+        match trace {
+            Value::String(encoded) => base64::decode(encoded).ok(),
+            _ => None,
+        }
+    }
+}
+
+impl Link for ApolloTraceLink {
+    fn call<'a>(
+        &'a self,
+        mut request: GraphQLRequest,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, ClientResult<LinkResponse>> {
+        Box::pin(async move {
+            request
+                .headers
+                .insert(INCLUDE_TRACE_HEADER, HeaderValue::from_static(FTV1));
+            let operation_name = request.operation_name.clone();
+            let query = request.query.clone().unwrap_or_default();
+
+            let mut response = next.run(request).await?;
+            if let Some(trace) = self.decode(&mut response) {
+                (self.sink)(TraceReport {
+                    operation_name,
+                    query,
+                    trace,
+                });
+            }
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use reqwest::header::HeaderMap;
+    use reqwest::StatusCode;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    fn response() -> LinkResponse {
+        let body = json!({
+          "data": { "person": null },
+          "extensions": { "ftv1": base64::encode([1, 2, 3]) }
+        });
+        LinkResponse::from_slice(
+            StatusCode::OK,
+            HeaderMap::new(),
+            body.to_string().as_bytes(),
+        )
+        .unwrap()
+    }
+
+    struct Traced;
+
+    impl Link for Traced {
+        fn call<'a>(
+            &'a self,
+            request: GraphQLRequest,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, ClientResult<LinkResponse>> {
+            assert_eq!(request.headers[INCLUDE_TRACE_HEADER], FTV1);
+            Box::pin(async { Ok(response()) })
+        }
+    }
+
+    #[test]
+    fn forward_trace_report() {
+        let reports = Arc::new(Mutex::new(vec![]));
+        let link = {
+            let reports = reports.clone();
+            ApolloTraceLink::new(move |report| reports.lock().unwrap().push(report))
+        };
+        let links: Vec<Arc<dyn Link>> = vec![Arc::new(link), Arc::new(Traced)];
+        let request = GraphQLRequest::new(&graphql_client::QueryBody {
+            variables: json!({}),
+            query: "query Person { person { name } }",
+            operation_name: "Person",
+        })
+        .unwrap();
+
+        let response = block_on(Next::new(&links).run(request)).unwrap();
+
+        assert!(response.extensions.is_empty());
+        assert_eq!(
+            *reports.lock().unwrap(),
+            vec![TraceReport {
+                operation_name: "Person".to_string(),
+                query: "query Person { person { name } }".to_string(),
+                trace: vec![1, 2, 3],
+            }]
+        );
+    }
+
+    #[test]
+    fn keep_extension() {
+        let link = ApolloTraceLink::new(|_| {}).keep_extension();
+        let mut response = response();
+
+        assert_eq!(link.decode(&mut response), Some(vec![1, 2, 3]));
+        assert!(response.extensions.contains_key(FTV1));
+    }
+}
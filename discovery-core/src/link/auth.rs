@@ -0,0 +1,166 @@
+use futures::future::BoxFuture;
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+use reqwest::StatusCode;
+use std::sync::Arc;
+
+use super::{GraphQLRequest, Link, LinkResponse, Next};
+use crate::client::{ClientError, ClientResult};
+
+const UNAUTHENTICATED: &str = "UNAUTHENTICATED";
+
+pub trait TokenProvider: Send + Sync {
+    fn token(&self) -> BoxFuture<'_, ClientResult<String>>;
+    fn refresh(&self) -> BoxFuture<'_, ClientResult<String>>;
+}
+
+pub struct StaticToken(String);
+
+impl StaticToken {
+    pub fn new(authorization: impl Into<String>) -> Self {
+        Self(authorization.into())
+    }
+}
+
+impl TokenProvider for StaticToken {
+    fn token(&self) -> BoxFuture<'_, ClientResult<String>> {
+        Box::pin(async move { Ok(self.0.clone()) })
+    }
+
+    fn refresh(&self) -> BoxFuture<'_, ClientResult<String>> {
+        self.token()
+    }
+}
+
+pub struct AuthLink {
+    provider: Arc<dyn TokenProvider>,
+}
+
+impl AuthLink {
+    pub fn new(provider: Arc<dyn TokenProvider>) -> Self {
+        Self { provider }
+    }
+}
+
+fn authorize(request: &mut GraphQLRequest, token: &str) -> ClientResult<()> {
+    let value = HeaderValue::from_str(token).map_err(|_| ClientError::InvalidToken)?;
+    request.headers.insert(AUTHORIZATION, value);
+    Ok(())
+}
+
+fn is_unauthenticated(response: &ClientResult<LinkResponse>) -> bool {
+    match response {
+        Err(ClientError::HttpError { status, .. }) => *status == StatusCode::UNAUTHORIZED,
+        Ok(response) => {
+            response.status == StatusCode::UNAUTHORIZED
+                || response.body.errors.iter().flatten().any(|error| {
+                    error
+                        .extensions
+                        .as_ref()
+                        .and_then(|extensions| extensions.get("code"))
+                        .is_some_and(|code| code == UNAUTHENTICATED)
+                })
+        }
+        _ => false,
+    }
+}
+
+impl Link for AuthLink {
+    fn call<'a>(
+        &'a self,
+        mut request: GraphQLRequest,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, ClientResult<LinkResponse>> {
+        Box::pin(async move {
+            authorize(&mut request, &self.provider.token().await?)?;
+            let response = next.run(request.clone()).await;
+            if !is_unauthenticated(&response) {
+                return response;
+            }
+            authorize(&mut request, &self.provider.refresh().await?)?;
+            next.run(request).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use graphql_client::Response;
+    use reqwest::header::HeaderMap;
+    use serde_json::{json, Map};
+    use std::sync::Mutex;
+
+    struct Rotating(Mutex<u32>);
+
+    impl TokenProvider for Rotating {
+        fn token(&self) -> BoxFuture<'_, ClientResult<String>> {
+            Box::pin(async move { Ok(format!("Bearer {}", self.0.lock().unwrap())) })
+        }
+
+        fn refresh(&self) -> BoxFuture<'_, ClientResult<String>> {
+            *self.0.lock().unwrap() += 1;
+            self.token()
+        }
+    }
+
+    struct RequireToken(&'static str);
+
+    impl Link for RequireToken {
+        fn call<'a>(
+            &'a self,
+            request: GraphQLRequest,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, ClientResult<LinkResponse>> {
+            let authorized = request.headers[AUTHORIZATION] == self.0;
+            Box::pin(async move {
+                let body = match authorized {
+                    true => json!({ "data": { "me": { "name": "Luke" } } }),
+                    false => json!({
+                      "data": null,
+                      "errors": [{ "message": "expired", "extensions": { "code": UNAUTHENTICATED } }]
+                    }),
+                };
+                Ok(LinkResponse {
+                    status: StatusCode::OK,
+                    headers: HeaderMap::new(),
+                    body: serde_json::from_value::<Response<_>>(body)?,
+                    extensions: Map::new(),
+                    incremental: None,
+                })
+            })
+        }
+    }
+
+    fn request() -> GraphQLRequest {
+        GraphQLRequest::raw("Me", "query Me { me { name } }", json!({}))
+    }
+
+    #[test]
+    fn refresh_once_on_unauthenticated() {
+        let provider = Arc::new(Rotating(Mutex::new(0)));
+        let links: Vec<Arc<dyn Link>> = vec![
+            Arc::new(AuthLink::new(provider.clone())),
+            Arc::new(RequireToken("Bearer 1")),
+        ];
+
+        let response = block_on(Next::new(&links).run(request())).unwrap();
+
+        assert!(response.body.errors.is_none());
+        assert_eq!(*provider.0.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn give_up_after_one_refresh() {
+        let provider = Arc::new(Rotating(Mutex::new(0)));
+        let links: Vec<Arc<dyn Link>> = vec![
+            Arc::new(AuthLink::new(provider.clone())),
+            Arc::new(RequireToken("Bearer 2")),
+        ];
+
+        let response = block_on(Next::new(&links).run(request())).unwrap();
+
+        assert!(response.body.errors.is_some());
+        assert_eq!(*provider.0.lock().unwrap(), 1);
+    }
+}
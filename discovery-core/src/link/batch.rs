@@ -0,0 +1,348 @@
+use futures::channel::oneshot;
+use futures::future::{join_all, BoxFuture};
+use reqwest::header::HeaderMap;
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::{
+    GraphQLRequest, HttpLink, Link, LinkResponse, Next, Transport, TransportLink, TransportRequest,
+    WireEncoding,
+};
+use crate::client::{ClientError, ClientResult};
+use crate::runtime::{default_runtime, Runtime};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchOptions {
+    pub window: Duration,
+    pub max_batch_size: usize,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_millis(10),
+            max_batch_size: 10,
+        }
+    }
+}
+
+type Pending = (GraphQLRequest, oneshot::Sender<ClientResult<LinkResponse>>);
+
+pub struct BatchHttpLink {
+    transport: Arc<dyn Transport>,
+    single: Arc<dyn Link>,
+    runtime: Option<Arc<dyn Runtime>>,
+    options: BatchOptions,
+    queue: Mutex<Vec<Pending>>,
+}
+
+impl BatchHttpLink {
+    pub fn new(http: HttpLink, options: BatchOptions) -> Self {
+        let http = Arc::new(http);
+        Self::with_links(http.clone(), http, options)
+    }
+
+    pub fn with_transport(transport: Arc<dyn Transport>, options: BatchOptions) -> Self {
+        let single = Arc::new(TransportLink::new(transport.clone()));
+        Self::with_links(transport, single, options)
+    }
+
+    fn with_links(
+        transport: Arc<dyn Transport>,
+        single: Arc<dyn Link>,
+        options: BatchOptions,
+    ) -> Self {
+        Self {
+            transport,
+            single,
+            runtime: default_runtime(),
+            options,
+            queue: Mutex::new(vec![]),
+        }
+    }
+
+    pub fn runtime(mut self, runtime: Arc<dyn Runtime>) -> Self {
+        self.runtime = Some(runtime);
+        self
+    }
+
+    fn enqueue(
+        &self,
+        request: GraphQLRequest,
+    ) -> (
+        oneshot::Receiver<ClientResult<LinkResponse>>,
+        Option<Vec<Pending>>,
+    ) {
+        let (sender, receiver) = oneshot::channel();
+        let mut queue = self.queue.lock().unwrap();
+        queue.push((request, sender));
+        let full = queue.len() >= self.options.max_batch_size;
+        (receiver, full.then(|| std::mem::take(&mut *queue)))
+    }
+
+    fn take(&self) -> Vec<Pending> {
+        std::mem::take(&mut *self.queue.lock().unwrap())
+    }
+
+    async fn flush(&self, batch: Vec<Pending>) {
+        let mut groups: Vec<(HeaderMap, Vec<Pending>)> = vec![];
+        for pending in batch {
+            match groups
+                .iter_mut()
+                .find(|(headers, _)| *headers == pending.0.headers)
+            {
+                Some((_, group)) => group.push(pending),
+                None => groups.push((pending.0.headers.clone(), vec![pending])),
+            }
+        }
+        join_all(
+            groups
+                .into_iter()
+                .map(|(headers, group)| self.send(headers, group)),
+        )
+        .await;
+    }
+
+    async fn send(&self, headers: HeaderMap, batch: Vec<Pending>) {
+        let (requests, senders): (Vec<_>, Vec<_>) = batch.into_iter().unzip();
+        let body = Value::Array(requests.iter().map(GraphQLRequest::body).collect());
+
+        let request = TransportRequest { headers, body };
+        let results = match self.transport.execute(request).await {
+            Ok(response) => demultiplex(
+                response.status,
+                response.headers,
+                &response.body,
+                senders.len(),
+            ),
+            Err(e) => Err(e),
+        };
+        match results {
+            Ok(responses) => {
+                for (sender, response) in senders.into_iter().zip(responses) {
+                    let _ = sender.send(response);
+                }
+            }
+            Err(e) => {
+                for sender in senders {
+                    let _ = sender.send(Err(share_error(&e)));
+                }
+            }
+        }
+    }
+}
+
+fn demultiplex(
+    status: reqwest::StatusCode,
+    headers: HeaderMap,
+    bytes: &[u8],
+    expected: usize,
+) -> ClientResult<Vec<ClientResult<LinkResponse>>> {
+    let bodies: Vec<Value> = serde_json::from_value(WireEncoding::of(&headers).decode(bytes)?)?;
+    if bodies.len() != expected {
+        return Err(ClientError::BatchError(format!(
+            "expected {} results, got {}",
+            expected,
+            bodies.len()
+        )));
+    }
+    Ok(bodies
+        .into_iter()
+        .map(|body| Ok(LinkResponse::from_value(status, headers.clone(), body)?))
+        .collect())
+}
+
+pub(crate) fn share_error(error: &ClientError) -> ClientError {
+    match error {
+        ClientError::HttpError { status, body } => ClientError::HttpError {
+            status: *status,
+            body: body.clone(),
+        },
+        ClientError::RateLimited { retry_after } => ClientError::RateLimited {
+            retry_after: *retry_after,
+        },
+        e => ClientError::BatchError(e.to_string()),
+    }
+}
+
+impl Link for BatchHttpLink {
+    fn call<'a>(
+        &'a self,
+        request: GraphQLRequest,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, ClientResult<LinkResponse>> {
+        if !request.files.is_empty() || request.accepts_incremental() {
+            return self.single.call(request, next);
+        }
+        Box::pin(async move {
+            let (receiver, full) = self.enqueue(request);
+            match full {
+                Some(batch) => self.flush(batch).await,
+                None => {
+                    if let Some(runtime) = &self.runtime {
+                        runtime.sleep(self.options.window).await;
+                    }
+                    self.flush(self.take()).await;
+                }
+            }
+            receiver
+                .await
+                .unwrap_or_else(|_| Err(ClientError::BatchError("batch dropped".to_string())))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::link::TransportResponse;
+    use futures::executor::block_on;
+    use reqwest::header::HeaderValue;
+    use reqwest::StatusCode;
+    use serde_json::json;
+
+    fn link(max_batch_size: usize) -> BatchHttpLink {
+        BatchHttpLink::new(
+            HttpLink::new(reqwest::Client::new(), "http://localhost/graphql"),
+            BatchOptions {
+                max_batch_size,
+                ..BatchOptions::default()
+            },
+        )
+    }
+
+    fn request(operation_name: &'static str) -> GraphQLRequest {
+        GraphQLRequest::new(&graphql_client::QueryBody {
+            variables: json!({}),
+            query: "query { a }",
+            operation_name,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn enqueue_until_full() {
+        let link = link(2);
+
+        let (_, full) = link.enqueue(request("A"));
+        assert!(full.is_none());
+        let (_, full) = link.enqueue(request("B"));
+        let batch = full.unwrap();
+
+        let names: Vec<_> = batch
+            .iter()
+            .map(|(r, _)| r.operation_name.as_str())
+            .collect();
+        assert_eq!(names, vec!["A", "B"]);
+        assert!(link.take().is_empty());
+    }
+
+    #[test]
+    fn demultiplex_in_order() {
+        let body = json!([{ "data": { "a": 1 } }, { "data": { "a": 2 } }]).to_string();
+
+        let responses = demultiplex(StatusCode::OK, HeaderMap::new(), body.as_bytes(), 2).unwrap();
+        let data: Vec<_> = responses
+            .into_iter()
+            .map(|r| r.unwrap().body.data.unwrap())
+            .collect();
+        assert_eq!(data, vec![json!({ "a": 1 }), json!({ "a": 2 })]);
+
+        assert!(matches!(
+            demultiplex(StatusCode::OK, HeaderMap::new(), body.as_bytes(), 3),
+            Err(ClientError::BatchError(_))
+        ));
+    }
+
+    struct Echo;
+
+    impl Transport for Echo {
+        fn execute(
+            &self,
+            request: TransportRequest,
+        ) -> BoxFuture<'_, ClientResult<TransportResponse>> {
+            Box::pin(async move {
+                let bodies: Vec<_> = request
+                    .body
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|_| json!({ "data": {} }))
+                    .collect();
+                Ok(TransportResponse {
+                    status: StatusCode::OK,
+                    headers: HeaderMap::new(),
+                    body: serde_json::to_vec(&bodies)?.into(),
+                })
+            })
+        }
+    }
+
+    #[test]
+    fn wait_window_on_custom_runtime() {
+        let windows = Arc::new(Mutex::new(vec![]));
+        let recorded = windows.clone();
+        let link = BatchHttpLink::with_transport(Arc::new(Echo), BatchOptions::default()).runtime(
+            Arc::new(move |window| -> BoxFuture<'static, ()> {
+                recorded.lock().unwrap().push(window);
+                Box::pin(async {})
+            }),
+        );
+        let links: Vec<Arc<dyn Link>> = vec![Arc::new(link)];
+
+        let response = block_on(Next::new(&links).run(request("A"))).unwrap();
+
+        assert_eq!(response.body.data, Some(json!({})));
+        assert_eq!(
+            *windows.lock().unwrap(),
+            vec![BatchOptions::default().window]
+        );
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        batches: Mutex<Vec<(Option<HeaderValue>, usize)>>,
+    }
+
+    impl Transport for Recorder {
+        fn execute(
+            &self,
+            request: TransportRequest,
+        ) -> BoxFuture<'_, ClientResult<TransportResponse>> {
+            let size = request.body.as_array().unwrap().len();
+            self.batches
+                .lock()
+                .unwrap()
+                .push((request.headers.get("authorization").cloned(), size));
+            Echo.execute(request)
+        }
+    }
+
+    #[test]
+    fn batch_by_headers() {
+        let transport = Arc::new(Recorder::default());
+        let link = BatchHttpLink::with_transport(transport.clone(), BatchOptions::default());
+        let mut requests = vec![request("A"), request("B"), request("C")];
+        requests[1]
+            .headers
+            .insert("authorization", HeaderValue::from_static("Bearer b"));
+        let (senders, receivers): (Vec<_>, Vec<_>) = requests
+            .into_iter()
+            .map(|request| {
+                let (sender, receiver) = oneshot::channel();
+                ((request, sender), receiver)
+            })
+            .unzip();
+
+        block_on(link.flush(senders));
+
+        for receiver in receivers {
+            assert!(block_on(receiver).unwrap().is_ok());
+        }
+        assert_eq!(
+            *transport.batches.lock().unwrap(),
+            vec![(None, 2), (Some(HeaderValue::from_static("Bearer b")), 1)]
+        );
+    }
+}
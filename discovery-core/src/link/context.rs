@@ -0,0 +1,51 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+#[derive(Clone, Default)]
+pub struct TypedContext(HashMap<TypeId, Arc<dyn Any + Send + Sync>>);
+
+impl TypedContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert<T: Any + Send + Sync>(&mut self, value: T) {
+        self.0.insert(TypeId::of::<T>(), Arc::new(value));
+    }
+
+    pub fn get<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.0.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(crate) fn extend_missing(&mut self, other: &TypedContext) {
+        for (id, value) in &other.0 {
+            self.0.entry(*id).or_insert_with(|| value.clone());
+        }
+    }
+}
+
+impl Debug for TypedContext {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TypedContext")
+            .field("len", &self.0.len())
+            .finish()
+    }
+}
+
+impl PartialEq for TypedContext {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.len() == other.0.len()
+            && self.0.iter().all(|(id, value)| {
+                other
+                    .0
+                    .get(id)
+                    .is_some_and(|other| Arc::ptr_eq(value, other))
+            })
+    }
+}
@@ -0,0 +1,214 @@
+use futures::channel::oneshot;
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::batch::share_error;
+use super::{GraphQLRequest, Link, LinkResponse, Next};
+use crate::client::{ClientError, ClientResult};
+use crate::result_key::canonical_json;
+use crate::runtime::{default_runtime, Runtime};
+
+type Followers = Vec<oneshot::Sender<ClientResult<LinkResponse>>>;
+
+pub struct DedupLink {
+    window: Duration,
+    runtime: Option<Arc<dyn Runtime>>,
+    in_flight: Mutex<HashMap<String, Followers>>,
+}
+
+impl DedupLink {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            runtime: default_runtime(),
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn runtime(mut self, runtime: Arc<dyn Runtime>) -> Self {
+        self.runtime = Some(runtime);
+        self
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.lock().unwrap().len()
+    }
+
+    fn key(request: &GraphQLRequest) -> Option<String> {
+        if !request.is_query() || !request.files.is_empty() || request.accepts_incremental() {
+            return None;
+        }
+        let mut headers: Vec<_> = request
+            .headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}", name, value.to_str().unwrap_or_default()))
+            .collect();
+        headers.sort();
+        Some(format!(
+            "{}\0{}",
+            canonical_json(&request.body()),
+            headers.join("\n")
+        ))
+    }
+
+    async fn lead(
+        &self,
+        key: String,
+        request: GraphQLRequest,
+        next: Next<'_>,
+    ) -> ClientResult<LinkResponse> {
+        let mut leader = Leader {
+            link: self,
+            key: Some(key),
+        };
+        if let (Some(runtime), false) = (&self.runtime, self.window.is_zero()) {
+            runtime.sleep(self.window).await;
+        }
+        let response = next.run(request).await;
+        for follower in leader.finish() {
+            let _ = follower.send(match &response {
+                Ok(response) => share_response(response),
+                Err(e) => Err(share_error(e)),
+            });
+        }
+        response
+    }
+}
+
+struct Leader<'a> {
+    link: &'a DedupLink,
+    key: Option<String>,
+}
+
+impl Leader<'_> {
+    fn finish(&mut self) -> Followers {
+        let key = self.key.take().unwrap_or_default();
+        let mut in_flight = self.link.in_flight.lock().unwrap();
+        in_flight.remove(&key).unwrap_or_default()
+    }
+}
+
+impl Drop for Leader<'_> {
+    fn drop(&mut self) {
+        if self.key.is_some() {
+            self.finish();
+        }
+    }
+}
+
+fn share_response(response: &LinkResponse) -> ClientResult<LinkResponse> {
+    Ok(LinkResponse {
+        status: response.status,
+        headers: response.headers.clone(),
+        body: serde_json::from_value(serde_json::to_value(&response.body)?)?,
+        extensions: response.extensions.clone(),
+        incremental: None,
+    })
+}
+
+impl Link for DedupLink {
+    fn call<'a>(
+        &'a self,
+        request: GraphQLRequest,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, ClientResult<LinkResponse>> {
+        let Some(key) = Self::key(&request) else {
+            return next.run(request);
+        };
+        let receiver = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            match in_flight.get_mut(&key) {
+                Some(followers) => {
+                    let (sender, receiver) = oneshot::channel();
+                    followers.push(sender);
+                    Some(receiver)
+                }
+                None => {
+                    in_flight.insert(key.clone(), vec![]);
+                    None
+                }
+            }
+        };
+        Box::pin(async move {
+            match receiver {
+                Some(receiver) => receiver.await.unwrap_or_else(|_| {
+                    Err(ClientError::BatchError(
+                        "deduplicated request dropped".to_string(),
+                    ))
+                }),
+                None => self.lead(key, request, next).await,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::link::{Transport, TransportLink, TransportRequest, TransportResponse};
+    use bytes::Bytes;
+    use futures::executor::block_on;
+    use futures::future::{join_all, poll_fn};
+    use reqwest::header::HeaderMap;
+    use reqwest::StatusCode;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::Poll;
+
+    struct Counting(Arc<AtomicUsize>);
+
+    impl Transport for Counting {
+        fn execute(&self, _: TransportRequest) -> BoxFuture<'_, ClientResult<TransportResponse>> {
+            let count = self.0.fetch_add(1, Ordering::SeqCst) + 1;
+            Box::pin(async move {
+                Ok(TransportResponse {
+                    status: StatusCode::OK,
+                    headers: HeaderMap::new(),
+                    body: Bytes::from(format!(r#"{{ "data": {{ "count": {} }} }}"#, count)),
+                })
+            })
+        }
+    }
+
+    #[test]
+    fn coalesce_identical_queries_within_window() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let links: Vec<Arc<dyn Link>> = vec![
+            Arc::new(DedupLink::new(Duration::from_millis(50)).runtime(Arc::new(
+                |_| -> BoxFuture<'static, ()> {
+                    let mut elapsed = false;
+                    Box::pin(poll_fn(move |cx| {
+                        match std::mem::replace(&mut elapsed, true) {
+                            true => Poll::Ready(()),
+                            false => {
+                                cx.waker().wake_by_ref();
+                                Poll::Pending
+                            }
+                        }
+                    }))
+                },
+            ))),
+            Arc::new(TransportLink::new(Arc::new(Counting(count.clone())))),
+        ];
+        let request = |name: &'static str| {
+            GraphQLRequest::raw(name, &format!("query {} {{ count }}", name), json!({}))
+        };
+
+        let responses = block_on(join_all(
+            ["Count", "Count", "Count", "Other"]
+                .into_iter()
+                .map(|name| Next::new(&links).run(request(name))),
+        ));
+        let counts: Vec<_> = responses
+            .into_iter()
+            .map(|response| response.unwrap().body.data.unwrap()["count"].clone())
+            .collect();
+
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+        assert_eq!(counts[0], counts[1]);
+        assert_eq!(counts[1], counts[2]);
+        assert_ne!(counts[0], counts[3]);
+    }
+}
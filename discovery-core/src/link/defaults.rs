@@ -0,0 +1,100 @@
+use futures::future::BoxFuture;
+use serde_json::{Map, Value};
+
+use super::{GraphQLRequest, Link, LinkResponse, Next, TypedContext};
+use crate::client::ClientResult;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RequestDefaults {
+    pub variables: Map<String, Value>,
+    pub context: TypedContext,
+}
+
+impl RequestDefaults {
+    pub fn is_empty(&self) -> bool {
+        self.variables.is_empty() && self.context.is_empty()
+    }
+
+    pub fn apply(&self, request: &mut GraphQLRequest) {
+        request.typed_context.extend_missing(&self.context);
+        let Some(query) = request.query.as_deref() else {
+            return;
+        };
+        let declared: Vec<_> = self
+            .variables
+            .iter()
+            .filter(|(name, _)| declares_variable(query, name))
+            .collect();
+        if declared.is_empty() {
+            return;
+        }
+        if !request.variables.is_object() {
+            request.variables = Value::Object(Map::new());
+        }
+        if let Value::Object(variables) = &mut request.variables {
+            for (name, value) in declared {
+                variables
+                    .entry(name.clone())
+                    .or_insert_with(|| value.clone());
+            }
+        }
+    }
+}
+
+fn declares_variable(query: &str, name: &str) -> bool {
+    let definitions = &query[..query.find('{').unwrap_or(query.len())];
+    let variable = format!("${}", name);
+    definitions.match_indices(&variable).any(|(index, _)| {
+        definitions[index + variable.len()..]
+            .chars()
+            .next()
+            .is_none_or(|c| !(c.is_alphanumeric() || c == '_'))
+    })
+}
+
+impl Link for RequestDefaults {
+    fn call<'a>(
+        &'a self,
+        mut request: GraphQLRequest,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, ClientResult<LinkResponse>> {
+        self.apply(&mut request);
+        next.run(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Debug, PartialEq)]
+    struct Tenant(&'static str);
+
+    #[test]
+    fn merge_declared_defaults() {
+        let mut defaults = RequestDefaults::default();
+        defaults.variables.insert("locale".to_string(), json!("ja"));
+        defaults
+            .variables
+            .insert("currency".to_string(), json!("JPY"));
+        defaults.context.insert(Tenant("acme"));
+
+        let mut request = GraphQLRequest::raw(
+            "Products",
+            "query Products($locale: String, $localeFallback: String) { products(locale: $locale) }",
+            json!({}),
+        );
+        defaults.apply(&mut request);
+        assert_eq!(request.variables, json!({ "locale": "ja" }));
+        assert_eq!(request.typed_context.get::<Tenant>(), Some(&Tenant("acme")));
+
+        let mut request = GraphQLRequest::raw(
+            "Products",
+            "query Products($locale: String) { products(locale: $locale) }",
+            json!({ "locale": "en" }),
+        );
+        defaults.apply(&mut request);
+        assert_eq!(request.variables, json!({ "locale": "en" }));
+    }
+}
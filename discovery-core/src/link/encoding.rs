@@ -0,0 +1,275 @@
+use reqwest::header::{HeaderMap, CONTENT_TYPE};
+use serde_json::{Map, Number, Value};
+
+use crate::client::{ClientError, ClientResult};
+
+pub const MSGPACK: &str = "application/msgpack";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WireEncoding {
+    #[default]
+    Json,
+    MessagePack,
+}
+
+impl WireEncoding {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::MessagePack => MSGPACK,
+        }
+    }
+
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let media_type = content_type.split(';').next()?.trim();
+        match media_type {
+            "application/json" | "application/graphql-response+json" => Some(Self::Json),
+            MSGPACK | "application/x-msgpack" | "application/vnd.msgpack" => {
+                Some(Self::MessagePack)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn of(headers: &HeaderMap) -> Self {
+        headers
+            .get(CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .and_then(Self::from_content_type)
+            .unwrap_or_default()
+    }
+
+    pub fn encode(self, value: &Value) -> ClientResult<Vec<u8>> {
+        match self {
+            Self::Json => Ok(serde_json::to_vec(value)?),
+            Self::MessagePack => {
+                let mut bytes = vec![];
+                write_msgpack(value, &mut bytes);
+                Ok(bytes)
+            }
+        }
+    }
+
+    pub fn decode(self, bytes: &[u8]) -> ClientResult<Value> {
+        match self {
+            Self::Json => Ok(serde_json::from_slice(bytes)?),
+            Self::MessagePack => {
+                let mut reader = Reader { bytes, pos: 0 };
+                let value = reader.value(0)?;
+                match reader.pos == bytes.len() {
+                    true => Ok(value),
+                    false => Err(decode_error("trailing bytes")),
+                }
+            }
+        }
+    }
+}
+
+const MAX_DEPTH: usize = 512;
+
+fn decode_error(message: &str) -> ClientError {
+    ClientError::DecodeError(message.to_string())
+}
+
+fn write_len(bytes: &mut Vec<u8>, len: usize, fix: u8, fix_max: usize, markers: [u8; 3]) {
+    if len <= fix_max {
+        bytes.push(fix | len as u8);
+    } else if len <= u8::MAX as usize && markers[0] != 0 {
+        bytes.extend([markers[0], len as u8]);
+    } else if len <= u16::MAX as usize {
+        bytes.push(markers[1]);
+        bytes.extend((len as u16).to_be_bytes());
+    } else {
+        bytes.push(markers[2]);
+        bytes.extend((len as u32).to_be_bytes());
+    }
+}
+
+fn write_msgpack(value: &Value, bytes: &mut Vec<u8>) {
+    match value {
+        Value::Null => bytes.push(0xc0),
+        Value::Bool(false) => bytes.push(0xc2),
+        Value::Bool(true) => bytes.push(0xc3),
+        Value::Number(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(n), _) if n < 0x80 => bytes.push(n as u8),
+            (Some(n), _) => {
+                bytes.push(0xcf);
+                bytes.extend(n.to_be_bytes());
+            }
+            (None, Some(n)) if n >= -32 => bytes.push(n as i8 as u8),
+            (None, Some(n)) => {
+                bytes.push(0xd3);
+                bytes.extend(n.to_be_bytes());
+            }
+            (None, None) => {
+                bytes.push(0xcb);
+                bytes.extend(n.as_f64().unwrap_or_default().to_be_bytes());
+            }
+        },
+        Value::String(s) => {
+            write_len(bytes, s.len(), 0xa0, 31, [0xd9, 0xda, 0xdb]);
+            bytes.extend(s.as_bytes());
+        }
+        Value::Array(items) => {
+            write_len(bytes, items.len(), 0x90, 15, [0, 0xdc, 0xdd]);
+            items.iter().for_each(|item| write_msgpack(item, bytes));
+        }
+        Value::Object(map) => {
+            write_len(bytes, map.len(), 0x80, 15, [0, 0xde, 0xdf]);
+            for (key, value) in map {
+                write_msgpack(&Value::String(key.clone()), bytes);
+                write_msgpack(value, bytes);
+            }
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> ClientResult<&[u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| decode_error("unexpected end of input"))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn array<const N: usize>(&mut self) -> ClientResult<[u8; N]> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn len(&mut self, width: usize) -> ClientResult<usize> {
+        Ok(match width {
+            1 => u8::from_be_bytes(self.array()?) as usize,
+            2 => u16::from_be_bytes(self.array()?) as usize,
+            _ => u32::from_be_bytes(self.array()?) as usize,
+        })
+    }
+
+    fn string(&mut self, len: usize) -> ClientResult<Value> {
+        let bytes = self.take(len)?;
+        std::str::from_utf8(bytes)
+            .map(|s| Value::String(s.to_string()))
+            .map_err(|_| decode_error("invalid utf-8 string"))
+    }
+
+    fn items(&mut self, len: usize, depth: usize) -> ClientResult<Value> {
+        (0..len)
+            .map(|_| self.value(depth + 1))
+            .collect::<ClientResult<_>>()
+            .map(Value::Array)
+    }
+
+    fn entries(&mut self, len: usize, depth: usize) -> ClientResult<Value> {
+        let mut map = Map::new();
+        for _ in 0..len {
+            let key = match self.value(depth + 1)? {
+                Value::String(key) => key,
+                key => key.to_string(),
+            };
+            map.insert(key, self.value(depth + 1)?);
+        }
+        Ok(Value::Object(map))
+    }
+
+    fn float(value: f64) -> ClientResult<Value> {
+        Number::from_f64(value)
+            .map(Value::Number)
+            .ok_or_else(|| decode_error("non-finite float"))
+    }
+
+    fn value(&mut self, depth: usize) -> ClientResult<Value> {
+        if depth > MAX_DEPTH {
+            return Err(decode_error("document too deep"));
+        }
+        let [marker] = self.array()?;
+        match marker {
+            0x00..=0x7f => Ok(Value::from(marker)),
+            0x80..=0x8f => self.entries((marker & 0x0f) as usize, depth),
+            0x90..=0x9f => self.items((marker & 0x0f) as usize, depth),
+            0xa0..=0xbf => self.string((marker & 0x1f) as usize),
+            0xc0 => Ok(Value::Null),
+            0xc2 => Ok(Value::Bool(false)),
+            0xc3 => Ok(Value::Bool(true)),
+            0xc4..=0xc6 => {
+                let len = self.len(1 << (marker - 0xc4))?;
+                Ok(Value::Array(
+                    self.take(len)?.iter().map(|b| Value::from(*b)).collect(),
+                ))
+            }
+            0xca => Self::float(f32::from_be_bytes(self.array()?) as f64),
+            0xcb => Self::float(f64::from_be_bytes(self.array()?)),
+            0xcc => Ok(Value::from(u8::from_be_bytes(self.array()?))),
+            0xcd => Ok(Value::from(u16::from_be_bytes(self.array()?))),
+            0xce => Ok(Value::from(u32::from_be_bytes(self.array()?))),
+            0xcf => Ok(Value::from(u64::from_be_bytes(self.array()?))),
+            0xd0 => Ok(Value::from(i8::from_be_bytes(self.array()?))),
+            0xd1 => Ok(Value::from(i16::from_be_bytes(self.array()?))),
+            0xd2 => Ok(Value::from(i32::from_be_bytes(self.array()?))),
+            0xd3 => Ok(Value::from(i64::from_be_bytes(self.array()?))),
+            0xd9..=0xdb => {
+                let len = self.len(1 << (marker - 0xd9))?;
+                self.string(len)
+            }
+            0xdc | 0xdd => {
+                let len = self.len(2 << (marker - 0xdc))?;
+                self.items(len, depth)
+            }
+            0xde | 0xdf => {
+                let len = self.len(2 << (marker - 0xde))?;
+                self.entries(len, depth)
+            }
+            0xe0..=0xff => Ok(Value::from(marker as i8)),
+            _ => Err(decode_error("unsupported msgpack type")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn msgpack_roundtrip() {
+        let value = json!({
+          "data": {
+            "series": [0, 127, 128, -1, -33, 70000, -70000, 1.5, u64::MAX, i64::MIN],
+            "name": "x".repeat(40),
+            "empty": {},
+            "nested": [[null, true, false]],
+          }
+        });
+
+        let bytes = WireEncoding::MessagePack.encode(&value).unwrap();
+        assert_eq!(WireEncoding::MessagePack.decode(&bytes).unwrap(), value);
+        assert!(WireEncoding::MessagePack
+            .decode(&bytes[..bytes.len() - 1])
+            .is_err());
+    }
+
+    #[test]
+    fn decode_compact_msgpack() {
+        let bytes = [
+            0x81, 0xa4, b'd', b'a', b't', b'a', 0x82, 0xa1, b'a', 0xcd, 0x01, 0x00, 0xa1, b'b',
+            0xca, 0x3f, 0xc0, 0x00, 0x00,
+        ];
+        assert_eq!(
+            WireEncoding::MessagePack.decode(&bytes).unwrap(),
+            json!({ "data": { "a": 256, "b": 1.5 } })
+        );
+        assert_eq!(
+            WireEncoding::from_content_type("application/x-msgpack; charset=binary"),
+            Some(WireEncoding::MessagePack)
+        );
+    }
+}
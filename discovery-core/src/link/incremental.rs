@@ -0,0 +1,265 @@
+use serde_json::{Map, Value};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum Separator {
+    // No member read yet; the container may close.
+    #[default]
+    Member,
+    // A `,` was just read; another member must follow.
+    Comma,
+    // A member was just read; `,` or the closing bracket must follow.
+    Expected,
+}
+
+#[derive(Debug)]
+enum Frame {
+    Object {
+        map: Map<String, Value>,
+        key: Option<String>,
+        colon: bool,
+        separator: Separator,
+    },
+    Array(Vec<Value>, Separator),
+}
+
+impl Frame {
+    fn separator(&mut self) -> &mut Separator {
+        match self {
+            Frame::Object { separator, .. } | Frame::Array(_, separator) => separator,
+        }
+    }
+}
+
+// Builds the response as bytes arrive so only the scalar being read is held as
+// text. The finished `Value` is still normalized as a whole: callers receive the
+// complete typed response, so entities cannot be released before it is built.
+#[derive(Debug, Default)]
+pub(crate) struct ResponseParser {
+    stack: Vec<Frame>,
+    token: Vec<u8>,
+    in_string: bool,
+    escaped: bool,
+    root: Option<Value>,
+}
+
+fn error(reason: &str) -> serde_json::Error {
+    serde::de::Error::custom(reason)
+}
+
+impl ResponseParser {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn feed(&mut self, bytes: &[u8]) -> serde_json::Result<()> {
+        let mut offset = 0;
+        while offset < bytes.len() {
+            if self.in_string {
+                offset += self.string(&bytes[offset..])?;
+                continue;
+            }
+            let byte = bytes[offset];
+            offset += 1;
+            if !matches!(
+                byte,
+                b'{' | b'}' | b'[' | b']' | b':' | b',' | b'"' | b' ' | b'\t' | b'\n' | b'\r'
+            ) {
+                self.token.push(byte);
+                continue;
+            }
+            self.scalar()?;
+            match byte {
+                b'"' => {
+                    self.in_string = true;
+                    self.token.push(byte);
+                }
+                b'{' => self.open(Frame::Object {
+                    map: Map::new(),
+                    key: None,
+                    colon: false,
+                    separator: Separator::Member,
+                })?,
+                b'[' => self.open(Frame::Array(vec![], Separator::Member))?,
+                b'}' => match self.stack.pop() {
+                    Some(Frame::Object {
+                        map,
+                        key: None,
+                        separator: Separator::Member | Separator::Expected,
+                        ..
+                    }) => self.value(Value::Object(map))?,
+                    _ => return Err(error("unexpected `}`")),
+                },
+                b']' => match self.stack.pop() {
+                    Some(Frame::Array(values, Separator::Member | Separator::Expected)) => {
+                        self.value(Value::Array(values))?
+                    }
+                    _ => return Err(error("unexpected `]`")),
+                },
+                b',' => match self.stack.last_mut().map(Frame::separator) {
+                    Some(separator) if *separator == Separator::Expected => {
+                        *separator = Separator::Comma
+                    }
+                    _ => return Err(error("unexpected `,`")),
+                },
+                b':' => match self.stack.last_mut() {
+                    Some(Frame::Object {
+                        key: Some(_),
+                        colon,
+                        ..
+                    }) if !*colon => *colon = true,
+                    _ => return Err(error("unexpected `:`")),
+                },
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn string(&mut self, bytes: &[u8]) -> serde_json::Result<usize> {
+        for (index, &byte) in bytes.iter().enumerate() {
+            match byte {
+                _ if self.escaped => self.escaped = false,
+                b'\\' => self.escaped = true,
+                b'"' => {
+                    self.token.extend_from_slice(&bytes[..=index]);
+                    self.in_string = false;
+                    let value = serde_json::from_slice(&self.token)?;
+                    self.token.clear();
+                    self.value(value)?;
+                    return Ok(index + 1);
+                }
+                _ => {}
+            }
+        }
+        self.token.extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn scalar(&mut self) -> serde_json::Result<()> {
+        if self.token.is_empty() {
+            return Ok(());
+        }
+        let value = serde_json::from_slice(&self.token)?;
+        self.token.clear();
+        self.value(value)
+    }
+
+    fn open(&mut self, frame: Frame) -> serde_json::Result<()> {
+        match self.stack.last() {
+            Some(Frame::Object { colon: false, .. }) => {
+                return Err(error("expected an object key"))
+            }
+            Some(Frame::Array(_, Separator::Expected)) => return Err(error("expected `,`")),
+            _ => {}
+        }
+        if self.stack.is_empty() && self.root.is_some() {
+            return Err(error("trailing characters"));
+        }
+        self.stack.push(frame);
+        Ok(())
+    }
+
+    fn value(&mut self, value: Value) -> serde_json::Result<()> {
+        match self.stack.last_mut() {
+            None if self.root.is_none() => self.root = Some(value),
+            None => return Err(error("trailing characters")),
+            Some(Frame::Array(_, Separator::Expected)) => return Err(error("expected `,`")),
+            Some(Frame::Array(values, separator)) => {
+                values.push(value);
+                *separator = Separator::Expected;
+            }
+            Some(Frame::Object {
+                map,
+                key,
+                colon,
+                separator,
+            }) => match (key.take(), value) {
+                (Some(key), value) if *colon => {
+                    *colon = false;
+                    *separator = Separator::Expected;
+                    map.insert(key, value);
+                }
+                (None, _) if *separator == Separator::Expected => {
+                    return Err(error("expected `,`"))
+                }
+                (None, Value::String(name)) => *key = Some(name),
+                _ => return Err(error("expected `:`")),
+            },
+        }
+        Ok(())
+    }
+
+    pub(crate) fn finish(mut self) -> serde_json::Result<Value> {
+        if self.in_string {
+            return Err(error("unexpected end of response"));
+        }
+        self.scalar()?;
+        match (self.root, self.stack.is_empty()) {
+            (Some(root @ Value::Object(_)), true) => Ok(root),
+            _ => Err(error("unexpected end of response")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parse(body: &str, chunk_size: usize) -> serde_json::Result<Value> {
+        let mut parser = ResponseParser::new();
+        for chunk in body.as_bytes().chunks(chunk_size) {
+            parser.feed(chunk)?;
+        }
+        parser.finish()
+    }
+
+    #[test]
+    fn parse_in_chunks() {
+        let body = json!({
+          "data": {
+            "person": { "name": "Luke \"}\" Skywalker", "films": [{ "id": "1" }, { "id": "2" }] },
+            "planets": [],
+            "empty": {}
+          },
+          "errors": [{ "message": "partial, sorry", "path": ["person", "homeworld"] }],
+          "extensions": { "cost": 3 }
+        });
+        let text = serde_json::to_string_pretty(&body).unwrap();
+
+        for chunk_size in [1, 7, text.len()] {
+            assert_eq!(parse(&text, chunk_size).unwrap(), body);
+        }
+        assert_eq!(
+            parse(r#"{"data":null,"errors":[]}"#, 3).unwrap(),
+            json!({ "data": null, "errors": [] })
+        );
+    }
+
+    #[test]
+    fn reject_truncated_body() {
+        assert!(parse(r#"{"data":{"person":{"name":"Luke"}"#, 4).is_err());
+        assert!(parse(r#"{"data":{"person":{"name":}}}"#, 4).is_err());
+        assert!(parse("", 4).is_err());
+        assert!(parse(r#"{"data":{"a" 1}}"#, 2).is_err());
+        assert!(parse(r#"{"data":{}}}"#, 2).is_err());
+    }
+
+    #[test]
+    fn reject_missing_and_extra_separators() {
+        for body in [
+            r#"{"data":[1 2]}"#,
+            r#"{"data":{"a":1 "b":2}}"#,
+            r#"{"data":[1,,2]}"#,
+            r#"{"data":{"a":1,}}"#,
+            r#"{"data":[1,]}"#,
+            r#"{"data":[,1]}"#,
+            r#"{,"data":1}"#,
+            r#"{"data":[{} {}]}"#,
+            r#"{"data":1},"#,
+        ] {
+            assert!(parse(body, 1).is_err(), "{}", body);
+            assert!(parse(body, body.len()).is_err(), "{}", body);
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use futures::future::BoxFuture;
+use graphql_client::Response;
+use serde_json::Value;
+use std::sync::Arc;
+
+use super::{GraphQLRequest, Link, LinkResponse, Next};
+use crate::client::ClientResult;
+
+pub type ResponseInterceptor = dyn Fn(&str, &mut Value) + Send + Sync;
+
+#[derive(Clone, Default)]
+pub struct Interceptors(Vec<Arc<ResponseInterceptor>>);
+
+impl Interceptors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, interceptor: impl Fn(&str, &mut Value) + Send + Sync + 'static) {
+        self.0.push(Arc::new(interceptor));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn apply(&self, operation_name: &str, response: &mut Response<Value>) {
+        if let Some(data) = response.data.as_mut() {
+            for interceptor in &self.0 {
+                interceptor(operation_name, data);
+            }
+        }
+    }
+}
+
+impl Link for Interceptors {
+    fn call<'a>(
+        &'a self,
+        request: GraphQLRequest,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, ClientResult<LinkResponse>> {
+        Box::pin(async move {
+            let operation_name = request.operation_name.clone();
+            let mut response = next.run(request).await?;
+            self.apply(&operation_name, &mut response.body);
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn rewrite_data_in_order() {
+        let mut interceptors = Interceptors::new();
+        interceptors.push(|_, data| {
+            if let Some(person) = data["person"].as_object_mut() {
+                person.remove("_debug");
+            }
+        });
+        interceptors.push(|operation, data| {
+            if let Some(height) = data["person"]["height"].as_str() {
+                data["person"]["height"] = json!(height.parse::<u32>().unwrap());
+            }
+            data["operation"] = json!(operation);
+        });
+
+        let mut response: Response<Value> = serde_json::from_value(json!({
+          "data": { "person": { "height": "172", "_debug": "node-3" } }
+        }))
+        .unwrap();
+        interceptors.apply("Person", &mut response);
+
+        assert_eq!(
+            response.data,
+            Some(json!({ "person": { "height": 172 }, "operation": "Person" }))
+        );
+    }
+}
@@ -0,0 +1,604 @@
+use futures::future::BoxFuture;
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
+use super::{GraphQLRequest, Link, LinkResponse, Next};
+use crate::cache::Cache;
+use crate::client::ClientResult;
+
+pub struct LocalField<'a> {
+    pub typename: &'a str,
+    pub field: &'a str,
+    pub parent: &'a Map<String, Value>,
+    pub variables: &'a Value,
+    pub cache: Option<&'a dyn Cache>,
+}
+
+pub type LocalResolver = dyn Fn(&LocalField<'_>) -> Value + Send + Sync;
+
+#[derive(Clone, Default)]
+pub struct LocalResolvers(HashMap<String, Arc<LocalResolver>>);
+
+impl LocalResolvers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(
+        &mut self,
+        coordinate: impl Into<String>,
+        resolver: impl Fn(&LocalField<'_>) -> Value + Send + Sync + 'static,
+    ) {
+        self.0.insert(coordinate.into(), Arc::new(resolver));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn resolve(&self, field: &LocalField<'_>) -> Value {
+        self.0
+            .get(&format!("{}.{}", field.typename, field.field))
+            .or_else(|| self.0.get(field.field))
+            .map(|resolver| resolver(field))
+            .unwrap_or(Value::Null)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token<'a> {
+    Name(&'a str),
+    Punct(u8),
+    Spread,
+    Value,
+}
+
+#[derive(Debug)]
+struct Lexed<'a> {
+    token: Token<'a>,
+    start: usize,
+    end: usize,
+}
+
+fn skip_string(bytes: &[u8], mut i: usize) -> usize {
+    if bytes[i..].starts_with(b"\"\"\"") {
+        i += 3;
+        while i < bytes.len() {
+            if bytes[i..].starts_with(b"\\\"\"\"") {
+                i += 4;
+            } else if bytes[i..].starts_with(b"\"\"\"") {
+                return i + 3;
+            } else {
+                i += 1;
+            }
+        }
+        return bytes.len();
+    }
+    i += 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return i + 1,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+fn tokenize(source: &str) -> Vec<Lexed<'_>> {
+    let bytes = source.as_bytes();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let token = match bytes[i] {
+            b'#' => {
+                while i < bytes.len() && bytes[i] != b'\n' && bytes[i] != b'\r' {
+                    i += 1;
+                }
+                continue;
+            }
+            c if c.is_ascii_whitespace() || c == b',' => {
+                i += 1;
+                continue;
+            }
+            b'"' => {
+                i = skip_string(bytes, i);
+                Token::Value
+            }
+            b'.' if bytes[i..].starts_with(b"...") => {
+                i += 3;
+                Token::Spread
+            }
+            c if c == b'_' || c.is_ascii_alphabetic() => {
+                while i < bytes.len() && (bytes[i] == b'_' || bytes[i].is_ascii_alphanumeric()) {
+                    i += 1;
+                }
+                Token::Name(&source[start..i])
+            }
+            c if c == b'-' || c.is_ascii_digit() => {
+                i += 1;
+                while i < bytes.len()
+                    && (bytes[i].is_ascii_alphanumeric() || matches!(bytes[i], b'.' | b'+' | b'-'))
+                {
+                    i += 1;
+                }
+                Token::Value
+            }
+            c => {
+                i += 1;
+                Token::Punct(c)
+            }
+        };
+        tokens.push(Lexed {
+            token,
+            start,
+            end: i,
+        });
+    }
+    tokens
+}
+
+#[derive(Debug)]
+enum Selection<'a> {
+    Field {
+        key: &'a str,
+        name: &'a str,
+        client: bool,
+        children: Vec<Selection<'a>>,
+    },
+    Spread(&'a str),
+    Inline(Option<&'a str>, Vec<Selection<'a>>),
+}
+
+#[derive(Debug)]
+struct Operation<'a> {
+    name: Option<&'a str>,
+    root: &'static str,
+    selections: Vec<Selection<'a>>,
+}
+
+#[derive(Debug, Default)]
+struct Document<'a> {
+    operations: Vec<Operation<'a>>,
+    fragments: HashMap<&'a str, (&'a str, Vec<Selection<'a>>)>,
+    removals: Vec<Range<usize>>,
+}
+
+struct Parser<'t, 'a> {
+    tokens: &'t [Lexed<'a>],
+    pos: usize,
+    removals: Vec<Range<usize>>,
+}
+
+impl<'a> Parser<'_, 'a> {
+    fn peek(&self) -> Option<Token<'a>> {
+        self.tokens.get(self.pos).map(|lexed| lexed.token)
+    }
+
+    fn eat(&mut self, token: Token<'a>) -> bool {
+        let matched = self.peek() == Some(token);
+        self.pos += matched as usize;
+        matched
+    }
+
+    fn name(&mut self) -> Option<&'a str> {
+        match self.peek()? {
+            Token::Name(name) => {
+                self.pos += 1;
+                Some(name)
+            }
+            _ => None,
+        }
+    }
+
+    fn skip_arguments(&mut self) {
+        if !self.eat(Token::Punct(b'(')) {
+            return;
+        }
+        let mut depth = 1;
+        while let Some(token) = self.peek() {
+            self.pos += 1;
+            match token {
+                Token::Punct(b'(') => depth += 1,
+                Token::Punct(b')') if depth == 1 => return,
+                Token::Punct(b')') => depth -= 1,
+                _ => {}
+            }
+        }
+    }
+
+    fn directives(&mut self) -> bool {
+        let mut client = false;
+        while self.eat(Token::Punct(b'@')) {
+            client |= self.name() == Some("client");
+            self.skip_arguments();
+        }
+        client
+    }
+
+    fn selection_set(&mut self) -> Vec<Selection<'a>> {
+        let mut selections = vec![];
+        if !self.eat(Token::Punct(b'{')) {
+            return selections;
+        }
+        while let Some(token) = self.peek() {
+            match token {
+                Token::Punct(b'}') => {
+                    self.pos += 1;
+                    break;
+                }
+                Token::Spread => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some(Token::Name("on")) => {
+                            self.pos += 1;
+                            let condition = self.name();
+                            self.directives();
+                            selections.push(Selection::Inline(condition, self.selection_set()));
+                        }
+                        Some(Token::Name(name)) => {
+                            self.pos += 1;
+                            self.directives();
+                            selections.push(Selection::Spread(name));
+                        }
+                        _ => {
+                            self.directives();
+                            selections.push(Selection::Inline(None, self.selection_set()));
+                        }
+                    }
+                }
+                Token::Name(_) => selections.push(self.field()),
+                _ => self.pos += 1,
+            }
+        }
+        selections
+    }
+
+    fn field(&mut self) -> Selection<'a> {
+        let start = self.tokens[self.pos].start;
+        let key = self.name().unwrap_or_default();
+        let name = match self.eat(Token::Punct(b':')) {
+            true => self.name().unwrap_or_default(),
+            false => key,
+        };
+        self.skip_arguments();
+        let client = self.directives();
+        let children = match self.peek() {
+            Some(Token::Punct(b'{')) => self.selection_set(),
+            _ => vec![],
+        };
+        if client {
+            self.removals.push(start..self.tokens[self.pos - 1].end);
+        }
+        Selection::Field {
+            key,
+            name,
+            client,
+            children,
+        }
+    }
+
+    fn header(&mut self) {
+        self.name();
+        self.skip_arguments();
+        self.directives();
+    }
+}
+
+impl<'a> Document<'a> {
+    fn parse(source: &'a str) -> Self {
+        let tokens = tokenize(source);
+        let mut parser = Parser {
+            tokens: &tokens,
+            pos: 0,
+            removals: vec![],
+        };
+        let mut document = Document::default();
+        while let Some(token) = parser.peek() {
+            let root = match token {
+                Token::Punct(b'{') => "Query",
+                Token::Name("query") => "Query",
+                Token::Name("mutation") => "Mutation",
+                Token::Name("subscription") => "Subscription",
+                Token::Name("fragment") => {
+                    parser.pos += 1;
+                    let name = parser.name().unwrap_or_default();
+                    parser.eat(Token::Name("on"));
+                    let condition = parser.name().unwrap_or_default();
+                    parser.directives();
+                    let selections = parser.selection_set();
+                    document.fragments.insert(name, (condition, selections));
+                    continue;
+                }
+                _ => {
+                    parser.pos += 1;
+                    continue;
+                }
+            };
+            let mut name = None;
+            if token != Token::Punct(b'{') {
+                parser.pos += 1;
+                if let Some(Token::Name(operation_name)) = parser.peek() {
+                    name = Some(operation_name);
+                }
+                parser.header();
+            }
+            document.operations.push(Operation {
+                name,
+                root,
+                selections: parser.selection_set(),
+            });
+        }
+        document.removals = parser.removals;
+        document
+    }
+
+    fn operation(&self, operation_name: &str) -> Option<&Operation<'a>> {
+        self.operations
+            .iter()
+            .find(|operation| operation.name == Some(operation_name))
+            .or_else(|| self.operations.first())
+    }
+
+    fn network_query(&self, source: &str) -> String {
+        let mut removals = self.removals.clone();
+        removals.sort_by_key(|range| range.start);
+        let mut query = String::with_capacity(source.len());
+        let mut cursor = 0;
+        for range in removals {
+            if range.start >= cursor {
+                query.push_str(&source[cursor..range.start]);
+                cursor = range.end;
+            }
+        }
+        query.push_str(&source[cursor..]);
+        query
+    }
+}
+
+fn is_local(selections: &[Selection<'_>]) -> bool {
+    selections
+        .iter()
+        .all(|selection| matches!(selection, Selection::Field { client: true, .. }))
+}
+
+pub struct LocalStateLink<C> {
+    resolvers: LocalResolvers,
+    cache: Option<Arc<Mutex<C>>>,
+}
+
+impl<C: Cache> LocalStateLink<C> {
+    pub fn new(resolvers: LocalResolvers, cache: Option<Arc<Mutex<C>>>) -> Self {
+        Self { resolvers, cache }
+    }
+
+    fn stitch(
+        &self,
+        document: &Document<'_>,
+        selections: &[Selection<'_>],
+        typename: &str,
+        value: &mut Value,
+        context: (&Value, Option<&dyn Cache>),
+    ) {
+        match value {
+            Value::Array(items) => items
+                .iter_mut()
+                .for_each(|item| self.stitch(document, selections, typename, item, context)),
+            Value::Object(map) => {
+                let typename = match map.get("__typename").and_then(Value::as_str) {
+                    Some(typename) => typename.to_string(),
+                    None => typename.to_string(),
+                };
+                self.stitch_object(document, selections, &typename, map, context);
+            }
+            _ => {}
+        }
+    }
+
+    fn stitch_object(
+        &self,
+        document: &Document<'_>,
+        selections: &[Selection<'_>],
+        typename: &str,
+        map: &mut Map<String, Value>,
+        context: (&Value, Option<&dyn Cache>),
+    ) {
+        for selection in selections {
+            match selection {
+                Selection::Field {
+                    key,
+                    name,
+                    client: true,
+                    ..
+                } => {
+                    let value = self.resolvers.resolve(&LocalField {
+                        typename,
+                        field: name,
+                        parent: map,
+                        variables: context.0,
+                        cache: context.1,
+                    });
+                    map.insert(key.to_string(), value);
+                }
+                Selection::Field { key, children, .. } => {
+                    if let Some(child) = map.get_mut(*key) {
+                        self.stitch(document, children, "", child, context);
+                    }
+                }
+                Selection::Spread(name) => {
+                    if let Some((condition, selections)) = document.fragments.get(name) {
+                        let typename = if typename.is_empty() {
+                            condition
+                        } else {
+                            typename
+                        };
+                        self.stitch_object(document, selections, typename, map, context);
+                    }
+                }
+                Selection::Inline(condition, selections) => {
+                    let typename = match (typename, condition) {
+                        ("", Some(condition)) => condition,
+                        _ => typename,
+                    };
+                    self.stitch_object(document, selections, typename, map, context);
+                }
+            }
+        }
+    }
+}
+
+impl<C: Cache + Send + 'static> Link for LocalStateLink<C> {
+    fn call<'a>(
+        &'a self,
+        mut request: GraphQLRequest,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, ClientResult<LinkResponse>> {
+        let Some(source) = request.query.clone() else {
+            return next.run(request);
+        };
+        if !source.contains("@client") {
+            return next.run(request);
+        }
+        Box::pin(async move {
+            let document = Document::parse(&source);
+            let Some(operation) = document.operation(&request.operation_name) else {
+                return next.run(request).await;
+            };
+            let variables = request.variables.clone();
+            let mut response = match is_local(&operation.selections) {
+                true => LinkResponse::from_value(
+                    StatusCode::OK,
+                    HeaderMap::new(),
+                    json!({ "data": {} }),
+                )?,
+                false => {
+                    request.query = Some(document.network_query(&source));
+                    next.run(request).await?
+                }
+            };
+            if let Some(data) = response.body.data.as_mut() {
+                let cache = self.cache.as_ref().map(|cache| cache.lock().unwrap());
+                let cache = cache.as_deref().map(|cache| cache as &dyn Cache);
+                self.stitch(
+                    &document,
+                    &operation.selections,
+                    operation.root,
+                    data,
+                    (&variables, cache),
+                );
+            }
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::InMemoryCache;
+    use crate::link::{Transport, TransportLink, TransportRequest, TransportResponse};
+    use crate::result_key::normalize_query;
+    use futures::executor::block_on;
+
+    #[test]
+    fn strip_client_fields() {
+        let source = r#"query Cart($id: ID!) {
+  cart(id: $id) {
+    id
+    isOpen @client
+    items { ...Item }
+  }
+  theme @client(always: true) { name }
+}
+
+fragment Item on CartItem {
+  sku
+  selected: isSelected @client
+}
+"#;
+        let document = Document::parse(source);
+        assert_eq!(
+            normalize_query(&document.network_query(source)),
+            normalize_query(
+                "query Cart($id: ID!) { cart(id: $id) { id items { ...Item } } }
+                 fragment Item on CartItem { sku }"
+            )
+        );
+        assert!(!is_local(&document.operation("Cart").unwrap().selections));
+    }
+
+    struct Server;
+
+    impl Transport for Server {
+        fn execute(
+            &self,
+            request: TransportRequest,
+        ) -> BoxFuture<'_, ClientResult<TransportResponse>> {
+            Box::pin(async move {
+                assert!(!request.body["query"].as_str().unwrap().contains("@client"));
+                Ok(TransportResponse {
+                    status: StatusCode::OK,
+                    headers: HeaderMap::new(),
+                    body: br#"{ "data": { "cart": { "__typename": "Cart", "id": "1", "items": [
+                        { "__typename": "CartItem", "sku": "a" },
+                        { "__typename": "CartItem", "sku": "b" }
+                    ] } } }"#
+                        .to_vec()
+                        .into(),
+                })
+            })
+        }
+    }
+
+    #[test]
+    fn stitch_local_results() {
+        let mut resolvers = LocalResolvers::new();
+        resolvers.insert("Cart.isOpen", |_| json!(true));
+        resolvers.insert("CartItem.isSelected", |field| {
+            json!(field.parent["sku"] == field.variables["selected"])
+        });
+        resolvers.insert("theme", |field| json!({ "name": field.typename }));
+        let links: Vec<Arc<dyn Link>> = vec![
+            Arc::new(LocalStateLink::new(
+                resolvers,
+                Some(Arc::new(Mutex::new(InMemoryCache::new()))),
+            )),
+            Arc::new(TransportLink::new(Arc::new(Server))),
+        ];
+
+        let request = GraphQLRequest::raw(
+            "Cart",
+            "query Cart { cart { id isOpen @client items { ...Item } } theme @client { name } }
+             fragment Item on CartItem { sku selected: isSelected @client }",
+            json!({ "selected": "b" }),
+        );
+        let response = block_on(Next::new(&links).run(request)).unwrap();
+        assert_eq!(
+            response.body.data.unwrap(),
+            json!({
+              "cart": {
+                "__typename": "Cart",
+                "id": "1",
+                "isOpen": true,
+                "items": [
+                  { "__typename": "CartItem", "sku": "a", "selected": false },
+                  { "__typename": "CartItem", "sku": "b", "selected": true }
+                ]
+              },
+              "theme": { "name": "Query" }
+            })
+        );
+
+        let request = GraphQLRequest::raw("Theme", "query Theme { theme @client }", json!({}));
+        let response = block_on(Next::new(&links[..1]).run(request)).unwrap();
+        assert_eq!(
+            response.body.data.unwrap(),
+            json!({ "theme": { "name": "Query" } })
+        );
+    }
+}
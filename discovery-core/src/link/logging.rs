@@ -0,0 +1,118 @@
+use futures::future::BoxFuture;
+use log::Level;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Instant;
+
+use super::{GraphQLRequest, Link, LinkResponse, Next};
+use crate::client::ClientResult;
+
+pub(crate) const LOG_TARGET: &str = "discovery";
+
+type Redact = dyn Fn(&mut Value) + Send + Sync;
+
+pub struct LoggingLink {
+    level: Level,
+    redact: Option<Arc<Redact>>,
+}
+
+impl LoggingLink {
+    pub fn new() -> Self {
+        Self {
+            level: Level::Debug,
+            redact: None,
+        }
+    }
+
+    pub fn with_level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    pub fn redact_variables(mut self, redact: impl Fn(&mut Value) + Send + Sync + 'static) -> Self {
+        self.redact = Some(Arc::new(redact));
+        self
+    }
+
+    fn variables(&self, request: &GraphQLRequest) -> Value {
+        let mut variables = request.variables.clone();
+        if let Some(redact) = &self.redact {
+            redact(&mut variables);
+        }
+        variables
+    }
+}
+
+impl Default for LoggingLink {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Link for LoggingLink {
+    fn call<'a>(
+        &'a self,
+        request: GraphQLRequest,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, ClientResult<LinkResponse>> {
+        Box::pin(async move {
+            let operation_name = request.operation_name.clone();
+            let variables = self.variables(&request);
+            let started = Instant::now();
+
+            let response = next.run(request).await;
+
+            let duration = started.elapsed();
+            match &response {
+                Ok(response) => log::log!(
+                    target: LOG_TARGET,
+                    self.level,
+                    "{} variables={} status={} errors={} duration={:?} cache_hit=false",
+                    operation_name,
+                    variables,
+                    response.status.as_u16(),
+                    response.body.errors.as_ref().map_or(0, Vec::len),
+                    duration,
+                ),
+                Err(e) => log::log!(
+                    target: LOG_TARGET,
+                    Level::Warn.min(self.level),
+                    "{} variables={} error={} duration={:?} cache_hit=false",
+                    operation_name,
+                    variables,
+                    e,
+                    duration,
+                ),
+            }
+            response
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use graphql_client::QueryBody;
+    use serde_json::json;
+
+    #[test]
+    fn redact_variables() {
+        let link = LoggingLink::new().redact_variables(|variables| {
+            if let Some(password) = variables.get_mut("password") {
+                *password = json!("[redacted]");
+            }
+        });
+        let request = GraphQLRequest::new(&QueryBody {
+            variables: json!({ "user": "luke", "password": "secret" }),
+            query: "mutation Login($user: String!, $password: String!) { login }",
+            operation_name: "Login",
+        })
+        .unwrap();
+
+        assert_eq!(
+            link.variables(&request),
+            json!({ "user": "luke", "password": "[redacted]" })
+        );
+        assert_eq!(request.variables["password"], json!("secret"));
+    }
+}
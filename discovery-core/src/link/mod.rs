@@ -0,0 +1,895 @@
+mod apollo;
+mod auth;
+mod batch;
+mod context;
+mod dedup;
+mod defaults;
+mod encoding;
+mod incremental;
+mod intercept;
+mod local;
+mod logging;
+mod oauth2;
+mod persisted;
+pub(crate) mod progress;
+mod rate_limit;
+mod schedule;
+mod sign;
+mod transport;
+
+pub use apollo::{ApolloTraceLink, TraceReport};
+pub use auth::{AuthLink, StaticToken, TokenProvider};
+pub use batch::{BatchHttpLink, BatchOptions};
+pub use context::TypedContext;
+pub use dedup::DedupLink;
+pub use defaults::RequestDefaults;
+pub use encoding::{WireEncoding, MSGPACK};
+pub use intercept::{Interceptors, ResponseInterceptor};
+pub use local::{LocalField, LocalResolver, LocalResolvers, LocalStateLink};
+pub use logging::LoggingLink;
+pub(crate) use logging::LOG_TARGET;
+pub use oauth2::ClientCredentials;
+pub use persisted::{
+    AutomaticPersistedQueryLink, PersistedOperation, PersistedOperationsLink,
+    PersistedQueryManifest,
+};
+pub use progress::Progress;
+pub(crate) use progress::ProgressFn;
+pub use rate_limit::{retry_after, RateLimitLink, RateLimitState, RateLimits};
+pub use schedule::{Priority, Scheduler, SchedulerLink, PRIORITY_CONTEXT};
+pub(crate) use sign::sign;
+pub use sign::{HmacSigner, RequestSigner, SignableRequest};
+pub use transport::{Transport, TransportLink, TransportRequest, TransportResponse};
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::stream::{BoxStream, Stream, StreamExt};
+use graphql_client::{QueryBody, Response};
+use graphql_parser::parse_query;
+use graphql_parser::query::{Definition, OperationDefinition};
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, CONTENT_LENGTH, CONTENT_TYPE};
+use reqwest::{Method, StatusCode};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use crate::client::{ClientError, ClientResult};
+use crate::defer::{self, IncrementalPayload, ACCEPT_INCREMENTAL};
+use crate::response::GraphQLError;
+use crate::upload::{self, Upload};
+use incremental::ResponseParser;
+
+pub const HTTP_METHOD_CONTEXT: &str = "httpMethod";
+pub const GRAPHQL_RESPONSE_JSON: &str = "application/graphql-response+json";
+const ACCEPT_GRAPHQL: &str = "application/graphql-response+json, application/json;q=0.9";
+const ACCEPT_MSGPACK: &str =
+    "application/msgpack, application/graphql-response+json;q=0.9, application/json;q=0.8";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphQLRequest {
+    pub operation_name: String,
+    pub query: Option<String>,
+    pub variables: Value,
+    pub extensions: Map<String, Value>,
+    pub headers: HeaderMap,
+    pub context: Map<String, Value>,
+    pub typed_context: TypedContext,
+    pub files: Vec<(String, Upload)>,
+}
+
+impl GraphQLRequest {
+    pub fn new<V: Serialize>(query_body: &QueryBody<V>) -> serde_json::Result<Self> {
+        Ok(Self::raw(
+            query_body.operation_name,
+            query_body.query,
+            serde_json::to_value(&query_body.variables)?,
+        ))
+    }
+
+    pub fn raw(operation_name: &str, query: &str, mut variables: Value) -> Self {
+        let mut files = vec![];
+        upload::extract(&mut variables, "variables".to_string(), &mut files);
+        Self {
+            operation_name: operation_name.to_string(),
+            query: Some(query.to_string()),
+            variables,
+            extensions: Map::new(),
+            headers: HeaderMap::new(),
+            context: Map::new(),
+            typed_context: TypedContext::new(),
+            files,
+        }
+    }
+
+    pub fn body(&self) -> Value {
+        let mut body = Map::new();
+        if let Some(query) = &self.query {
+            body.insert("query".to_string(), Value::String(query.clone()));
+        }
+        body.insert("variables".to_string(), self.variables.clone());
+        body.insert(
+            "operationName".to_string(),
+            Value::String(self.operation_name.clone()),
+        );
+        if !self.extensions.is_empty() {
+            body.insert(
+                "extensions".to_string(),
+                Value::Object(self.extensions.clone()),
+            );
+        }
+        Value::Object(body)
+    }
+
+    pub fn is_query(&self) -> bool {
+        let Some(Ok(document)) = self.query.as_deref().map(parse_query) else {
+            return false;
+        };
+        let operations: Vec<_> = document
+            .definitions
+            .iter()
+            .filter_map(|definition| match definition {
+                Definition::Operation(OperationDefinition::SelectionSet(_)) => Some((None, true)),
+                Definition::Operation(OperationDefinition::Query(query)) => {
+                    Some((query.name.as_deref(), true))
+                }
+                Definition::Operation(OperationDefinition::Mutation(mutation)) => {
+                    Some((mutation.name.as_deref(), false))
+                }
+                Definition::Operation(OperationDefinition::Subscription(subscription)) => {
+                    Some((subscription.name.as_deref(), false))
+                }
+                Definition::Fragment(_) => None,
+            })
+            .collect();
+        match operations.as_slice() {
+            [(_, is_query)] => *is_query,
+            operations => operations
+                .iter()
+                .any(|(name, is_query)| *name == Some(self.operation_name.as_str()) && *is_query),
+        }
+    }
+
+    pub fn accepts_incremental(&self) -> bool {
+        self.headers
+            .get(ACCEPT)
+            .is_some_and(|accept| accept == ACCEPT_INCREMENTAL)
+    }
+
+    fn query_params(&self) -> serde_json::Result<Vec<(&'static str, String)>> {
+        let mut params = vec![];
+        if let Some(query) = &self.query {
+            params.push(("query", query.clone()));
+        }
+        params.push(("operationName", self.operation_name.clone()));
+        params.push(("variables", serde_json::to_string(&self.variables)?));
+        if !self.extensions.is_empty() {
+            params.push(("extensions", serde_json::to_string(&self.extensions)?));
+        }
+        Ok(params)
+    }
+}
+
+pub struct IncrementalStream(BoxStream<'static, ClientResult<IncrementalPayload>>);
+
+impl IncrementalStream {
+    pub fn new(
+        payloads: impl Stream<Item = ClientResult<IncrementalPayload>> + Send + 'static,
+    ) -> Self {
+        Self(payloads.boxed())
+    }
+}
+
+impl Stream for IncrementalStream {
+    type Item = ClientResult<IncrementalPayload>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.0.poll_next_unpin(cx)
+    }
+}
+
+impl fmt::Debug for IncrementalStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("IncrementalStream")
+    }
+}
+
+#[derive(Debug)]
+pub struct LinkResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Response<Value>,
+    pub extensions: Map<String, Value>,
+    pub incremental: Option<IncrementalStream>,
+}
+
+impl LinkResponse {
+    pub fn from_slice(
+        status: StatusCode,
+        headers: HeaderMap,
+        bytes: &[u8],
+    ) -> serde_json::Result<Self> {
+        Self::from_value(status, headers, serde_json::from_slice(bytes)?)
+    }
+
+    pub fn decode(status: StatusCode, headers: HeaderMap, bytes: &[u8]) -> ClientResult<Self> {
+        let body = WireEncoding::of(&headers).decode(bytes)?;
+        Ok(Self::from_value(status, headers, body)?)
+    }
+
+    pub fn from_value(
+        status: StatusCode,
+        headers: HeaderMap,
+        mut body: Value,
+    ) -> serde_json::Result<Self> {
+        let extensions = match body.as_object_mut().and_then(|b| b.remove("extensions")) {
+            Some(Value::Object(extensions)) => extensions,
+            _ => Map::new(),
+        };
+        Ok(Self {
+            status,
+            headers,
+            body: serde_json::from_value(body)?,
+            extensions,
+            incremental: None,
+        })
+    }
+
+    pub(crate) async fn from_parts(
+        status: StatusCode,
+        headers: HeaderMap,
+        parts: impl Stream<Item = ClientResult<String>> + Send + 'static,
+    ) -> ClientResult<Self> {
+        let mut parts = parts.boxed();
+        let initial = match parts.next().await {
+            Some(part) => serde_json::from_str(&part?)?,
+            None => {
+                return Err(ClientError::DeserializeError(serde::de::Error::custom(
+                    "unexpected end of response",
+                )))
+            }
+        };
+        let mut response = Self::from_value(status, headers, initial)?;
+        response.incremental = Some(IncrementalStream::new(
+            parts.map(|part| Ok(serde_json::from_str(&part?)?)),
+        ));
+        Ok(response)
+    }
+
+    pub fn is_graphql_response(&self) -> bool {
+        is_graphql_response(&self.headers)
+    }
+
+    pub(crate) fn into_result(self) -> ClientResult<Self> {
+        if self.status.is_success() || !self.is_graphql_response() || self.body.data.is_some() {
+            return Ok(self);
+        }
+        Err(ClientError::RequestError {
+            status: self.status,
+            errors: self
+                .body
+                .errors
+                .unwrap_or_default()
+                .into_iter()
+                .map(GraphQLError::from)
+                .collect(),
+        })
+    }
+}
+
+fn multipart_boundary(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .and_then(defer::boundary)
+}
+
+fn is_graphql_response(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with(GRAPHQL_RESPONSE_JSON))
+}
+
+pub trait Link: Send + Sync {
+    fn call<'a>(
+        &'a self,
+        request: GraphQLRequest,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, ClientResult<LinkResponse>>;
+}
+
+#[derive(Clone, Copy)]
+pub struct Next<'a> {
+    links: &'a [Arc<dyn Link>],
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(links: &'a [Arc<dyn Link>]) -> Self {
+        Self { links }
+    }
+
+    pub fn run(self, request: GraphQLRequest) -> BoxFuture<'a, ClientResult<LinkResponse>> {
+        match self.links.split_first() {
+            Some((link, rest)) => link.call(request, Next { links: rest }),
+            None => Box::pin(async { Err(ClientError::LinkChainNotTerminated) }),
+        }
+    }
+}
+
+pub struct HttpLink {
+    client: reqwest::Client,
+    uri: String,
+    upload_progress: Option<Arc<ProgressFn>>,
+    download_progress: Option<Arc<ProgressFn>>,
+    signer: Option<Arc<dyn RequestSigner>>,
+    headers: HeaderMap,
+    get_queries: bool,
+    max_response_size: Option<u64>,
+    encoding: WireEncoding,
+    encode_requests: bool,
+}
+
+impl HttpLink {
+    pub fn new(client: reqwest::Client, uri: impl Into<String>) -> Self {
+        Self {
+            client,
+            uri: uri.into(),
+            upload_progress: None,
+            download_progress: None,
+            signer: None,
+            headers: HeaderMap::new(),
+            get_queries: false,
+            max_response_size: None,
+            encoding: WireEncoding::Json,
+            encode_requests: false,
+        }
+    }
+
+    pub fn encoding(mut self, encoding: WireEncoding, encode_requests: bool) -> Self {
+        self.encoding = encoding;
+        self.encode_requests = encode_requests;
+        self
+    }
+
+    pub fn max_response_size(mut self, limit: Option<u64>) -> Self {
+        self.max_response_size = limit;
+        self
+    }
+
+    pub fn use_get_for_queries(mut self, enable: bool) -> Self {
+        self.get_queries = enable;
+        self
+    }
+
+    pub fn on_upload_progress(
+        mut self,
+        progress: impl Fn(Progress) + Send + Sync + 'static,
+    ) -> Self {
+        self.upload_progress = Some(Arc::new(progress));
+        self
+    }
+
+    pub fn on_download_progress(
+        mut self,
+        progress: impl Fn(Progress) + Send + Sync + 'static,
+    ) -> Self {
+        self.download_progress = Some(Arc::new(progress));
+        self
+    }
+
+    pub fn signer(mut self, signer: impl RequestSigner + 'static) -> Self {
+        self.signer = Some(Arc::new(signer));
+        self
+    }
+
+    pub(crate) fn with_signer(mut self, signer: Option<Arc<dyn RequestSigner>>) -> Self {
+        self.signer = signer;
+        self
+    }
+
+    pub(crate) fn with_headers(mut self, headers: HeaderMap) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    pub(crate) fn with_progress(
+        mut self,
+        upload: Option<Arc<ProgressFn>>,
+        download: Option<Arc<ProgressFn>>,
+    ) -> Self {
+        self.upload_progress = upload;
+        self.download_progress = download;
+        self
+    }
+
+    pub(crate) async fn post(
+        &self,
+        headers: &HeaderMap,
+        body: &Value,
+    ) -> ClientResult<(StatusCode, HeaderMap, Bytes)> {
+        let res = self.post_json(headers, body).await?;
+        let status = res.status();
+        let headers = res.headers().clone();
+        let mut bytes = vec![];
+        self.read(res, |chunk| {
+            bytes.extend_from_slice(chunk);
+            Ok(())
+        })
+        .await?;
+        Ok((status, headers, Bytes::from(bytes)))
+    }
+
+    async fn post_json(
+        &self,
+        headers: &HeaderMap,
+        body: &Value,
+    ) -> ClientResult<reqwest::Response> {
+        let encoding = match self.encode_requests {
+            true => self.encoding,
+            false => WireEncoding::Json,
+        };
+        let bytes = Bytes::from(encoding.encode(body)?);
+        let mut headers = self.headers(headers);
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static(encoding.content_type()),
+        );
+        sign(
+            self.signer.as_ref(),
+            &Method::POST,
+            &self.uri,
+            &mut headers,
+            &bytes,
+        )?;
+
+        let request = self.client.post(self.uri.as_str()).headers(headers);
+        let request = match &self.upload_progress {
+            Some(progress) => {
+                let len = bytes.len() as u64;
+                request
+                    .header(CONTENT_LENGTH, len)
+                    .body(reqwest::Body::wrap_stream(progress::chunked(
+                        bytes,
+                        0,
+                        len,
+                        progress.clone(),
+                    )))
+            }
+            None => request.body(bytes),
+        };
+        self.send(request).await
+    }
+
+    fn get_request(&self, request: &GraphQLRequest) -> ClientResult<reqwest::RequestBuilder> {
+        let url = self
+            .client
+            .get(self.uri.as_str())
+            .query(&request.query_params()?)
+            .build()?
+            .url()
+            .clone();
+        let mut headers = self.headers(&request.headers);
+        sign(
+            self.signer.as_ref(),
+            &Method::GET,
+            url.as_str(),
+            &mut headers,
+            &[],
+        )?;
+        Ok(self.client.get(url).headers(headers))
+    }
+
+    async fn get(&self, request: &GraphQLRequest) -> ClientResult<reqwest::Response> {
+        self.send(self.get_request(request)?).await
+    }
+
+    async fn post_multipart(
+        &self,
+        headers: &HeaderMap,
+        body: &Value,
+        files: &[(String, Upload)],
+    ) -> ClientResult<reqwest::Response> {
+        if self.signer.is_some() {
+            return Err(ClientError::SigningError(
+                "multipart requests cannot be signed".to_string(),
+            ));
+        }
+        self.send(
+            self.client
+                .post(self.uri.as_str())
+                .headers(self.headers(headers))
+                .multipart(upload::form(body, files, self.upload_progress.as_ref())?),
+        )
+        .await
+    }
+
+    fn use_get(&self, request: &GraphQLRequest) -> bool {
+        match request
+            .context
+            .get(HTTP_METHOD_CONTEXT)
+            .and_then(Value::as_str)
+        {
+            Some(method) => method.eq_ignore_ascii_case("GET"),
+            None => self.get_queries && request.is_query(),
+        }
+    }
+
+    fn headers(&self, headers: &HeaderMap) -> HeaderMap {
+        let mut merged = self.headers.clone();
+        merged.extend(headers.clone());
+        let accept = match self.encoding {
+            WireEncoding::Json => ACCEPT_GRAPHQL,
+            WireEncoding::MessagePack => ACCEPT_MSGPACK,
+        };
+        merged
+            .entry(ACCEPT)
+            .or_insert(HeaderValue::from_static(accept));
+        merged
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder) -> ClientResult<reqwest::Response> {
+        let res = request.send().await?;
+
+        let status = res.status();
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(ClientError::RateLimited {
+                retry_after: retry_after(res.headers()),
+            });
+        }
+        if !status.is_success()
+            && status != StatusCode::NOT_MODIFIED
+            && !is_graphql_response(res.headers())
+        {
+            let body = res.text().await.unwrap_or_default();
+            return Err(ClientError::HttpError { status, body });
+        }
+        Ok(res)
+    }
+
+    fn chunks(
+        &self,
+        res: reqwest::Response,
+    ) -> ClientResult<impl Stream<Item = ClientResult<Bytes>> + Send + Unpin + 'static> {
+        let total = res.content_length();
+        let limit = self.max_response_size;
+        if let (Some(total), Some(limit)) = (total, limit) {
+            if total > limit {
+                return Err(ClientError::ResponseTooLarge(limit));
+            }
+        }
+        let progress = self.download_progress.clone();
+        let mut transferred = 0;
+        Ok(res.bytes_stream().map(move |chunk| {
+            let chunk = chunk?;
+            transferred += chunk.len() as u64;
+            if let Some(limit) = limit.filter(|limit| transferred > *limit) {
+                return Err(ClientError::ResponseTooLarge(limit));
+            }
+            if let Some(progress) = &progress {
+                progress(Progress { transferred, total });
+            }
+            Ok(chunk)
+        }))
+    }
+
+    async fn read(
+        &self,
+        mut res: reqwest::Response,
+        mut f: impl FnMut(&[u8]) -> ClientResult<()>,
+    ) -> ClientResult<()> {
+        let total = res.content_length();
+        let limit = self.max_response_size;
+        if let (Some(total), Some(limit)) = (total, limit) {
+            if total > limit {
+                return Err(ClientError::ResponseTooLarge(limit));
+            }
+        }
+        let mut transferred = 0;
+        while let Some(chunk) = res.chunk().await? {
+            transferred += chunk.len() as u64;
+            if let Some(limit) = limit.filter(|limit| transferred > *limit) {
+                return Err(ClientError::ResponseTooLarge(limit));
+            }
+            f(&chunk)?;
+            if let Some(progress) = &self.download_progress {
+                progress(Progress { transferred, total });
+            }
+        }
+        record!("bytes" = transferred);
+        Ok(())
+    }
+}
+
+impl Link for HttpLink {
+    fn call<'a>(
+        &'a self,
+        request: GraphQLRequest,
+        _next: Next<'a>,
+    ) -> BoxFuture<'a, ClientResult<LinkResponse>> {
+        Box::pin(async move {
+            let body = request.body();
+            let res = if !request.files.is_empty() {
+                self.post_multipart(&request.headers, &body, &request.files)
+                    .await?
+            } else if self.use_get(&request) {
+                self.get(&request).await?
+            } else {
+                self.post_json(&request.headers, &body).await?
+            };
+            let status = res.status();
+            let headers = res.headers().clone();
+            if status == StatusCode::NOT_MODIFIED {
+                return Ok(LinkResponse::from_value(
+                    status,
+                    headers,
+                    Value::Object(Map::new()),
+                )?);
+            }
+            if let Some(boundary) = multipart_boundary(&headers) {
+                let parts = defer::multipart_parts(self.chunks(res)?, &boundary);
+                return LinkResponse::from_parts(status, headers, parts)
+                    .await?
+                    .into_result();
+            }
+            if WireEncoding::of(&headers) != WireEncoding::Json {
+                let mut bytes = vec![];
+                self.read(res, |chunk| {
+                    bytes.extend_from_slice(chunk);
+                    Ok(())
+                })
+                .await?;
+                return LinkResponse::decode(status, headers, &bytes)?.into_result();
+            }
+            let mut parser = ResponseParser::new();
+            self.read(res, |chunk| Ok(parser.feed(chunk)?)).await?;
+            LinkResponse::from_value(status, headers, parser.finish()?)?.into_result()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use reqwest::header::{HeaderName, HeaderValue};
+    use serde_json::json;
+
+    struct Header(&'static str);
+
+    impl Link for Header {
+        fn call<'a>(
+            &'a self,
+            mut request: GraphQLRequest,
+            next: Next<'a>,
+        ) -> BoxFuture<'a, ClientResult<LinkResponse>> {
+            let value = match request.headers.get("x-trail") {
+                Some(trail) => format!("{},{}", trail.to_str().unwrap(), self.0),
+                None => self.0.to_string(),
+            };
+            request
+                .headers
+                .insert("x-trail", HeaderValue::from_str(&value).unwrap());
+            next.run(request)
+        }
+    }
+
+    struct Echo;
+
+    impl Link for Echo {
+        fn call<'a>(
+            &'a self,
+            request: GraphQLRequest,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, ClientResult<LinkResponse>> {
+            Box::pin(async move {
+                Ok(LinkResponse {
+                    status: StatusCode::OK,
+                    headers: request.headers.clone(),
+                    body: Response {
+                        data: Some(request.body()),
+                        errors: None,
+                    },
+                    extensions: Map::new(),
+                    incremental: None,
+                })
+            })
+        }
+    }
+
+    fn request() -> GraphQLRequest {
+        GraphQLRequest::new(&QueryBody {
+            variables: json!({ "id": "1" }),
+            query: "query Person($id: ID!) { person(id: $id) { name } }",
+            operation_name: "Person",
+        })
+        .unwrap()
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn abort_oversized_response() {
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let uri = format!("http://{}/graphql", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(2) {
+                let mut stream = stream.unwrap();
+                let _ = stream.read(&mut [0; 4096]);
+                let body = format!(r#"{{ "data": {{ "blob": "{}" }} }}"#, "x".repeat(1024));
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+            }
+        });
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let run = |limit| {
+            let links: Vec<Arc<dyn Link>> = vec![Arc::new(
+                HttpLink::new(reqwest::Client::new(), uri.as_str()).max_response_size(limit),
+            )];
+            runtime.block_on(Next::new(&links).run(request()))
+        };
+
+        assert!(matches!(
+            run(Some(100)),
+            Err(ClientError::ResponseTooLarge(100))
+        ));
+        assert!(run(Some(4096)).is_ok());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn limit_incremental_chunks() {
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let uri = format!("http://{}/graphql", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let mut stream = listener.incoming().next().unwrap().unwrap();
+            let _ = stream.read(&mut [0; 4096]);
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\ncontent-type: multipart/mixed; boundary=\"-\"\r\nconnection: close\r\n\r\n\
+\r\n---\r\n\r\n{{\"data\":{{\"a\":1}},\"hasNext\":true}}\r\n---"
+            );
+            let _ = stream.flush();
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            let _ = write!(
+                stream,
+                "\r\n\r\n{{\"incremental\":[{{\"data\":{{\"blob\":\"{}\"}},\"path\":[]}}],\"hasNext\":false}}\r\n-----\r\n",
+                "x".repeat(1024)
+            );
+        });
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let links: Vec<Arc<dyn Link>> = vec![Arc::new(
+            HttpLink::new(reqwest::Client::new(), uri.as_str()).max_response_size(Some(512)),
+        )];
+
+        let payloads = runtime.block_on(async {
+            let response = Next::new(&links).run(request()).await.unwrap();
+            assert_eq!(response.body.data, Some(json!({ "a": 1 })));
+            response.incremental.unwrap().collect::<Vec<_>>().await
+        });
+
+        assert!(matches!(
+            payloads.as_slice(),
+            [Err(ClientError::ResponseTooLarge(512))]
+        ));
+    }
+
+    #[test]
+    fn map_graphql_response_status() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static(GRAPHQL_RESPONSE_JSON),
+        );
+        let response = |status, body: &str| {
+            LinkResponse::from_slice(status, headers.clone(), body.as_bytes())
+                .unwrap()
+                .into_result()
+        };
+
+        let error = response(
+            StatusCode::BAD_REQUEST,
+            r#"{ "errors": [{ "message": "Cannot query field \"nme\"" }] }"#,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            &error,
+            ClientError::RequestError { status, errors }
+                if *status == StatusCode::BAD_REQUEST && errors.len() == 1
+        ));
+        assert!(response(StatusCode::BAD_GATEWAY, r#"{ "data": { "me": null } }"#).is_ok());
+
+        let link = HttpLink::new(reqwest::Client::new(), "http://localhost/graphql");
+        assert_eq!(link.headers(&HeaderMap::new())[ACCEPT], ACCEPT_GRAPHQL);
+    }
+
+    #[test]
+    fn detect_operation_type() {
+        let is_query = |operation_name, query| {
+            GraphQLRequest::raw(operation_name, query, json!({})).is_query()
+        };
+
+        assert!(is_query("Person", "query Person { person { name } }"));
+        assert!(is_query("Person", "{ person { name } }"));
+        assert!(!is_query(
+            "Rename",
+            "fragment Parts on Person { name }\n\nmutation Rename { rename { ...Parts } }"
+        ));
+        assert!(!is_query(
+            "Rename",
+            "# rename a person\nmutation Rename { rename { name } }"
+        ));
+        assert!(!is_query(
+            "Rename",
+            "query Person { person { name } }\nmutation Rename { rename { name } }"
+        ));
+        assert!(!is_query("Person", "query Person {"));
+    }
+
+    #[test]
+    fn sign_get_query_string() {
+        let link = HttpLink::new(reqwest::Client::new(), "http://localhost/graphql").signer(
+            HmacSigner::new(HeaderName::from_static("x-signature"), "key"),
+        );
+        let signed = |query: &str| {
+            let request = GraphQLRequest::raw("Person", query, json!({}));
+            let request = link.get_request(&request).unwrap().build().unwrap();
+            assert!(request
+                .url()
+                .query()
+                .unwrap()
+                .contains("operationName=Person"));
+            request.headers()["x-signature"].clone()
+        };
+
+        assert_ne!(
+            signed("query Person { person { name } }"),
+            signed("query Person { person { email } }")
+        );
+    }
+
+    #[test]
+    fn links_run_in_order() {
+        let links: Vec<Arc<dyn Link>> =
+            vec![Arc::new(Header("a")), Arc::new(Header("b")), Arc::new(Echo)];
+
+        let response = block_on(Next::new(&links).run(request())).unwrap();
+
+        assert_eq!(response.headers["x-trail"], "a,b");
+        assert_eq!(
+            response.body.data.unwrap(),
+            json!({
+              "query": "query Person($id: ID!) { person(id: $id) { name } }",
+              "variables": { "id": "1" },
+              "operationName": "Person",
+            })
+        );
+    }
+
+    #[test]
+    fn unterminated_chain() {
+        let links: Vec<Arc<dyn Link>> = vec![Arc::new(Header("a"))];
+
+        assert!(matches!(
+            block_on(Next::new(&links).run(request())),
+            Err(ClientError::LinkChainNotTerminated)
+        ));
+    }
+}
@@ -0,0 +1,162 @@
+use futures::future::BoxFuture;
+use futures::lock::Mutex;
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+
+use super::TokenProvider;
+use crate::client::{ClientError, ClientResult};
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    token_type: Option<String>,
+    #[serde(default)]
+    expires_in: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+struct CachedToken {
+    authorization: String,
+    expires_at: Option<Instant>,
+}
+
+impl CachedToken {
+    fn new(response: TokenResponse, now: Instant) -> Self {
+        let token_type = response.token_type.unwrap_or_else(|| "Bearer".to_string());
+        let token_type = match token_type.eq_ignore_ascii_case("bearer") {
+            true => "Bearer".to_string(),
+            false => token_type,
+        };
+        Self {
+            authorization: format!("{} {}", token_type, response.access_token),
+            expires_at: response
+                .expires_in
+                .map(|expires_in| now + Duration::from_secs(expires_in)),
+        }
+    }
+
+    fn is_fresh(&self, now: Instant, margin: Duration) -> bool {
+        self.expires_at
+            .is_none_or(|expires_at| now + margin < expires_at)
+    }
+}
+
+pub struct ClientCredentials {
+    http: reqwest::Client,
+    token_url: String,
+    client_id: String,
+    client_secret: String,
+    scope: Option<String>,
+    refresh_margin: Duration,
+    cached: Mutex<Option<CachedToken>>,
+}
+
+impl ClientCredentials {
+    pub fn new(
+        token_url: impl Into<String>,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+    ) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            token_url: token_url.into(),
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            scope: None,
+            refresh_margin: Duration::from_secs(60),
+            cached: Mutex::new(None),
+        }
+    }
+
+    pub fn scope(mut self, scope: impl Into<String>) -> Self {
+        self.scope = Some(scope.into());
+        self
+    }
+
+    pub fn refresh_margin(mut self, margin: Duration) -> Self {
+        self.refresh_margin = margin;
+        self
+    }
+
+    pub fn http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    async fn fetch(&self) -> ClientResult<CachedToken> {
+        let mut form = vec![("grant_type", "client_credentials")];
+        if let Some(scope) = &self.scope {
+            form.push(("scope", scope));
+        }
+        let res = self
+            .http
+            .post(self.token_url.as_str())
+            .basic_auth(&self.client_id, Some(&self.client_secret))
+            .form(&form)
+            .send()
+            .await?;
+
+        let status = res.status();
+        if !status.is_success() {
+            let body = res.text().await.unwrap_or_default();
+            return Err(ClientError::HttpError { status, body });
+        }
+        let response = serde_json::from_slice(&res.bytes().await?)?;
+        Ok(CachedToken::new(response, Instant::now()))
+    }
+
+    async fn authorization(&self, force: bool) -> ClientResult<String> {
+        let mut cached = self.cached.lock().await;
+        match cached.as_ref() {
+            Some(token) if !force && token.is_fresh(Instant::now(), self.refresh_margin) => {
+                Ok(token.authorization.clone())
+            }
+            _ => {
+                let token = self.fetch().await?;
+                let authorization = token.authorization.clone();
+                *cached = Some(token);
+                Ok(authorization)
+            }
+        }
+    }
+}
+
+impl TokenProvider for ClientCredentials {
+    fn token(&self) -> BoxFuture<'_, ClientResult<String>> {
+        Box::pin(self.authorization(false))
+    }
+
+    fn refresh(&self) -> BoxFuture<'_, ClientResult<String>> {
+        Box::pin(self.authorization(true))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cached(json: &str) -> CachedToken {
+        CachedToken::new(serde_json::from_str(json).unwrap(), Instant::now())
+    }
+
+    #[test]
+    fn authorization_header() {
+        let token =
+            cached(r#"{ "access_token": "abc", "token_type": "bearer", "expires_in": 3600 }"#);
+        assert_eq!(token.authorization, "Bearer abc");
+
+        let token = cached(r#"{ "access_token": "abc", "token_type": "MAC" }"#);
+        assert_eq!(token.authorization, "MAC abc");
+    }
+
+    #[test]
+    fn refresh_before_expiry() {
+        let token = cached(r#"{ "access_token": "abc", "expires_in": 300 }"#);
+        let now = Instant::now();
+
+        assert!(token.is_fresh(now, Duration::from_secs(60)));
+        assert!(!token.is_fresh(now + Duration::from_secs(240), Duration::from_secs(60)));
+        assert!(cached(r#"{ "access_token": "abc" }"#).is_fresh(now, Duration::from_secs(60)));
+    }
+}
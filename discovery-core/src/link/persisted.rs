@@ -0,0 +1,283 @@
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use super::{GraphQLRequest, Link, LinkResponse, Next, HTTP_METHOD_CONTEXT};
+use crate::client::{ClientError, ClientResult};
+
+const NOT_SUPPORTED: &str = "PersistedQueryNotSupported";
+const PERSISTED_QUERY_ERRORS: [(&str, &str); 2] = [
+    ("PersistedQueryNotFound", "PERSISTED_QUERY_NOT_FOUND"),
+    (NOT_SUPPORTED, "PERSISTED_QUERY_NOT_SUPPORTED"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PersistedOperation {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub operation_type: String,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PersistedQueryManifest {
+    pub format: String,
+    pub version: u32,
+    pub operations: Vec<PersistedOperation>,
+}
+
+impl PersistedQueryManifest {
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    pub fn operation(&self, name: &str) -> Option<&PersistedOperation> {
+        self.operations.iter().find(|op| op.name == name)
+    }
+
+    pub fn persist(&self, request: &mut GraphQLRequest) -> ClientResult<()> {
+        let operation = self
+            .operation(&request.operation_name)
+            .ok_or_else(|| ClientError::OperationNotPersisted(request.operation_name.clone()))?;
+        request.query = None;
+        request.extensions.insert(
+            "persistedQuery".to_string(),
+            json!({ "version": 1, "sha256Hash": operation.id }),
+        );
+        Ok(())
+    }
+}
+
+pub struct PersistedOperationsLink {
+    manifest: Arc<PersistedQueryManifest>,
+}
+
+impl PersistedOperationsLink {
+    pub fn new(manifest: Arc<PersistedQueryManifest>) -> Self {
+        Self { manifest }
+    }
+}
+
+impl Link for PersistedOperationsLink {
+    fn call<'a>(
+        &'a self,
+        mut request: GraphQLRequest,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, ClientResult<LinkResponse>> {
+        if let Err(e) = self.manifest.persist(&mut request) {
+            return Box::pin(async { Err(e) });
+        }
+        next.run(request)
+    }
+}
+
+pub struct AutomaticPersistedQueryLink {
+    use_get: bool,
+    supported: AtomicBool,
+}
+
+impl Default for AutomaticPersistedQueryLink {
+    fn default() -> Self {
+        Self {
+            use_get: true,
+            supported: AtomicBool::new(true),
+        }
+    }
+}
+
+impl AutomaticPersistedQueryLink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn use_get(mut self, use_get: bool) -> Self {
+        self.use_get = use_get;
+        self
+    }
+}
+
+fn persisted_query_error(response: &ClientResult<LinkResponse>) -> Option<&'static str> {
+    let errors: Vec<(&str, Option<&str>)> = match response {
+        Ok(response) => response
+            .body
+            .errors
+            .iter()
+            .flatten()
+            .map(|error| {
+                let code = error
+                    .extensions
+                    .as_ref()
+                    .and_then(|extensions| extensions.get("code"))
+                    .and_then(Value::as_str);
+                (error.message.as_str(), code)
+            })
+            .collect(),
+        Err(ClientError::RequestError { errors, .. }) => errors
+            .iter()
+            .map(|error| (error.message.as_str(), error.code()))
+            .collect(),
+        Err(_) => return None,
+    };
+    errors.into_iter().find_map(|(message, code)| {
+        PERSISTED_QUERY_ERRORS
+            .iter()
+            .find(|(expected, expected_code)| message == *expected || code == Some(expected_code))
+            .map(|(expected, _)| *expected)
+    })
+}
+
+impl Link for AutomaticPersistedQueryLink {
+    fn call<'a>(
+        &'a self,
+        request: GraphQLRequest,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, ClientResult<LinkResponse>> {
+        let query = match &request.query {
+            Some(query) if self.supported.load(Ordering::Relaxed) => query.clone(),
+            _ => return next.run(request),
+        };
+        let hash = request
+            .extensions
+            .get("persistedQuery")
+            .and_then(|persisted| persisted.get("sha256Hash"))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("{:x}", Sha256::digest(&query)));
+        Box::pin(async move {
+            let mut persisted = request.clone();
+            persisted.query = None;
+            persisted.extensions.insert(
+                "persistedQuery".to_string(),
+                json!({ "version": 1, "sha256Hash": hash }),
+            );
+            if self.use_get && request.is_query() {
+                persisted
+                    .context
+                    .insert(HTTP_METHOD_CONTEXT.to_string(), json!("GET"));
+            }
+
+            let response = next.run(persisted.clone()).await;
+            match persisted_query_error(&response) {
+                None => response,
+                Some(error) => {
+                    if error == NOT_SUPPORTED {
+                        self.supported.store(false, Ordering::Relaxed);
+                    }
+                    persisted.query = Some(query);
+                    persisted
+                        .context
+                        .insert(HTTP_METHOD_CONTEXT.to_string(), json!("POST"));
+                    next.run(persisted).await
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use graphql_client::QueryBody;
+
+    fn manifest() -> PersistedQueryManifest {
+        PersistedQueryManifest::from_json(
+            r#"{
+              "format": "apollo-persisted-query-manifest",
+              "version": 1,
+              "operations": [
+                {
+                  "id": "abc123",
+                  "name": "Person",
+                  "type": "query",
+                  "body": "query Person { person { name } }"
+                }
+              ]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    fn request(operation_name: &'static str) -> GraphQLRequest {
+        GraphQLRequest::new(&QueryBody {
+            variables: json!({}),
+            query: "query Person { person { name } }",
+            operation_name,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn persist_replaces_query_with_id() {
+        let mut request = request("Person");
+        manifest().persist(&mut request).unwrap();
+
+        assert_eq!(
+            request.body(),
+            json!({
+              "variables": {},
+              "operationName": "Person",
+              "extensions": {
+                "persistedQuery": { "version": 1, "sha256Hash": "abc123" }
+              }
+            })
+        );
+    }
+
+    #[test]
+    fn refuse_unregistered_operation() {
+        let mut request = request("Planet");
+
+        assert!(matches!(
+            manifest().persist(&mut request),
+            Err(ClientError::OperationNotPersisted(name)) if name == "Planet"
+        ));
+        assert!(request.query.is_some());
+    }
+
+    struct Server(std::sync::Mutex<Vec<(bool, Option<Value>)>>);
+
+    impl Link for Server {
+        fn call<'a>(
+            &'a self,
+            request: GraphQLRequest,
+            _next: Next<'a>,
+        ) -> BoxFuture<'a, ClientResult<LinkResponse>> {
+            let method = request.context.get(HTTP_METHOD_CONTEXT).cloned();
+            self.0
+                .lock()
+                .unwrap()
+                .push((request.query.is_some(), method));
+            let body = match request.query {
+                Some(_) => json!({ "data": { "person": { "name": "Luke" } } }),
+                None => json!({ "errors": [{ "message": "PersistedQueryNotFound" }] }),
+            };
+            Box::pin(async move {
+                Ok(LinkResponse::from_value(
+                    reqwest::StatusCode::OK,
+                    Default::default(),
+                    body,
+                )?)
+            })
+        }
+    }
+
+    #[test]
+    fn fall_back_to_post_for_unknown_hash() {
+        let server = Arc::new(Server(Default::default()));
+        let links: Vec<Arc<dyn Link>> =
+            vec![Arc::new(AutomaticPersistedQueryLink::new()), server.clone()];
+
+        let response =
+            futures::executor::block_on(Next::new(&links).run(request("Person"))).unwrap();
+
+        assert!(response.body.errors.is_none());
+        assert_eq!(
+            *server.0.lock().unwrap(),
+            vec![(false, Some(json!("GET"))), (true, Some(json!("POST")))]
+        );
+    }
+}
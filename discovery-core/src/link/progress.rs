@@ -0,0 +1,59 @@
+use bytes::Bytes;
+use futures::{stream, Stream, StreamExt};
+use std::convert::Infallible;
+use std::sync::Arc;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub transferred: u64,
+    pub total: Option<u64>,
+}
+
+pub(crate) type ProgressFn = dyn Fn(Progress) + Send + Sync;
+
+pub(crate) fn chunked(
+    data: Bytes,
+    offset: u64,
+    total: u64,
+    progress: Arc<ProgressFn>,
+) -> impl Stream<Item = Result<Bytes, Infallible>> + Send + Sync + 'static {
+    stream::iter((0..data.len()).step_by(CHUNK_SIZE)).map(move |start| {
+        let end = (start + CHUNK_SIZE).min(data.len());
+        progress(Progress {
+            transferred: offset + end as u64,
+            total: Some(total),
+        });
+        Ok(data.slice(start..end))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use std::sync::Mutex;
+
+    #[test]
+    fn report_each_chunk() {
+        let reports = Arc::new(Mutex::new(vec![]));
+        let progress: Arc<ProgressFn> = {
+            let reports = reports.clone();
+            Arc::new(move |p: Progress| reports.lock().unwrap().push(p.transferred))
+        };
+        let data = Bytes::from(vec![0; CHUNK_SIZE * 2 + 1]);
+
+        let chunks: Vec<_> = block_on(chunked(data, 10, 200_000, progress).collect());
+
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(
+            *reports.lock().unwrap(),
+            vec![
+                10 + CHUNK_SIZE as u64,
+                10 + 2 * CHUNK_SIZE as u64,
+                11 + 2 * CHUNK_SIZE as u64
+            ]
+        );
+    }
+}
@@ -0,0 +1,262 @@
+use futures::future::BoxFuture;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use super::{GraphQLRequest, Link, LinkResponse, Next};
+use crate::client::{ClientError, ClientResult};
+use crate::runtime::Runtime;
+
+const RATE_LIMITED: &str = "RATE_LIMITED";
+const DEFAULT_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RateLimitState {
+    pub limit: Option<u64>,
+    pub remaining: Option<u64>,
+    pub reset_at: Option<SystemTime>,
+    pub retry_at: Option<SystemTime>,
+}
+
+impl RateLimitState {
+    pub fn is_limited(&self) -> bool {
+        let now = SystemTime::now();
+        self.retry_at.is_some_and(|at| at > now)
+            || (self.remaining == Some(0) && self.reset_at.is_some_and(|at| at > now))
+    }
+
+    pub fn wait_time(&self) -> Option<Duration> {
+        let until = match (self.retry_at, self.remaining) {
+            (Some(at), _) => at,
+            (None, Some(0)) => self.reset_at?,
+            _ => return None,
+        };
+        until
+            .duration_since(SystemTime::now())
+            .ok()
+            .filter(|wait| !wait.is_zero())
+    }
+
+    fn update(&mut self, headers: &HeaderMap) {
+        let number = |names: [&str; 2]| {
+            names.iter().find_map(|name| {
+                headers
+                    .get(*name)?
+                    .to_str()
+                    .ok()?
+                    .trim()
+                    .parse::<u64>()
+                    .ok()
+            })
+        };
+        if let Some(limit) = number(["ratelimit-limit", "x-ratelimit-limit"]) {
+            self.limit = Some(limit);
+        }
+        if let Some(remaining) = number(["ratelimit-remaining", "x-ratelimit-remaining"]) {
+            self.remaining = Some(remaining);
+        }
+        if let Some(reset) = number(["ratelimit-reset", "x-ratelimit-reset"]) {
+            self.reset_at = Some(SystemTime::now() + Duration::from_secs(reset));
+        }
+    }
+}
+
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    match value.parse::<u64>() {
+        Ok(seconds) => Some(Duration::from_secs(seconds)),
+        Err(_) => httpdate::parse_http_date(value)
+            .ok()?
+            .duration_since(SystemTime::now())
+            .ok(),
+    }
+}
+
+fn rate_limited_extension(response: &LinkResponse) -> Option<Option<Duration>> {
+    response.body.errors.iter().flatten().find_map(|error| {
+        let extensions = error.extensions.as_ref()?;
+        if extensions.get("code")? != RATE_LIMITED {
+            return None;
+        }
+        Some(
+            extensions
+                .get("retryAfter")
+                .and_then(|seconds| seconds.as_f64())
+                .map(Duration::from_secs_f64),
+        )
+    })
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RateLimits(Arc<Mutex<RateLimitState>>);
+
+impl RateLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn state(&self) -> RateLimitState {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+pub struct RateLimitLink {
+    runtime: Arc<dyn Runtime>,
+    limits: RateLimits,
+    max_retries: u32,
+    max_delay: Duration,
+}
+
+impl RateLimitLink {
+    pub fn new(runtime: Arc<dyn Runtime>) -> Self {
+        Self {
+            runtime,
+            limits: RateLimits::default(),
+            max_retries: 3,
+            max_delay: Duration::from_secs(60),
+        }
+    }
+
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    pub fn limits(mut self, limits: RateLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    async fn wait(&self) {
+        let wait = self.limits.state().wait_time();
+        if let Some(wait) = wait {
+            self.runtime.sleep(wait.min(self.max_delay)).await;
+        }
+    }
+
+    fn rate_limited(&self, response: &ClientResult<LinkResponse>) -> Option<Duration> {
+        let retry_after = match response {
+            Err(ClientError::RateLimited { retry_after }) => *retry_after,
+            Ok(response) if response.status == StatusCode::TOO_MANY_REQUESTS => {
+                retry_after(&response.headers)
+            }
+            Ok(response) => rate_limited_extension(response)?,
+            _ => return None,
+        };
+        Some(retry_after.unwrap_or(DEFAULT_DELAY))
+    }
+}
+
+impl Link for RateLimitLink {
+    fn call<'a>(
+        &'a self,
+        request: GraphQLRequest,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, ClientResult<LinkResponse>> {
+        Box::pin(async move {
+            let mut attempt = 0;
+            loop {
+                self.wait().await;
+                let response = next.run(request.clone()).await;
+                let mut state = self.limits.0.lock().unwrap();
+                if let Ok(response) = &response {
+                    state.update(&response.headers);
+                }
+                let Some(delay) = self.rate_limited(&response) else {
+                    state.retry_at = None;
+                    return response;
+                };
+                state.retry_at = Some(SystemTime::now() + delay);
+                drop(state);
+                if attempt >= self.max_retries {
+                    return response;
+                }
+                attempt += 1;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::link::{Transport, TransportLink, TransportRequest, TransportResponse};
+    use bytes::Bytes;
+    use futures::executor::block_on;
+    use reqwest::header::HeaderValue;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct Throttled(AtomicUsize);
+
+    impl Transport for Throttled {
+        fn execute(
+            &self,
+            _request: TransportRequest,
+        ) -> BoxFuture<'_, ClientResult<TransportResponse>> {
+            let attempt = self.0.fetch_add(1, Ordering::SeqCst);
+            let mut headers = HeaderMap::new();
+            headers.insert("x-ratelimit-limit", HeaderValue::from_static("10"));
+            headers.insert("x-ratelimit-remaining", HeaderValue::from_static("0"));
+            Box::pin(async move {
+                if attempt == 0 {
+                    headers.insert(RETRY_AFTER, HeaderValue::from_static("2"));
+                    return Ok(TransportResponse {
+                        status: StatusCode::TOO_MANY_REQUESTS,
+                        headers,
+                        body: Bytes::from_static(b"{}"),
+                    });
+                }
+                Ok(TransportResponse {
+                    status: StatusCode::OK,
+                    headers,
+                    body: Bytes::from_static(br#"{ "data": { "me": "Luke" } }"#),
+                })
+            })
+        }
+    }
+
+    #[test]
+    fn wait_for_retry_after() {
+        let slept = Arc::new(Mutex::new(vec![]));
+        let runtime = {
+            let slept = slept.clone();
+            move |duration: Duration| -> BoxFuture<'static, ()> {
+                slept.lock().unwrap().push(duration);
+                Box::pin(async {})
+            }
+        };
+        let limits = RateLimits::new();
+        let links: Vec<Arc<dyn Link>> = vec![
+            Arc::new(RateLimitLink::new(Arc::new(runtime)).limits(limits.clone())),
+            Arc::new(TransportLink::new(Arc::new(Throttled(AtomicUsize::new(0))))),
+        ];
+
+        let request = GraphQLRequest::raw("Me", "query Me { me }", json!({}));
+        let response = block_on(Next::new(&links).run(request)).unwrap();
+
+        assert_eq!(response.body.data, Some(json!({ "me": "Luke" })));
+        let slept = slept.lock().unwrap()[0];
+        assert!(slept > Duration::from_secs(1) && slept <= Duration::from_secs(2));
+        let state = limits.state();
+        assert_eq!((state.limit, state.remaining), (Some(10), Some(0)));
+        assert!(state.retry_at.is_none());
+    }
+
+    #[test]
+    fn parse_retry_after() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("120"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(120)));
+
+        let date = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(30));
+        headers.insert(RETRY_AFTER, HeaderValue::from_str(&date).unwrap());
+        assert!(retry_after(&headers).is_some_and(|wait| wait <= Duration::from_secs(30)));
+    }
+}
@@ -0,0 +1,233 @@
+use futures::channel::oneshot;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use super::{GraphQLRequest, Link, LinkResponse, Next};
+use crate::client::ClientResult;
+
+pub const PRIORITY_CONTEXT: &str = "priority";
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Priority {
+    UserBlocking,
+    #[default]
+    Default,
+    Prefetch,
+}
+
+const PRIORITIES: [Priority; 3] = [
+    Priority::UserBlocking,
+    Priority::Default,
+    Priority::Prefetch,
+];
+
+impl Priority {
+    pub(crate) fn of(request: &GraphQLRequest) -> Self {
+        request
+            .context
+            .get(PRIORITY_CONTEXT)
+            .and_then(|priority| serde_json::from_value(priority.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn to_value(self) -> Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    class_limits: [Option<usize>; 3],
+    in_flight: usize,
+    running: [usize; 3],
+    waiting: [VecDeque<oneshot::Sender<Permit>>; 3],
+}
+
+#[derive(Debug)]
+struct Inner {
+    max_in_flight: usize,
+    state: Mutex<State>,
+}
+
+impl Inner {
+    fn can_run(&self, state: &State, priority: Priority) -> bool {
+        state.in_flight < self.max_in_flight
+            && state.class_limits[priority.index()]
+                .is_none_or(|limit| state.running[priority.index()] < limit)
+    }
+
+    fn release(self: &Arc<Self>, priority: Priority) {
+        let mut state = self.state.lock().unwrap();
+        state.in_flight -= 1;
+        state.running[priority.index()] -= 1;
+        for priority in PRIORITIES {
+            while self.can_run(&state, priority) {
+                let Some(waiter) = state.waiting[priority.index()].pop_front() else {
+                    break;
+                };
+                state.in_flight += 1;
+                state.running[priority.index()] += 1;
+                let permit = Permit {
+                    scheduler: Some(self.clone()),
+                    priority,
+                };
+                if let Err(mut permit) = waiter.send(permit) {
+                    permit.scheduler = None;
+                    state.in_flight -= 1;
+                    state.running[priority.index()] -= 1;
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Permit {
+    scheduler: Option<Arc<Inner>>,
+    priority: Priority,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if let Some(scheduler) = self.scheduler.take() {
+            scheduler.release(self.priority);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Scheduler(Arc<Inner>);
+
+impl Scheduler {
+    pub fn new(max_in_flight: usize) -> Self {
+        Self(Arc::new(Inner {
+            max_in_flight: max_in_flight.max(1),
+            state: Mutex::new(State::default()),
+        }))
+    }
+
+    pub fn class_limit(self, priority: Priority, limit: usize) -> Self {
+        self.0.state.lock().unwrap().class_limits[priority.index()] = Some(limit.max(1));
+        self
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.0.state.lock().unwrap().in_flight
+    }
+
+    pub fn waiting(&self) -> usize {
+        self.0
+            .state
+            .lock()
+            .unwrap()
+            .waiting
+            .iter()
+            .map(VecDeque::len)
+            .sum()
+    }
+
+    async fn acquire(&self, priority: Priority) -> Permit {
+        let receiver = {
+            let mut state = self.0.state.lock().unwrap();
+            if self.0.can_run(&state, priority) {
+                state.in_flight += 1;
+                state.running[priority.index()] += 1;
+                return Permit {
+                    scheduler: Some(self.0.clone()),
+                    priority,
+                };
+            }
+            let (sender, receiver) = oneshot::channel();
+            state.waiting[priority.index()].push_back(sender);
+            receiver
+        };
+        receiver.await.expect("scheduler dropped a waiting request")
+    }
+}
+
+pub struct SchedulerLink {
+    scheduler: Scheduler,
+}
+
+impl SchedulerLink {
+    pub fn new(scheduler: Scheduler) -> Self {
+        Self { scheduler }
+    }
+}
+
+impl Link for SchedulerLink {
+    fn call<'a>(
+        &'a self,
+        request: GraphQLRequest,
+        next: Next<'a>,
+    ) -> BoxFuture<'a, ClientResult<LinkResponse>> {
+        Box::pin(async move {
+            let _permit = self.scheduler.acquire(Priority::of(&request)).await;
+            next.run(request).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::LocalPool;
+    use futures::task::LocalSpawnExt;
+    use serde_json::json;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn user_blocking_runs_before_prefetch() {
+        let scheduler = Scheduler::new(1).class_limit(Priority::Prefetch, 1);
+        let order = Rc::new(RefCell::new(vec![]));
+        let mut pool = LocalPool::new();
+
+        let first = pool.run_until(scheduler.acquire(Priority::Prefetch));
+        for (name, priority) in [
+            ("prefetch", Priority::Prefetch),
+            ("default", Priority::Default),
+            ("user-blocking", Priority::UserBlocking),
+        ] {
+            let (scheduler, order) = (scheduler.clone(), order.clone());
+            pool.spawner()
+                .spawn_local(async move {
+                    let _permit = scheduler.acquire(priority).await;
+                    order.borrow_mut().push(name);
+                })
+                .unwrap();
+        }
+        pool.run_until_stalled();
+        assert_eq!((scheduler.in_flight(), scheduler.waiting()), (1, 3));
+
+        drop(first);
+        pool.run_until_stalled();
+        assert_eq!(
+            *order.borrow(),
+            vec!["user-blocking", "default", "prefetch"]
+        );
+        assert_eq!(scheduler.in_flight(), 0);
+    }
+
+    #[test]
+    fn read_priority_from_context() {
+        let mut request = GraphQLRequest::raw("Me", "query Me { me }", json!({}));
+        assert_eq!(Priority::of(&request), Priority::Default);
+
+        request
+            .context
+            .insert(PRIORITY_CONTEXT.to_string(), Priority::Prefetch.to_value());
+        assert_eq!(request.context[PRIORITY_CONTEXT], json!("prefetch"));
+        assert_eq!(Priority::of(&request), Priority::Prefetch);
+    }
+}
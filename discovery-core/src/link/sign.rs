@@ -0,0 +1,132 @@
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::Method;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+use crate::client::{ClientError, ClientResult};
+
+#[derive(Debug)]
+pub struct SignableRequest<'a> {
+    pub method: &'a Method,
+    pub uri: &'a str,
+    pub headers: &'a HeaderMap,
+    pub body: &'a [u8],
+}
+
+pub trait RequestSigner: Send + Sync {
+    fn sign(&self, request: &SignableRequest<'_>) -> ClientResult<HeaderMap>;
+}
+
+impl<F> RequestSigner for F
+where
+    F: Fn(&SignableRequest<'_>) -> ClientResult<HeaderMap> + Send + Sync,
+{
+    fn sign(&self, request: &SignableRequest<'_>) -> ClientResult<HeaderMap> {
+        self(request)
+    }
+}
+
+pub struct HmacSigner {
+    header: HeaderName,
+    key: Vec<u8>,
+}
+
+impl HmacSigner {
+    pub fn new(header: HeaderName, key: impl Into<Vec<u8>>) -> Self {
+        Self {
+            header,
+            key: key.into(),
+        }
+    }
+}
+
+impl RequestSigner for HmacSigner {
+    fn sign(&self, request: &SignableRequest<'_>) -> ClientResult<HeaderMap> {
+        let mut message = format!("{}\n{}\n", request.method, request.uri).into_bytes();
+        message.extend_from_slice(request.body);
+        let signature = hmac_sha256(&self.key, &message)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            self.header.clone(),
+            HeaderValue::from_str(&signature)
+                .map_err(|e| ClientError::SigningError(e.to_string()))?,
+        );
+        Ok(headers)
+    }
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; 64];
+    if key.len() > block.len() {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha256::new();
+    inner.update(block.map(|b| b ^ 0x36));
+    inner.update(message);
+    let mut outer = Sha256::new();
+    outer.update(block.map(|b| b ^ 0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().into()
+}
+
+pub(crate) fn sign(
+    signer: Option<&Arc<dyn RequestSigner>>,
+    method: &Method,
+    uri: &str,
+    headers: &mut HeaderMap,
+    body: &[u8],
+) -> ClientResult<()> {
+    if let Some(signer) = signer {
+        let signature = signer.sign(&SignableRequest {
+            method,
+            uri,
+            headers,
+            body,
+        })?;
+        headers.extend(signature);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hmac_signature_header() {
+        assert_eq!(
+            hmac_sha256(b"Jefe", b"what do ya want for nothing?")
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>(),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+
+        let signer = HmacSigner::new(HeaderName::from_static("x-signature"), "Jefe");
+        let signature = |method, uri| {
+            signer
+                .sign(&SignableRequest {
+                    method: &method,
+                    uri,
+                    headers: &HeaderMap::new(),
+                    body: b"",
+                })
+                .unwrap()["x-signature"]
+                .clone()
+        };
+        assert_ne!(
+            signature(Method::GET, "http://localhost/graphql?query=a"),
+            signature(Method::GET, "http://localhost/graphql?query=b")
+        );
+        assert_ne!(
+            signature(Method::GET, "http://localhost/graphql"),
+            signature(Method::POST, "http://localhost/graphql")
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::stream;
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use serde_json::Value;
+use std::sync::Arc;
+
+use super::{multipart_boundary, GraphQLRequest, HttpLink, Link, LinkResponse, Next};
+use crate::client::{ClientError, ClientResult};
+use crate::defer;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransportRequest {
+    pub headers: HeaderMap,
+    pub body: Value,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransportResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+pub trait Transport: Send + Sync {
+    fn execute(&self, request: TransportRequest) -> BoxFuture<'_, ClientResult<TransportResponse>>;
+}
+
+impl Transport for HttpLink {
+    fn execute(&self, request: TransportRequest) -> BoxFuture<'_, ClientResult<TransportResponse>> {
+        Box::pin(async move {
+            let (status, headers, body) = self.post(&request.headers, &request.body).await?;
+            Ok(TransportResponse {
+                status,
+                headers,
+                body,
+            })
+        })
+    }
+}
+
+pub struct TransportLink {
+    transport: Arc<dyn Transport>,
+    max_response_size: Option<u64>,
+}
+
+impl TransportLink {
+    pub fn new(transport: Arc<dyn Transport>) -> Self {
+        Self {
+            transport,
+            max_response_size: None,
+        }
+    }
+
+    pub fn max_response_size(mut self, limit: Option<u64>) -> Self {
+        self.max_response_size = limit;
+        self
+    }
+}
+
+impl Link for TransportLink {
+    fn call<'a>(
+        &'a self,
+        request: GraphQLRequest,
+        _next: Next<'a>,
+    ) -> BoxFuture<'a, ClientResult<LinkResponse>> {
+        Box::pin(async move {
+            if !request.files.is_empty() {
+                return Err(ClientError::UploadsNotSupported);
+            }
+            let response = self
+                .transport
+                .execute(TransportRequest {
+                    body: request.body(),
+                    headers: request.headers,
+                })
+                .await?;
+            if let Some(limit) = self
+                .max_response_size
+                .filter(|limit| response.body.len() as u64 > *limit)
+            {
+                return Err(ClientError::ResponseTooLarge(limit));
+            }
+            if response.status == StatusCode::NOT_MODIFIED {
+                return Ok(LinkResponse::from_value(
+                    response.status,
+                    response.headers,
+                    Value::Object(Default::default()),
+                )?);
+            }
+            if let Some(boundary) = multipart_boundary(&response.headers) {
+                let parts = defer::multipart_parts(stream::iter([Ok(response.body)]), &boundary);
+                return LinkResponse::from_parts(response.status, response.headers, parts)
+                    .await?
+                    .into_result();
+            }
+            LinkResponse::decode(response.status, response.headers, &response.body)?.into_result()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::upload::Upload;
+    use futures::executor::block_on;
+    use serde_json::json;
+
+    struct Echo;
+
+    impl Transport for Echo {
+        fn execute(
+            &self,
+            request: TransportRequest,
+        ) -> BoxFuture<'_, ClientResult<TransportResponse>> {
+            Box::pin(async move {
+                let body = json!({ "data": { "operationName": request.body["operationName"] } });
+                Ok(TransportResponse {
+                    status: StatusCode::OK,
+                    headers: HeaderMap::new(),
+                    body: Bytes::from(serde_json::to_vec(&body)?),
+                })
+            })
+        }
+    }
+
+    #[test]
+    fn execute_through_transport() {
+        let links: Vec<Arc<dyn Link>> = vec![Arc::new(TransportLink::new(Arc::new(Echo)))];
+        let request = GraphQLRequest::raw("Me", "query Me { me { name } }", json!({}));
+
+        let response = block_on(Next::new(&links).run(request)).unwrap();
+        assert_eq!(response.body.data, Some(json!({ "operationName": "Me" })));
+
+        let file = Upload::new("a.txt", b"a".to_vec());
+        let request = GraphQLRequest::raw(
+            "Upload",
+            "mutation Upload($file: Upload!) { upload(file: $file) }",
+            json!({ "file": file }),
+        );
+        assert!(matches!(
+            block_on(Next::new(&links).run(request)),
+            Err(ClientError::UploadsNotSupported)
+        ));
+    }
+}
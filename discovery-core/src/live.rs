@@ -0,0 +1,200 @@
+use graphql_client::Response;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::client::{ClientError, ClientResult};
+
+pub fn is_live(query: &str) -> bool {
+    query.match_indices("@live").any(|(index, directive)| {
+        query[index + directive.len()..]
+            .chars()
+            .next()
+            .is_none_or(|c| !(c.is_alphanumeric() || c == '_'))
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOperation {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Value },
+}
+
+fn patch_error(message: &str, path: &str) -> ClientError {
+    ClientError::PatchError(format!("{} at \"{}\"", message, path))
+}
+
+fn split(path: &str) -> ClientResult<(&str, String)> {
+    let index = path
+        .rfind('/')
+        .ok_or_else(|| patch_error("invalid pointer", path))?;
+    let last = path[index + 1..].replace("~1", "/").replace("~0", "~");
+    Ok((&path[..index], last))
+}
+
+fn index(segment: &str, len: usize, path: &str) -> ClientResult<usize> {
+    segment
+        .parse()
+        .ok()
+        .filter(|index| *index <= len)
+        .ok_or_else(|| patch_error("index out of bounds", path))
+}
+
+fn add(target: &mut Value, path: &str, value: Value) -> ClientResult<()> {
+    if path.is_empty() {
+        *target = value;
+        return Ok(());
+    }
+    let (parent, last) = split(path)?;
+    match target.pointer_mut(parent) {
+        Some(Value::Object(map)) => {
+            map.insert(last, value);
+        }
+        Some(Value::Array(items)) if last == "-" => items.push(value),
+        Some(Value::Array(items)) => {
+            let index = index(&last, items.len(), path)?;
+            items.insert(index, value);
+        }
+        _ => return Err(patch_error("missing parent", path)),
+    }
+    Ok(())
+}
+
+fn remove(target: &mut Value, path: &str) -> ClientResult<Value> {
+    let (parent, last) = split(path)?;
+    let removed = match target.pointer_mut(parent) {
+        Some(Value::Object(map)) => map.remove(&last),
+        Some(Value::Array(items)) => {
+            let index = index(&last, items.len(), path)?;
+            (index < items.len()).then(|| items.remove(index))
+        }
+        _ => None,
+    };
+    removed.ok_or_else(|| patch_error("missing value", path))
+}
+
+pub fn apply_patch(target: &mut Value, operations: &[PatchOperation]) -> ClientResult<()> {
+    for operation in operations {
+        match operation {
+            PatchOperation::Add { path, value } => add(target, path, value.clone())?,
+            PatchOperation::Remove { path } => {
+                remove(target, path)?;
+            }
+            PatchOperation::Replace { path, value } => {
+                *target
+                    .pointer_mut(path)
+                    .ok_or_else(|| patch_error("missing value", path))? = value.clone();
+            }
+            PatchOperation::Move { from, path } => {
+                let value = remove(target, from)?;
+                add(target, path, value)?;
+            }
+            PatchOperation::Copy { from, path } => {
+                let value = target
+                    .pointer(from)
+                    .cloned()
+                    .ok_or_else(|| patch_error("missing value", from))?;
+                add(target, path, value)?;
+            }
+            PatchOperation::Test { path, value } => {
+                if target.pointer(path) != Some(value) {
+                    return Err(patch_error("test failed", path));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct LivePayload {
+    #[serde(default)]
+    data: Option<Value>,
+    #[serde(default)]
+    patch: Option<Vec<PatchOperation>>,
+    #[serde(default)]
+    errors: Option<Vec<graphql_client::Error>>,
+}
+
+impl LivePayload {
+    pub(crate) fn apply(self, current: &mut Option<Value>) -> ClientResult<Response<Value>> {
+        match (self.data, self.patch) {
+            (Some(data), _) => *current = Some(data),
+            (None, Some(patch)) => {
+                let data = current
+                    .as_mut()
+                    .ok_or_else(|| patch_error("patch before initial result", ""))?;
+                apply_patch(data, &patch)?;
+            }
+            (None, None) => {}
+        }
+        Ok(Response {
+            data: current.clone(),
+            errors: self.errors,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn apply_json_patch() {
+        let mut data = json!({ "feed": { "posts": [{ "id": "1" }], "count": 1 } });
+        let patch: Vec<PatchOperation> = serde_json::from_value(json!([
+          { "op": "add", "path": "/feed/posts/-", "value": { "id": "2" } },
+          { "op": "replace", "path": "/feed/count", "value": 2 },
+          { "op": "test", "path": "/feed/posts/0/id", "value": "1" },
+          { "op": "copy", "from": "/feed/count", "path": "/feed/total" },
+          { "op": "move", "from": "/feed/posts/0", "path": "/feed/pinned" },
+          { "op": "remove", "path": "/feed/total" }
+        ]))
+        .unwrap();
+
+        apply_patch(&mut data, &patch).unwrap();
+        assert_eq!(
+            data,
+            json!({ "feed": { "posts": [{ "id": "2" }], "count": 2, "pinned": { "id": "1" } } })
+        );
+        assert!(matches!(
+            apply_patch(
+                &mut data,
+                &[PatchOperation::Remove {
+                    path: "/feed/posts/5".to_string()
+                }]
+            ),
+            Err(ClientError::PatchError(_))
+        ));
+    }
+
+    #[test]
+    fn fold_live_payloads() {
+        assert!(is_live("query Feed @live { feed }"));
+        assert!(!is_live("query Feed @liveness { feed }"));
+
+        let mut current = None;
+        let payload: LivePayload =
+            serde_json::from_value(json!({ "patch": [], "revision": 1 })).unwrap();
+        assert!(payload.apply(&mut current).is_err());
+
+        for (payload, expected) in [
+            (
+                json!({ "data": { "count": 1 }, "revision": 1 }),
+                json!({ "count": 1 }),
+            ),
+            (
+                json!({ "patch": [{ "op": "replace", "path": "/count", "value": 2 }], "revision": 2 }),
+                json!({ "count": 2 }),
+            ),
+        ] {
+            let payload: LivePayload = serde_json::from_value(payload).unwrap();
+            assert_eq!(payload.apply(&mut current).unwrap().data, Some(expected));
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use std::time::Duration;
+
+pub trait MetricsRecorder: Send + Sync {
+    fn record_request(&self, operation_name: &str, duration: Duration, success: bool);
+    fn record_cache_lookup(&self, operation_name: &str, hit: bool);
+}
+
+#[cfg(feature = "opentelemetry")]
+pub use self::opentelemetry::OpenTelemetryMetrics;
+
+#[cfg(feature = "opentelemetry")]
+mod opentelemetry {
+    use opentelemetry::metrics::{Counter, Histogram, Meter};
+    use opentelemetry::KeyValue;
+    use std::time::Duration;
+
+    use super::MetricsRecorder;
+
+    pub struct OpenTelemetryMetrics {
+        requests: Counter<u64>,
+        errors: Counter<u64>,
+        latency: Histogram<f64>,
+        cache_lookups: Counter<u64>,
+        cache_hits: Counter<u64>,
+    }
+
+    impl OpenTelemetryMetrics {
+        pub fn new(meter: &Meter) -> Self {
+            Self {
+                requests: meter
+                    .u64_counter("discovery.client.requests")
+                    .with_description("GraphQL requests sent over the network")
+                    .init(),
+                errors: meter
+                    .u64_counter("discovery.client.errors")
+                    .with_description("GraphQL requests that failed")
+                    .init(),
+                latency: meter
+                    .f64_histogram("discovery.client.duration")
+                    .with_description("GraphQL request latency")
+                    .with_unit("s")
+                    .init(),
+                cache_lookups: meter
+                    .u64_counter("discovery.cache.lookups")
+                    .with_description("Result cache lookups")
+                    .init(),
+                cache_hits: meter
+                    .u64_counter("discovery.cache.hits")
+                    .with_description("Result cache lookups that hit")
+                    .init(),
+            }
+        }
+    }
+
+    impl MetricsRecorder for OpenTelemetryMetrics {
+        fn record_request(&self, operation_name: &str, duration: Duration, success: bool) {
+            let attributes = [KeyValue::new("operation", operation_name.to_string())];
+            self.requests.add(1, &attributes);
+            self.latency.record(duration.as_secs_f64(), &attributes);
+            if !success {
+                self.errors.add(1, &attributes);
+            }
+        }
+
+        fn record_cache_lookup(&self, operation_name: &str, hit: bool) {
+            let attributes = [KeyValue::new("operation", operation_name.to_string())];
+            self.cache_lookups.add(1, &attributes);
+            if hit {
+                self.cache_hits.add(1, &attributes);
+            }
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use std::sync::{Arc, Mutex};
+
+use crate::client::ClientError;
+use crate::outbox::is_offline;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NetworkStatus {
+    #[default]
+    Online,
+    Degraded,
+    Offline,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    status: NetworkStatus,
+    platform: Option<NetworkStatus>,
+    failures: u32,
+    watchers: Vec<UnboundedSender<NetworkStatus>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NetworkMonitor {
+    state: Arc<Mutex<State>>,
+    degraded_after: u32,
+}
+
+impl Default for NetworkMonitor {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State::default())),
+            degraded_after: 3,
+        }
+    }
+}
+
+impl NetworkMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn degraded_after(mut self, failures: u32) -> Self {
+        self.degraded_after = failures;
+        self
+    }
+
+    pub fn status(&self) -> NetworkStatus {
+        self.state.lock().unwrap().status
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.status() == NetworkStatus::Offline
+    }
+
+    pub fn watch(&self) -> UnboundedReceiver<NetworkStatus> {
+        let (sender, receiver) = unbounded();
+        self.state.lock().unwrap().watchers.push(sender);
+        receiver
+    }
+
+    pub fn set_platform_status(&self, status: Option<NetworkStatus>) {
+        let mut state = self.state.lock().unwrap();
+        state.platform = status;
+        state.failures = 0;
+        let status = status.unwrap_or(NetworkStatus::Online);
+        update(&mut state, status);
+    }
+
+    pub(crate) fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.failures = 0;
+        if state.platform != Some(NetworkStatus::Offline) {
+            update(&mut state, NetworkStatus::Online);
+        }
+    }
+
+    pub(crate) fn record_failure(&self, error: &ClientError) {
+        let mut state = self.state.lock().unwrap();
+        if is_offline(error) {
+            update(&mut state, NetworkStatus::Offline);
+            return;
+        }
+        let server_error = match error {
+            ClientError::HttpError { status, .. } => status.is_server_error(),
+            ClientError::TransportError(_) => true,
+            _ => false,
+        };
+        if !server_error {
+            return;
+        }
+        state.failures += 1;
+        if state.failures >= self.degraded_after && state.platform != Some(NetworkStatus::Offline) {
+            update(&mut state, NetworkStatus::Degraded);
+        }
+    }
+}
+
+fn update(state: &mut State, status: NetworkStatus) {
+    if state.status == status {
+        return;
+    }
+    state.status = status;
+    state
+        .watchers
+        .retain(|watcher| watcher.unbounded_send(status).is_ok());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use reqwest::StatusCode;
+
+    fn server_error() -> ClientError {
+        ClientError::HttpError {
+            status: StatusCode::BAD_GATEWAY,
+            body: String::new(),
+        }
+    }
+
+    #[test]
+    fn derive_status_from_outcomes() {
+        let monitor = NetworkMonitor::new().degraded_after(2);
+        let changes = monitor.watch();
+
+        monitor.record_failure(&server_error());
+        assert_eq!(monitor.status(), NetworkStatus::Online);
+        monitor.record_failure(&server_error());
+        assert_eq!(monitor.status(), NetworkStatus::Degraded);
+        monitor.record_failure(&ClientError::Offline);
+        monitor.record_success();
+
+        monitor.set_platform_status(Some(NetworkStatus::Offline));
+        monitor.record_success();
+        assert!(monitor.is_offline());
+        monitor.set_platform_status(None);
+
+        drop(monitor);
+        let changes: Vec<_> = futures::executor::block_on(changes.collect());
+        assert_eq!(
+            changes,
+            vec![
+                NetworkStatus::Degraded,
+                NetworkStatus::Offline,
+                NetworkStatus::Online,
+                NetworkStatus::Offline,
+                NetworkStatus::Online,
+            ]
+        );
+    }
+}
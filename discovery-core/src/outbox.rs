@@ -0,0 +1,325 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::client::{ClientError, ClientResult};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedMutation {
+    pub id: u64,
+    pub operation_name: String,
+    pub query: String,
+    pub variables: Value,
+}
+
+pub trait OutboxStore: Send + Sync {
+    fn load(&self) -> ClientResult<Vec<QueuedMutation>>;
+    fn save(&self, mutations: &[QueuedMutation]) -> ClientResult<()>;
+}
+
+#[derive(Debug, Default)]
+pub struct MemoryStore(Mutex<Vec<QueuedMutation>>);
+
+impl OutboxStore for MemoryStore {
+    fn load(&self) -> ClientResult<Vec<QueuedMutation>> {
+        Ok(self.0.lock().unwrap().clone())
+    }
+
+    fn save(&self, mutations: &[QueuedMutation]) -> ClientResult<()> {
+        *self.0.lock().unwrap() = mutations.to_vec();
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FileStore {
+    path: PathBuf,
+}
+
+impl FileStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl OutboxStore for FileStore {
+    fn load(&self) -> ClientResult<Vec<QueuedMutation>> {
+        match std::fs::read(&self.path) {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(vec![]),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save(&self, mutations: &[QueuedMutation]) -> ClientResult<()> {
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec(mutations)?)?;
+        std::fs::rename(tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Conflict {
+    Discard,
+    Retry(Value),
+    Stop,
+}
+
+pub type ConflictFn = dyn Fn(&QueuedMutation, &ClientError) -> Conflict + Send + Sync;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ReplaySummary {
+    pub sent: usize,
+    pub discarded: usize,
+    pub remaining: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MutationOutcome<T> {
+    Sent(T),
+    Queued(u64),
+}
+
+pub struct Outbox {
+    store: Box<dyn OutboxStore>,
+    queue: Mutex<VecDeque<QueuedMutation>>,
+    conflicts: HashMap<String, Arc<ConflictFn>>,
+}
+
+impl Outbox {
+    pub fn new(store: impl OutboxStore + 'static) -> ClientResult<Self> {
+        let queue = store.load()?.into();
+        Ok(Self {
+            store: Box::new(store),
+            queue: Mutex::new(queue),
+            conflicts: HashMap::new(),
+        })
+    }
+
+    pub fn on_conflict(
+        mut self,
+        operation_name: impl Into<String>,
+        resolve: impl Fn(&QueuedMutation, &ClientError) -> Conflict + Send + Sync + 'static,
+    ) -> Self {
+        self.conflicts
+            .insert(operation_name.into(), Arc::new(resolve));
+        self
+    }
+
+    pub fn pending(&self) -> Vec<QueuedMutation> {
+        self.queue.lock().unwrap().iter().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(crate) fn enqueue(
+        &self,
+        operation_name: &str,
+        query: &str,
+        variables: Value,
+    ) -> ClientResult<u64> {
+        let mut queue = self.queue.lock().unwrap();
+        let id = queue.back().map_or(0, |last| last.id + 1);
+        queue.push_back(QueuedMutation {
+            id,
+            operation_name: operation_name.to_string(),
+            query: query.to_string(),
+            variables,
+        });
+        self.persist(&mut queue)?;
+        Ok(id)
+    }
+
+    pub(crate) fn front(&self) -> Option<QueuedMutation> {
+        self.queue.lock().unwrap().front().cloned()
+    }
+
+    pub(crate) fn complete(&self, id: u64) -> ClientResult<()> {
+        let mut queue = self.queue.lock().unwrap();
+        queue.retain(|mutation| mutation.id != id);
+        self.persist(&mut queue)
+    }
+
+    pub(crate) fn conflict(&self, mutation: &QueuedMutation, error: &ClientError) -> Conflict {
+        match self.conflicts.get(&mutation.operation_name) {
+            Some(resolve) => resolve(mutation, error),
+            None => Conflict::Discard,
+        }
+    }
+
+    pub(crate) fn retry(&self, id: u64, variables: Value) -> ClientResult<()> {
+        let mut queue = self.queue.lock().unwrap();
+        if let Some(mutation) = queue.iter_mut().find(|mutation| mutation.id == id) {
+            mutation.variables = variables;
+        }
+        self.persist(&mut queue)
+    }
+
+    fn persist(&self, queue: &mut VecDeque<QueuedMutation>) -> ClientResult<()> {
+        self.store.save(queue.make_contiguous())
+    }
+}
+
+pub fn is_offline(error: &ClientError) -> bool {
+    match error {
+        ClientError::Offline => true,
+        ClientError::TransportError(e) => e.is_connect(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn persist_across_restarts() {
+        let path = std::env::temp_dir().join(format!("outbox-{}.json", std::process::id()));
+        let outbox = Outbox::new(FileStore::new(&path)).unwrap();
+        outbox
+            .enqueue("Like", "mutation Like { like }", json!({ "id": 1 }))
+            .unwrap();
+        outbox
+            .enqueue("Like", "mutation Like { like }", json!({ "id": 2 }))
+            .unwrap();
+        outbox.complete(0).unwrap();
+
+        let restored = Outbox::new(FileStore::new(&path)).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(restored.pending(), outbox.pending());
+        assert_eq!(restored.front().unwrap().variables, json!({ "id": 2 }));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn surface_timeouts() {
+        use std::net::TcpListener;
+        use std::time::Duration;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let uri = format!("http://{}/graphql", listener.local_addr().unwrap());
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(50))
+            .build()
+            .unwrap();
+        let send = || -> ClientError {
+            runtime
+                .block_on(async { client.post(uri.as_str()).send().await })
+                .unwrap_err()
+                .into()
+        };
+        let error = send();
+        drop(listener);
+        let refused = send();
+
+        assert!(!is_offline(&error));
+        assert!(!is_offline(&ClientError::Timeout(Duration::from_millis(
+            50
+        ))));
+        assert!(is_offline(&refused));
+        assert!(is_offline(&ClientError::Offline));
+    }
+
+    #[cfg(feature = "tokio")]
+    mod replay {
+        use super::*;
+        use crate::cache::InMemoryCache;
+        use crate::client::DiscoveryClientBuilder;
+        use crate::link::{Transport, TransportRequest, TransportResponse};
+        use bytes::Bytes;
+        use futures::executor::block_on;
+        use futures::future::BoxFuture;
+        use graphql_client::{GraphQLQuery, QueryBody};
+        use reqwest::header::HeaderMap;
+        use reqwest::StatusCode;
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        struct Like;
+
+        impl GraphQLQuery for Like {
+            type Variables = Value;
+            type ResponseData = Value;
+
+            fn build_query(variables: Value) -> QueryBody<Value> {
+                QueryBody {
+                    variables,
+                    query: "mutation Like($id: ID!) { like(id: $id) }",
+                    operation_name: "Like",
+                }
+            }
+        }
+
+        struct Flaky(Arc<AtomicBool>);
+
+        impl Transport for Flaky {
+            fn execute(
+                &self,
+                request: TransportRequest,
+            ) -> BoxFuture<'_, ClientResult<TransportResponse>> {
+                Box::pin(async move {
+                    if !self.0.load(Ordering::SeqCst) {
+                        return Err(ClientError::Offline);
+                    }
+                    let body = match request.body["variables"]["id"].as_i64() {
+                        Some(0) => json!({ "errors": [{ "message": "conflict" }] }),
+                        id => json!({ "data": { "like": id } }),
+                    };
+                    Ok(TransportResponse {
+                        status: StatusCode::OK,
+                        headers: HeaderMap::new(),
+                        body: Bytes::from(serde_json::to_vec(&body)?),
+                    })
+                })
+            }
+        }
+
+        #[test]
+        fn replay_queued_mutations() {
+            let online = Arc::new(AtomicBool::new(false));
+            let outbox = Outbox::new(MemoryStore::default())
+                .unwrap()
+                .on_conflict("Like", |_, _| Conflict::Retry(json!({ "id": 3 })));
+            let client = DiscoveryClientBuilder::<InMemoryCache>::new()
+                .uri("http://localhost/graphql".to_string())
+                .transport(Flaky(online.clone()))
+                .outbox(outbox)
+                .build()
+                .unwrap();
+
+            for id in 0..3 {
+                let outcome = block_on(client.mutate_or_queue::<Like>(json!({ "id": id })));
+                assert_eq!(outcome.unwrap(), MutationOutcome::Queued(id));
+            }
+            let summary = block_on(client.replay_outbox()).unwrap();
+            assert_eq!(summary.remaining, 3);
+
+            online.store(true, Ordering::SeqCst);
+            let summary = block_on(client.replay_outbox()).unwrap();
+            assert_eq!(
+                summary,
+                ReplaySummary {
+                    sent: 3,
+                    discarded: 0,
+                    remaining: 0,
+                }
+            );
+            assert!(client.outbox().unwrap().is_empty());
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use futures::future::poll_fn;
+use std::cell::{Cell, RefCell};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::rc::Rc;
+use std::task::{Poll, Waker};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PollOptions {
+    pub interval: Duration,
+    pub jitter: Duration,
+}
+
+impl PollOptions {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            jitter: Duration::ZERO,
+        }
+    }
+
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn delay(&self) -> Duration {
+        let jitter = self.jitter.as_millis() as u64;
+        if jitter == 0 {
+            return self.interval;
+        }
+        let offset = random() % (2 * jitter + 1);
+        (self.interval + Duration::from_millis(offset)).saturating_sub(self.jitter)
+    }
+}
+
+impl From<Duration> for PollOptions {
+    fn from(interval: Duration) -> Self {
+        Self::new(interval)
+    }
+}
+
+fn random() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+#[derive(Debug, Default)]
+struct PollState {
+    paused: Cell<bool>,
+    waker: RefCell<Option<Waker>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PollHandle(Rc<PollState>);
+
+impl PollHandle {
+    pub fn pause(&self) {
+        self.0.paused.set(true);
+    }
+
+    pub fn resume(&self) {
+        self.0.paused.set(false);
+        if let Some(waker) = self.0.waker.borrow_mut().take() {
+            waker.wake();
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.0.paused.get()
+    }
+
+    pub(crate) async fn resumed(&self) {
+        poll_fn(|cx| {
+            if self.is_paused() {
+                *self.0.waker.borrow_mut() = Some(cx.waker().clone());
+                Poll::Pending
+            } else {
+                Poll::Ready(())
+            }
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::FutureExt;
+
+    #[test]
+    fn delay_within_jitter() {
+        let options = PollOptions::new(Duration::from_secs(10)).jitter(Duration::from_secs(1));
+        for _ in 0..100 {
+            let delay = options.delay();
+            assert!(delay >= Duration::from_secs(9) && delay <= Duration::from_secs(11));
+        }
+        assert_eq!(
+            PollOptions::from(Duration::from_secs(10)).delay(),
+            Duration::from_secs(10)
+        );
+    }
+
+    #[test]
+    fn wait_until_resumed() {
+        let handle = PollHandle::default();
+        handle.pause();
+
+        let mut resumed = Box::pin(handle.resumed());
+        assert!(resumed.as_mut().now_or_never().is_none());
+
+        handle.resume();
+        assert!(resumed.now_or_never().is_some());
+    }
+}
@@ -0,0 +1,167 @@
+use graphql_client::GraphQLQuery;
+use serde_json::Value;
+use std::any::TypeId;
+use std::collections::HashMap;
+
+use crate::cache::Cache;
+use crate::client::{ClientError, ClientResult, DiscoveryClient};
+use crate::response::GraphQLResponse;
+
+pub struct ClientRegistry<C> {
+    endpoints: HashMap<String, DiscoveryClient<C>>,
+    routes: HashMap<TypeId, String>,
+    default: Option<String>,
+}
+
+impl<C> Default for ClientRegistry<C> {
+    fn default() -> Self {
+        Self {
+            endpoints: HashMap::new(),
+            routes: HashMap::new(),
+            default: None,
+        }
+    }
+}
+
+impl<C: Cache + Send + 'static> ClientRegistry<C> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn endpoint(mut self, name: impl Into<String>, client: DiscoveryClient<C>) -> Self {
+        let name = name.into();
+        self.default.get_or_insert_with(|| name.clone());
+        self.endpoints.insert(name, client);
+        self
+    }
+
+    pub fn default_endpoint(mut self, name: impl Into<String>) -> Self {
+        self.default = Some(name.into());
+        self
+    }
+
+    pub fn route<Q: GraphQLQuery + 'static>(mut self, endpoint: impl Into<String>) -> Self {
+        self.routes.insert(TypeId::of::<Q>(), endpoint.into());
+        self
+    }
+
+    pub fn client(&self, name: &str) -> ClientResult<&DiscoveryClient<C>> {
+        self.endpoints
+            .get(name)
+            .ok_or_else(|| ClientError::EndpointNotFound(name.to_string()))
+    }
+
+    pub fn client_for<Q: GraphQLQuery + 'static>(&self) -> ClientResult<&DiscoveryClient<C>> {
+        let name = self
+            .routes
+            .get(&TypeId::of::<Q>())
+            .or(self.default.as_ref())
+            .ok_or_else(|| ClientError::EndpointNotFound(String::new()))?;
+        self.client(name)
+    }
+
+    pub async fn query<Q: GraphQLQuery + 'static>(
+        &self,
+        variables: <Q as GraphQLQuery>::Variables,
+    ) -> ClientResult<GraphQLResponse<<Q as GraphQLQuery>::ResponseData>> {
+        self.client_for::<Q>()?.query::<Q>(variables).await
+    }
+
+    pub async fn mutate<M: GraphQLQuery + 'static>(
+        &self,
+        variables: <M as GraphQLQuery>::Variables,
+    ) -> ClientResult<GraphQLResponse<<M as GraphQLQuery>::ResponseData>> {
+        self.client_for::<M>()?.mutate::<M>(variables).await
+    }
+
+    pub async fn query_raw(
+        &self,
+        endpoint: &str,
+        query: &str,
+        variables: Value,
+    ) -> ClientResult<GraphQLResponse<Value>> {
+        self.client(endpoint)?.query_raw(query, variables).await
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use super::*;
+    use crate::cache::InMemoryCache;
+    use crate::client::{CacheWrap, DiscoveryClientBuilder};
+    use crate::link::{Transport, TransportRequest, TransportResponse};
+    use crate::result_key::{CanonicalSha256, Namespaced};
+    use bytes::Bytes;
+    use futures::executor::block_on;
+    use futures::future::BoxFuture;
+    use graphql_client::QueryBody;
+    use reqwest::header::HeaderMap;
+    use reqwest::StatusCode;
+    use serde_json::json;
+
+    struct Events;
+
+    impl GraphQLQuery for Events {
+        type Variables = ();
+        type ResponseData = Value;
+
+        fn build_query(variables: ()) -> QueryBody<()> {
+            QueryBody {
+                variables,
+                query: "query Events { events }",
+                operation_name: "Events",
+            }
+        }
+    }
+
+    struct Endpoint(&'static str);
+
+    impl Transport for Endpoint {
+        fn execute(
+            &self,
+            _request: TransportRequest,
+        ) -> BoxFuture<'_, ClientResult<TransportResponse>> {
+            let body = json!({ "data": { "events": self.0 } });
+            Box::pin(async move {
+                Ok(TransportResponse {
+                    status: StatusCode::OK,
+                    headers: HeaderMap::new(),
+                    body: Bytes::from(serde_json::to_vec(&body)?),
+                })
+            })
+        }
+    }
+
+    fn client(
+        name: &'static str,
+        cache: &CacheWrap<InMemoryCache>,
+    ) -> DiscoveryClient<InMemoryCache> {
+        DiscoveryClientBuilder::new()
+            .uri(format!("http://{}/graphql", name))
+            .cache(cache.clone())
+            .result_key_strategy(Namespaced(name.to_string(), CanonicalSha256))
+            .transport(Endpoint(name))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn route_operations_to_endpoints() {
+        let cache = CacheWrap::new(InMemoryCache::new());
+        let registry = ClientRegistry::new()
+            .endpoint("main", client("main", &cache))
+            .endpoint("analytics", client("analytics", &cache))
+            .route::<Events>("analytics");
+
+        let routed = block_on(registry.query::<Events>(())).unwrap();
+        assert_eq!(routed.data, Some(json!({ "events": "analytics" })));
+
+        let main = block_on(registry.query_raw("main", "query Events { events }", json!({})));
+        assert_eq!(main.unwrap().data, Some(json!({ "events": "main" })));
+
+        assert!(matches!(
+            registry.client("billing"),
+            Err(ClientError::EndpointNotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,152 @@
+use serde_json::{Map, Value};
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+impl Display for PathSegment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Key(key) => write!(f, "{}", key),
+            Self::Index(index) => write!(f, "{}", index),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorLocation {
+    pub line: i32,
+    pub column: i32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphQLError {
+    pub message: String,
+    pub locations: Vec<ErrorLocation>,
+    pub path: Vec<PathSegment>,
+    pub extensions: Map<String, Value>,
+}
+
+impl GraphQLError {
+    pub fn path_string(&self) -> String {
+        self.path
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(" > ")
+    }
+
+    pub fn is_at(&self, path: &str) -> bool {
+        let path: Vec<_> = path
+            .split(" > ")
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .collect();
+        path.len() <= self.path.len()
+            && path
+                .iter()
+                .zip(&self.path)
+                .all(|(expected, segment)| *expected == segment.to_string())
+    }
+
+    pub fn code(&self) -> Option<&str> {
+        self.extensions.get("code")?.as_str()
+    }
+}
+
+impl Display for GraphQLError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{} (at {})", self.message, self.path_string())
+        }
+    }
+}
+
+impl From<graphql_client::Error> for GraphQLError {
+    fn from(error: graphql_client::Error) -> Self {
+        Self {
+            message: error.message,
+            locations: error
+                .locations
+                .unwrap_or_default()
+                .into_iter()
+                .map(|l| ErrorLocation {
+                    line: l.line,
+                    column: l.column,
+                })
+                .collect(),
+            path: error
+                .path
+                .unwrap_or_default()
+                .into_iter()
+                .map(|p| match p {
+                    graphql_client::PathFragment::Key(key) => PathSegment::Key(key),
+                    graphql_client::PathFragment::Index(index) => {
+                        PathSegment::Index(index.max(0) as usize)
+                    }
+                })
+                .collect(),
+            extensions: error.extensions.unwrap_or_default().into_iter().collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphQLResponse<T> {
+    pub data: Option<T>,
+    pub errors: Vec<GraphQLError>,
+}
+
+impl<T> GraphQLResponse<T> {
+    pub fn has_errors(&self) -> bool {
+        !self.errors.is_empty()
+    }
+
+    pub fn errors_at<'a>(&'a self, path: &'a str) -> impl Iterator<Item = &'a GraphQLError> {
+        self.errors.iter().filter(move |e| e.is_at(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn errors_at_path() {
+        let errors: Vec<graphql_client::Error> = serde_json::from_value(json!([
+          { "message": "a", "path": ["person", "friends", 1, "name"] },
+          { "message": "b", "path": ["person", "homeworld"], "extensions": { "code": "NOT_FOUND" } },
+          { "message": "c", "locations": [{ "line": 1, "column": 2 }] }
+        ]))
+        .unwrap();
+        let response = GraphQLResponse::<()> {
+            data: None,
+            errors: errors.into_iter().map(GraphQLError::from).collect(),
+        };
+
+        let messages = |path| {
+            response
+                .errors_at(path)
+                .map(|e| e.message.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(messages("person"), vec!["a", "b"]);
+        assert_eq!(messages("person > friends > 1"), vec!["a"]);
+        assert_eq!(messages("person > friends > 0"), Vec::<&str>::new());
+        assert_eq!(response.errors[1].code(), Some("NOT_FOUND"));
+        assert_eq!(
+            response.errors[0].to_string(),
+            "a (at person > friends > 1 > name)"
+        );
+        assert_eq!(
+            response.errors[2].locations,
+            vec![ErrorLocation { line: 1, column: 2 }]
+        );
+    }
+}
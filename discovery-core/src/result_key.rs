@@ -0,0 +1,314 @@
+use reqwest::header::{HeaderMap, HeaderName};
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::cache::{Namespace, ResultKey};
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Operation<'a> {
+    pub uri: &'a str,
+    pub operation_name: &'a str,
+    pub query: &'a str,
+    pub variables: &'a Value,
+    #[serde(skip)]
+    pub headers: &'a HeaderMap,
+}
+
+pub trait ResultKeyStrategy: Send + Sync {
+    fn result_key(&self, operation: &Operation<'_>) -> ResultKey;
+}
+
+impl<F> ResultKeyStrategy for F
+where
+    F: Fn(&Operation<'_>) -> ResultKey + Send + Sync,
+{
+    fn result_key(&self, operation: &Operation<'_>) -> ResultKey {
+        self(operation)
+    }
+}
+
+pub fn canonical_json(value: &Value) -> String {
+    match value {
+        Value::Object(obj) => {
+            let mut entries: Vec<_> = obj.iter().collect();
+            entries.sort_by_key(|(k, _)| *k);
+            let entries: Vec<_> = entries
+                .into_iter()
+                .map(|(k, v)| format!("{}:{}", Value::String(k.clone()), canonical_json(v)))
+                .collect();
+            format!("{{{}}}", entries.join(","))
+        }
+        Value::Array(arr) => {
+            let items: Vec<_> = arr.iter().map(canonical_json).collect();
+            format!("[{}]", items.join(","))
+        }
+        _ => value.to_string(),
+    }
+}
+
+fn is_punctuator(c: char) -> bool {
+    "!$&()...:=@[]{}|".contains(c)
+}
+
+pub fn normalize_query(query: &str) -> String {
+    let mut normalized = String::with_capacity(query.len());
+    let mut chars = query.chars().peekable();
+    let mut pending_space = false;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '#' => {
+                for c in chars.by_ref() {
+                    if c == '\n' || c == '\r' {
+                        break;
+                    }
+                }
+                pending_space = true;
+            }
+            c if c.is_whitespace() || c == ',' => pending_space = true,
+            '"' => {
+                if pending_space && !normalized.ends_with(is_punctuator) && !normalized.is_empty() {
+                    normalized.push(' ');
+                }
+                pending_space = false;
+                normalized.push(c);
+                let block = chars.peek() == Some(&'"') && {
+                    let rest: String = chars.clone().take(2).collect();
+                    rest == "\"\""
+                };
+                if block {
+                    normalized.push_str("\"\"");
+                    chars.nth(1);
+                    for c in chars.by_ref() {
+                        normalized.push(c);
+                        if c == '"'
+                            && normalized.ends_with("\"\"\"")
+                            && !normalized.ends_with("\\\"\"\"")
+                        {
+                            break;
+                        }
+                    }
+                } else {
+                    while let Some(c) = chars.next() {
+                        normalized.push(c);
+                        match c {
+                            '\\' => normalized.extend(chars.next()),
+                            '"' => break,
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            c => {
+                if pending_space
+                    && !normalized.is_empty()
+                    && !is_punctuator(c)
+                    && !normalized.ends_with(is_punctuator)
+                {
+                    normalized.push(' ');
+                }
+                pending_space = false;
+                normalized.push(c);
+            }
+        }
+    }
+    normalized
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CanonicalSha256;
+
+impl ResultKeyStrategy for CanonicalSha256 {
+    fn result_key(&self, operation: &Operation<'_>) -> ResultKey {
+        let mut hasher = Sha256::new();
+        hasher.update(operation.uri);
+        hasher.update([0]);
+        hasher.update(normalize_query(operation.query));
+        hasher.update([0]);
+        hasher.update(operation.operation_name);
+        hasher.update([0]);
+        hasher.update(canonical_json(operation.variables));
+        format!("{:x}", hasher.finalize()).into()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OperationNameAndVariables;
+
+impl ResultKeyStrategy for OperationNameAndVariables {
+    fn result_key(&self, operation: &Operation<'_>) -> ResultKey {
+        format!(
+            "{}({})",
+            operation.operation_name,
+            canonical_json(operation.variables)
+        )
+        .into()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WithEndpoint<S>(pub S);
+
+impl<S: ResultKeyStrategy> ResultKeyStrategy for WithEndpoint<S> {
+    fn result_key(&self, operation: &Operation<'_>) -> ResultKey {
+        format!("{}#{}", operation.uri, self.0.result_key(operation).key()).into()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WithHeaders<S> {
+    names: Vec<HeaderName>,
+    strategy: S,
+}
+
+impl<S> WithHeaders<S> {
+    pub fn new(names: impl IntoIterator<Item = HeaderName>, strategy: S) -> Self {
+        Self {
+            names: names.into_iter().collect(),
+            strategy,
+        }
+    }
+}
+
+impl<S: ResultKeyStrategy> ResultKeyStrategy for WithHeaders<S> {
+    fn result_key(&self, operation: &Operation<'_>) -> ResultKey {
+        let key = self.strategy.result_key(operation);
+        let headers: Vec<_> = self
+            .names
+            .iter()
+            .filter_map(|name| {
+                let value = operation.headers.get(name)?.to_str().ok()?;
+                Some(format!("{}={}", name, value))
+            })
+            .collect();
+        if headers.is_empty() {
+            return key;
+        }
+        let suffixed = format!("{}#{}", key.key(), headers.join(";"));
+        match key.namespace() {
+            Some(namespace) => ResultKey::namespaced(namespace, suffixed),
+            None => suffixed.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Namespaced<S>(pub Namespace, pub S);
+
+impl<S: ResultKeyStrategy> ResultKeyStrategy for Namespaced<S> {
+    fn result_key(&self, operation: &Operation<'_>) -> ResultKey {
+        ResultKey::namespaced(self.0.clone(), self.1.result_key(operation).key())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::OnceLock;
+
+    static NO_HEADERS: OnceLock<HeaderMap> = OnceLock::new();
+
+    fn operation<'a>(uri: &'a str, variables: &'a Value) -> Operation<'a> {
+        Operation {
+            uri,
+            operation_name: "MeQuery",
+            query: "query MeQuery($limit: Int) { users(limit: $limit) { id } }",
+            variables,
+            headers: NO_HEADERS.get_or_init(HeaderMap::new),
+        }
+    }
+
+    #[test]
+    fn canonical_sha256_is_stable() {
+        let a = json!({ "limit": 1, "filter": { "b": 2, "a": 1 } });
+        let b = json!({ "filter": { "a": 1, "b": 2 }, "limit": 1 });
+        let reformatted = Operation {
+            query: "query MeQuery( $limit : Int ) {\n  # comment\n  users(limit: $limit) {\n    id\n  }\n}\n",
+            ..operation("http://a", &b)
+        };
+
+        let key = CanonicalSha256.result_key(&operation("http://a", &a));
+
+        assert_eq!(key, CanonicalSha256.result_key(&reformatted));
+        assert_eq!(key.key().len(), 64);
+    }
+
+    #[test]
+    fn canonical_sha256_distinguishes_variables() {
+        let a = json!({ "limit": 1 });
+        let b = json!({ "limit": 2 });
+
+        assert_ne!(
+            CanonicalSha256.result_key(&operation("http://a", &a)),
+            CanonicalSha256.result_key(&operation("http://a", &b))
+        );
+    }
+
+    #[test]
+    fn endpoint_and_headers_in_key() {
+        let variables = json!({});
+        let staging = operation("https://staging/graphql", &variables);
+        let production = operation("https://production/graphql", &variables);
+
+        assert_ne!(
+            CanonicalSha256.result_key(&staging),
+            CanonicalSha256.result_key(&production)
+        );
+
+        let strategy = WithHeaders::new([HeaderName::from_static("x-tenant")], CanonicalSha256);
+        let mut headers = HeaderMap::new();
+        headers.insert("x-tenant", "acme".parse().unwrap());
+        let tenant = Operation {
+            headers: &headers,
+            ..staging
+        };
+
+        assert_ne!(strategy.result_key(&staging), strategy.result_key(&tenant));
+        assert_eq!(
+            strategy.result_key(&staging),
+            CanonicalSha256.result_key(&staging)
+        );
+    }
+
+    #[test]
+    fn normalize_query_keeps_strings() {
+        assert_eq!(
+            normalize_query("{ a(s: \"x  y\", t: \"\"\"  b \"\"\") {\n id, name } }"),
+            "{a(s:\"x  y\" t:\"\"\"  b \"\"\"){id name}}"
+        );
+    }
+
+    #[test]
+    fn operation_name_and_variables() {
+        let variables = json!({ "offset": 0, "limit": 1 });
+
+        assert_eq!(
+            OperationNameAndVariables.result_key(&operation("http://a", &variables)),
+            ResultKey::from(r#"MeQuery({"limit":1,"offset":0})"#)
+        );
+    }
+
+    #[test]
+    fn with_endpoint() {
+        let variables = json!({});
+        let strategy = WithEndpoint(OperationNameAndVariables);
+
+        assert_ne!(
+            strategy.result_key(&operation("http://a", &variables)),
+            strategy.result_key(&operation("http://b", &variables))
+        );
+    }
+
+    #[test]
+    fn closure_strategy() {
+        let variables = json!({});
+        let strategy = |op: &Operation<'_>| ResultKey::from(op.operation_name);
+
+        assert_eq!(
+            strategy.result_key(&operation("http://a", &variables)),
+            ResultKey::from("MeQuery")
+        );
+    }
+}
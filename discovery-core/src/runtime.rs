@@ -0,0 +1,34 @@
+use futures::future::BoxFuture;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub trait Runtime: Send + Sync {
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+}
+
+impl<F> Runtime for F
+where
+    F: Fn(Duration) -> BoxFuture<'static, ()> + Send + Sync,
+{
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        self(duration)
+    }
+}
+
+#[cfg(feature = "tokio")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioRuntime;
+
+#[cfg(feature = "tokio")]
+impl Runtime for TokioRuntime {
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+pub(crate) fn default_runtime() -> Option<Arc<dyn Runtime>> {
+    #[cfg(feature = "tokio")]
+    return Some(Arc::new(TokioRuntime));
+    #[cfg(not(feature = "tokio"))]
+    return None;
+}
@@ -0,0 +1,200 @@
+use futures::channel::oneshot;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use crate::cache::{Cache, Data, ResultKey};
+use crate::client::ClientResult;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct HydratedResult {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    namespace: Option<String>,
+    key: String,
+    data: Value,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HydrationSnapshot {
+    results: Vec<HydratedResult>,
+}
+
+impl HydrationSnapshot {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn capture<C: Cache>(cache: &C) -> Self {
+        let mut keys = cache.result_keys();
+        keys.sort();
+        let mut snapshot = Self::new();
+        for key in keys {
+            if let Ok(data) = cache.get_result_data(&key) {
+                snapshot.insert(&key, &data);
+            }
+        }
+        snapshot
+    }
+
+    pub fn insert(&mut self, key: &ResultKey, data: &Data) {
+        self.results.retain(|result| !result.is(key));
+        self.results.push(HydratedResult {
+            namespace: key.namespace().map(str::to_string),
+            key: key.key().to_string(),
+            data: data.value().clone(),
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+
+    pub fn restore<C: Cache>(&self, cache: &mut C) -> ClientResult<()> {
+        for result in &self.results {
+            let key = match &result.namespace {
+                Some(namespace) => ResultKey::namespaced(namespace, &result.key),
+                None => ResultKey::new(&result.key),
+            };
+            cache.store_result_data(&key, Data::new(result.data.clone())?)?;
+        }
+        Ok(())
+    }
+}
+
+impl HydratedResult {
+    fn is(&self, key: &ResultKey) -> bool {
+        self.namespace.as_deref() == key.namespace() && self.key == key.key()
+    }
+}
+
+#[derive(Debug, Default)]
+struct InFlightState {
+    count: usize,
+    waiters: Vec<oneshot::Sender<()>>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct InFlight(Mutex<InFlightState>);
+
+impl InFlight {
+    pub(crate) fn start(self: &Arc<Self>) -> InFlightGuard {
+        self.0.lock().unwrap().count += 1;
+        InFlightGuard(self.clone())
+    }
+
+    pub(crate) fn settled(&self) -> impl Future<Output = ()> {
+        let receiver = {
+            let mut state = self.0.lock().unwrap();
+            (state.count > 0).then(|| {
+                let (sender, receiver) = oneshot::channel();
+                state.waiters.push(sender);
+                receiver
+            })
+        };
+        async move {
+            if let Some(receiver) = receiver {
+                let _ = receiver.await;
+            }
+        }
+    }
+}
+
+pub(crate) struct InFlightGuard(Arc<InFlight>);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        let mut state = self.0 .0.lock().unwrap();
+        state.count -= 1;
+        if state.count == 0 {
+            for waiter in state.waiters.drain(..) {
+                let _ = waiter.send(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::InMemoryCache;
+    use futures::executor::LocalPool;
+    use futures::task::LocalSpawnExt;
+    use serde_json::json;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn restore_serialized_snapshot() {
+        let snapshot: HydrationSnapshot = serde_json::from_value(json!({
+          "results": [
+            {
+              "key": "Person",
+              "data": { "person": { "__typename": "Person", "id": "1", "name": "Ann" } }
+            },
+            { "namespace": "admin", "key": "Me", "data": { "me": { "name": "root" } } }
+          ]
+        }))
+        .unwrap();
+
+        let mut cache = InMemoryCache::new();
+        snapshot.restore(&mut cache).unwrap();
+
+        assert_eq!(
+            cache
+                .get_result_data(&ResultKey::new("Person"))
+                .unwrap()
+                .value(),
+            &json!({ "person": { "__typename": "Person", "id": "1", "name": "Ann" } })
+        );
+        assert_eq!(
+            cache
+                .get_result_data(&ResultKey::namespaced("admin", "Me"))
+                .unwrap()
+                .value(),
+            &json!({ "me": { "name": "root" } })
+        );
+    }
+
+    #[test]
+    fn capture_after_in_flight_settles() {
+        let mut cache = InMemoryCache::new();
+        let data = Data::new(json!({ "me": { "name": "Ann" } })).unwrap();
+        cache
+            .store_result_data(&ResultKey::new("Me"), data.clone())
+            .unwrap();
+        let snapshot = HydrationSnapshot::capture(&cache);
+        assert_eq!(snapshot.len(), 1);
+
+        let mut restored = InMemoryCache::new();
+        snapshot.restore(&mut restored).unwrap();
+        assert_eq!(
+            restored.get_result_data(&ResultKey::new("Me")).unwrap(),
+            data
+        );
+
+        let in_flight = Arc::new(InFlight::default());
+        let settled = Rc::new(Cell::new(false));
+        let guard = in_flight.start();
+        let mut pool = LocalPool::new();
+        {
+            let (settle, settled) = (in_flight.settled(), settled.clone());
+            pool.spawner()
+                .spawn_local(async move {
+                    settle.await;
+                    settled.set(true);
+                })
+                .unwrap();
+        }
+        pool.run_until_stalled();
+        assert!(!settled.get());
+
+        drop(guard);
+        pool.run_until_stalled();
+        assert!(settled.get());
+    }
+}
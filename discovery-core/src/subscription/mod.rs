@@ -0,0 +1,404 @@
+mod multiplex;
+
+pub(crate) use multiplex::Multiplexer;
+
+use futures::stream::{BoxStream, Stream, StreamExt};
+use futures::Future;
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::client::{ClientError, ClientResult};
+use crate::link::TokenProvider;
+use crate::runtime::Runtime;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubscriptionTransport {
+    Sse { uri: Option<String> },
+}
+
+impl SubscriptionTransport {
+    pub fn sse() -> Self {
+        Self::Sse { uri: None }
+    }
+
+    pub fn sse_with_uri(uri: impl Into<String>) -> Self {
+        Self::Sse {
+            uri: Some(uri.into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+    pub multiplier: u32,
+    pub max_attempts: Option<u32>,
+}
+
+impl Backoff {
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = self
+            .multiplier
+            .checked_pow(attempt.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        self.initial.saturating_mul(factor).min(self.max)
+    }
+
+    fn exhausted(&self, attempt: u32) -> bool {
+        self.max_attempts.is_some_and(|max| attempt > max)
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_millis(500),
+            max: Duration::from_secs(30),
+            multiplier: 2,
+            max_attempts: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    Connected,
+    Reconnecting { attempt: u32, delay: Duration },
+    Disconnected,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubscriptionEvent<T> {
+    State(ConnectionState),
+    Next(T),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SseEvent {
+    pub event: Option<String>,
+    pub data: String,
+    pub id: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+    pending: VecDeque<SseEvent>,
+}
+
+impl SseParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) {
+        self.buffer.extend(chunk.iter().filter(|b| **b != b'\r'));
+        while let Some(end) = self.buffer.windows(2).position(|w| w == b"\n\n") {
+            let block: Vec<u8> = self.buffer.drain(..end + 2).collect();
+            if let Some(event) = parse_block(&String::from_utf8_lossy(&block)) {
+                self.pending.push_back(event);
+            }
+        }
+    }
+
+    pub fn next_event(&mut self) -> Option<SseEvent> {
+        self.pending.pop_front()
+    }
+}
+
+pub(crate) fn sse_events(
+    response: reqwest::Response,
+) -> impl Stream<Item = Result<SseEvent, reqwest::Error>> {
+    futures::stream::unfold(
+        (Some(response), SseParser::new()),
+        |(mut response, mut parser)| async move {
+            loop {
+                if let Some(event) = parser.next_event() {
+                    return Some((Ok(event), (response, parser)));
+                }
+                match response.as_mut()?.chunk().await {
+                    Ok(Some(chunk)) => parser.push(&chunk),
+                    Ok(None) => return None,
+                    Err(e) => return Some((Err(e), (None, parser))),
+                }
+            }
+        },
+    )
+}
+
+struct Connection {
+    client: reqwest::Client,
+    uri: String,
+    headers: HeaderMap,
+    token_provider: Option<Arc<dyn TokenProvider>>,
+    body: Value,
+    backoff: Backoff,
+    runtime: Arc<dyn Runtime>,
+    events: Option<BoxStream<'static, Result<SseEvent, reqwest::Error>>>,
+    attempt: u32,
+    delay: Option<Duration>,
+    last_event_id: Option<String>,
+}
+
+impl Connection {
+    fn connect(&self) -> impl Future<Output = ClientResult<reqwest::Response>> {
+        let mut request = self
+            .client
+            .post(self.uri.as_str())
+            .headers(self.headers.clone())
+            .header(ACCEPT, "text/event-stream")
+            .json(&self.body);
+        if let Some(id) = &self.last_event_id {
+            request = request.header("Last-Event-ID", id.as_str());
+        }
+        let token_provider = self.token_provider.clone();
+        async move {
+            if let Some(provider) = token_provider {
+                let token = provider.token().await?;
+                request = request.header(
+                    AUTHORIZATION,
+                    HeaderValue::from_str(&token).map_err(|_| ClientError::InvalidToken)?,
+                );
+            }
+            Ok(request.send().await?.error_for_status()?)
+        }
+    }
+
+    async fn next(&mut self) -> Option<SubscriptionEvent<SseEvent>> {
+        loop {
+            if let Some(delay) = self.delay.take() {
+                self.runtime.sleep(delay).await;
+                match self.connect().await {
+                    Ok(response) => {
+                        self.events = Some(sse_events(response).boxed());
+                        self.attempt = 0;
+                        return Some(SubscriptionEvent::State(ConnectionState::Connected));
+                    }
+                    Err(_) => return Some(self.reconnect()),
+                }
+            }
+            match self.events.as_mut()?.next().await {
+                Some(Ok(event)) if event.event.as_deref() == Some("complete") => {
+                    self.events = None;
+                    return None;
+                }
+                Some(Ok(event)) => {
+                    if event.id.is_some() {
+                        self.last_event_id = event.id.clone();
+                    }
+                    if !event.data.is_empty() {
+                        return Some(SubscriptionEvent::Next(event));
+                    }
+                }
+                Some(Err(_)) | None => return Some(self.reconnect()),
+            }
+        }
+    }
+
+    fn reconnect(&mut self) -> SubscriptionEvent<SseEvent> {
+        self.events = None;
+        self.attempt += 1;
+        if self.backoff.exhausted(self.attempt) {
+            return SubscriptionEvent::State(ConnectionState::Disconnected);
+        }
+        let delay = self.backoff.delay(self.attempt);
+        self.delay = Some(delay);
+        SubscriptionEvent::State(ConnectionState::Reconnecting {
+            attempt: self.attempt,
+            delay,
+        })
+    }
+}
+
+pub(crate) async fn resumable_sse_events(
+    client: reqwest::Client,
+    uri: String,
+    headers: HeaderMap,
+    token_provider: Option<Arc<dyn TokenProvider>>,
+    body: Value,
+    backoff: Backoff,
+    runtime: Arc<dyn Runtime>,
+) -> ClientResult<impl Stream<Item = SubscriptionEvent<SseEvent>>> {
+    let mut connection = Connection {
+        client,
+        uri,
+        headers,
+        token_provider,
+        body,
+        backoff,
+        runtime,
+        events: None,
+        attempt: 0,
+        delay: None,
+        last_event_id: None,
+    };
+    connection.events = Some(sse_events(connection.connect().await?).boxed());
+    Ok(futures::stream::unfold(
+        connection,
+        |mut connection| async move {
+            let event = connection.next().await?;
+            Some((event, connection))
+        },
+    ))
+}
+
+fn parse_block(block: &str) -> Option<SseEvent> {
+    let mut event = SseEvent::default();
+    let mut data = vec![];
+    for line in block.lines() {
+        if line.is_empty() || line.starts_with(':') {
+            continue;
+        }
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            "event" => event.event = Some(value.to_string()),
+            "data" => data.push(value),
+            "id" => event.id = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    if event.event.is_none() && data.is_empty() {
+        return None;
+    }
+    event.data = data.join("\n");
+    Some(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_events_across_chunks() {
+        let mut parser = SseParser::new();
+        parser.push(b": keep-alive\n\nevent: next\ndata: {\"data\":");
+        assert_eq!(parser.next_event(), None);
+
+        parser.push(b"{\"a\":1}}\n\nevent: complete\r\ndata:\r\n\r\n");
+        assert_eq!(
+            parser.next_event(),
+            Some(SseEvent {
+                event: Some("next".to_string()),
+                data: "{\"data\":{\"a\":1}}".to_string(),
+                id: None,
+            })
+        );
+        assert_eq!(
+            parser.next_event().unwrap().event.as_deref(),
+            Some("complete")
+        );
+        assert_eq!(parser.next_event(), None);
+    }
+
+    #[test]
+    fn backoff_delay() {
+        let backoff = Backoff {
+            max_attempts: Some(3),
+            ..Backoff::default()
+        };
+        assert_eq!(backoff.delay(1), Duration::from_millis(500));
+        assert_eq!(backoff.delay(3), Duration::from_secs(2));
+        assert_eq!(backoff.delay(100), Duration::from_secs(30));
+        assert!(!backoff.exhausted(3));
+        assert!(backoff.exhausted(4));
+    }
+
+    #[test]
+    fn multiline_data() {
+        let mut parser = SseParser::new();
+        parser.push(b"data: a\ndata: b\nid: 1\n\n");
+        assert_eq!(
+            parser.next_event(),
+            Some(SseEvent {
+                event: None,
+                data: "a\nb".to_string(),
+                id: Some("1".to_string()),
+            })
+        );
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn resolve_token_on_reconnect() {
+        use futures::future::BoxFuture;
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Mutex;
+
+        struct Rotating(AtomicUsize);
+
+        impl TokenProvider for Rotating {
+            fn token(&self) -> BoxFuture<'_, ClientResult<String>> {
+                let token = format!("Bearer {}", self.0.fetch_add(1, Ordering::SeqCst));
+                Box::pin(async move { Ok(token) })
+            }
+
+            fn refresh(&self) -> BoxFuture<'_, ClientResult<String>> {
+                self.token()
+            }
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let uri = format!("http://{}/graphql", listener.local_addr().unwrap());
+        let seen = Arc::new(Mutex::new(vec![]));
+        let recorded = seen.clone();
+        std::thread::spawn(move || {
+            for (index, stream) in listener.incoming().take(2).enumerate() {
+                let mut stream = stream.unwrap();
+                let mut request = [0; 4096];
+                let len = stream.read(&mut request).unwrap();
+                let authorization = String::from_utf8_lossy(&request[..len])
+                    .lines()
+                    .find_map(|line| line.strip_prefix("authorization: ").map(str::to_string));
+                recorded.lock().unwrap().push(authorization);
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\ndata: {}\n\n",
+                    index
+                );
+            }
+        });
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        let events = runtime.block_on(async {
+            resumable_sse_events(
+                reqwest::Client::new(),
+                uri,
+                HeaderMap::new(),
+                Some(Arc::new(Rotating(AtomicUsize::new(1)))),
+                Value::Null,
+                Backoff {
+                    max_attempts: Some(1),
+                    ..Backoff::default()
+                },
+                Arc::new(|_: Duration| -> BoxFuture<'static, ()> { Box::pin(async {}) }),
+            )
+            .await
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await
+        });
+
+        let data: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                SubscriptionEvent::Next(event) => Some(event.data.as_str()),
+                SubscriptionEvent::State(_) => None,
+            })
+            .collect();
+        assert_eq!(data, vec!["0", "1"]);
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![Some("Bearer 1".to_string()), Some("Bearer 2".to_string())]
+        );
+    }
+}
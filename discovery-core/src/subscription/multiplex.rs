@@ -0,0 +1,201 @@
+use futures::stream::{BoxStream, Stream, StreamExt};
+use std::collections::{HashMap, VecDeque};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, Weak};
+use std::task::{Context, Poll, Wake, Waker};
+
+type ConsumerId = u64;
+
+#[derive(Default)]
+struct WakeAll(Mutex<HashMap<ConsumerId, Waker>>);
+
+impl Wake for WakeAll {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        for waker in self.0.lock().unwrap().values() {
+            waker.wake_by_ref();
+        }
+    }
+}
+
+struct Upstream<T> {
+    events: BoxStream<'static, T>,
+    queues: HashMap<ConsumerId, VecDeque<T>>,
+    wakers: Arc<WakeAll>,
+    next_id: ConsumerId,
+    done: bool,
+}
+
+type Upstreams<T> = Arc<Mutex<HashMap<String, Weak<Mutex<Upstream<T>>>>>>;
+
+pub(crate) struct Multiplexer<T> {
+    upstreams: Upstreams<T>,
+}
+
+impl<T> Clone for Multiplexer<T> {
+    fn clone(&self) -> Self {
+        Self {
+            upstreams: self.upstreams.clone(),
+        }
+    }
+}
+
+impl<T> Default for Multiplexer<T> {
+    fn default() -> Self {
+        Self {
+            upstreams: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+impl<T: Clone> Multiplexer<T> {
+    pub(crate) fn join(&self, key: &str) -> Option<SharedStream<T>> {
+        let upstream = self.upstreams.lock().unwrap().get(key)?.upgrade()?;
+        Some(self.consumer(key, upstream))
+    }
+
+    pub(crate) fn insert(&self, key: &str, events: BoxStream<'static, T>) -> SharedStream<T> {
+        let upstream = Arc::new(Mutex::new(Upstream {
+            events,
+            queues: HashMap::new(),
+            wakers: Arc::new(WakeAll::default()),
+            next_id: 0,
+            done: false,
+        }));
+        self.upstreams
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), Arc::downgrade(&upstream));
+        self.consumer(key, upstream)
+    }
+
+    fn consumer(&self, key: &str, upstream: Arc<Mutex<Upstream<T>>>) -> SharedStream<T> {
+        let id = {
+            let mut upstream = upstream.lock().unwrap();
+            let id = upstream.next_id;
+            upstream.next_id += 1;
+            upstream.queues.insert(id, VecDeque::new());
+            id
+        };
+        SharedStream {
+            id,
+            key: key.to_string(),
+            upstream,
+            upstreams: self.upstreams.clone(),
+        }
+    }
+}
+
+pub(crate) struct SharedStream<T> {
+    id: ConsumerId,
+    key: String,
+    upstream: Arc<Mutex<Upstream<T>>>,
+    upstreams: Upstreams<T>,
+}
+
+impl<T: Clone> Stream for SharedStream<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let mut upstream = self.upstream.lock().unwrap();
+        let upstream = &mut *upstream;
+        loop {
+            if let Some(item) = upstream
+                .queues
+                .get_mut(&self.id)
+                .and_then(VecDeque::pop_front)
+            {
+                return Poll::Ready(Some(item));
+            }
+            if upstream.done {
+                return Poll::Ready(None);
+            }
+            upstream
+                .wakers
+                .0
+                .lock()
+                .unwrap()
+                .insert(self.id, cx.waker().clone());
+            let waker = Waker::from(upstream.wakers.clone());
+            match upstream
+                .events
+                .poll_next_unpin(&mut Context::from_waker(&waker))
+            {
+                Poll::Ready(Some(item)) => {
+                    for queue in upstream.queues.values_mut() {
+                        queue.push_back(item.clone());
+                    }
+                    upstream.wakers.wake_by_ref();
+                }
+                Poll::Ready(None) => {
+                    upstream.done = true;
+                    upstream.wakers.wake_by_ref();
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<T> Drop for SharedStream<T> {
+    fn drop(&mut self) {
+        {
+            let mut upstream = self.upstream.lock().unwrap();
+            upstream.queues.remove(&self.id);
+            upstream.wakers.0.lock().unwrap().remove(&self.id);
+        }
+        if Arc::strong_count(&self.upstream) == 1 {
+            let mut upstreams = self.upstreams.lock().unwrap();
+            if upstreams
+                .get(&self.key)
+                .is_some_and(|u| u.ptr_eq(&Arc::downgrade(&self.upstream)))
+            {
+                upstreams.remove(&self.key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::channel::mpsc::unbounded;
+    use futures::executor::block_on;
+
+    #[test]
+    fn consumers_share_one_upstream() {
+        let multiplexer = Multiplexer::default();
+        let (sender, receiver) = unbounded();
+        let mut a = multiplexer.insert("op", receiver.boxed());
+        let mut b = multiplexer.join("op").unwrap();
+
+        sender.unbounded_send(1).unwrap();
+        sender.unbounded_send(2).unwrap();
+        assert_eq!(block_on(a.next()), Some(1));
+        assert_eq!(block_on(a.next()), Some(2));
+        assert_eq!(block_on(b.next()), Some(1));
+        assert_eq!(block_on(b.next()), Some(2));
+
+        drop(sender);
+        assert_eq!(block_on(b.next()), None);
+        assert_eq!(block_on(a.next()), None);
+    }
+
+    #[test]
+    fn last_consumer_tears_down_upstream() {
+        let multiplexer = Multiplexer::default();
+        let (sender, receiver) = unbounded::<u32>();
+        let a = multiplexer.insert("op", receiver.boxed());
+        let b = multiplexer.join("op").unwrap();
+        assert!(multiplexer.join("other").is_none());
+
+        drop(a);
+        assert!(!sender.is_closed());
+        drop(b);
+        assert!(multiplexer.upstreams.lock().unwrap().is_empty());
+        assert!(sender.is_closed());
+    }
+}
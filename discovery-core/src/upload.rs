@@ -0,0 +1,219 @@
+use bytes::Bytes;
+use reqwest::multipart::{Form, Part};
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+
+use crate::link::{progress, ProgressFn};
+
+const MARKER: &str = "__discoveryUpload";
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+static REGISTRY: Mutex<BTreeMap<u64, Weak<File>>> = Mutex::new(BTreeMap::new());
+
+#[derive(Debug)]
+struct File {
+    id: u64,
+    file_name: String,
+    content_type: Option<String>,
+    data: Bytes,
+}
+
+impl Drop for File {
+    fn drop(&mut self) {
+        REGISTRY.lock().unwrap().remove(&self.id);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Upload(Arc<File>);
+
+impl Upload {
+    pub fn new(file_name: impl Into<String>, data: impl Into<Bytes>) -> Self {
+        Self::register(file_name.into(), None, data.into())
+    }
+
+    pub fn with_content_type(
+        file_name: impl Into<String>,
+        content_type: impl Into<String>,
+        data: impl Into<Bytes>,
+    ) -> Self {
+        Self::register(file_name.into(), Some(content_type.into()), data.into())
+    }
+
+    pub fn from_path(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok(Self::new(file_name, std::fs::read(path)?))
+    }
+
+    fn register(file_name: String, content_type: Option<String>, data: Bytes) -> Self {
+        let file = Arc::new(File {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            file_name,
+            content_type,
+            data,
+        });
+        REGISTRY
+            .lock()
+            .unwrap()
+            .insert(file.id, Arc::downgrade(&file));
+        Self(file)
+    }
+
+    pub fn file_name(&self) -> &str {
+        &self.0.file_name
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.0.content_type.as_deref()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.data.is_empty()
+    }
+
+    fn part(&self, progress: Option<(u64, u64, &Arc<ProgressFn>)>) -> reqwest::Result<Part> {
+        let data = self.0.data.clone();
+        let body = match progress {
+            Some((offset, total, progress)) => {
+                reqwest::Body::wrap_stream(progress::chunked(data, offset, total, progress.clone()))
+            }
+            None => data.into(),
+        };
+        let part =
+            Part::stream_with_length(body, self.len() as u64).file_name(self.0.file_name.clone());
+        match &self.0.content_type {
+            Some(content_type) => part.mime_str(content_type),
+            None => Ok(part),
+        }
+    }
+}
+
+impl PartialEq for Upload {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Serialize for Upload {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("Upload", 1)?;
+        s.serialize_field(MARKER, &self.0.id)?;
+        s.end()
+    }
+}
+
+fn lookup(object: &Map<String, Value>) -> Option<Upload> {
+    if object.len() != 1 {
+        return None;
+    }
+    let id = object.get(MARKER)?.as_u64()?;
+    let file = REGISTRY.lock().unwrap().get(&id)?.upgrade()?;
+    Some(Upload(file))
+}
+
+pub(crate) fn extract(value: &mut Value, path: String, files: &mut Vec<(String, Upload)>) {
+    match value {
+        Value::Object(object) => match lookup(object) {
+            Some(upload) => {
+                *value = Value::Null;
+                files.push((path, upload));
+            }
+            None => {
+                for (key, value) in object.iter_mut() {
+                    extract(value, format!("{}.{}", path, key), files);
+                }
+            }
+        },
+        Value::Array(array) => {
+            for (i, value) in array.iter_mut().enumerate() {
+                extract(value, format!("{}.{}", path, i), files);
+            }
+        }
+        _ => {}
+    }
+}
+
+pub(crate) fn form(
+    operations: &Value,
+    files: &[(String, Upload)],
+    progress: Option<&Arc<ProgressFn>>,
+) -> reqwest::Result<Form> {
+    let map: Map<String, Value> = files
+        .iter()
+        .enumerate()
+        .map(|(i, (path, _))| (i.to_string(), Value::Array(vec![path.clone().into()])))
+        .collect();
+    let mut form = Form::new()
+        .text("operations", operations.to_string())
+        .text("map", Value::Object(map).to_string());
+    let total = files.iter().map(|(_, upload)| upload.len() as u64).sum();
+    let mut offset = 0;
+    for (i, (_, upload)) in files.iter().enumerate() {
+        let part = upload.part(progress.map(|progress| (offset, total, progress)))?;
+        form = form.part(i.to_string(), part);
+        offset += upload.len() as u64;
+    }
+    Ok(form)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn extract_uploads_from_variables() {
+        let avatar = Upload::with_content_type("avatar.png", "image/png", vec![1, 2, 3]);
+        let attachments = vec![Upload::new("a.txt", "a"), Upload::new("b.txt", "b")];
+        let mut variables = json!({
+          "id": "1",
+          "avatar": avatar,
+          "attachments": attachments,
+        });
+
+        let mut files = vec![];
+        extract(&mut variables, "variables".to_string(), &mut files);
+
+        assert_eq!(
+            variables,
+            json!({ "id": "1", "avatar": null, "attachments": [null, null] })
+        );
+        assert_eq!(
+            files,
+            vec![
+                (
+                    "variables.attachments.0".to_string(),
+                    attachments[0].clone()
+                ),
+                (
+                    "variables.attachments.1".to_string(),
+                    attachments[1].clone()
+                ),
+                ("variables.avatar".to_string(), avatar),
+            ]
+        );
+        assert_eq!(files[2].1.content_type(), Some("image/png"));
+    }
+
+    #[test]
+    fn dropped_upload_is_unregistered() {
+        let upload = Upload::new("a.txt", "a");
+        let id = upload.0.id;
+        drop(upload);
+
+        assert!(!REGISTRY.lock().unwrap().contains_key(&id));
+    }
+}
@@ -0,0 +1,319 @@
+use std::fmt;
+
+use crate::error::Span;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document {
+    pub definitions: Vec<Definition>,
+}
+
+impl Document {
+    pub fn operations(&self) -> impl Iterator<Item = &OperationDefinition> {
+        self.definitions
+            .iter()
+            .filter_map(|definition| match definition {
+                Definition::Operation(operation) => Some(operation),
+                Definition::Fragment(_) => None,
+            })
+    }
+
+    pub fn fragments(&self) -> impl Iterator<Item = &FragmentDefinition> {
+        self.definitions
+            .iter()
+            .filter_map(|definition| match definition {
+                Definition::Fragment(fragment) => Some(fragment),
+                Definition::Operation(_) => None,
+            })
+    }
+
+    pub fn operation(&self, name: &str) -> Option<&OperationDefinition> {
+        self.operations()
+            .find(|operation| operation.name.as_deref() == Some(name))
+    }
+
+    pub fn fragment(&self, name: &str) -> Option<&FragmentDefinition> {
+        self.fragments().find(|fragment| fragment.name == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Definition {
+    Operation(OperationDefinition),
+    Fragment(FragmentDefinition),
+}
+
+impl Definition {
+    pub fn span(&self) -> Span {
+        match self {
+            Definition::Operation(operation) => operation.span,
+            Definition::Fragment(fragment) => fragment.span,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperationType {
+    Query,
+    Mutation,
+    Subscription,
+}
+
+impl OperationType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OperationType::Query => "query",
+            OperationType::Mutation => "mutation",
+            OperationType::Subscription => "subscription",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperationDefinition {
+    pub span: Span,
+    pub operation_type: OperationType,
+    pub name: Option<String>,
+    pub variable_definitions: Vec<VariableDefinition>,
+    pub directives: Vec<Directive>,
+    pub selection_set: SelectionSet,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FragmentDefinition {
+    pub span: Span,
+    pub name: String,
+    pub type_condition: String,
+    pub directives: Vec<Directive>,
+    pub selection_set: SelectionSet,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariableDefinition {
+    pub span: Span,
+    pub name: String,
+    pub ty: Type,
+    pub default_value: Option<Value>,
+    pub directives: Vec<Directive>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    Named(String),
+    List(Box<Type>),
+    NonNull(Box<Type>),
+}
+
+impl Type {
+    pub fn named_type(&self) -> &str {
+        match self {
+            Type::Named(name) => name,
+            Type::List(ty) | Type::NonNull(ty) => ty.named_type(),
+        }
+    }
+
+    pub fn is_non_null(&self) -> bool {
+        matches!(self, Type::NonNull(_))
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Named(name) => write!(f, "{}", name),
+            Type::List(ty) => write!(f, "[{}]", ty),
+            Type::NonNull(ty) => write!(f, "{}!", ty),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectionSet {
+    pub span: Span,
+    pub selections: Vec<Selection>,
+}
+
+impl SelectionSet {
+    pub fn fragment_spreads(&self) -> Vec<&FragmentSpread> {
+        let mut spreads = vec![];
+        for selection in &self.selections {
+            match selection {
+                Selection::Field(field) => {
+                    if let Some(selection_set) = &field.selection_set {
+                        spreads.extend(selection_set.fragment_spreads());
+                    }
+                }
+                Selection::FragmentSpread(spread) => spreads.push(spread),
+                Selection::InlineFragment(fragment) => {
+                    spreads.extend(fragment.selection_set.fragment_spreads())
+                }
+            }
+        }
+        spreads
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selection {
+    Field(Field),
+    FragmentSpread(FragmentSpread),
+    InlineFragment(InlineFragment),
+}
+
+impl Selection {
+    pub fn span(&self) -> Span {
+        match self {
+            Selection::Field(field) => field.span,
+            Selection::FragmentSpread(spread) => spread.span,
+            Selection::InlineFragment(fragment) => fragment.span,
+        }
+    }
+
+    pub fn directives(&self) -> &[Directive] {
+        match self {
+            Selection::Field(field) => &field.directives,
+            Selection::FragmentSpread(spread) => &spread.directives,
+            Selection::InlineFragment(fragment) => &fragment.directives,
+        }
+    }
+
+    pub fn is_conditional(&self) -> bool {
+        is_conditional(self.directives())
+    }
+}
+
+pub(crate) fn is_conditional(directives: &[Directive]) -> bool {
+    directives.iter().any(|directive| {
+        let condition = directive.arguments.first().map(|argument| &argument.value);
+        match (directive.name.as_str(), condition) {
+            ("include", Some(Value::Boolean(true))) | ("skip", Some(Value::Boolean(false))) => {
+                false
+            }
+            (name, _) => name == "include" || name == "skip",
+        }
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub span: Span,
+    pub alias: Option<String>,
+    pub name: String,
+    pub arguments: Vec<Argument>,
+    pub directives: Vec<Directive>,
+    pub selection_set: Option<SelectionSet>,
+}
+
+impl Field {
+    pub fn response_name(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FragmentSpread {
+    pub span: Span,
+    pub fragment_name: String,
+    pub directives: Vec<Directive>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InlineFragment {
+    pub span: Span,
+    pub type_condition: Option<String>,
+    pub directives: Vec<Directive>,
+    pub selection_set: SelectionSet,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Directive {
+    pub span: Span,
+    pub name: String,
+    pub arguments: Vec<Argument>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Argument {
+    pub span: Span,
+    pub name: String,
+    pub value: Value,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Variable(String),
+    Int(String),
+    Float(String),
+    String(String),
+    Boolean(bool),
+    Null,
+    Enum(String),
+    List(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn as_string(&self) -> Option<String> {
+        match self {
+            Value::String(raw) => Some(decode_string(raw)),
+            _ => None,
+        }
+    }
+}
+
+fn decode_string(raw: &str) -> String {
+    if let Some(body) = raw
+        .strip_prefix("\"\"\"")
+        .and_then(|raw| raw.strip_suffix("\"\"\""))
+    {
+        return block_string(&body.replace("\\\"\"\"", "\"\"\""));
+    }
+    let body = &raw[1..raw.len() - 1];
+    let mut output = String::with_capacity(body.len());
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            output.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('b') => output.push('\u{8}'),
+            Some('f') => output.push('\u{c}'),
+            Some('n') => output.push('\n'),
+            Some('r') => output.push('\r'),
+            Some('t') => output.push('\t'),
+            Some('u') => {
+                let hex: String = chars.by_ref().take(4).collect();
+                output.extend(u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32));
+            }
+            Some(c) => output.push(c),
+            None => {}
+        }
+    }
+    output
+}
+
+fn block_string(body: &str) -> String {
+    let lines: Vec<&str> = body.lines().collect();
+    let indent = lines
+        .iter()
+        .skip(1)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let mut lines: Vec<&str> = lines
+        .iter()
+        .enumerate()
+        .map(|(index, line)| match index {
+            0 => line,
+            _ => &line[indent.min(line.len())..],
+        })
+        .collect();
+    while lines.first().is_some_and(|line| line.trim().is_empty()) {
+        lines.remove(0);
+    }
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+    lines.join("\n")
+}
@@ -0,0 +1,159 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::error::CompileError;
+use crate::schema::Schema;
+use crate::transformer::Transformer;
+
+#[derive(Error, Debug)]
+pub enum BuildError {
+    #[error("failed to access `{}`: {source}", path.display())]
+    Io { path: PathBuf, source: io::Error },
+    #[error("{}: {source}", path.display())]
+    Compile { path: PathBuf, source: CompileError },
+    #[error("OUT_DIR is not defined; run the build from a build script or set `out_dir`")]
+    OutDir,
+}
+
+/// Transforms queries from a build script, where passes registered with
+/// `register_pass` are visible, and writes them to `$OUT_DIR/discovery-query`
+/// for `#[discovery_query(query_path = "$OUT_DIR/...")]` to pick up.
+#[derive(Debug, Default)]
+pub struct QueryBuild {
+    transformer: Transformer,
+    schema_path: Option<PathBuf>,
+    query_paths: Vec<PathBuf>,
+    out_dir: Option<PathBuf>,
+}
+
+impl QueryBuild {
+    pub fn new(transformer: Transformer) -> Self {
+        Self {
+            transformer,
+            ..Self::default()
+        }
+    }
+
+    pub fn schema(mut self, path: impl Into<PathBuf>) -> Self {
+        self.schema_path = Some(path.into());
+        self
+    }
+
+    pub fn query(mut self, path: impl Into<PathBuf>) -> Self {
+        self.query_paths.push(path.into());
+        self
+    }
+
+    pub fn out_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.out_dir = Some(path.into());
+        self
+    }
+
+    pub fn run(self) -> Result<Vec<PathBuf>, BuildError> {
+        let out_dir = match self.out_dir {
+            Some(out_dir) => out_dir,
+            None => PathBuf::from(env::var_os("OUT_DIR").ok_or(BuildError::OutDir)?),
+        }
+        .join("discovery-query");
+        let mut transformer = self.transformer;
+        if let Some(path) = &self.schema_path {
+            println!("cargo:rerun-if-changed={}", path.display());
+            if path.extension().and_then(|extension| extension.to_str()) != Some("json") {
+                let schema = Schema::parse(&read(path)?).map_err(|source| BuildError::Compile {
+                    path: path.clone(),
+                    source,
+                })?;
+                transformer = transformer.schema(schema);
+            }
+        }
+        fs::create_dir_all(&out_dir).map_err(|source| BuildError::Io {
+            path: out_dir.clone(),
+            source,
+        })?;
+
+        let mut written = vec![];
+        for path in &self.query_paths {
+            println!("cargo:rerun-if-changed={}", path.display());
+            let query =
+                transformer
+                    .transform(&read(path)?)
+                    .map_err(|source| BuildError::Compile {
+                        path: path.clone(),
+                        source,
+                    })?;
+            let file_name = path.file_name().ok_or_else(|| BuildError::Io {
+                path: path.clone(),
+                source: io::Error::new(io::ErrorKind::InvalidInput, "not a file"),
+            })?;
+            let target = out_dir.join(file_name);
+            fs::write(&target, query).map_err(|source| BuildError::Io {
+                path: target.clone(),
+                source,
+            })?;
+            written.push(target);
+        }
+        Ok(written)
+    }
+}
+
+fn read(path: &Path) -> Result<String, BuildError> {
+    fs::read_to_string(path).map_err(|source| BuildError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edit::Edit;
+    use crate::pipeline::{register_pass, PassContext};
+
+    #[test]
+    fn run_registered_passes_from_build_script() {
+        register_pass("build-marker", |context: &PassContext| {
+            Ok(vec![Edit::insert(context.code.len(), "# built\n")])
+        });
+        let directory =
+            env::temp_dir().join(format!("discovery-query-build-{}", std::process::id()));
+        let source = directory.join("src");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(
+            source.join("schema.graphql"),
+            "type User { id: ID! login: String! }\ntype Query { viewer: User }\n",
+        )
+        .unwrap();
+        fs::write(
+            source.join("viewer.graphql"),
+            "query Viewer { viewer { login } }\n",
+        )
+        .unwrap();
+
+        let written = QueryBuild::new(Transformer::new().pass("build-marker"))
+            .schema(source.join("schema.graphql"))
+            .query(source.join("viewer.graphql"))
+            .out_dir(directory.join("out"))
+            .run()
+            .unwrap();
+
+        assert_eq!(
+            written,
+            vec![directory.join("out/discovery-query/viewer.graphql")]
+        );
+        assert_eq!(
+            fs::read_to_string(&written[0]).unwrap(),
+            "query Viewer { viewer { __typename login } }\n# built\n"
+        );
+        assert!(matches!(
+            QueryBuild::new(Transformer::new().pass("missing"))
+                .query(source.join("viewer.graphql"))
+                .out_dir(directory.join("out"))
+                .run(),
+            Err(BuildError::Compile { .. })
+        ));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,113 @@
+use crate::ast::*;
+use crate::error::CompileResult;
+use crate::parser::parse;
+use crate::printer::{print_with, PrintOptions};
+
+fn sort_value(value: &mut Value) {
+    match value {
+        Value::List(values) => values.iter_mut().for_each(sort_value),
+        Value::Object(fields) => {
+            fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+            fields.iter_mut().for_each(|(_, value)| sort_value(value));
+        }
+        _ => {}
+    }
+}
+
+fn sort_arguments(arguments: &mut [Argument]) {
+    arguments.sort_by(|a, b| a.name.cmp(&b.name));
+    for argument in arguments {
+        sort_value(&mut argument.value);
+    }
+}
+
+fn sort_directives(directives: &mut [Directive]) {
+    for directive in directives {
+        sort_arguments(&mut directive.arguments);
+    }
+}
+
+fn sort_selection_set(selection_set: &mut SelectionSet) {
+    for selection in &mut selection_set.selections {
+        match selection {
+            Selection::Field(field) => {
+                sort_arguments(&mut field.arguments);
+                sort_directives(&mut field.directives);
+                if let Some(selection_set) = &mut field.selection_set {
+                    sort_selection_set(selection_set);
+                }
+            }
+            Selection::FragmentSpread(spread) => sort_directives(&mut spread.directives),
+            Selection::InlineFragment(fragment) => {
+                sort_directives(&mut fragment.directives);
+                sort_selection_set(&mut fragment.selection_set);
+            }
+        }
+    }
+}
+
+pub fn canonicalize(code: &str) -> CompileResult<String> {
+    let mut document = parse(code)?;
+    for definition in &mut document.definitions {
+        match definition {
+            Definition::Operation(operation) => {
+                operation
+                    .variable_definitions
+                    .sort_by(|a, b| a.name.cmp(&b.name));
+                for definition in &mut operation.variable_definitions {
+                    if let Some(value) = &mut definition.default_value {
+                        sort_value(value);
+                    }
+                    sort_directives(&mut definition.directives);
+                }
+                sort_directives(&mut operation.directives);
+                sort_selection_set(&mut operation.selection_set);
+            }
+            Definition::Fragment(fragment) => {
+                sort_directives(&mut fragment.directives);
+                sort_selection_set(&mut fragment.selection_set);
+            }
+        }
+    }
+    Ok(print_with(
+        &document,
+        &PrintOptions::new().single_line(true),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonicalize_formatting() {
+        let canonical = "query Search($after: String, $term: String! = \"rust\") { \
+search(after: $after, filter: {language: RUST, stars: [1, 2]}, term: $term) @cached(scope: PUBLIC, ttl: 60) \
+{ ...Result } } fragment Result on SearchResult { total }";
+        assert_eq!(
+            canonicalize(
+                r#"
+# Search repositories
+query Search(
+  $term: String! = """
+    rust
+  """,
+  $after: String
+) {
+  search(term: $term, filter: { stars: [1, 2], language: RUST }, after: $after)
+    @cached(ttl: 60, scope: PUBLIC) {
+    ...Result
+  }
+}
+
+fragment Result on SearchResult {
+  total
+}
+"#
+            )
+            .unwrap(),
+            canonical
+        );
+        assert_eq!(canonicalize(canonical).unwrap(), canonical);
+    }
+}
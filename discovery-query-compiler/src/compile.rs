@@ -0,0 +1,139 @@
+use sha2::{Digest, Sha256};
+
+use crate::ast::OperationType;
+use crate::canonical::canonicalize;
+use crate::connection::{connections, strip_connection_directives, Connection};
+use crate::error::{CompileError, CompileResult};
+use crate::extract::extract_operation;
+use crate::parser::parse;
+use crate::printer::minify;
+use crate::registry::referenced_types;
+use crate::strip::strip_client_fields;
+use crate::transformer::Transformer;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledOperation {
+    pub name: String,
+    pub operation_type: OperationType,
+    pub document: String,
+    pub network_document: Option<String>,
+    pub canonical_document: Option<String>,
+    pub hash: Option<String>,
+    pub types: Vec<String>,
+    pub connections: Vec<Connection>,
+}
+
+impl Transformer {
+    pub fn compile(&self, code: &str) -> CompileResult<Vec<CompiledOperation>> {
+        let transformed = self.transform(code)?;
+        let parsed = parse(&transformed)?;
+
+        let mut operations = vec![];
+        for operation in parsed.operations() {
+            let name = operation
+                .name
+                .clone()
+                .ok_or_else(|| CompileError::Unsupported {
+                    span: operation.span,
+                    reason: "anonymous operations need a name to be compiled".to_string(),
+                })?;
+            let document = extract_operation(&transformed, &name)?;
+            let mut network_document = strip_client_fields(&document)?
+                .map(|network_document| strip_connection_directives(&network_document))
+                .transpose()?;
+            if self.minify {
+                network_document = network_document.as_deref().map(minify).transpose()?;
+            }
+            let canonical_document = network_document.as_deref().map(canonicalize).transpose()?;
+            let hash = canonical_document
+                .as_ref()
+                .map(|document| format!("{:x}", Sha256::digest(document)));
+            operations.push(CompiledOperation {
+                network_document,
+                canonical_document,
+                hash,
+                connections: connections(&parsed, operation)?,
+                types: referenced_types(&parsed, operation, self.schema.as_deref()),
+                operation_type: operation.operation_type,
+                document,
+                name,
+            });
+        }
+        Ok(operations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compile_network_documents() {
+        let code = "query Viewer {
+  viewer { login theme @client }
+}
+
+query Session { isLoggedIn @client }
+";
+        let operations = Transformer::new().compile(code).unwrap();
+
+        assert_eq!(operations.len(), 2);
+        assert_eq!(
+            operations[0].document,
+            "query Viewer {\n  viewer { __typename login theme @client }\n}\n"
+        );
+        assert_eq!(
+            operations[0].network_document.as_deref(),
+            Some("query Viewer {\n  viewer { __typename login }\n}\n")
+        );
+        assert_eq!(operations[1].name, "Session");
+        assert_eq!(operations[1].network_document, None);
+        assert_eq!(operations[1].hash, None);
+
+        let operations = Transformer::new().minify(true).compile(code).unwrap();
+        assert_eq!(
+            operations[0].network_document.as_deref(),
+            Some("query Viewer{viewer{__typename login}}")
+        );
+        assert!(matches!(
+            Transformer::new().compile("{ viewer { login } }"),
+            Err(CompileError::Unsupported { .. })
+        ));
+    }
+
+    #[test]
+    fn hash_canonical_documents() {
+        let compile = |code: &str| Transformer::new().compile(code).unwrap().remove(0);
+        let operation = compile(
+            "query Feed($first: Int, $after: String) { feed(first: $first, after: $after) { id } }",
+        );
+        let reformatted = compile(
+            "# feed
+query Feed(
+  $after: String
+  $first: Int
+) {
+  feed(after: $after, first: $first) {
+    id
+  }
+}
+",
+        );
+
+        assert_eq!(
+            operation.canonical_document.as_deref(),
+            Some("query Feed($after: String, $first: Int) { feed(after: $after, first: $first) { __typename id } }")
+        );
+        assert_eq!(
+            operation.hash.as_deref(),
+            Some(
+                format!(
+                    "{:x}",
+                    Sha256::digest(operation.canonical_document.as_ref().unwrap())
+                )
+                .as_str()
+            )
+        );
+        assert_eq!(operation.hash, reformatted.hash);
+    }
+}
@@ -0,0 +1,207 @@
+use crate::ast::*;
+use crate::edit::{self, Edit};
+use crate::error::{CompileError, CompileResult, Span};
+use crate::parser::parse;
+
+const CONNECTION: &str = "connection";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Connection {
+    pub path: Vec<String>,
+    pub key: String,
+    pub filter: Vec<String>,
+}
+
+impl Connection {
+    pub fn storage_key(&self, arguments: &[(&str, &str)]) -> String {
+        let filter: Vec<_> = self
+            .filter
+            .iter()
+            .filter_map(|name| {
+                let (_, value) = arguments.iter().find(|(argument, _)| argument == name)?;
+                Some(format!("\"{}\":{}", name, value))
+            })
+            .collect();
+        match filter.is_empty() {
+            true => self.key.clone(),
+            false => format!("{}({{{}}})", self.key, filter.join(",")),
+        }
+    }
+}
+
+fn connection(directive: &Directive, path: Vec<String>) -> CompileResult<Connection> {
+    let invalid = |reason: &str| CompileError::Validation {
+        span: directive.span,
+        reason: format!("@connection {}", reason),
+    };
+    let mut key = None;
+    let mut filter = vec![];
+    for argument in &directive.arguments {
+        match (argument.name.as_str(), &argument.value) {
+            ("key", value) => {
+                key = Some(
+                    value
+                        .as_string()
+                        .ok_or_else(|| invalid("key must be a string"))?,
+                )
+            }
+            ("filter", Value::List(values)) => {
+                filter = values
+                    .iter()
+                    .map(Value::as_string)
+                    .collect::<Option<_>>()
+                    .ok_or_else(|| invalid("filter must be a list of strings"))?
+            }
+            (name, _) => return Err(invalid(&format!("has an invalid argument `{}`", name))),
+        }
+    }
+    Ok(Connection {
+        path,
+        key: key.ok_or_else(|| invalid("requires a key"))?,
+        filter,
+    })
+}
+
+struct Collector<'a> {
+    document: &'a Document,
+    visited: Vec<&'a str>,
+    connections: Vec<Connection>,
+}
+
+impl<'a> Collector<'a> {
+    fn collect(&mut self, selection_set: &'a SelectionSet, path: &[String]) -> CompileResult<()> {
+        for selection in &selection_set.selections {
+            match selection {
+                Selection::Field(field) => {
+                    let mut path = path.to_vec();
+                    path.push(field.response_name().to_string());
+                    for directive in &field.directives {
+                        if directive.name == CONNECTION {
+                            self.connections.push(connection(directive, path.clone())?);
+                        }
+                    }
+                    if let Some(selection_set) = &field.selection_set {
+                        self.collect(selection_set, &path)?;
+                    }
+                }
+                Selection::InlineFragment(fragment) => {
+                    self.collect(&fragment.selection_set, path)?
+                }
+                Selection::FragmentSpread(spread) => {
+                    if self.visited.contains(&spread.fragment_name.as_str()) {
+                        continue;
+                    }
+                    self.visited.push(&spread.fragment_name);
+                    if let Some(fragment) = self.document.fragment(&spread.fragment_name) {
+                        self.collect(&fragment.selection_set, path)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn connections(
+    document: &Document,
+    operation: &OperationDefinition,
+) -> CompileResult<Vec<Connection>> {
+    let mut collector = Collector {
+        document,
+        visited: vec![],
+        connections: vec![],
+    };
+    collector.collect(&operation.selection_set, &[])?;
+    Ok(collector.connections)
+}
+
+fn directive_edits(code: &str, selection_set: &SelectionSet, edits: &mut Vec<Edit>) {
+    for selection in &selection_set.selections {
+        for directive in selection.directives() {
+            if directive.name == CONNECTION {
+                let start = code[..directive.span.start].trim_end().len();
+                edits.push(Edit::remove(Span::new(start, directive.span.end)));
+            }
+        }
+        match selection {
+            Selection::Field(field) => {
+                if let Some(selection_set) = &field.selection_set {
+                    directive_edits(code, selection_set, edits);
+                }
+            }
+            Selection::InlineFragment(fragment) => {
+                directive_edits(code, &fragment.selection_set, edits)
+            }
+            Selection::FragmentSpread(_) => {}
+        }
+    }
+}
+
+pub fn strip_connection_directives(code: &str) -> CompileResult<String> {
+    let document = parse(code)?;
+    let mut edits = vec![];
+    for definition in &document.definitions {
+        match definition {
+            Definition::Operation(operation) => {
+                directive_edits(code, &operation.selection_set, &mut edits)
+            }
+            Definition::Fragment(fragment) => {
+                directive_edits(code, &fragment.selection_set, &mut edits)
+            }
+        }
+    }
+    edit::apply(code, edits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODE: &str = r#"query Feed($type: FeedType!, $after: String) {
+  viewer {
+    ...FeedParts
+  }
+}
+
+fragment FeedParts on User {
+  feed(type: $type, first: 10, after: $after) @connection(key: "feed", filter: ["type"]) {
+    edges { node { id } }
+  }
+}
+"#;
+
+    #[test]
+    fn collect_connections() {
+        let document = parse(CODE).unwrap();
+        let operation = document.operation("Feed").unwrap();
+        let connections = connections(&document, operation).unwrap();
+
+        assert_eq!(
+            connections,
+            [Connection {
+                path: vec!["viewer".to_string(), "feed".to_string()],
+                key: "feed".to_string(),
+                filter: vec!["type".to_string()],
+            }]
+        );
+        assert_eq!(
+            connections[0].storage_key(&[("type", "\"TOP\""), ("after", "\"YXJyYXk=\"")]),
+            r#"feed({"type":"TOP"})"#
+        );
+
+        let document = parse("{ feed @connection(filter: [1]) { id } }").unwrap();
+        let operation = document.operations().next().unwrap();
+        assert!(matches!(
+            super::connections(&document, operation),
+            Err(CompileError::Validation { .. })
+        ));
+    }
+
+    #[test]
+    fn strip_connection_from_network_document() {
+        assert_eq!(
+            strip_connection_directives(CODE).unwrap(),
+            CODE.replace(r#" @connection(key: "feed", filter: ["type"])"#, "")
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use crate::error::{CompileError, CompileResult, Span};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    span: Span,
+    text: String,
+}
+
+impl Edit {
+    pub fn insert(offset: usize, text: impl Into<String>) -> Self {
+        Self::replace(Span::new(offset, offset), text)
+    }
+
+    pub fn remove(span: Span) -> Self {
+        Self::replace(span, "")
+    }
+
+    pub fn replace(span: Span, text: impl Into<String>) -> Self {
+        Self {
+            span,
+            text: text.into(),
+        }
+    }
+}
+
+pub(crate) fn remove_items(spans: &[Span], removed: &[bool]) -> Vec<Edit> {
+    let mut edits = vec![];
+    let Some(kept) = removed.iter().position(|removed| !removed) else {
+        return edits;
+    };
+    if kept > 0 {
+        edits.push(Edit::remove(Span::new(spans[0].start, spans[kept].start)));
+    }
+    for index in kept + 1..spans.len() {
+        if removed[index] {
+            edits.push(Edit::remove(Span::new(
+                spans[index - 1].end,
+                spans[index].end,
+            )));
+        }
+    }
+    edits
+}
+
+fn invalid(span: Span, reason: &str) -> CompileError {
+    CompileError::Edit {
+        span,
+        reason: reason.to_string(),
+    }
+}
+
+pub(crate) fn apply(code: &str, mut edits: Vec<Edit>) -> CompileResult<String> {
+    edits.sort_by_key(|edit| (edit.span.start, edit.span.end));
+    let mut output = String::with_capacity(code.len() + edits.len() * 16);
+    let mut cursor = 0;
+    for edit in edits {
+        let Span { start, end } = edit.span;
+        if start > end || end > code.len() {
+            return Err(invalid(edit.span, "span is out of bounds"));
+        }
+        if !code.is_char_boundary(start) || !code.is_char_boundary(end) {
+            return Err(invalid(edit.span, "span is not on a character boundary"));
+        }
+        if start < cursor {
+            return Err(invalid(edit.span, "span overlaps a previous edit"));
+        }
+        output.push_str(&code[cursor..start]);
+        output.push_str(&edit.text);
+        cursor = end;
+    }
+    output.push_str(&code[cursor..]);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reject_invalid_edits() {
+        let code = "{ héros }";
+        assert_eq!(
+            apply(
+                code,
+                vec![
+                    Edit::replace(Span::new(2, 8), "hero"),
+                    Edit::insert(2, "id "),
+                    Edit::insert(10, " ")
+                ]
+            )
+            .unwrap(),
+            "{ id hero } "
+        );
+
+        let reason = |edit: Edit| apply(code, vec![edit]).unwrap_err().reason().to_string();
+        assert_eq!(
+            reason(Edit::remove(Span::new(4, 20))),
+            "span is out of bounds"
+        );
+        assert_eq!(
+            reason(Edit::remove(Span::new(5, 4))),
+            "span is out of bounds"
+        );
+        assert_eq!(
+            reason(Edit::insert(4, "x")),
+            "span is not on a character boundary"
+        );
+        assert!(matches!(
+            apply(
+                code,
+                vec![Edit::remove(Span::new(2, 6)), Edit::insert(3, "x")]
+            ),
+            Err(CompileError::Edit { .. })
+        ));
+    }
+}
@@ -0,0 +1,65 @@
+use std::fmt;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    pub fn line_column(&self, code: &str) -> (usize, usize) {
+        let before = &code[..self.start.min(code.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+        (line, column)
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum CompileError {
+    #[error("parse error at {span}: {reason}")]
+    Parse { span: Span, reason: String },
+    #[error("unsupported construct at {span}: {reason}")]
+    Unsupported { span: Span, reason: String },
+    #[error("unresolved name at {span}: {reason}")]
+    Unresolved { span: Span, reason: String },
+    #[error("validation error at {span}: {reason}")]
+    Validation { span: Span, reason: String },
+    #[error("invalid edit at {span}: {reason}")]
+    Edit { span: Span, reason: String },
+}
+
+impl CompileError {
+    pub fn span(&self) -> Span {
+        match self {
+            CompileError::Parse { span, .. }
+            | CompileError::Unsupported { span, .. }
+            | CompileError::Unresolved { span, .. }
+            | CompileError::Validation { span, .. }
+            | CompileError::Edit { span, .. } => *span,
+        }
+    }
+
+    pub fn reason(&self) -> &str {
+        match self {
+            CompileError::Parse { reason, .. }
+            | CompileError::Unsupported { reason, .. }
+            | CompileError::Unresolved { reason, .. }
+            | CompileError::Validation { reason, .. }
+            | CompileError::Edit { reason, .. } => reason,
+        }
+    }
+}
+
+pub type CompileResult<T> = std::result::Result<T, CompileError>;
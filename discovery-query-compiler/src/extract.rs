@@ -0,0 +1,107 @@
+use crate::ast::{Document, FragmentDefinition, SelectionSet};
+use crate::error::{CompileError, CompileResult, Span};
+use crate::parser::parse;
+
+pub(crate) fn used_fragments<'a>(
+    document: &'a Document,
+    selection_set: &'a SelectionSet,
+) -> CompileResult<Vec<&'a FragmentDefinition>> {
+    let mut used: Vec<&FragmentDefinition> = vec![];
+    let mut pending = selection_set.fragment_spreads();
+    while let Some(spread) = pending.pop() {
+        if used
+            .iter()
+            .any(|fragment| fragment.name == spread.fragment_name)
+        {
+            continue;
+        }
+        let fragment =
+            document
+                .fragment(&spread.fragment_name)
+                .ok_or_else(|| CompileError::Unresolved {
+                    span: spread.span,
+                    reason: format!("unknown fragment `{}`", spread.fragment_name),
+                })?;
+        pending.extend(fragment.selection_set.fragment_spreads());
+        used.push(fragment);
+    }
+    used.sort_by_key(|fragment| fragment.span.start);
+    Ok(used)
+}
+
+pub fn extract_operation(code: &str, name: &str) -> CompileResult<String> {
+    let document = parse(code)?;
+    let operation = document
+        .operation(name)
+        .ok_or_else(|| CompileError::Unresolved {
+            span: Span::new(0, code.len()),
+            reason: format!("unknown operation `{}`", name),
+        })?;
+
+    let mut output = code[operation.span.start..operation.span.end].to_string();
+    for fragment in used_fragments(&document, &operation.selection_set)? {
+        output.push_str("\n\n");
+        output.push_str(&code[fragment.span.start..fragment.span.end]);
+    }
+    output.push('\n');
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformer::add_type_field;
+
+    #[test]
+    fn extract_operation_with_used_fragments() {
+        let code = "query Viewer {
+  viewer { ...UserParts }
+}
+
+fragment AvatarParts on Avatar { url }
+
+query Repository($name: String!) {
+  repository(name: $name) { name }
+}
+
+fragment UserParts on User {
+  login
+  avatar { ...AvatarParts }
+}
+
+fragment Unused on User { id }
+";
+        assert_eq!(
+            extract_operation(&add_type_field(code).unwrap(), "Viewer").unwrap(),
+            "query Viewer {
+  viewer { __typename ...UserParts }
+}
+
+fragment AvatarParts on Avatar { __typename url }
+
+fragment UserParts on User {
+  __typename
+  login
+  avatar { __typename ...AvatarParts }
+}
+"
+        );
+        assert_eq!(
+            extract_operation(code, "Repository").unwrap(),
+            "query Repository($name: String!) {
+  repository(name: $name) { name }
+}
+"
+        );
+        assert_eq!(
+            extract_operation(code, "Missing").unwrap_err().reason(),
+            "unknown operation `Missing`"
+        );
+        assert_eq!(
+            extract_operation("query A { ...Missing }", "A")
+                .unwrap_err()
+                .span(),
+            Span::new(10, 20)
+        );
+    }
+}
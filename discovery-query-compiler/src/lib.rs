@@ -1,4 +1,41 @@
+pub mod ast;
+mod build;
+mod canonical;
+mod compile;
+mod connection;
+mod edit;
+mod error;
+mod extract;
+mod lint;
+mod manifest;
+mod parser;
+mod pipeline;
+mod printer;
+mod registry;
+mod schema;
+mod strip;
 mod transformer;
+mod validate;
+
+pub use build::{BuildError, QueryBuild};
+pub use canonical::canonicalize;
+pub use compile::CompiledOperation;
+pub use connection::{connections, strip_connection_directives, Connection};
+pub use edit::Edit;
+pub use error::{CompileError, CompileResult, Span};
+pub use extract::extract_operation;
+pub use lint::{lint, remove_unused, Lint};
+pub use manifest::{apollo_manifest, relay_query_map};
+pub use parser::parse;
+pub use pipeline::{register_pass, walk, Pass, PassContext, TransformPipeline, Visitor};
+pub use printer::{minify, print, print_with, PrintOptions};
+pub use registry::generate_registry;
+pub use schema::{FieldDefinition, InputValueDefinition, Schema, TypeDefinition, TypeKind};
+pub use strip::strip_client_fields;
+pub use transformer::{
+    add_type_field, AddKeyFields, AddTypename, KeyFields, NameAnonymousOperation, Transformer,
+};
+pub use validate::validate;
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,327 @@
+use std::fmt;
+
+use crate::ast::*;
+use crate::edit::{self, Edit};
+use crate::error::{CompileResult, Span};
+use crate::extract::used_fragments;
+use crate::parser::parse;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Lint {
+    UnusedVariable { span: Span, name: String },
+    UnusedFragment { span: Span, name: String },
+    ConditionalSelectionSet { span: Span },
+}
+
+impl Lint {
+    pub fn span(&self) -> Span {
+        match self {
+            Lint::UnusedVariable { span, .. }
+            | Lint::UnusedFragment { span, .. }
+            | Lint::ConditionalSelectionSet { span } => *span,
+        }
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Lint::UnusedVariable { name, .. } => write!(f, "variable `${}` is never used", name),
+            Lint::UnusedFragment { name, .. } => write!(f, "fragment `{}` is never used", name),
+            Lint::ConditionalSelectionSet { .. } => {
+                write!(f, "every selection in this selection set is conditional")
+            }
+        }
+    }
+}
+
+fn value_variables<'a>(value: &'a Value, variables: &mut Vec<&'a str>) {
+    match value {
+        Value::Variable(name) => variables.push(name),
+        Value::List(values) => {
+            for value in values {
+                value_variables(value, variables);
+            }
+        }
+        Value::Object(fields) => {
+            for (_, value) in fields {
+                value_variables(value, variables);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn directive_variables<'a>(directives: &'a [Directive], variables: &mut Vec<&'a str>) {
+    for directive in directives {
+        for argument in &directive.arguments {
+            value_variables(&argument.value, variables);
+        }
+    }
+}
+
+fn selection_variables<'a>(selection_set: &'a SelectionSet, variables: &mut Vec<&'a str>) {
+    for selection in &selection_set.selections {
+        directive_variables(selection.directives(), variables);
+        match selection {
+            Selection::Field(field) => {
+                for argument in &field.arguments {
+                    value_variables(&argument.value, variables);
+                }
+                if let Some(selection_set) = &field.selection_set {
+                    selection_variables(selection_set, variables);
+                }
+            }
+            Selection::InlineFragment(fragment) => {
+                selection_variables(&fragment.selection_set, variables)
+            }
+            Selection::FragmentSpread(_) => {}
+        }
+    }
+}
+
+fn conditional_selection_sets(selection_set: &SelectionSet, lints: &mut Vec<Lint>) {
+    if selection_set
+        .selections
+        .iter()
+        .all(Selection::is_conditional)
+    {
+        lints.push(Lint::ConditionalSelectionSet {
+            span: selection_set.span,
+        });
+    }
+    for selection in &selection_set.selections {
+        match selection {
+            Selection::Field(field) => {
+                if let Some(selection_set) = &field.selection_set {
+                    conditional_selection_sets(selection_set, lints);
+                }
+            }
+            Selection::InlineFragment(fragment) => {
+                conditional_selection_sets(&fragment.selection_set, lints)
+            }
+            Selection::FragmentSpread(_) => {}
+        }
+    }
+}
+
+fn used_variables<'a>(
+    document: &'a Document,
+    operation: &'a OperationDefinition,
+) -> CompileResult<Vec<&'a str>> {
+    let mut variables = vec![];
+    directive_variables(&operation.directives, &mut variables);
+    selection_variables(&operation.selection_set, &mut variables);
+    for fragment in used_fragments(document, &operation.selection_set)? {
+        directive_variables(&fragment.directives, &mut variables);
+        selection_variables(&fragment.selection_set, &mut variables);
+    }
+    Ok(variables)
+}
+
+pub fn lint(document: &Document) -> CompileResult<Vec<Lint>> {
+    let mut lints = vec![];
+    let mut spread: Vec<&str> = vec![];
+    for operation in document.operations() {
+        let variables = used_variables(document, operation)?;
+        for definition in &operation.variable_definitions {
+            if !variables.contains(&definition.name.as_str()) {
+                lints.push(Lint::UnusedVariable {
+                    span: definition.span,
+                    name: definition.name.clone(),
+                });
+            }
+        }
+        spread.extend(
+            used_fragments(document, &operation.selection_set)?
+                .into_iter()
+                .map(|fragment| fragment.name.as_str()),
+        );
+    }
+    for definition in &document.definitions {
+        match definition {
+            Definition::Operation(operation) => {
+                conditional_selection_sets(&operation.selection_set, &mut lints)
+            }
+            Definition::Fragment(fragment) => {
+                conditional_selection_sets(&fragment.selection_set, &mut lints)
+            }
+        }
+    }
+    for fragment in document.fragments() {
+        if !spread.contains(&fragment.name.as_str()) {
+            lints.push(Lint::UnusedFragment {
+                span: fragment.span,
+                name: fragment.name.clone(),
+            });
+        }
+    }
+    Ok(lints)
+}
+
+fn remove_variables(
+    code: &str,
+    operation: &OperationDefinition,
+    lints: &[Lint],
+    edits: &mut Vec<Edit>,
+) {
+    let definitions = &operation.variable_definitions;
+    let unused: Vec<bool> = definitions
+        .iter()
+        .map(|definition| {
+            lints.contains(&Lint::UnusedVariable {
+                span: definition.span,
+                name: definition.name.clone(),
+            })
+        })
+        .collect();
+    if unused.iter().all(|unused| *unused) {
+        if let (Some(first), Some(last)) = (definitions.first(), definitions.last()) {
+            let start = code[..first.span.start]
+                .rfind('(')
+                .unwrap_or(first.span.start);
+            let end = code[last.span.end..]
+                .find(')')
+                .map_or(last.span.end, |end| last.span.end + end + 1);
+            edits.push(Edit::remove(Span::new(start, end)));
+        }
+        return;
+    }
+    let spans: Vec<_> = definitions
+        .iter()
+        .map(|definition| definition.span)
+        .collect();
+    edits.extend(edit::remove_items(&spans, &unused));
+}
+
+pub fn remove_unused(code: &str) -> CompileResult<String> {
+    let document = parse(code)?;
+    let lints = lint(&document)?;
+
+    let mut edits = vec![];
+    for (index, definition) in document.definitions.iter().enumerate() {
+        match definition {
+            Definition::Operation(operation) => {
+                remove_variables(code, operation, &lints, &mut edits)
+            }
+            Definition::Fragment(fragment) => {
+                let unused = Lint::UnusedFragment {
+                    span: fragment.span,
+                    name: fragment.name.clone(),
+                };
+                if !lints.contains(&unused) {
+                    continue;
+                }
+                let span = match index.checked_sub(1) {
+                    Some(previous) => {
+                        Span::new(document.definitions[previous].span().end, fragment.span.end)
+                    }
+                    None => Span::new(
+                        fragment.span.start,
+                        document
+                            .definitions
+                            .get(1)
+                            .map_or(code.len(), |next| next.span().start),
+                    ),
+                };
+                edits.push(Edit::remove(span));
+            }
+        }
+    }
+    edit::apply(code, edits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODE: &str = "query Viewer($login: String!, $first: Int, $unused: Boolean) {
+  user(login: $login) { ...UserParts }
+}
+
+query Search(
+  $term: String!
+  $after: String
+) {
+  search(term: $term) { total }
+}
+
+fragment UserParts on User {
+  repositories(first: $first) { ...RepositoryParts }
+}
+
+fragment RepositoryParts on Repository { name }
+
+fragment Orphan on User { ...Nested }
+
+fragment Nested on User { login }
+";
+
+    #[test]
+    fn report_unused_definitions() {
+        let lints: Vec<String> = lint(&parse(CODE).unwrap())
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            lints,
+            [
+                "variable `$unused` is never used",
+                "variable `$after` is never used",
+                "fragment `Orphan` is never used",
+                "fragment `Nested` is never used",
+            ]
+        );
+    }
+
+    #[test]
+    fn remove_unused_definitions() {
+        assert_eq!(
+            remove_unused(CODE).unwrap(),
+            "query Viewer($login: String!, $first: Int) {
+  user(login: $login) { ...UserParts }
+}
+
+query Search(
+  $term: String!
+) {
+  search(term: $term) { total }
+}
+
+fragment UserParts on User {
+  repositories(first: $first) { ...RepositoryParts }
+}
+
+fragment RepositoryParts on Repository { name }
+"
+        );
+        assert_eq!(
+            remove_unused("query A($a: Int, $b: Int) { a }").unwrap(),
+            "query A { a }"
+        );
+        assert_eq!(
+            remove_unused("query A($a: Int, $b: Int) { a(b: $b) }").unwrap(),
+            "query A($b: Int) { a(b: $b) }"
+        );
+    }
+
+    #[test]
+    fn report_conditional_selection_sets() {
+        let code = "query Viewer($full: Boolean!) {
+  viewer {
+    login @include(if: $full)
+    ... @skip(if: $full) { name }
+  }
+}
+";
+        let start = code.find("{\n    login").unwrap();
+        let end = code.rfind("}\n}").unwrap() + 1;
+        assert_eq!(
+            lint(&parse(code).unwrap()).unwrap(),
+            [Lint::ConditionalSelectionSet {
+                span: Span::new(start, end)
+            }]
+        );
+    }
+}
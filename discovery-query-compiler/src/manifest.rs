@@ -0,0 +1,95 @@
+use serde_json::{json, Map, Value};
+
+use crate::compile::CompiledOperation;
+
+fn persisted(
+    operations: &[CompiledOperation],
+) -> impl Iterator<Item = (&CompiledOperation, &str, &str)> {
+    operations.iter().filter_map(|operation| {
+        Some((
+            operation,
+            operation.hash.as_deref()?,
+            operation.canonical_document.as_deref()?,
+        ))
+    })
+}
+
+pub fn apollo_manifest(operations: &[CompiledOperation]) -> String {
+    let operations: Vec<Value> = persisted(operations)
+        .map(|(operation, hash, document)| {
+            json!({
+                "id": hash,
+                "name": operation.name,
+                "type": operation.operation_type.as_str(),
+                "body": document,
+            })
+        })
+        .collect();
+    let manifest = json!({
+        "format": "apollo-persisted-query-manifest",
+        "version": 1,
+        "operations": operations,
+    });
+    serde_json::to_string_pretty(&manifest).unwrap()
+}
+
+pub fn relay_query_map(operations: &[CompiledOperation]) -> String {
+    let query_map: Map<String, Value> = persisted(operations)
+        .map(|(_, hash, document)| (hash.to_string(), Value::String(document.to_string())))
+        .collect();
+    serde_json::to_string_pretty(&query_map).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformer::Transformer;
+
+    #[test]
+    fn generate_manifests() {
+        let operations = Transformer::new()
+            .compile(
+                "query Viewer { viewer { login } }
+
+mutation Logout { logout }
+
+query Session { isLoggedIn @client }
+",
+            )
+            .unwrap();
+        let viewer = &operations[0];
+        let logout = &operations[1];
+
+        let manifest: Value = serde_json::from_str(&apollo_manifest(&operations)).unwrap();
+        assert_eq!(
+            manifest,
+            json!({
+                "format": "apollo-persisted-query-manifest",
+                "version": 1,
+                "operations": [
+                    {
+                        "id": viewer.hash,
+                        "name": "Viewer",
+                        "type": "query",
+                        "body": "query Viewer { viewer { __typename login } }",
+                    },
+                    {
+                        "id": logout.hash,
+                        "name": "Logout",
+                        "type": "mutation",
+                        "body": "mutation Logout { logout }",
+                    },
+                ],
+            })
+        );
+
+        let query_map: Value = serde_json::from_str(&relay_query_map(&operations)).unwrap();
+        assert_eq!(
+            query_map,
+            json!({
+                viewer.hash.as_deref().unwrap(): viewer.canonical_document,
+                logout.hash.as_deref().unwrap(): logout.canonical_document,
+            })
+        );
+    }
+}
@@ -0,0 +1,816 @@
+use crate::ast::*;
+use crate::error::{CompileError, CompileResult, Span};
+use crate::schema::{FieldDefinition, InputValueDefinition, TypeDefinition, TypeKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind {
+    Punctuator,
+    Name,
+    Int,
+    Float,
+    String,
+}
+
+pub(crate) enum TypeSystemDefinition {
+    Schema(Vec<(OperationType, String)>),
+    Type(TypeDefinition),
+    Extension(TypeDefinition, Span),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Token {
+    kind: TokenKind,
+    span: Span,
+}
+
+fn parse_error(span: Span, reason: impl Into<String>) -> CompileError {
+    CompileError::Parse {
+        span,
+        reason: reason.into(),
+    }
+}
+
+fn tokenize(code: &str) -> CompileResult<Vec<Token>> {
+    let bytes = code.as_bytes();
+    let mut tokens = vec![];
+    let mut offset = 0;
+    while let Some(c) = code[offset..].chars().next() {
+        let start = offset;
+        let kind = match c {
+            ' ' | '\t' | '\n' | '\r' | ',' | '\u{feff}' => {
+                offset += c.len_utf8();
+                continue;
+            }
+            '#' => {
+                offset = code[offset..]
+                    .find(['\n', '\r'])
+                    .map_or(code.len(), |end| offset + end);
+                continue;
+            }
+            '!' | '$' | '&' | '(' | ')' | ':' | '=' | '@' | '[' | ']' | '{' | '|' | '}' => {
+                offset += 1;
+                TokenKind::Punctuator
+            }
+            '.' if code[offset..].starts_with("...") => {
+                offset += 3;
+                TokenKind::Punctuator
+            }
+            '_' | 'a'..='z' | 'A'..='Z' => {
+                offset += 1;
+                while offset < bytes.len()
+                    && (bytes[offset] == b'_' || bytes[offset].is_ascii_alphanumeric())
+                {
+                    offset += 1;
+                }
+                TokenKind::Name
+            }
+            '-' | '0'..='9' => {
+                let digits = |offset: &mut usize| {
+                    let start = *offset;
+                    while *offset < bytes.len() && bytes[*offset].is_ascii_digit() {
+                        *offset += 1;
+                    }
+                    *offset > start
+                };
+                offset += usize::from(c == '-');
+                let mut kind = TokenKind::Int;
+                let integer = offset;
+                let mut valid = digits(&mut offset);
+                valid &= bytes.get(integer) != Some(&b'0') || offset == integer + 1;
+                if bytes.get(offset) == Some(&b'.') {
+                    offset += 1;
+                    kind = TokenKind::Float;
+                    valid &= digits(&mut offset);
+                }
+                if matches!(bytes.get(offset), Some(b'e' | b'E')) {
+                    offset += 1;
+                    if matches!(bytes.get(offset), Some(b'+' | b'-')) {
+                        offset += 1;
+                    }
+                    kind = TokenKind::Float;
+                    valid &= digits(&mut offset);
+                }
+                // A number can not be directly followed by a name or a dot,
+                // so `123abc` is one bad token rather than `123` and `abc`.
+                while offset < bytes.len()
+                    && (bytes[offset] == b'_'
+                        || bytes[offset] == b'.'
+                        || bytes[offset].is_ascii_alphanumeric())
+                {
+                    valid = false;
+                    offset += 1;
+                }
+                if !valid {
+                    return Err(parse_error(
+                        Span::new(start, offset),
+                        "invalid number literal",
+                    ));
+                }
+                kind
+            }
+            '"' if code[offset..].starts_with("\"\"\"") => {
+                let body = offset + 3;
+                let mut end = None;
+                let mut cursor = body;
+                while let Some(index) = code[cursor..].find("\"\"\"") {
+                    let index = cursor + index;
+                    if code[..index].ends_with('\\') {
+                        cursor = index + 3;
+                        continue;
+                    }
+                    end = Some(index + 3);
+                    break;
+                }
+                offset = end.ok_or_else(|| {
+                    parse_error(Span::new(start, code.len()), "unterminated block string")
+                })?;
+                TokenKind::String
+            }
+            '"' => {
+                offset += 1;
+                loop {
+                    match bytes.get(offset) {
+                        Some(b'"') => break,
+                        Some(b'\\') => {
+                            let escape = match bytes.get(offset + 1) {
+                                Some(b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't') => 2,
+                                Some(b'u')
+                                    if bytes.len() >= offset + 6
+                                        && bytes[offset + 2..offset + 6]
+                                            .iter()
+                                            .all(u8::is_ascii_hexdigit) =>
+                                {
+                                    6
+                                }
+                                _ => {
+                                    return Err(parse_error(
+                                        Span::new(offset, (offset + 2).min(code.len())),
+                                        "invalid escape sequence",
+                                    ))
+                                }
+                            };
+                            offset += escape;
+                        }
+                        Some(b'\n' | b'\r') | None => {
+                            return Err(parse_error(
+                                Span::new(start, offset),
+                                "unterminated string",
+                            ))
+                        }
+                        Some(_) => offset += 1,
+                    }
+                }
+                offset += 1;
+                TokenKind::String
+            }
+            c => {
+                return Err(parse_error(
+                    Span::new(start, start + c.len_utf8()),
+                    format!("unexpected character `{}`", c),
+                ))
+            }
+        };
+        tokens.push(Token {
+            kind,
+            span: Span::new(start, offset),
+        });
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    code: &'a str,
+    tokens: Vec<Token>,
+    cursor: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn text(&self, token: Token) -> &'a str {
+        &self.code[token.span.start..token.span.end]
+    }
+
+    fn peek(&self) -> Option<Token> {
+        self.tokens.get(self.cursor).copied()
+    }
+
+    fn peek_is(&self, text: &str) -> bool {
+        self.peek()
+            .is_some_and(|token| token.kind != TokenKind::String && self.text(token) == text)
+    }
+
+    fn peek_kind(&self, kind: TokenKind) -> bool {
+        self.peek().is_some_and(|token| token.kind == kind)
+    }
+
+    fn end(&self) -> usize {
+        self.tokens
+            .get(self.cursor.saturating_sub(1))
+            .map_or(0, |token| token.span.end)
+    }
+
+    fn start(&self) -> usize {
+        self.peek()
+            .map_or(self.code.len(), |token| token.span.start)
+    }
+
+    fn span_from(&self, start: usize) -> Span {
+        Span::new(start, self.end())
+    }
+
+    fn unexpected(&self, expected: &str) -> CompileError {
+        match self.peek() {
+            Some(token) => parse_error(
+                token.span,
+                format!("expected {}, found `{}`", expected, self.text(token)),
+            ),
+            None => parse_error(
+                Span::new(self.code.len(), self.code.len()),
+                format!("expected {}, found end of input", expected),
+            ),
+        }
+    }
+
+    fn bump(&mut self) -> Token {
+        let token = self.tokens[self.cursor];
+        self.cursor += 1;
+        token
+    }
+
+    fn eat(&mut self, text: &str) -> bool {
+        let matched = self.peek_is(text);
+        if matched {
+            self.cursor += 1;
+        }
+        matched
+    }
+
+    fn expect(&mut self, text: &str) -> CompileResult<()> {
+        match self.eat(text) {
+            true => Ok(()),
+            false => Err(self.unexpected(&format!("`{}`", text))),
+        }
+    }
+
+    fn name(&mut self) -> CompileResult<String> {
+        match self.peek_kind(TokenKind::Name) {
+            true => {
+                let token = self.bump();
+                Ok(self.text(token).to_string())
+            }
+            false => Err(self.unexpected("a name")),
+        }
+    }
+
+    fn document(&mut self) -> CompileResult<Document> {
+        let mut definitions = vec![];
+        while let Some(token) = self.peek() {
+            let definition = match (token.kind, self.text(token)) {
+                (TokenKind::Punctuator, "{")
+                | (TokenKind::Name, "query" | "mutation" | "subscription") => {
+                    Definition::Operation(self.operation()?)
+                }
+                (TokenKind::Name, "fragment") => Definition::Fragment(self.fragment()?),
+                (
+                    TokenKind::Name,
+                    "schema" | "scalar" | "type" | "interface" | "union" | "enum" | "input"
+                    | "directive" | "extend",
+                )
+                | (TokenKind::String, _) => {
+                    return Err(CompileError::Unsupported {
+                        span: token.span,
+                        reason: "type system definitions can not be transformed".to_string(),
+                    })
+                }
+                _ => return Err(self.unexpected("a definition")),
+            };
+            definitions.push(definition);
+        }
+        if definitions.is_empty() {
+            return Err(self.unexpected("a definition"));
+        }
+        Ok(Document { definitions })
+    }
+
+    fn type_system_document(&mut self) -> CompileResult<Vec<TypeSystemDefinition>> {
+        let mut definitions = vec![];
+        while self.peek().is_some() {
+            let start = self.start();
+            self.description();
+            let extend = self.eat("extend");
+            let keyword_start = self.start();
+            let definition = match self.name()?.as_str() {
+                "schema" => {
+                    self.directives(true)?;
+                    let mut roots = vec![];
+                    if self.eat("{") {
+                        while !self.eat("}") {
+                            let start = self.start();
+                            let operation_type = match self.name()?.as_str() {
+                                "query" => OperationType::Query,
+                                "mutation" => OperationType::Mutation,
+                                "subscription" => OperationType::Subscription,
+                                _ => {
+                                    return Err(parse_error(
+                                        self.span_from(start),
+                                        "expected an operation type",
+                                    ))
+                                }
+                            };
+                            self.expect(":")?;
+                            roots.push((operation_type, self.name()?));
+                        }
+                    }
+                    TypeSystemDefinition::Schema(roots)
+                }
+                "directive" => {
+                    self.expect("@")?;
+                    self.name()?;
+                    self.input_values("(", ")")?;
+                    self.eat("repeatable");
+                    self.expect("on")?;
+                    self.eat("|");
+                    self.name()?;
+                    while self.eat("|") {
+                        self.name()?;
+                    }
+                    continue;
+                }
+                keyword => {
+                    let definition = self.type_definition(keyword, keyword_start)?;
+                    match extend {
+                        true => TypeSystemDefinition::Extension(definition, self.span_from(start)),
+                        false => TypeSystemDefinition::Type(definition),
+                    }
+                }
+            };
+            definitions.push(definition);
+        }
+        Ok(definitions)
+    }
+
+    fn description(&mut self) {
+        if self.peek_kind(TokenKind::String) {
+            self.bump();
+        }
+    }
+
+    fn type_definition(&mut self, keyword: &str, start: usize) -> CompileResult<TypeDefinition> {
+        let kind = match keyword {
+            "scalar" => TypeKind::Scalar,
+            "type" => TypeKind::Object,
+            "interface" => TypeKind::Interface,
+            "union" => TypeKind::Union,
+            "enum" => TypeKind::Enum,
+            "input" => TypeKind::InputObject,
+            _ => {
+                return Err(parse_error(
+                    self.span_from(start),
+                    "expected a type system definition",
+                ))
+            }
+        };
+        let mut definition = TypeDefinition::new(&self.name()?, kind);
+        if self.eat("implements") {
+            self.eat("&");
+            definition.interfaces.push(self.name()?);
+            while self.eat("&") {
+                definition.interfaces.push(self.name()?);
+            }
+        }
+        self.directives(true)?;
+        match kind {
+            TypeKind::Scalar => {}
+            TypeKind::Object | TypeKind::Interface => {
+                if self.eat("{") {
+                    while !self.eat("}") {
+                        self.description();
+                        let name = self.name()?;
+                        let arguments = self.input_values("(", ")")?;
+                        self.expect(":")?;
+                        let ty = self.ty()?;
+                        self.directives(true)?;
+                        definition.fields.push(FieldDefinition {
+                            name,
+                            arguments,
+                            ty,
+                        });
+                    }
+                }
+            }
+            TypeKind::Union => {
+                if self.eat("=") {
+                    self.eat("|");
+                    definition.members.push(self.name()?);
+                    while self.eat("|") {
+                        definition.members.push(self.name()?);
+                    }
+                }
+            }
+            TypeKind::Enum => {
+                if self.eat("{") {
+                    while !self.eat("}") {
+                        self.description();
+                        definition.values.push(self.name()?);
+                        self.directives(true)?;
+                    }
+                }
+            }
+            TypeKind::InputObject => definition.input_fields = self.input_values("{", "}")?,
+        }
+        Ok(definition)
+    }
+
+    fn input_values(
+        &mut self,
+        open: &str,
+        close: &str,
+    ) -> CompileResult<Vec<InputValueDefinition>> {
+        let mut values = vec![];
+        if !self.eat(open) {
+            return Ok(values);
+        }
+        while !self.eat(close) {
+            self.description();
+            let name = self.name()?;
+            self.expect(":")?;
+            let ty = self.ty()?;
+            let has_default = self.eat("=");
+            if has_default {
+                self.value(true)?;
+            }
+            self.directives(true)?;
+            values.push(InputValueDefinition {
+                name,
+                ty,
+                has_default,
+            });
+        }
+        Ok(values)
+    }
+
+    fn operation(&mut self) -> CompileResult<OperationDefinition> {
+        let start = self.start();
+        if self.peek_is("{") {
+            return Ok(OperationDefinition {
+                operation_type: OperationType::Query,
+                name: None,
+                variable_definitions: vec![],
+                directives: vec![],
+                selection_set: self.selection_set()?,
+                span: self.span_from(start),
+            });
+        }
+        let operation_type = match self.name()?.as_str() {
+            "mutation" => OperationType::Mutation,
+            "subscription" => OperationType::Subscription,
+            _ => OperationType::Query,
+        };
+        let name = match self.peek_kind(TokenKind::Name) {
+            true => Some(self.name()?),
+            false => None,
+        };
+        let variable_definitions = self.variable_definitions()?;
+        let directives = self.directives(false)?;
+        let selection_set = self.selection_set()?;
+        Ok(OperationDefinition {
+            span: self.span_from(start),
+            operation_type,
+            name,
+            variable_definitions,
+            directives,
+            selection_set,
+        })
+    }
+
+    fn fragment(&mut self) -> CompileResult<FragmentDefinition> {
+        let start = self.start();
+        self.expect("fragment")?;
+        if self.peek_is("on") {
+            return Err(self.unexpected("a fragment name"));
+        }
+        let name = self.name()?;
+        self.expect("on")?;
+        let type_condition = self.name()?;
+        let directives = self.directives(false)?;
+        let selection_set = self.selection_set()?;
+        Ok(FragmentDefinition {
+            span: self.span_from(start),
+            name,
+            type_condition,
+            directives,
+            selection_set,
+        })
+    }
+
+    fn variable_definitions(&mut self) -> CompileResult<Vec<VariableDefinition>> {
+        let mut definitions = vec![];
+        if !self.eat("(") {
+            return Ok(definitions);
+        }
+        loop {
+            let start = self.start();
+            self.expect("$")?;
+            let name = self.name()?;
+            self.expect(":")?;
+            let ty = self.ty()?;
+            let default_value = match self.eat("=") {
+                true => Some(self.value(true)?),
+                false => None,
+            };
+            let directives = self.directives(true)?;
+            definitions.push(VariableDefinition {
+                span: self.span_from(start),
+                name,
+                ty,
+                default_value,
+                directives,
+            });
+            if self.eat(")") {
+                return Ok(definitions);
+            }
+        }
+    }
+
+    fn ty(&mut self) -> CompileResult<Type> {
+        let ty = match self.eat("[") {
+            true => {
+                let ty = self.ty()?;
+                self.expect("]")?;
+                Type::List(Box::new(ty))
+            }
+            false => Type::Named(self.name()?),
+        };
+        Ok(match self.eat("!") {
+            true => Type::NonNull(Box::new(ty)),
+            false => ty,
+        })
+    }
+
+    fn directives(&mut self, constant: bool) -> CompileResult<Vec<Directive>> {
+        let mut directives = vec![];
+        while self.peek_is("@") {
+            let start = self.start();
+            self.bump();
+            let name = self.name()?;
+            let arguments = self.arguments(constant)?;
+            directives.push(Directive {
+                span: self.span_from(start),
+                name,
+                arguments,
+            });
+        }
+        Ok(directives)
+    }
+
+    fn arguments(&mut self, constant: bool) -> CompileResult<Vec<Argument>> {
+        let mut arguments = vec![];
+        if !self.eat("(") {
+            return Ok(arguments);
+        }
+        loop {
+            let start = self.start();
+            let name = self.name()?;
+            self.expect(":")?;
+            let value = self.value(constant)?;
+            arguments.push(Argument {
+                span: self.span_from(start),
+                name,
+                value,
+            });
+            if self.eat(")") {
+                return Ok(arguments);
+            }
+        }
+    }
+
+    fn value(&mut self, constant: bool) -> CompileResult<Value> {
+        let Some(token) = self.peek() else {
+            return Err(self.unexpected("a value"));
+        };
+        let text = self.text(token);
+        let value = match (token.kind, text) {
+            (TokenKind::Punctuator, "$") if !constant => {
+                self.bump();
+                return Ok(Value::Variable(self.name()?));
+            }
+            (TokenKind::Punctuator, "[") => {
+                self.bump();
+                let mut values = vec![];
+                while !self.eat("]") {
+                    values.push(self.value(constant)?);
+                }
+                return Ok(Value::List(values));
+            }
+            (TokenKind::Punctuator, "{") => {
+                self.bump();
+                let mut fields = vec![];
+                while !self.eat("}") {
+                    let name = self.name()?;
+                    self.expect(":")?;
+                    fields.push((name, self.value(constant)?));
+                }
+                return Ok(Value::Object(fields));
+            }
+            (TokenKind::Int, _) => Value::Int(text.to_string()),
+            (TokenKind::Float, _) => Value::Float(text.to_string()),
+            (TokenKind::String, _) => Value::String(text.to_string()),
+            (TokenKind::Name, "true") => Value::Boolean(true),
+            (TokenKind::Name, "false") => Value::Boolean(false),
+            (TokenKind::Name, "null") => Value::Null,
+            (TokenKind::Name, _) => Value::Enum(text.to_string()),
+            _ => return Err(self.unexpected("a value")),
+        };
+        self.bump();
+        Ok(value)
+    }
+
+    fn selection_set(&mut self) -> CompileResult<SelectionSet> {
+        let start = self.start();
+        self.expect("{")?;
+        let mut selections = vec![];
+        loop {
+            selections.push(self.selection()?);
+            if self.eat("}") {
+                return Ok(SelectionSet {
+                    span: self.span_from(start),
+                    selections,
+                });
+            }
+        }
+    }
+
+    fn selection(&mut self) -> CompileResult<Selection> {
+        let start = self.start();
+        if self.eat("...") {
+            if self.peek_kind(TokenKind::Name) && !self.peek_is("on") {
+                let fragment_name = self.name()?;
+                let directives = self.directives(false)?;
+                return Ok(Selection::FragmentSpread(FragmentSpread {
+                    span: self.span_from(start),
+                    fragment_name,
+                    directives,
+                }));
+            }
+            let type_condition = match self.eat("on") {
+                true => Some(self.name()?),
+                false => None,
+            };
+            let directives = self.directives(false)?;
+            let selection_set = self.selection_set()?;
+            return Ok(Selection::InlineFragment(InlineFragment {
+                span: self.span_from(start),
+                type_condition,
+                directives,
+                selection_set,
+            }));
+        }
+        if !self.peek_kind(TokenKind::Name) {
+            return Err(self.unexpected("a selection"));
+        }
+        let mut name = self.name()?;
+        let mut alias = None;
+        if self.eat(":") {
+            alias = Some(name);
+            name = self.name()?;
+        }
+        let arguments = self.arguments(false)?;
+        let directives = self.directives(false)?;
+        let selection_set = match self.peek_is("{") {
+            true => Some(self.selection_set()?),
+            false => None,
+        };
+        Ok(Selection::Field(Field {
+            span: self.span_from(start),
+            alias,
+            name,
+            arguments,
+            directives,
+            selection_set,
+        }))
+    }
+}
+
+pub fn parse(code: &str) -> CompileResult<Document> {
+    let mut parser = Parser {
+        code,
+        tokens: tokenize(code)?,
+        cursor: 0,
+    };
+    parser.document()
+}
+
+pub(crate) fn parse_type_system(code: &str) -> CompileResult<Vec<TypeSystemDefinition>> {
+    let mut parser = Parser {
+        code,
+        tokens: tokenize(code)?,
+        cursor: 0,
+    };
+    parser.type_system_document()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_executable_document() {
+        let code = r#"query Search($ids: [ID!]! = ["1"], $first: Int = 10) @cached(ttl: 60) {
+  nodes(ids: $ids) {
+    ... on User { login }
+    ... @include(if: true) { id }
+    ...Parts
+  }
+  search(query: """block "quoted" text""", first: $first, after: -1.5e3) { total }
+}
+
+fragment Parts on Node { id }
+"#;
+        let document = parse(code).unwrap();
+        assert_eq!(document.definitions.len(), 2);
+
+        let Definition::Operation(operation) = &document.definitions[0] else {
+            panic!("expected an operation");
+        };
+        assert_eq!(operation.name.as_deref(), Some("Search"));
+        assert_eq!(
+            operation.variable_definitions[0].ty,
+            Type::NonNull(Box::new(Type::List(Box::new(Type::NonNull(Box::new(
+                Type::Named("ID".to_string())
+            ))))))
+        );
+        assert_eq!(
+            operation.variable_definitions[1].default_value,
+            Some(Value::Int("10".to_string()))
+        );
+
+        let Selection::Field(nodes) = &operation.selection_set.selections[0] else {
+            panic!("expected a field");
+        };
+        let selections = &nodes.selection_set.as_ref().unwrap().selections;
+        assert!(matches!(
+            &selections[1],
+            Selection::InlineFragment(InlineFragment { type_condition: None, directives, .. })
+                if directives[0].name == "include"
+        ));
+        assert!(matches!(
+            &selections[2],
+            Selection::FragmentSpread(FragmentSpread { fragment_name, .. }) if fragment_name == "Parts"
+        ));
+        let span = nodes.span;
+        assert!(code[span.start..span.end].starts_with("nodes(ids: $ids) {"));
+        assert!(code[span.start..span.end].ends_with('}'));
+
+        let error = parse("query { viewer { login }").unwrap_err();
+        assert_eq!(error.reason(), "expected a selection, found end of input");
+    }
+
+    #[test]
+    fn parse_every_operation() {
+        let code = "query A { a } # comment\nmutation B($x: Int) { b(x: $x) }\n{ c }";
+        let document = parse(code).unwrap();
+        let operations: Vec<_> = document
+            .operations()
+            .map(|operation| (operation.operation_type, operation.name.as_deref()))
+            .collect();
+        assert_eq!(
+            operations,
+            vec![
+                (OperationType::Query, Some("A")),
+                (OperationType::Mutation, Some("B")),
+                (OperationType::Query, None),
+            ]
+        );
+        let span = document.definitions[0].span();
+        assert_eq!(&code[span.start..span.end], "query A { a }");
+    }
+
+    #[test]
+    fn reject_malformed_tokens() {
+        for (code, reason) in [
+            ("{ f(a: 123abc) }", "invalid number literal"),
+            ("{ f(a: [123abc]) }", "invalid number literal"),
+            ("{ f(a: 1.5e3x) }", "invalid number literal"),
+            ("{ f(a: 1.2.3) }", "invalid number literal"),
+            ("{ f(a: 0123) }", "invalid number literal"),
+            ("{ f(a: -) }", "invalid number literal"),
+            ("{ f(a: \"\\q\") }", "invalid escape sequence"),
+            ("{ f(a: \"\\u12\") }", "invalid escape sequence"),
+            ("{ f(a: \"open) }", "unterminated string"),
+            ("{ f(a: 1) ? }", "unexpected character `?`"),
+        ] {
+            let error = parse(code).unwrap_err();
+            assert!(
+                matches!(error, CompileError::Parse { .. }) && error.reason() == reason,
+                "{:?}: {:?}",
+                code,
+                error
+            );
+        }
+        for code in [
+            "{ f(a: 0, b: -0.5, c: 10e-2, d: \"\\u00e9\\n\") }",
+            "{ f(a: [1,2]) }",
+        ] {
+            assert!(parse(code).is_ok(), "{:?} should parse", code);
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use crate::ast::*;
+use crate::edit::{self, Edit};
+use crate::error::{CompileError, CompileResult, Span};
+use crate::parser::parse;
+use crate::schema::Schema;
+
+pub struct PassContext<'a> {
+    pub code: &'a str,
+    pub document: &'a Document,
+    pub schema: Option<&'a Schema>,
+}
+
+pub trait Pass: Send + Sync {
+    fn run(&self, context: &PassContext) -> CompileResult<Vec<Edit>>;
+}
+
+impl<F> Pass for F
+where
+    F: Fn(&PassContext) -> CompileResult<Vec<Edit>> + Send + Sync,
+{
+    fn run(&self, context: &PassContext) -> CompileResult<Vec<Edit>> {
+        self(context)
+    }
+}
+
+type Passes = RwLock<HashMap<String, Arc<dyn Pass>>>;
+
+fn passes() -> &'static Passes {
+    static PASSES: OnceLock<Passes> = OnceLock::new();
+    PASSES.get_or_init(Default::default)
+}
+
+pub fn register_pass(name: impl Into<String>, pass: impl Pass + 'static) {
+    passes()
+        .write()
+        .unwrap()
+        .insert(name.into(), Arc::new(pass));
+}
+
+pub(crate) fn registered_pass(name: &str) -> CompileResult<Arc<dyn Pass>> {
+    passes()
+        .read()
+        .unwrap()
+        .get(name)
+        .cloned()
+        .ok_or_else(|| CompileError::Unresolved {
+            span: Span::new(0, 0),
+            reason: format!("no pass is registered as `{}`", name),
+        })
+}
+
+pub trait Visitor {
+    fn operation(
+        &mut self,
+        _context: &PassContext,
+        _operation: &OperationDefinition,
+        _typename: Option<&str>,
+    ) {
+    }
+
+    fn fragment(&mut self, _context: &PassContext, _fragment: &FragmentDefinition) {}
+
+    fn field(&mut self, _context: &PassContext, _field: &Field, _typename: Option<&str>) {}
+
+    fn inline_fragment(
+        &mut self,
+        _context: &PassContext,
+        _fragment: &InlineFragment,
+        _typename: Option<&str>,
+    ) {
+    }
+
+    fn fragment_spread(&mut self, _context: &PassContext, _spread: &FragmentSpread) {}
+}
+
+fn walk_selection_set<'a>(
+    context: &PassContext<'a>,
+    visitor: &mut impl Visitor,
+    selection_set: &'a SelectionSet,
+    typename: Option<&'a str>,
+) {
+    for selection in &selection_set.selections {
+        match selection {
+            Selection::Field(field) => {
+                let field_type = context
+                    .schema
+                    .zip(typename)
+                    .and_then(|(schema, typename)| schema.field(typename, &field.name))
+                    .map(|definition| definition.ty.named_type());
+                visitor.field(context, field, field_type);
+                if let Some(selection_set) = &field.selection_set {
+                    walk_selection_set(context, visitor, selection_set, field_type);
+                }
+            }
+            Selection::InlineFragment(fragment) => {
+                let typename = fragment.type_condition.as_deref().or(typename);
+                visitor.inline_fragment(context, fragment, typename);
+                walk_selection_set(context, visitor, &fragment.selection_set, typename);
+            }
+            Selection::FragmentSpread(spread) => visitor.fragment_spread(context, spread),
+        }
+    }
+}
+
+pub fn walk(context: &PassContext, visitor: &mut impl Visitor) {
+    for definition in &context.document.definitions {
+        match definition {
+            Definition::Operation(operation) => {
+                let root = context
+                    .schema
+                    .and_then(|schema| schema.root_type(operation.operation_type));
+                visitor.operation(context, operation, root);
+                walk_selection_set(context, visitor, &operation.selection_set, root);
+            }
+            Definition::Fragment(fragment) => {
+                visitor.fragment(context, fragment);
+                let typename = Some(fragment.type_condition.as_str());
+                walk_selection_set(context, visitor, &fragment.selection_set, typename);
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct TransformPipeline {
+    passes: Vec<Arc<dyn Pass>>,
+    schema: Option<Arc<Schema>>,
+}
+
+impl TransformPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn schema(mut self, schema: Schema) -> Self {
+        self.schema = Some(Arc::new(schema));
+        self
+    }
+
+    pub(crate) fn shared_schema(mut self, schema: Option<Arc<Schema>>) -> Self {
+        self.schema = schema;
+        self
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn add(mut self, pass: impl Pass + 'static) -> Self {
+        self.passes.push(Arc::new(pass));
+        self
+    }
+
+    pub fn add_registered(mut self, name: &str) -> CompileResult<Self> {
+        self.passes.push(registered_pass(name)?);
+        Ok(self)
+    }
+
+    pub fn transform(&self, code: &str) -> CompileResult<String> {
+        let mut code = code.to_string();
+        for pass in &self.passes {
+            let document = parse(&code)?;
+            let edits = pass.run(&PassContext {
+                code: &code,
+                document: &document,
+                schema: self.schema.as_deref(),
+            })?;
+            code = edit::apply(&code, edits)?;
+        }
+        Ok(code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformer::{AddKeyFields, AddTypename, KeyFields};
+
+    struct LegacyIds {
+        edits: Vec<Edit>,
+    }
+
+    impl Visitor for LegacyIds {
+        fn field(&mut self, _context: &PassContext, field: &Field, _typename: Option<&str>) {
+            if field.name == "legacyId" {
+                self.edits.push(Edit::replace(field.span, "id"));
+            }
+        }
+    }
+
+    struct RenameLegacyId;
+
+    impl Pass for RenameLegacyId {
+        fn run(&self, context: &PassContext) -> CompileResult<Vec<Edit>> {
+            let mut visitor = LegacyIds { edits: vec![] };
+            walk(context, &mut visitor);
+            Ok(visitor.edits)
+        }
+    }
+
+    #[test]
+    fn run_passes_in_order() {
+        let schema = Schema::parse(
+            "type Organization { id: ID! name: String! }
+type User { id: ID! legacyId: ID! login: String! organization: Organization }
+type Query { viewer: User }
+",
+        )
+        .unwrap();
+        let pipeline = TransformPipeline::new()
+            .schema(schema)
+            .add(RenameLegacyId)
+            .add(AddKeyFields::new(KeyFields::new()))
+            .add(AddTypename);
+
+        assert_eq!(
+            pipeline
+                .transform("query Viewer { viewer { legacyId login organization { name } } }")
+                .unwrap(),
+            "query Viewer { viewer { __typename id login organization { __typename id name } } }"
+        );
+    }
+}
@@ -0,0 +1,367 @@
+use crate::ast::*;
+use crate::error::CompileResult;
+use crate::parser::parse;
+
+pub(crate) fn encode_string(value: &str) -> String {
+    let mut output = String::with_capacity(value.len() + 2);
+    output.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            '\u{8}' => output.push_str("\\b"),
+            '\u{c}' => output.push_str("\\f"),
+            c if c.is_control() => output.push_str(&format!("\\u{:04X}", c as u32)),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+    output
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrintOptions {
+    indent: String,
+    single_line: bool,
+    minify: bool,
+}
+
+impl PrintOptions {
+    pub fn new() -> Self {
+        Self {
+            indent: "  ".to_string(),
+            single_line: false,
+            minify: false,
+        }
+    }
+
+    pub fn indent(mut self, indent: impl Into<String>) -> Self {
+        self.indent = indent.into();
+        self
+    }
+
+    pub fn single_line(mut self, single_line: bool) -> Self {
+        self.single_line = single_line;
+        self
+    }
+
+    pub fn minify(mut self, minify: bool) -> Self {
+        self.minify = minify;
+        self
+    }
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct Printer<'a> {
+    options: &'a PrintOptions,
+    output: String,
+    space: bool,
+    depth: usize,
+}
+
+fn is_name(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+impl<'a> Printer<'a> {
+    fn write(&mut self, text: &str) {
+        if self.space {
+            self.space = false;
+            let separate = !self.options.minify
+                || self.output.ends_with(is_name) && text.starts_with(|c| is_name(c) || c == '-');
+            if separate {
+                self.output.push(' ');
+            }
+        }
+        self.output.push_str(text);
+    }
+
+    fn space(&mut self) {
+        self.space = !self.output.is_empty();
+    }
+
+    fn newline(&mut self) {
+        if self.options.single_line || self.options.minify {
+            self.space();
+            return;
+        }
+        self.space = false;
+        self.output.push('\n');
+        for _ in 0..self.depth {
+            self.output.push_str(&self.options.indent);
+        }
+    }
+
+    fn separator(&mut self) {
+        self.write(",");
+        self.space();
+    }
+
+    fn colon(&mut self) {
+        self.write(":");
+        self.space();
+    }
+
+    fn value(&mut self, value: &Value) {
+        match value {
+            Value::Variable(name) => {
+                self.write("$");
+                self.write(name);
+            }
+            Value::Int(value) | Value::Float(value) | Value::Enum(value) => self.write(value),
+            Value::String(_) => {
+                let value = value.as_string().unwrap_or_default();
+                self.write(&encode_string(&value));
+            }
+            Value::Boolean(value) => self.write(&value.to_string()),
+            Value::Null => self.write("null"),
+            Value::List(values) => {
+                self.write("[");
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        self.separator();
+                    }
+                    self.value(value);
+                }
+                self.write("]");
+            }
+            Value::Object(fields) => {
+                self.write("{");
+                for (index, (name, value)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        self.separator();
+                    }
+                    self.write(name);
+                    self.colon();
+                    self.value(value);
+                }
+                self.write("}");
+            }
+        }
+    }
+
+    fn arguments(&mut self, arguments: &[Argument]) {
+        if arguments.is_empty() {
+            return;
+        }
+        self.write("(");
+        for (index, argument) in arguments.iter().enumerate() {
+            if index > 0 {
+                self.separator();
+            }
+            self.write(&argument.name);
+            self.colon();
+            self.value(&argument.value);
+        }
+        self.write(")");
+    }
+
+    fn directives(&mut self, directives: &[Directive]) {
+        for directive in directives {
+            self.space();
+            self.write("@");
+            self.write(&directive.name);
+            self.arguments(&directive.arguments);
+        }
+    }
+
+    fn selection_set(&mut self, selection_set: &SelectionSet) {
+        self.space();
+        self.write("{");
+        self.depth += 1;
+        for selection in &selection_set.selections {
+            self.newline();
+            self.selection(selection);
+        }
+        self.depth -= 1;
+        self.newline();
+        self.write("}");
+    }
+
+    fn selection(&mut self, selection: &Selection) {
+        match selection {
+            Selection::Field(field) => {
+                if let Some(alias) = &field.alias {
+                    self.write(alias);
+                    self.colon();
+                }
+                self.write(&field.name);
+                self.arguments(&field.arguments);
+                self.directives(&field.directives);
+                if let Some(selection_set) = &field.selection_set {
+                    self.selection_set(selection_set);
+                }
+            }
+            Selection::FragmentSpread(spread) => {
+                self.write("...");
+                self.write(&spread.fragment_name);
+                self.directives(&spread.directives);
+            }
+            Selection::InlineFragment(fragment) => {
+                self.write("...");
+                if let Some(type_condition) = &fragment.type_condition {
+                    self.space();
+                    self.write("on");
+                    self.space();
+                    self.write(type_condition);
+                }
+                self.directives(&fragment.directives);
+                self.selection_set(&fragment.selection_set);
+            }
+        }
+    }
+
+    fn operation(&mut self, operation: &OperationDefinition) {
+        let shorthand = operation.operation_type == OperationType::Query
+            && operation.name.is_none()
+            && operation.variable_definitions.is_empty()
+            && operation.directives.is_empty();
+        if !shorthand {
+            self.write(operation.operation_type.as_str());
+            if let Some(name) = &operation.name {
+                self.space();
+                self.write(name);
+            }
+            if !operation.variable_definitions.is_empty() {
+                self.write("(");
+                for (index, definition) in operation.variable_definitions.iter().enumerate() {
+                    if index > 0 {
+                        self.separator();
+                    }
+                    self.write("$");
+                    self.write(&definition.name);
+                    self.colon();
+                    self.write(&definition.ty.to_string());
+                    if let Some(value) = &definition.default_value {
+                        self.space();
+                        self.write("=");
+                        self.space();
+                        self.value(value);
+                    }
+                    self.directives(&definition.directives);
+                }
+                self.write(")");
+            }
+            self.directives(&operation.directives);
+        }
+        self.selection_set(&operation.selection_set);
+    }
+
+    fn fragment(&mut self, fragment: &FragmentDefinition) {
+        self.write("fragment");
+        self.space();
+        self.write(&fragment.name);
+        self.space();
+        self.write("on");
+        self.space();
+        self.write(&fragment.type_condition);
+        self.directives(&fragment.directives);
+        self.selection_set(&fragment.selection_set);
+    }
+}
+
+pub fn print(document: &Document) -> String {
+    print_with(document, &PrintOptions::new())
+}
+
+pub fn print_with(document: &Document, options: &PrintOptions) -> String {
+    let mut printer = Printer {
+        options,
+        output: String::new(),
+        space: false,
+        depth: 0,
+    };
+    for (index, definition) in document.definitions.iter().enumerate() {
+        if index > 0 {
+            printer.newline();
+            printer.newline();
+        }
+        match definition {
+            Definition::Operation(operation) => printer.operation(operation),
+            Definition::Fragment(fragment) => printer.fragment(fragment),
+        }
+    }
+    printer.output
+}
+
+pub fn minify(code: &str) -> CompileResult<String> {
+    Ok(print_with(&parse(code)?, &PrintOptions::new().minify(true)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn print_documents() {
+        let document = parse(
+            "query Viewer($first: Int = 10) { viewer { login repositories(first: $first) { ...Parts } } }
+fragment Parts on Repository { name ... on Fork @include(if: true) { parent { name } } }",
+        )
+        .unwrap();
+
+        assert_eq!(
+            print(&document),
+            "query Viewer($first: Int = 10) {
+  viewer {
+    login
+    repositories(first: $first) {
+      ...Parts
+    }
+  }
+}
+
+fragment Parts on Repository {
+  name
+  ... on Fork @include(if: true) {
+    parent {
+      name
+    }
+  }
+}"
+        );
+        assert_eq!(
+            print_with(
+                &parse("{ viewer { login } }").unwrap(),
+                &PrintOptions::new().indent("\t")
+            ),
+            "{\n\tviewer {\n\t\tlogin\n\t}\n}"
+        );
+        assert_eq!(
+            print_with(&document, &PrintOptions::new().single_line(true)),
+            "query Viewer($first: Int = 10) { viewer { login repositories(first: $first) { ...Parts } } } \
+fragment Parts on Repository { name ... on Fork @include(if: true) { parent { name } } }"
+        );
+    }
+
+    #[test]
+    fn minify_documents() {
+        assert_eq!(
+            minify(
+                r#"# Search repositories
+query Search($term: String! = "rust", $first: Int = -1) @cached(ttl: 60) {
+  search(term: $term, first: $first, filter: { stars: [1, 2] }) {
+    total
+    ... on Repository @include(if: true) { name }
+    ...Owner
+  }
+}
+
+fragment Owner on User { login }
+"#
+            )
+            .unwrap(),
+            "query Search($term:String!=\"rust\",$first:Int=-1)@cached(ttl:60)\
+{search(term:$term,first:$first,filter:{stars:[1,2]}){total...on Repository@include(if:true){name}...Owner}}\
+fragment Owner on User{login}"
+        );
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use crate::ast::*;
+use crate::compile::CompiledOperation;
+use crate::schema::Schema;
+
+struct Types<'a> {
+    document: &'a Document,
+    schema: Option<&'a Schema>,
+    visited: Vec<&'a str>,
+    types: BTreeSet<String>,
+}
+
+impl<'a> Types<'a> {
+    fn selection_set(&mut self, selection_set: &'a SelectionSet, typename: Option<&str>) {
+        for selection in &selection_set.selections {
+            match selection {
+                Selection::Field(field) => {
+                    let Some(selection_set) = &field.selection_set else {
+                        continue;
+                    };
+                    let field_type = self
+                        .schema
+                        .zip(typename)
+                        .and_then(|(schema, typename)| schema.field(typename, &field.name))
+                        .map(|definition| definition.ty.named_type().to_string());
+                    if let Some(field_type) = &field_type {
+                        self.types.insert(field_type.clone());
+                    }
+                    self.selection_set(selection_set, field_type.as_deref());
+                }
+                Selection::InlineFragment(fragment) => {
+                    if let Some(type_condition) = &fragment.type_condition {
+                        self.types.insert(type_condition.clone());
+                    }
+                    let typename = fragment.type_condition.as_deref().or(typename);
+                    self.selection_set(&fragment.selection_set, typename);
+                }
+                Selection::FragmentSpread(spread) => {
+                    let Some(fragment) = self.document.fragment(&spread.fragment_name) else {
+                        continue;
+                    };
+                    if self.visited.contains(&fragment.name.as_str()) {
+                        continue;
+                    }
+                    self.visited.push(&fragment.name);
+                    self.types.insert(fragment.type_condition.clone());
+                    self.selection_set(&fragment.selection_set, Some(&fragment.type_condition));
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn referenced_types(
+    document: &Document,
+    operation: &OperationDefinition,
+    schema: Option<&Schema>,
+) -> Vec<String> {
+    let mut types = Types {
+        document,
+        schema,
+        visited: vec![],
+        types: BTreeSet::new(),
+    };
+    let root = schema.and_then(|schema| schema.root_type(operation.operation_type));
+    if let Some(root) = root {
+        types.types.insert(root.to_string());
+    }
+    types.selection_set(&operation.selection_set, root);
+    types.types.into_iter().collect()
+}
+
+const PRELUDE: &str = "// @generated by discovery-query-compiler
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperationType {
+    Query,
+    Mutation,
+    Subscription,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Operation {
+    pub name: &'static str,
+    pub hash: Option<&'static str>,
+    pub operation_type: OperationType,
+    pub types: &'static [&'static str],
+    pub document: &'static str,
+}
+
+pub fn operation(name: &str) -> Option<&'static Operation> {
+    OPERATIONS.iter().find(|operation| operation.name == name)
+}
+
+pub fn operations_touching(typename: &str) -> impl Iterator<Item = &'static Operation> + '_ {
+    OPERATIONS
+        .iter()
+        .filter(move |operation| operation.types.contains(&typename))
+}
+";
+
+pub fn generate_registry(operations: &[CompiledOperation]) -> String {
+    let mut code = PRELUDE.to_string();
+    code.push_str("\npub const OPERATIONS: &[Operation] = &[\n");
+    for operation in operations {
+        let operation_type = match operation.operation_type {
+            OperationType::Query => "Query",
+            OperationType::Mutation => "Mutation",
+            OperationType::Subscription => "Subscription",
+        };
+        let hash = match &operation.hash {
+            Some(hash) => format!("Some({:?})", hash),
+            None => "None".to_string(),
+        };
+        let types: Vec<String> = operation
+            .types
+            .iter()
+            .map(|typename| format!("{:?}", typename))
+            .collect();
+        write!(
+            code,
+            "    Operation {{
+        name: {:?},
+        hash: {},
+        operation_type: OperationType::{},
+        types: &[{}],
+        document: {:?},
+    }},
+",
+            operation.name,
+            hash,
+            operation_type,
+            types.join(", "),
+            operation.document
+        )
+        .unwrap();
+    }
+    code.push_str("];\n");
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+    use crate::transformer::Transformer;
+
+    #[test]
+    fn collect_referenced_types() {
+        let schema = Schema::parse(
+            "interface Node { id: ID! }
+type User implements Node { id: ID! login: String! repositories: [Repository!]! }
+type Repository implements Node { id: ID! name: String! }
+type Query { node(id: ID!): Node viewer: User }
+",
+        )
+        .unwrap();
+        let document = parse(
+            "query Node($id: ID!) {
+  node(id: $id) { ... on User { login } ...RepositoryParts }
+}
+
+fragment RepositoryParts on Repository { name }
+",
+        )
+        .unwrap();
+        let operation = document.operations().next().unwrap();
+
+        assert_eq!(
+            referenced_types(&document, operation, Some(&schema)),
+            ["Node", "Query", "Repository", "User"]
+        );
+        assert_eq!(
+            referenced_types(&document, operation, None),
+            ["Repository", "User"]
+        );
+    }
+
+    #[test]
+    fn generate_registry_module() {
+        let operations = Transformer::new()
+            .typename(false)
+            .compile("query Session { isLoggedIn @client }\n")
+            .unwrap();
+
+        assert_eq!(
+            generate_registry(&operations),
+            format!(
+                "{}
+pub const OPERATIONS: &[Operation] = &[
+    Operation {{
+        name: \"Session\",
+        hash: None,
+        operation_type: OperationType::Query,
+        types: &[],
+        document: \"query Session {{ isLoggedIn @client }}\\n\",
+    }},
+];
+",
+                PRELUDE
+            )
+        );
+    }
+}
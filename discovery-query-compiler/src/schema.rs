@@ -0,0 +1,257 @@
+use std::collections::HashMap;
+
+use crate::ast::{OperationType, Type};
+use crate::error::{CompileError, CompileResult, Span};
+use crate::parser::{parse_type_system, TypeSystemDefinition};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypeKind {
+    Scalar,
+    Object,
+    Interface,
+    Union,
+    Enum,
+    InputObject,
+}
+
+impl TypeKind {
+    pub fn is_composite(&self) -> bool {
+        matches!(
+            self,
+            TypeKind::Object | TypeKind::Interface | TypeKind::Union
+        )
+    }
+
+    pub fn is_input(&self) -> bool {
+        matches!(
+            self,
+            TypeKind::Scalar | TypeKind::Enum | TypeKind::InputObject
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputValueDefinition {
+    pub name: String,
+    pub ty: Type,
+    pub has_default: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDefinition {
+    pub name: String,
+    pub arguments: Vec<InputValueDefinition>,
+    pub ty: Type,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeDefinition {
+    pub name: String,
+    pub kind: TypeKind,
+    pub fields: Vec<FieldDefinition>,
+    pub interfaces: Vec<String>,
+    pub members: Vec<String>,
+    pub input_fields: Vec<InputValueDefinition>,
+    pub values: Vec<String>,
+}
+
+impl TypeDefinition {
+    pub(crate) fn new(name: &str, kind: TypeKind) -> Self {
+        Self {
+            name: name.to_string(),
+            kind,
+            fields: vec![],
+            interfaces: vec![],
+            members: vec![],
+            input_fields: vec![],
+            values: vec![],
+        }
+    }
+
+    pub fn field(&self, name: &str) -> Option<&FieldDefinition> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    pub fn input_field(&self, name: &str) -> Option<&InputValueDefinition> {
+        self.input_fields.iter().find(|field| field.name == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schema {
+    types: HashMap<String, TypeDefinition>,
+    query: Option<String>,
+    mutation: Option<String>,
+    subscription: Option<String>,
+}
+
+impl Schema {
+    pub fn parse(sdl: &str) -> CompileResult<Self> {
+        let definitions = parse_type_system(sdl)?;
+
+        let mut schema = Schema {
+            types: HashMap::new(),
+            query: None,
+            mutation: None,
+            subscription: None,
+        };
+        for name in ["Int", "Float", "String", "Boolean", "ID"] {
+            schema.insert(TypeDefinition::new(name, TypeKind::Scalar));
+        }
+
+        let mut extensions = vec![];
+        for definition in definitions {
+            match definition {
+                TypeSystemDefinition::Schema(roots) => {
+                    for (operation_type, name) in roots {
+                        match operation_type {
+                            OperationType::Query => schema.query = Some(name),
+                            OperationType::Mutation => schema.mutation = Some(name),
+                            OperationType::Subscription => schema.subscription = Some(name),
+                        }
+                    }
+                }
+                TypeSystemDefinition::Type(definition) => schema.insert(definition),
+                TypeSystemDefinition::Extension(extension, span) => {
+                    extensions.push((extension, span))
+                }
+            }
+        }
+        for (extension, span) in extensions {
+            schema.extend(extension, span)?;
+        }
+
+        for (root, name) in [
+            (&mut schema.query, "Query"),
+            (&mut schema.mutation, "Mutation"),
+            (&mut schema.subscription, "Subscription"),
+        ] {
+            if root.is_none() && schema.types.contains_key(name) {
+                *root = Some(name.to_string());
+            }
+        }
+        Ok(schema)
+    }
+
+    fn insert(&mut self, definition: TypeDefinition) {
+        self.types.insert(definition.name.clone(), definition);
+    }
+
+    fn extend(&mut self, extension: TypeDefinition, span: Span) -> CompileResult<()> {
+        let definition =
+            self.types
+                .get_mut(&extension.name)
+                .ok_or_else(|| CompileError::Unresolved {
+                    span,
+                    reason: format!("extension of unknown type `{}`", extension.name),
+                })?;
+        definition.fields.extend(extension.fields);
+        definition.interfaces.extend(extension.interfaces);
+        definition.members.extend(extension.members);
+        definition.input_fields.extend(extension.input_fields);
+        definition.values.extend(extension.values);
+        Ok(())
+    }
+
+    pub fn type_definition(&self, name: &str) -> Option<&TypeDefinition> {
+        self.types.get(name)
+    }
+
+    pub fn root_type(&self, operation_type: OperationType) -> Option<&str> {
+        match operation_type {
+            OperationType::Query => self.query.as_deref(),
+            OperationType::Mutation => self.mutation.as_deref(),
+            OperationType::Subscription => self.subscription.as_deref(),
+        }
+    }
+
+    pub fn field(&self, typename: &str, field: &str) -> Option<&FieldDefinition> {
+        self.types.get(typename)?.field(field)
+    }
+
+    pub fn is_composite(&self, typename: &str) -> bool {
+        self.types
+            .get(typename)
+            .is_some_and(|definition| definition.kind.is_composite())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_schema_definitions() {
+        let schema = Schema::parse(
+            r#"
+"""The schema root."""
+schema @link(url: "https://specs.apollo.dev/federation/v2.0") { query: Root }
+
+directive @key(fields: String!, resolvable: Boolean = true) repeatable on OBJECT | INTERFACE
+
+scalar JSON @specifiedBy(url: "https://www.json.org")
+
+interface Node { id: ID! }
+
+"""
+A GitHub user.
+"""
+type User implements Node & Actor @key(fields: "id") {
+  id: ID!
+  "The login name."
+  login: String! @deprecated(reason: "use handle")
+  settings(scope: Scope = GLOBAL, filter: SettingsFilter): JSON
+}
+
+enum Scope { GLOBAL @deprecated USER }
+
+input SettingsFilter { keys: [String!] = [] prefix: String }
+
+type Root {
+  node(id: ID!): Node
+  search(term: String!, first: Int = 10): [SearchResult!]!
+}
+
+union SearchResult = User
+
+extend type User { email: String }
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(schema.root_type(OperationType::Query), Some("Root"));
+        assert_eq!(schema.root_type(OperationType::Mutation), None);
+        assert!(schema.is_composite("SearchResult"));
+        assert!(!schema.is_composite("JSON"));
+        assert!(schema.field("User", "email").is_some());
+
+        let search = schema.field("Root", "search").unwrap();
+        assert_eq!(search.ty.named_type(), "SearchResult");
+        assert!(!search.arguments[0].has_default);
+        assert!(search.arguments[1].has_default);
+        assert_eq!(
+            schema.type_definition("User").unwrap().interfaces,
+            ["Node", "Actor"]
+        );
+        assert_eq!(
+            schema.type_definition("Scope").unwrap().values,
+            ["GLOBAL", "USER"]
+        );
+        let filter = schema.type_definition("SettingsFilter").unwrap();
+        assert!(filter.input_field("keys").unwrap().has_default);
+        assert!(!filter.input_field("prefix").unwrap().has_default);
+        assert_eq!(
+            schema.field("User", "settings").unwrap().arguments[0].name,
+            "scope"
+        );
+
+        let code = "type Query { a: Int }\nextend type Missing { b: Int }";
+        let error = Schema::parse(code).unwrap_err();
+        assert!(matches!(error, CompileError::Unresolved { .. }));
+        assert_eq!(error.span().line_column(code), (2, 1));
+        assert!(matches!(
+            Schema::parse("type Query { a: }"),
+            Err(CompileError::Parse { .. })
+        ));
+    }
+}
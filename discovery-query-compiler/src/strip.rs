@@ -0,0 +1,140 @@
+use crate::ast::*;
+use crate::edit::{self, Edit};
+use crate::error::CompileResult;
+use crate::lint::remove_unused;
+use crate::parser::parse;
+
+const CLIENT: &str = "client";
+
+struct Stripper<'a> {
+    empty_fragments: Vec<&'a str>,
+}
+
+impl<'a> Stripper<'a> {
+    fn removed(&self, selection: &Selection, edits: &mut Vec<Edit>) -> bool {
+        if selection
+            .directives()
+            .iter()
+            .any(|directive| directive.name == CLIENT)
+        {
+            return true;
+        }
+        match selection {
+            Selection::Field(field) => field
+                .selection_set
+                .as_ref()
+                .is_some_and(|selection_set| self.strip(selection_set, edits)),
+            Selection::InlineFragment(fragment) => self.strip(&fragment.selection_set, edits),
+            Selection::FragmentSpread(spread) => self
+                .empty_fragments
+                .contains(&spread.fragment_name.as_str()),
+        }
+    }
+
+    fn strip(&self, selection_set: &SelectionSet, edits: &mut Vec<Edit>) -> bool {
+        let mut nested = vec![];
+        let removed: Vec<bool> = selection_set
+            .selections
+            .iter()
+            .map(|selection| self.removed(selection, &mut nested))
+            .collect();
+        let empty = selection_set
+            .selections
+            .iter()
+            .zip(&removed)
+            .all(|(selection, removed)| {
+                *removed
+                    || matches!(selection, Selection::Field(field) if field.name == "__typename")
+            });
+        if !empty {
+            let spans: Vec<_> = selection_set
+                .selections
+                .iter()
+                .map(Selection::span)
+                .collect();
+            edits.extend(edit::remove_items(&spans, &removed));
+            edits.extend(nested);
+        }
+        empty
+    }
+}
+
+pub fn strip_client_fields(code: &str) -> CompileResult<Option<String>> {
+    let document = parse(code)?;
+
+    let mut stripper = Stripper {
+        empty_fragments: vec![],
+    };
+    loop {
+        let empty: Vec<_> = document
+            .fragments()
+            .filter(|fragment| !stripper.empty_fragments.contains(&fragment.name.as_str()))
+            .filter(|fragment| stripper.strip(&fragment.selection_set, &mut vec![]))
+            .map(|fragment| fragment.name.as_str())
+            .collect();
+        if empty.is_empty() {
+            break;
+        }
+        stripper.empty_fragments.extend(empty);
+    }
+
+    let mut edits = vec![];
+    let mut remote = false;
+    for definition in &document.definitions {
+        match definition {
+            Definition::Operation(operation) => {
+                remote |= !stripper.strip(&operation.selection_set, &mut edits);
+            }
+            Definition::Fragment(fragment) => {
+                if !stripper.empty_fragments.contains(&fragment.name.as_str()) {
+                    stripper.strip(&fragment.selection_set, &mut edits);
+                }
+            }
+        }
+    }
+    if !remote {
+        return Ok(None);
+    }
+    remove_unused(&edit::apply(code, edits)?).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_client_selections() {
+        let code = "query Cart($locale: String) {
+  cart {
+    __typename
+    id
+    items { __typename name }
+    isOpen @client
+    preferences @client { currency }
+    ...LocalCart
+  }
+  localized(locale: $locale) @client { __typename title }
+}
+
+fragment LocalCart on Cart {
+  __typename
+  draft @client
+}
+";
+        assert_eq!(
+            strip_client_fields(code).unwrap().unwrap(),
+            "query Cart {
+  cart {
+    __typename
+    id
+    items { __typename name }
+  }
+}
+"
+        );
+        assert_eq!(
+            strip_client_fields("query Local { isLoggedIn @client }").unwrap(),
+            None
+        );
+    }
+}
@@ -1,42 +1,374 @@
-use apollo_parser::{ast, Parser};
-
-fn add_type_field(code: &str) -> String {
-    let parser = Parser::new(code);
-
-    let ast = parser.parse();
-
-    for def in ast.document().definitions() {
-        match def {
-            ast::Definition::OperationDefinition(op_def) => {
-                dbg!(&op_def);
-                dbg!(op_def.directives());
-                dbg!(op_def.variable_definitions());
-                dbg!(op_def.name());
-                dbg!(op_def.operation_type());
-                dbg!(op_def.selection_set());
-                dbg!(op_def.selection_set().unwrap().selections().next().unwrap());
-                let s = op_def.selection_set().unwrap().selections().next().unwrap();
-                match s {
-                    ast::Selection::Field(f) => {
-                        dbg!(f.name(), f.arguments(), f.selection_set());
-                    }
-                    _ => {
-                        panic!("無理");
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::ast::{
+    is_conditional, Field, FragmentDefinition, InlineFragment, OperationDefinition, Selection,
+    SelectionSet,
+};
+use crate::edit::Edit;
+use crate::error::{CompileError, CompileResult};
+use crate::parser::parse;
+use crate::pipeline::{walk, Pass, PassContext, TransformPipeline, Visitor};
+use crate::schema::Schema;
+use crate::validate::validate;
+
+const TYPENAME: &str = "__typename";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyFields {
+    default: Vec<String>,
+    types: HashMap<String, Vec<String>>,
+}
+
+impl KeyFields {
+    pub fn new() -> Self {
+        Self {
+            default: vec!["id".to_string()],
+            types: HashMap::new(),
+        }
+    }
+
+    pub fn default_fields<I, S>(mut self, fields: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.default = fields.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn type_fields<I, S>(mut self, typename: &str, fields: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.types.insert(
+            typename.to_string(),
+            fields.into_iter().map(Into::into).collect(),
+        );
+        self
+    }
+
+    fn configured(&self, typename: &str) -> Option<&[String]> {
+        self.types.get(typename).map(Vec::as_slice)
+    }
+
+    pub fn get(&self, typename: Option<&str>) -> &[String] {
+        typename
+            .and_then(|typename| self.types.get(typename))
+            .unwrap_or(&self.default)
+    }
+}
+
+impl Default for KeyFields {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transformer {
+    typename: bool,
+    key_fields: Option<KeyFields>,
+    anonymous_operation_name: Option<String>,
+    pub(crate) schema: Option<Arc<Schema>>,
+    validate: bool,
+    pub(crate) minify: bool,
+    passes: Vec<String>,
+}
+
+impl Transformer {
+    pub fn new() -> Self {
+        Self {
+            typename: true,
+            key_fields: None,
+            anonymous_operation_name: None,
+            schema: None,
+            validate: false,
+            minify: false,
+            passes: vec![],
+        }
+    }
+
+    pub fn typename(mut self, typename: bool) -> Self {
+        self.typename = typename;
+        self
+    }
+
+    pub fn key_fields(mut self, key_fields: KeyFields) -> Self {
+        self.key_fields = Some(key_fields);
+        self
+    }
+
+    pub fn schema(mut self, schema: Schema) -> Self {
+        self.schema = Some(Arc::new(schema));
+        self
+    }
+
+    pub fn validate(mut self, validate: bool) -> Self {
+        self.validate = validate;
+        self
+    }
+
+    pub fn minify(mut self, minify: bool) -> Self {
+        self.minify = minify;
+        self
+    }
+
+    pub fn pass(mut self, name: impl Into<String>) -> Self {
+        self.passes.push(name.into());
+        self
+    }
+
+    pub fn anonymous_operation_name(mut self, name: impl Into<String>) -> Self {
+        self.anonymous_operation_name = Some(name.into());
+        self
+    }
+
+    pub fn pipeline(&self) -> CompileResult<TransformPipeline> {
+        let mut pipeline = TransformPipeline::new().shared_schema(self.schema.clone());
+        if let Some(name) = &self.anonymous_operation_name {
+            pipeline = pipeline.add(NameAnonymousOperation::new(name.clone()));
+        }
+        if let Some(key_fields) = &self.key_fields {
+            pipeline = pipeline.add(AddKeyFields::new(key_fields.clone()));
+        }
+        if self.typename {
+            pipeline = pipeline.add(AddTypename);
+        }
+        for name in &self.passes {
+            pipeline = pipeline.add_registered(name)?;
+        }
+        Ok(pipeline)
+    }
+
+    pub fn transform(&self, code: &str) -> CompileResult<String> {
+        let document = parse(code)?;
+        if let (Some(schema), true) = (&self.schema, self.validate) {
+            if let Some(error) = validate(&document, schema).into_iter().next() {
+                return Err(error);
+            }
+        }
+        self.pipeline()?.transform(code)
+    }
+}
+
+impl Default for Transformer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct InsertFields<F> {
+    fields: F,
+    edits: Vec<Edit>,
+}
+
+impl<F: Fn(&PassContext, Option<&str>) -> Vec<String>> InsertFields<F> {
+    fn insert(
+        &mut self,
+        context: &PassContext,
+        selection_set: &SelectionSet,
+        typename: Option<&str>,
+    ) {
+        if let (Some(schema), Some(typename)) = (context.schema, typename) {
+            if !schema.is_composite(typename) {
+                return;
+            }
+        }
+        let fields = (self.fields)(context, typename);
+        let fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+        self.edits
+            .extend(insertion(context.code, selection_set, &fields));
+    }
+}
+
+impl<F: Fn(&PassContext, Option<&str>) -> Vec<String>> Visitor for InsertFields<F> {
+    fn fragment(&mut self, context: &PassContext, fragment: &FragmentDefinition) {
+        self.insert(
+            context,
+            &fragment.selection_set,
+            Some(&fragment.type_condition),
+        );
+    }
+
+    fn field(&mut self, context: &PassContext, field: &Field, typename: Option<&str>) {
+        let Some(selection_set) = &field.selection_set else {
+            return;
+        };
+        if context.schema.is_some() && typename.is_none() {
+            return;
+        }
+        self.insert(context, selection_set, typename);
+    }
+
+    fn inline_fragment(
+        &mut self,
+        context: &PassContext,
+        fragment: &InlineFragment,
+        typename: Option<&str>,
+    ) {
+        if fragment.type_condition.is_some() || !is_conditional(&fragment.directives) {
+            self.insert(context, &fragment.selection_set, typename);
+        }
+    }
+}
+
+fn insert_fields(
+    context: &PassContext,
+    fields: impl Fn(&PassContext, Option<&str>) -> Vec<String>,
+) -> Vec<Edit> {
+    let mut visitor = InsertFields {
+        fields,
+        edits: vec![],
+    };
+    walk(context, &mut visitor);
+    visitor.edits
+}
+
+pub struct AddTypename;
+
+impl Pass for AddTypename {
+    fn run(&self, context: &PassContext) -> CompileResult<Vec<Edit>> {
+        Ok(insert_fields(context, |_, _| vec![TYPENAME.to_string()]))
+    }
+}
+
+pub struct AddKeyFields {
+    key_fields: KeyFields,
+}
+
+impl AddKeyFields {
+    pub fn new(key_fields: KeyFields) -> Self {
+        Self { key_fields }
+    }
+}
+
+impl Pass for AddKeyFields {
+    fn run(&self, context: &PassContext) -> CompileResult<Vec<Edit>> {
+        Ok(insert_fields(context, |context, typename| {
+            let Some(typename) = typename else {
+                return vec![];
+            };
+            let keys = match context.schema {
+                Some(schema) => {
+                    let keys = self.key_fields.get(Some(typename));
+                    let identifiable = keys
+                        .iter()
+                        .all(|key| key == "__typename" || schema.field(typename, key).is_some());
+                    match identifiable {
+                        true => keys,
+                        false => &[],
                     }
                 }
+                None => self.key_fields.configured(typename).unwrap_or_default(),
+            };
+            keys.to_vec()
+        }))
+    }
+}
+
+pub struct NameAnonymousOperation {
+    name: String,
+}
+
+impl NameAnonymousOperation {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into() }
+    }
+}
+
+impl Pass for NameAnonymousOperation {
+    fn run(&self, context: &PassContext) -> CompileResult<Vec<Edit>> {
+        let mut edits = vec![];
+        for operation in context.document.operations() {
+            if operation.name.is_some() {
+                continue;
+            }
+            if context.document.operations().count() > 1 {
+                return Err(CompileError::Unsupported {
+                    span: operation.span,
+                    reason: "an anonymous operation must be the only operation in the document"
+                        .to_string(),
+                });
+            }
+            edits.push(name_insertion(context.code, operation, &self.name));
+        }
+        Ok(edits)
+    }
+}
+
+fn name_insertion(code: &str, operation: &OperationDefinition, name: &str) -> Edit {
+    let start = operation.span.start;
+    let keyword = operation.operation_type.as_str();
+    match code[start..].starts_with('{') {
+        true => Edit::insert(start, format!("{} {} ", keyword, name)),
+        false => Edit::insert(start + keyword.len(), format!(" {}", name)),
+    }
+}
+
+fn insertion(code: &str, selection_set: &SelectionSet, fields: &[&str]) -> Option<Edit> {
+    let mut selected: Vec<&str> = selection_set
+        .selections
+        .iter()
+        .filter_map(|selection| match selection {
+            Selection::Field(field) if !selection.is_conditional() => Some(field.response_name()),
+            _ => None,
+        })
+        .collect();
+    let fields: Vec<&str> = fields
+        .iter()
+        .filter(|field| {
+            let missing = !selected.contains(field);
+            if missing {
+                selected.push(field);
             }
-            _ => {
-                dbg!("other");
+            missing
+        })
+        .copied()
+        .collect();
+    if fields.is_empty() {
+        return None;
+    }
+    let offset = selection_set.span.start + 1;
+    let first_offset = selection_set.selections.first()?.span().start;
+
+    match code[offset..first_offset].find('\n') {
+        Some(line_end) => {
+            let mut offset = offset + line_end;
+            let newline = match code[..offset].ends_with('\r') {
+                true => {
+                    offset -= 1;
+                    "\r\n"
+                }
+                false => "\n",
+            };
+            let line_start = code[..first_offset].rfind('\n').map_or(0, |i| i + 1);
+            let indent = &code[line_start..first_offset];
+            let text: String = fields
+                .iter()
+                .map(|field| format!("{}{}{}", newline, indent, field))
+                .collect();
+            Some(Edit::insert(offset, text))
+        }
+        None => {
+            let mut text: String = fields.iter().map(|field| format!(" {}", field)).collect();
+            if !code[offset..].starts_with(char::is_whitespace) {
+                text.push(' ');
             }
+            Some(Edit::insert(offset, text))
         }
     }
+}
 
-    "".to_string()
+pub fn add_type_field(code: &str) -> CompileResult<String> {
+    Transformer::new().transform(code)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::Span;
 
     #[test]
     fn test_add_type_field() {
@@ -65,7 +397,7 @@ mod tests {
 ";
 
         assert_eq!(
-            add_type_field(code),
+            add_type_field(code).unwrap(),
             r"query MeQuery {
     users(limit: 1) {
         __typename
@@ -96,4 +428,390 @@ mod tests {
 "
         );
     }
+
+    #[test]
+    fn preserve_comments_and_formatting() {
+        let code = "query Feed($first: Int = 10) {\r
+  # newest first\r
+  feed(first: $first, order: { by: CREATED_AT, direction: DESC }) { # paginated\r
+\t\t# the author is denormalized\r
+\t\tauthor { name }\r
+  }\r
+}\r
+";
+
+        assert_eq!(
+            add_type_field(code).unwrap(),
+            "query Feed($first: Int = 10) {\r
+  # newest first\r
+  feed(first: $first, order: { by: CREATED_AT, direction: DESC }) { # paginated\r
+\t\t__typename\r
+\t\t# the author is denormalized\r
+\t\tauthor { __typename name }\r
+  }\r
+}\r
+"
+        );
+    }
+
+    #[test]
+    fn insert_key_fields() {
+        let code = "query Repository {
+  repository(owner: \"rust-lang\") {
+    name
+    owner { login }
+  }
+}
+";
+        let schema = Schema::parse(
+            "type User { id: ID! nodeId: ID! login: String! }
+type Repository { id: ID! nodeId: ID! name: String! owner: User! }
+type Query { repository(owner: String!): Repository }
+",
+        )
+        .unwrap();
+        let transformer = Transformer::new()
+            .schema(schema)
+            .key_fields(KeyFields::new().default_fields(["id", "nodeId"]));
+
+        assert_eq!(
+            transformer.transform(code).unwrap(),
+            "query Repository {
+  repository(owner: \"rust-lang\") {
+    __typename
+    id
+    nodeId
+    name
+    owner { __typename id nodeId login }
+  }
+}
+"
+        );
+        assert_eq!(
+            Transformer::new()
+                .typename(false)
+                .key_fields(KeyFields::new())
+                .transform("{ viewer { login } }")
+                .unwrap(),
+            "{ viewer { login } }"
+        );
+
+        let key_fields = KeyFields::new().type_fields("Repository", ["owner", "name"]);
+        assert_eq!(key_fields.get(Some("Repository")), ["owner", "name"]);
+        assert_eq!(key_fields.get(Some("User")), ["id"]);
+        assert_eq!(key_fields.get(None), ["id"]);
+    }
+
+    #[test]
+    fn transform_fragment_definitions() {
+        let code = "query Viewer {
+  viewer {
+    ...UserParts
+  }
+}
+
+fragment UserParts on User {
+  login
+  repositories(first: 10) {
+    nodes { name }
+  }
+}
+";
+        let transformer =
+            Transformer::new().key_fields(KeyFields::new().type_fields("User", ["databaseId"]));
+
+        assert_eq!(
+            transformer.transform(code).unwrap(),
+            "query Viewer {
+  viewer {
+    __typename
+    ...UserParts
+  }
+}
+
+fragment UserParts on User {
+  __typename
+  databaseId
+  login
+  repositories(first: 10) {
+    __typename
+    nodes { __typename name }
+  }
+}
+"
+        );
+    }
+
+    #[test]
+    fn transform_inline_fragments() {
+        let code = "query Hero {
+  hero {
+    name
+    ... on Droid {
+      primaryFunction
+      ... { model }
+    }
+    ... on Human { starships { name } }
+  }
+}
+";
+        let transformer = Transformer::new().key_fields(
+            KeyFields::new()
+                .type_fields("Droid", ["serial"])
+                .type_fields("Human", ["name"]),
+        );
+
+        assert_eq!(
+            transformer.transform(code).unwrap(),
+            "query Hero {
+  hero {
+    __typename
+    name
+    ... on Droid {
+      __typename
+      serial
+      primaryFunction
+      ... { __typename serial model }
+    }
+    ... on Human { __typename name starships { __typename name } }
+  }
+}
+"
+        );
+    }
+
+    #[test]
+    fn skip_selected_fields() {
+        let code = "query Viewer {
+  viewer {
+    id
+    login
+    organization: primaryOrganization { __typename name }
+  }
+}
+";
+        let schema = Schema::parse(
+            "type Organization { id: ID! name: String! }
+type User { id: ID! login: String! primaryOrganization: Organization }
+type Query { viewer: User }
+",
+        )
+        .unwrap();
+        let transformer = Transformer::new()
+            .schema(schema)
+            .key_fields(KeyFields::new().default_fields(["id", "__typename", "id"]));
+        let transformed = transformer.transform(code).unwrap();
+
+        assert_eq!(
+            transformed,
+            "query Viewer {
+  viewer {
+    __typename
+    id
+    login
+    organization: primaryOrganization { id __typename name }
+  }
+}
+"
+        );
+        assert_eq!(transformer.transform(&transformed).unwrap(), transformed);
+    }
+
+    #[test]
+    fn report_errors_with_span() {
+        let code = "query Broken {\n  viewer { login }\n  @\n}\n";
+        let error = add_type_field(code).unwrap_err();
+        assert!(matches!(error, CompileError::Parse { .. }));
+        assert_eq!(error.span(), Span::new(36, 37));
+        assert_eq!(error.span().line_column(code), (3, 3));
+
+        let error = add_type_field("type User { id: ID! }").unwrap_err();
+        assert!(matches!(error, CompileError::Unsupported { .. }));
+        assert_eq!(error.span(), Span::new(0, 4));
+    }
+
+    #[test]
+    fn transform_mutations_and_subscriptions() {
+        let code = "mutation AddStar($id: ID!) {
+  addStar(input: { starrableId: $id }) {
+    starrable { stargazerCount }
+  }
+}
+
+subscription OnStar {
+  starAdded { starrable { stargazerCount } }
+}
+";
+        let transformer = Transformer::new().key_fields(KeyFields::new());
+
+        assert_eq!(
+            transformer.transform(code).unwrap(),
+            "mutation AddStar($id: ID!) {
+  addStar(input: { starrableId: $id }) {
+    __typename
+    starrable { __typename stargazerCount }
+  }
+}
+
+subscription OnStar {
+  starAdded { __typename starrable { __typename stargazerCount } }
+}
+"
+        );
+    }
+
+    #[test]
+    fn name_anonymous_operations() {
+        let transformer = Transformer::new().anonymous_operation_name("Anonymous");
+
+        assert_eq!(
+            transformer.transform("{ users { id } }").unwrap(),
+            "query Anonymous { users { __typename id } }"
+        );
+        assert_eq!(
+            transformer
+                .transform("mutation($id: ID!) { delete(id: $id) { id } }")
+                .unwrap(),
+            "mutation Anonymous($id: ID!) { delete(id: $id) { __typename id } }"
+        );
+        assert_eq!(
+            add_type_field("{ users { id } }").unwrap(),
+            "{ users { __typename id } }"
+        );
+        assert!(matches!(
+            transformer.transform("{ a } query B { b }"),
+            Err(CompileError::Unsupported { .. })
+        ));
+    }
+
+    #[test]
+    fn insert_on_composite_types_only() {
+        let schema = Schema::parse(
+            "scalar JSON
+interface Node { id: ID! }
+type User implements Node { id: ID! login: String! settings: JSON }
+type Team { name: String! members: [User!]! }
+union Member = User | Team
+type Query { node(id: ID!): Node members: [Member!]! viewer: User }
+",
+        )
+        .unwrap();
+        let transformer = Transformer::new()
+            .schema(schema)
+            .key_fields(KeyFields::new());
+
+        assert!(matches!(
+            transformer
+                .clone()
+                .validate(true)
+                .transform("{ viewer { email } }"),
+            Err(CompileError::Validation { .. })
+        ));
+        assert_eq!(
+            transformer
+                .transform(
+                    "query Members {
+  viewer { login settings { theme } }
+  node(id: 1) { ... on User { login } }
+  members {
+    ... on Team { name members { login } }
+  }
+}
+"
+                )
+                .unwrap(),
+            "query Members {
+  viewer { __typename id login settings { theme } }
+  node(id: 1) { __typename id ... on User { __typename id login } }
+  members {
+    __typename
+    ... on Team { __typename name members { __typename id login } }
+  }
+}
+"
+        );
+    }
+
+    #[test]
+    fn insert_outside_conditional_selections() {
+        let schema = Schema::parse(
+            "interface User { id: ID! name: String login: String }
+type Admin implements User { id: ID! name: String login: String level: Int }
+type Query { user: User }
+",
+        )
+        .unwrap();
+        let transformer = Transformer::new()
+            .schema(schema)
+            .key_fields(KeyFields::new());
+
+        assert_eq!(
+            transformer
+                .transform(
+                    "query User($full: Boolean!) {
+  user {
+    id @include(if: $full)
+    name @include(if: true)
+    ... @skip(if: $full) { login }
+    ... on Admin @include(if: $full) { level }
+  }
+}
+"
+                )
+                .unwrap(),
+            "query User($full: Boolean!) {
+  user {
+    __typename
+    id
+    id @include(if: $full)
+    name @include(if: true)
+    ... @skip(if: $full) { login }
+    ... on Admin @include(if: $full) { __typename id level }
+  }
+}
+"
+        );
+    }
+
+    #[test]
+    fn run_registered_passes() {
+        crate::pipeline::register_pass("audit", |context: &PassContext| {
+            Ok(context
+                .document
+                .operations()
+                .map(|operation| Edit::insert(operation.selection_set.span.start, "@audit "))
+                .collect())
+        });
+
+        assert_eq!(
+            Transformer::new()
+                .pass("audit")
+                .transform("query Viewer { viewer { login } }")
+                .unwrap(),
+            "query Viewer @audit { viewer { __typename login } }"
+        );
+        assert_eq!(
+            Transformer::new()
+                .pass("missing")
+                .transform("{ viewer { login } }")
+                .unwrap_err()
+                .reason(),
+            "no pass is registered as `missing`"
+        );
+    }
+
+    #[test]
+    fn transform_compact_documents() {
+        assert_eq!(add_type_field("{a{b}}").unwrap(), "{a{ __typename b}}");
+        assert_eq!(
+            add_type_field(
+                &crate::printer::minify(
+                    "query Q { node { id ...F } }\nfragment F on Node { name }"
+                )
+                .unwrap()
+            )
+            .unwrap(),
+            "query Q{node{ __typename id...F}}fragment F on Node{ __typename name}"
+        );
+    }
 }
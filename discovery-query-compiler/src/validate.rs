@@ -0,0 +1,372 @@
+use crate::ast::*;
+use crate::error::{CompileError, Span};
+use crate::schema::{InputValueDefinition, Schema, TypeKind};
+
+fn compatible(variable: &Type, location: &Type) -> bool {
+    match (variable, location) {
+        (Type::NonNull(variable), Type::NonNull(location)) => compatible(variable, location),
+        (_, Type::NonNull(_)) => false,
+        (Type::NonNull(variable), location) => compatible(variable, location),
+        (Type::List(variable), Type::List(location)) => compatible(variable, location),
+        (Type::Named(variable), Type::Named(location)) => variable == location,
+        _ => false,
+    }
+}
+
+struct Validator<'a> {
+    schema: &'a Schema,
+    document: &'a Document,
+    variables: Option<&'a [VariableDefinition]>,
+    visited: Vec<&'a str>,
+    errors: Vec<CompileError>,
+}
+
+impl<'a> Validator<'a> {
+    fn error(&mut self, span: Span, reason: String) {
+        let error = CompileError::Validation { span, reason };
+        if !self.errors.contains(&error) {
+            self.errors.push(error);
+        }
+    }
+
+    fn operation(&mut self, operation: &'a OperationDefinition) {
+        self.variables = Some(&operation.variable_definitions);
+        self.visited.clear();
+        for definition in &operation.variable_definitions {
+            let typename = definition.ty.named_type();
+            match self.schema.type_definition(typename) {
+                Some(ty) if ty.kind.is_input() => {}
+                _ => self.error(
+                    definition.span,
+                    format!(
+                        "variable `${}` must be an input type, found `{}`",
+                        definition.name, definition.ty
+                    ),
+                ),
+            }
+            if let Some(value) = &definition.default_value {
+                self.value(value, &definition.ty, false, definition.span);
+            }
+        }
+        match self.schema.root_type(operation.operation_type) {
+            Some(root) => self.selection_set(&operation.selection_set, root),
+            None => self.error(
+                operation.span,
+                format!(
+                    "schema does not support {} operations",
+                    operation.operation_type.as_str()
+                ),
+            ),
+        }
+    }
+
+    fn selection_set(&mut self, selection_set: &'a SelectionSet, typename: &str) {
+        for selection in &selection_set.selections {
+            for directive in selection.directives() {
+                for argument in &directive.arguments {
+                    if let Value::Variable(_) = argument.value {
+                        let ty = Type::NonNull(Box::new(Type::Named("Boolean".to_string())));
+                        self.value(&argument.value, &ty, false, argument.span);
+                    }
+                }
+            }
+            match selection {
+                Selection::Field(field) => self.field(field, typename),
+                Selection::InlineFragment(fragment) => {
+                    let typename = fragment.type_condition.as_deref().unwrap_or(typename);
+                    if self.type_condition(typename, fragment.span) {
+                        self.selection_set(&fragment.selection_set, typename);
+                    }
+                }
+                Selection::FragmentSpread(spread) => {
+                    let Some(fragment) = self.document.fragment(&spread.fragment_name) else {
+                        self.error(
+                            spread.span,
+                            format!("unknown fragment `{}`", spread.fragment_name),
+                        );
+                        continue;
+                    };
+                    if self.visited.contains(&fragment.name.as_str()) {
+                        continue;
+                    }
+                    self.visited.push(&fragment.name);
+                    self.fragment(fragment);
+                }
+            }
+        }
+    }
+
+    fn fragment(&mut self, fragment: &'a FragmentDefinition) {
+        if self.type_condition(&fragment.type_condition, fragment.span) {
+            self.selection_set(&fragment.selection_set, &fragment.type_condition);
+        }
+    }
+
+    fn type_condition(&mut self, typename: &str, span: Span) -> bool {
+        let composite = self.schema.is_composite(typename);
+        if !composite {
+            self.error(
+                span,
+                format!(
+                    "fragment type condition `{}` is not a composite type",
+                    typename
+                ),
+            );
+        }
+        composite
+    }
+
+    fn field(&mut self, field: &'a Field, typename: &str) {
+        if field.name == "__typename" {
+            return;
+        }
+        let Some(definition) = self.schema.field(typename, &field.name) else {
+            self.error(
+                field.span,
+                format!(
+                    "field `{}` does not exist on type `{}`",
+                    field.name, typename
+                ),
+            );
+            return;
+        };
+
+        for argument in &field.arguments {
+            match definition
+                .arguments
+                .iter()
+                .find(|definition| definition.name == argument.name)
+            {
+                Some(definition) => self.value(
+                    &argument.value,
+                    &definition.ty,
+                    definition.has_default,
+                    argument.span,
+                ),
+                None => self.error(
+                    argument.span,
+                    format!(
+                        "unknown argument `{}` on field `{}.{}`",
+                        argument.name, typename, field.name
+                    ),
+                ),
+            }
+        }
+        let provided: Vec<_> = field
+            .arguments
+            .iter()
+            .map(|argument| argument.name.as_str())
+            .collect();
+        self.required(&definition.arguments, &provided, field.span, || {
+            format!("field `{}.{}` requires argument", typename, field.name)
+        });
+
+        let field_type = definition.ty.named_type();
+        match (self.schema.is_composite(field_type), &field.selection_set) {
+            (true, Some(selection_set)) => self.selection_set(selection_set, field_type),
+            (true, None) => self.error(
+                field.span,
+                format!(
+                    "field `{}` of type `{}` must have a selection of subfields",
+                    field.name, definition.ty
+                ),
+            ),
+            (false, Some(_)) => self.error(
+                field.span,
+                format!(
+                    "field `{}` of type `{}` must not have a selection",
+                    field.name, definition.ty
+                ),
+            ),
+            (false, None) => {}
+        }
+    }
+
+    fn required(
+        &mut self,
+        definitions: &[InputValueDefinition],
+        provided: &[&str],
+        span: Span,
+        location: impl Fn() -> String,
+    ) {
+        for definition in definitions {
+            if definition.ty.is_non_null()
+                && !definition.has_default
+                && !provided.contains(&definition.name.as_str())
+            {
+                self.error(
+                    span,
+                    format!(
+                        "{} `{}` of type `{}`",
+                        location(),
+                        definition.name,
+                        definition.ty
+                    ),
+                );
+            }
+        }
+    }
+
+    fn value(&mut self, value: &Value, ty: &Type, has_default: bool, span: Span) {
+        let mismatch = |validator: &mut Self| {
+            validator.error(span, format!("expected a value of type `{}`", ty));
+        };
+        match (value, ty) {
+            (Value::Variable(name), _) => {
+                let Some(variables) = self.variables else {
+                    return;
+                };
+                let Some(variable) = variables.iter().find(|variable| &variable.name == name)
+                else {
+                    self.error(span, format!("variable `${}` is not defined", name));
+                    return;
+                };
+                let location = match ty {
+                    Type::NonNull(inner)
+                        if has_default
+                            || matches!(&variable.default_value, Some(value) if value != &Value::Null) =>
+                    {
+                        inner
+                    }
+                    _ => ty,
+                };
+                if !compatible(&variable.ty, location) {
+                    self.error(
+                        span,
+                        format!(
+                            "variable `${}` of type `{}` can not be used where `{}` is expected",
+                            name, variable.ty, ty
+                        ),
+                    );
+                }
+            }
+            (Value::Null, Type::NonNull(_)) => mismatch(self),
+            (Value::Null, _) => {}
+            (value, Type::NonNull(ty)) => self.value(value, ty, false, span),
+            (Value::List(values), Type::List(ty)) => {
+                for value in values {
+                    self.value(value, ty, false, span);
+                }
+            }
+            (value, Type::List(ty)) => self.value(value, ty, false, span),
+            (value, Type::Named(name)) => {
+                let Some(definition) = self.schema.type_definition(name) else {
+                    return;
+                };
+                let valid = match (definition.kind, value) {
+                    (TypeKind::Scalar, value) => match name.as_str() {
+                        "Int" => matches!(value, Value::Int(_)),
+                        "Float" => matches!(value, Value::Int(_) | Value::Float(_)),
+                        "String" => matches!(value, Value::String(_)),
+                        "Boolean" => matches!(value, Value::Boolean(_)),
+                        "ID" => matches!(value, Value::Int(_) | Value::String(_)),
+                        _ => true,
+                    },
+                    (TypeKind::Enum, Value::Enum(value)) => definition.values.contains(value),
+                    (TypeKind::InputObject, Value::Object(fields)) => {
+                        for (field, value) in fields {
+                            match definition.input_field(field) {
+                                Some(input) => {
+                                    self.value(value, &input.ty, input.has_default, span)
+                                }
+                                None => self.error(
+                                    span,
+                                    format!("unknown field `{}` on input type `{}`", field, name),
+                                ),
+                            }
+                        }
+                        let provided: Vec<_> =
+                            fields.iter().map(|(field, _)| field.as_str()).collect();
+                        self.required(&definition.input_fields, &provided, span, || {
+                            format!("input type `{}` requires field", name)
+                        });
+                        true
+                    }
+                    _ => false,
+                };
+                if !valid {
+                    mismatch(self);
+                }
+            }
+        }
+    }
+}
+
+pub fn validate(document: &Document, schema: &Schema) -> Vec<CompileError> {
+    let mut validator = Validator {
+        schema,
+        document,
+        variables: None,
+        visited: vec![],
+        errors: vec![],
+    };
+    for operation in document.operations() {
+        validator.operation(operation);
+    }
+    validator.variables = None;
+    for fragment in document.fragments() {
+        validator.fragment(fragment);
+    }
+    validator.errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn validate_against_schema() {
+        let schema = Schema::parse(
+            "enum Order { ASC DESC }
+input Filter { term: String! order: Order = ASC }
+type User { id: ID! login: String! repositories(first: Int!, filter: Filter): [Repository!]! }
+type Repository { name: String! }
+type Query { user(login: String!): User }
+",
+        )
+        .unwrap();
+        let reasons = |code: &str| -> Vec<String> {
+            validate(&parse(code).unwrap(), &schema)
+                .iter()
+                .map(|error| error.reason().to_string())
+                .collect()
+        };
+
+        assert!(reasons(
+            "query User($login: String!, $first: Int = 10) {
+  user(login: $login) {
+    id
+    repositories(first: $first, filter: { term: \"rust\", order: DESC }) { name }
+  }
+}
+"
+        )
+        .is_empty());
+        assert_eq!(
+            reasons(
+                "query User($login: String, $filter: Filter) {
+  user(login: $login) {
+    email
+    login { length }
+    repositories(filter: { order: UP }) { ...Parts }
+  }
+  ...Missing
+}
+
+fragment Parts on Repository { name size }
+"
+            ),
+            [
+                "variable `$login` of type `String` can not be used where `String!` is expected",
+                "field `email` does not exist on type `User`",
+                "field `login` of type `String!` must not have a selection",
+                "expected a value of type `Order`",
+                "input type `Filter` requires field `term` of type `String!`",
+                "field `User.repositories` requires argument `first` of type `Int!`",
+                "field `size` does not exist on type `Repository`",
+                "unknown fragment `Missing`",
+            ]
+        );
+    }
+}
@@ -0,0 +1,263 @@
+use graphql_client_codegen::{
+    generate_module_token_stream, CodegenMode, GraphQLClientCodegenOptions,
+};
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use syn::{AttributeArgs, ItemStruct, Lit, Meta, NestedMeta};
+
+use discovery_query_compiler::{
+    strip_client_fields, strip_connection_directives, CompileError, CompileResult, KeyFields,
+    Schema, Span as CodeSpan, Transformer,
+};
+
+#[derive(Default)]
+struct Arguments {
+    schema_path: Option<String>,
+    query_path: Option<String>,
+    response_derives: Option<String>,
+    variables_derives: Option<String>,
+    custom_scalars_module: Option<String>,
+    key_fields: Option<String>,
+}
+
+impl Arguments {
+    fn parse(args: AttributeArgs) -> syn::Result<Self> {
+        let mut arguments = Arguments::default();
+        for arg in args {
+            let NestedMeta::Meta(Meta::NameValue(name_value)) = &arg else {
+                return Err(syn::Error::new_spanned(arg, "expected `name = \"value\"`"));
+            };
+            let Lit::Str(value) = &name_value.lit else {
+                return Err(syn::Error::new_spanned(
+                    &name_value.lit,
+                    "expected a string literal",
+                ));
+            };
+            let field = match name_value
+                .path
+                .get_ident()
+                .map(ToString::to_string)
+                .as_deref()
+            {
+                Some("schema_path") => &mut arguments.schema_path,
+                Some("query_path") => &mut arguments.query_path,
+                Some("response_derives") => &mut arguments.response_derives,
+                Some("variables_derives") => &mut arguments.variables_derives,
+                Some("custom_scalars_module") => &mut arguments.custom_scalars_module,
+                Some("key_fields") => &mut arguments.key_fields,
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        &name_value.path,
+                        "unknown argument",
+                    ))
+                }
+            };
+            *field = Some(value.value());
+        }
+        Ok(arguments)
+    }
+
+    fn required(value: &Option<String>, name: &str) -> syn::Result<String> {
+        value.clone().ok_or_else(|| {
+            syn::Error::new(Span::call_site(), format!("missing argument `{}`", name))
+        })
+    }
+}
+
+fn prepare_query(
+    schema: Option<Schema>,
+    key_fields: Option<&str>,
+    query: &str,
+) -> CompileResult<String> {
+    let mut transformer = Transformer::new();
+    if let Some(key_fields) = key_fields {
+        transformer = transformer.key_fields(
+            KeyFields::new().default_fields(
+                key_fields
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|field| !field.is_empty()),
+            ),
+        );
+    }
+    if let Some(schema) = schema {
+        transformer = transformer.schema(schema);
+    }
+    let query = strip_connection_directives(&transformer.transform(query)?)?;
+    strip_client_fields(&query)?.ok_or_else(|| CompileError::Unsupported {
+        span: CodeSpan::new(0, query.len()),
+        reason: "every field is `@client`, so there is nothing to send to the server".to_string(),
+    })
+}
+
+fn read(path: &Path) -> syn::Result<String> {
+    fs::read_to_string(path).map_err(|e| {
+        syn::Error::new(
+            Span::call_site(),
+            format!("failed to read `{}`: {}", path.display(), e),
+        )
+    })
+}
+
+// Queries transformed by `QueryBuild` in a build script, where registered
+// passes are available, are referenced as `$OUT_DIR/discovery-query/...`.
+fn resolve(manifest_dir: &Path, path: &str) -> syn::Result<PathBuf> {
+    match path.strip_prefix("$OUT_DIR/") {
+        Some(path) => env::var_os("OUT_DIR")
+            .map(|out_dir| PathBuf::from(out_dir).join(path))
+            .ok_or_else(|| {
+                syn::Error::new(
+                    Span::call_site(),
+                    "OUT_DIR is not defined; add a build script that runs `QueryBuild`",
+                )
+            }),
+        None => Ok(manifest_dir.join(path)),
+    }
+}
+
+// The transformed query goes under the crate's OUT_DIR when it has a build
+// script. Otherwise it falls back to the system temp dir; file names are
+// content-addressed, so rebuilds reuse the same file instead of piling up.
+fn write_query(item: &ItemStruct, query: &str) -> syn::Result<PathBuf> {
+    let directory = env::var_os("OUT_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(env::temp_dir)
+        .join("discovery-query");
+    let path = directory.join(format!(
+        "{}-{:x}.graphql",
+        item.ident,
+        Sha256::digest(query)
+    ));
+    fs::create_dir_all(&directory)
+        .and_then(|_| fs::write(&path, query))
+        .map_err(|e| {
+            syn::Error::new(
+                Span::call_site(),
+                format!("failed to write `{}`: {}", path.display(), e),
+            )
+        })?;
+    Ok(path)
+}
+
+fn expand(args: AttributeArgs, item: ItemStruct) -> syn::Result<TokenStream> {
+    let arguments = Arguments::parse(args)?;
+    let manifest_dir =
+        PathBuf::from(env::var("CARGO_MANIFEST_DIR").map_err(|_| {
+            syn::Error::new(Span::call_site(), "CARGO_MANIFEST_DIR is not defined")
+        })?);
+    let schema_path =
+        manifest_dir.join(Arguments::required(&arguments.schema_path, "schema_path")?);
+    let query_path = resolve(
+        &manifest_dir,
+        &Arguments::required(&arguments.query_path, "query_path")?,
+    )?;
+
+    let schema = match schema_path
+        .extension()
+        .and_then(|extension| extension.to_str())
+    {
+        Some("json") => None,
+        _ => Some(Schema::parse(&read(&schema_path)?).map_err(|e| {
+            syn::Error::new(
+                Span::call_site(),
+                format!("{}: {}", schema_path.display(), e.reason()),
+            )
+        })?),
+    };
+    let query = read(&query_path)?;
+    let query = prepare_query(schema, arguments.key_fields.as_deref(), &query).map_err(|e| {
+        let (line, column) = e.span().line_column(&query);
+        syn::Error::new(
+            Span::call_site(),
+            format!(
+                "{}:{}:{}: {}",
+                query_path.display(),
+                line,
+                column,
+                e.reason()
+            ),
+        )
+    })?;
+    let transformed_path = write_query(&item, &query)?;
+
+    let mut options = GraphQLClientCodegenOptions::new(CodegenMode::Derive);
+    options.set_query_file(transformed_path.clone());
+    if let Some(response_derives) = arguments.response_derives {
+        options.set_response_derives(response_derives);
+    }
+    if let Some(variables_derives) = arguments.variables_derives {
+        options.set_variables_derives(variables_derives);
+    }
+    if let Some(custom_scalars_module) = arguments.custom_scalars_module {
+        options.set_custom_scalars_module(syn::parse_str(&custom_scalars_module)?);
+    }
+    options.set_struct_ident(item.ident.clone());
+    options.set_module_visibility(item.vis.clone());
+    options.set_operation_name(item.ident.to_string());
+
+    let generated =
+        generate_module_token_stream(transformed_path, &schema_path, options).map_err(|e| {
+            syn::Error::new_spanned(
+                &item.ident,
+                format!("failed to generate GraphQLQuery impl: {}", e),
+            )
+        })?;
+    let schema_path = schema_path.display().to_string();
+    let query_path = query_path.display().to_string();
+    Ok(quote! {
+        #item
+
+        const _: &[&str] = &[include_str!(#schema_path), include_str!(#query_path)];
+
+        #generated
+    })
+}
+
+#[proc_macro_attribute]
+pub fn discovery_query(
+    args: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let args = syn::parse_macro_input!(args as AttributeArgs);
+    let item = syn::parse_macro_input!(item as ItemStruct);
+    expand(args, item)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prepare_query_for_codegen() {
+        let schema = "type User { id: ID! login: String! }
+type Query { viewer: User }
+";
+        assert_eq!(
+            prepare_query(
+                Some(Schema::parse(schema).unwrap()),
+                None,
+                "query Viewer {\n  viewer { login theme @client }\n}\n"
+            )
+            .unwrap(),
+            "query Viewer {\n  viewer { __typename login }\n}\n"
+        );
+        assert_eq!(
+            prepare_query(
+                Some(Schema::parse(schema).unwrap()),
+                Some("id, login"),
+                "query Viewer { viewer { theme @client } }"
+            )
+            .unwrap(),
+            "query Viewer { viewer { __typename id login } }"
+        );
+        assert!(matches!(
+            prepare_query(None, None, "query Session { isLoggedIn @client }"),
+            Err(CompileError::Unsupported { .. })
+        ));
+    }
+}
@@ -0,0 +1,23 @@
+use discovery_query_macro::discovery_query;
+use graphql_client::GraphQLQuery;
+
+#[discovery_query(
+    schema_path = "tests/schema.graphql",
+    query_path = "tests/viewer.graphql",
+    response_derives = "Debug, PartialEq",
+    key_fields = "id"
+)]
+pub struct Viewer;
+
+#[test]
+fn expand_discovery_query() {
+    let body = Viewer::build_query(viewer::Variables);
+    assert_eq!(body.operation_name, "Viewer");
+    assert!(!body.query.contains("@client"));
+
+    let data: viewer::ResponseData = serde_json::from_value(serde_json::json!({
+        "viewer": { "__typename": "User", "id": "1", "login": "octocat" }
+    }))
+    .unwrap();
+    assert_eq!(data.viewer.unwrap().login, "octocat");
+}